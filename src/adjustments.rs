@@ -0,0 +1,52 @@
+//! Adjustments made to a song while it plays, and where they are kept.
+//!
+//! Changing a song's key, slowing it down or nudging its lyric timing helps
+//! the singer of the moment, but may surprise the next one. The policy
+//! decides whether the changes stick to the song for everyone, to the
+//! singer who made them, or only last until the song ends.
+
+use serde::{Deserialize, Serialize};
+
+/// Key, tempo and lyric timing of a song as it is being sung.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SongAdjustments {
+    /// Rehearsal playback rate (`None` = normal speed)
+    pub playback_rate: Option<f32>,
+    /// Semitones the backing track is shifted from its key (`None` = the
+    /// singer's preferred key, or the original without one)
+    pub key_shift: Option<i8>,
+    /// Lyric timing nudge in milliseconds, on top of the `.lrc`'s own
+    /// offset; positive shows lines earlier
+    pub lyric_offset_ms: i64,
+}
+
+impl SongAdjustments {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Where adjustments made during a song are kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdjustmentPolicy {
+    /// With the song, for every singer
+    #[default]
+    PerSong,
+    /// With the singer's profile; songs without a singer keep nothing
+    PerSinger,
+    /// Nowhere: every song starts unadjusted
+    Discard,
+}
+
+impl AdjustmentPolicy {
+    pub const ALL: [Self; 3] = [Self::PerSong, Self::PerSinger, Self::Discard];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PerSong => "Saved with the song",
+            Self::PerSinger => "Saved per singer",
+            Self::Discard => "Reset after the song",
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Spoken "Next up" announcements between queued songs.
+//!
+//! Speech comes from the system's text-to-speech command: `say` on macOS,
+//! System.Speech through PowerShell on Windows and `espeak-ng` elsewhere.
+//! It plays straight to the default output device, outside the mix, while
+//! the player waits between songs.
+
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+/// An announcement still speaking after this long is cut off, so a hung
+/// speech command cannot hold up the queue.
+const MAX_DURATION: Duration = Duration::from_secs(15);
+
+/// Example voice names shown in Settings for this system's command.
+#[cfg(target_os = "macos")]
+pub const VOICE_HINT: &str = "e.g. Samantha, Daniel";
+#[cfg(windows)]
+pub const VOICE_HINT: &str = "e.g. Microsoft Zira Desktop";
+#[cfg(not(any(target_os = "macos", windows)))]
+pub const VOICE_HINT: &str = "e.g. en-us, en-gb+f3";
+
+/// "Next up: Ana singing Yesterday", or without the singer when nobody is
+/// known to sing it.
+pub fn next_up(singer: Option<&str>, title: &str) -> String {
+    match singer {
+        Some(singer) => format!("Next up: {singer} singing {title}"),
+        None => format!("Next up: {title}"),
+    }
+}
+
+/// Text being spoken by a speech command.
+pub struct Announcement {
+    child: Child,
+    started: Instant,
+}
+
+impl Announcement {
+    /// Start speaking `text` with `voice`, or the system's default voice.
+    pub fn speak(text: &str, voice: Option<&str>) -> Result<Self> {
+        let voice = voice.map(str::trim).filter(|voice| !voice.is_empty());
+        let child = speech_command(text, voice)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Cannot run the text-to-speech command")?;
+        Ok(Self {
+            child,
+            started: Instant::now(),
+        })
+    }
+
+    /// True once the announcement has been spoken, or has taken too long
+    /// and was stopped.
+    pub fn is_finished(&mut self) -> bool {
+        match self.child.try_wait() {
+            Ok(Some(status)) => {
+                if !status.success() {
+                    tracing::warn!("Text-to-speech command failed ({status})");
+                }
+                true
+            },
+            Ok(None) if self.started.elapsed() < MAX_DURATION => false,
+            Ok(None) => {
+                tracing::warn!("Announcement took too long, stopping it");
+                self.stop();
+                true
+            },
+            Err(e) => {
+                tracing::warn!("Lost the text-to-speech command: {e}");
+                true
+            },
+        }
+    }
+
+    /// Stop speaking.
+    pub fn stop(&mut self) {
+        // Fails only when it has already exited
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Drop for Announcement {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    let mut command = Command::new("say");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    command.arg(text);
+    command
+}
+
+#[cfg(windows)]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    // Passed through the environment so no quoting of the text is needed
+    let script = "Add-Type -AssemblyName System.Speech; \
+                  $speech = New-Object System.Speech.Synthesis.SpeechSynthesizer; \
+                  if ($env:PWE_VOICE) { $speech.SelectVoice($env:PWE_VOICE) }; \
+                  $speech.Speak($env:PWE_ANNOUNCEMENT)";
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", script])
+        .env("PWE_ANNOUNCEMENT", text)
+        .env("PWE_VOICE", voice.unwrap_or_default());
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn speech_command(text: &str, voice: Option<&str>) -> Command {
+    let mut command = Command::new("espeak-ng");
+    if let Some(voice) = voice {
+        command.args(["-v", voice]);
+    }
+    command.arg("--").arg(text);
+    command
+}
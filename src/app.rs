@@ -0,0 +1,2196 @@
+//! Main application state and top-level layout.
+
+use std::collections::{BTreeSet, VecDeque};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Receiver;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::adjustments::{AdjustmentPolicy, SongAdjustments};
+use crate::announcer::{self, Announcement};
+use crate::audio::export::{Backing, ExportJob, MixPreset, Performance};
+use crate::audio::generator;
+use crate::audio::input::MonitorOutput;
+use crate::audio::key::KeyDetection;
+use crate::audio::melody::{Melody, MelodyJob};
+use crate::audio::processor::MIC_COUNT;
+use crate::audio::recorder::Recorder;
+use crate::audio::separation::{self, Separator};
+use crate::audio::spectrum::SpectrumFrame;
+use crate::audio::warmup::WarmupSession;
+use crate::audio::waveform::{Waveform, WaveformJob};
+use crate::audio::{AudioError, AudioPlayer, MicInput, ProcessorParams};
+use crate::cdg::CdgPlayback;
+use crate::config::{self, AppConfig, AudioConfig};
+use crate::cover_art::CoverArt;
+use crate::download::pipeline::{PipelineStep, StepStatus};
+use crate::download::DownloadJob;
+use crate::library::content_store::{self, ImportJob};
+use crate::library::difficulty::DifficultyJob;
+use crate::library::language::LanguageJob;
+use crate::library::results::{KaraokeRun, RunResults};
+use crate::library::sections::{self, SectionKind};
+use crate::library::storage::{LibraryStorage, SongEntry};
+use crate::lrc::bidi;
+use crate::lrc::chords::ChordLine;
+use crate::lrc::LrcDocument;
+use crate::lyrics::LyricsFetchJob;
+use crate::migration::{self, DataMigration};
+use crate::profiles::Profiles;
+use crate::remote::votes::{self, QueueVotes};
+use crate::remote::{song_id, QueuedSong, RemoteServer, RemoteSong};
+use crate::session::{LogEntry, PlaybackState, SessionEvent, SessionLog};
+use crate::ui::device_export::DeviceExportDialog;
+use crate::ui::karaoke_view::Teleprompter;
+use crate::ui::lrc_import::LrcImportWizard;
+use crate::ui::lyrics_editor::LyricsEditor;
+use crate::ui::pitch_guide::PitchTrail;
+use crate::ui::song_summary::SongSummary;
+use crate::ui::warmup_view::WarmupOptions;
+use crate::ui::waveform_bar::{section_color, waveform_seek_bar};
+use crate::ui::{fonts, theme};
+use crate::video::VideoPlayback;
+
+/// Fraction of the current song after which the next queued song is preloaded.
+const PRELOAD_AT: f32 = 0.9;
+/// Step of the lyric timing hotkeys and buttons, in milliseconds.
+pub(crate) const LYRIC_NUDGE_MS: i64 = 100;
+/// Queued songs decoded into memory ahead of playing them.
+const PRECACHE_AHEAD: usize = 3;
+/// Count-in tempo for songs without a known BPM.
+pub(crate) const DEFAULT_BPM: f32 = 100.0;
+/// Id of the queue sidebar.
+pub(crate) const QUEUE_PANEL_ID: &str = "queue_panel";
+/// Allowed width of the queue sidebar.
+const QUEUE_PANEL_WIDTH: RangeInclusive<f32> = 140.0..=480.0;
+/// Fade-out of the audio when quitting.
+const SHUTDOWN_FADE: Duration = Duration::from_millis(600);
+/// Longest wait for files and background jobs when quitting.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Main views reachable from the top panel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum View {
+    Library,
+    Karaoke,
+    WarmUp,
+    Practice,
+    Sessions,
+    /// Timing lyrics by tapping along
+    Editor,
+    Settings,
+}
+
+pub struct KaraokeApp {
+    pub(crate) config: AppConfig,
+    pub(crate) params: Arc<ProcessorParams>,
+    /// `None` when no output device could be opened
+    pub(crate) player: Option<AudioPlayer>,
+    /// Live microphone passthrough, running while enabled in the settings
+    pub(crate) mic: Option<MicInput>,
+    /// Second singer's microphone, running alongside `mic` in duet mode
+    pub(crate) duet_mic: Option<MicInput>,
+    /// Recording of the current song's performance
+    pub(crate) recording: Option<(PathBuf, Backing, Recorder)>,
+    /// Stopped recordings whose files are still being written
+    pub(crate) saving_recordings: Vec<(Backing, [Duration; MIC_COUNT], Recorder)>,
+    /// The last recording saved, for exporting as one mixed file
+    pub(crate) last_performance: Option<Performance>,
+    pub(crate) export_job: Option<ExportJob>,
+    pub(crate) session_log: SessionLog,
+    /// Past session shown in the Sessions view, `None` for the running one
+    pub(crate) viewed_session: Option<(PathBuf, Vec<LogEntry>)>,
+    /// Copy of the data directory to a new folder, while running
+    pub(crate) data_migration: Option<DataMigration>,
+    /// The previous data directory after a move, and the files moved out of
+    /// it, until the user deletes or keeps them
+    pub(crate) moved_data: Option<(PathBuf, Vec<PathBuf>)>,
+    pub(crate) view: View,
+    pub(crate) storage: LibraryStorage,
+    pub(crate) queue: VecDeque<PathBuf>,
+    pub(crate) separator: Separator,
+    /// Key analysis of scanned songs and of the current one, when their key
+    /// is not known yet
+    pub(crate) key_detection: Option<KeyDetection>,
+    pub(crate) warmup: Option<WarmupSession>,
+    /// Waveform of the current song, once loaded
+    pub(crate) waveform: Option<(PathBuf, Waveform)>,
+    pub(crate) waveform_job: Option<WaveformJob>,
+    /// Estimated melody of the current song, once extracted
+    pub(crate) melody: Option<(PathBuf, Melody)>,
+    pub(crate) melody_job: Option<MelodyJob>,
+    /// Pitch sung lately on each mic, drawn behind the pitch guide's
+    /// playhead
+    pub(crate) pitch_trails: [PitchTrail; MIC_COUNT],
+    /// Scoring of the current song, while it is sung with the mic on
+    pub(crate) run: Option<KaraokeRun>,
+    /// Results of the last scored song, until dismissed
+    pub(crate) run_results: Option<RunResults>,
+    /// When the current song was started from its beginning
+    pub(crate) song_started: Option<Instant>,
+    /// The song that just ended, until its summary is dismissed
+    pub(crate) song_summary: Option<SongSummary>,
+    pub(crate) profiles: Profiles,
+    /// Who sings the current song, when known
+    pub(crate) current_singer: Option<String>,
+    /// Name typed for the next singer profile
+    pub(crate) new_profile_name: String,
+    /// Lyrics and chords of the song in the practice view
+    pub(crate) chord_sheet: Option<(PathBuf, Vec<ChordLine>)>,
+    /// Synced lyrics of the song in the karaoke view, with the modification
+    /// time of the `.lrc` they were read from
+    pub(crate) lyrics: Option<(PathBuf, Option<SystemTime>, LrcDocument)>,
+    /// Unsynced lyrics tag of that song, shown when it has no `.lrc`
+    pub(crate) plain_lyrics: Option<String>,
+    /// Scrolling through `plain_lyrics` while they are shown as a
+    /// teleprompter
+    pub(crate) teleprompter: Option<Teleprompter>,
+    /// Languages the song in the karaoke view has lyrics translations in
+    pub(crate) translation_languages: Option<(PathBuf, Vec<String>)>,
+    /// Translation shown under the lyrics: its `.lrc`, read like `lyrics`
+    pub(crate) translation: Option<(PathBuf, Option<SystemTime>, LrcDocument)>,
+    /// Romaji companion of the lyrics, read like `lyrics`; empty when the
+    /// song has none
+    pub(crate) romaji: Option<(PathBuf, Option<SystemTime>, LrcDocument)>,
+    /// Lyric line being sung and the input time it started showing, for
+    /// the line transition
+    pub(crate) lyric_line_shown: (Option<usize>, f64),
+    /// Key, tempo and lyric timing of the current song, kept where the
+    /// adjustment policy says
+    pub(crate) adjustments: SongAdjustments,
+    /// Where the singer screen was last sent, once it is open
+    pub(crate) singer_screen_placed: Option<egui::Pos2>,
+    /// Web remote server, running while enabled in the settings
+    pub(crate) remote: Option<RemoteServer>,
+    /// Guests' votes on the queued songs
+    pub(crate) queue_votes: QueueVotes,
+    /// Name typed for the next guest link
+    pub(crate) guest_label: String,
+    pub(crate) warmup_options: WarmupOptions,
+    /// LRC import wizard, while open
+    pub(crate) lrc_import: Option<LrcImportWizard>,
+    /// Song whose lyrics are being timed, while one is open
+    pub(crate) lyrics_editor: Option<LyricsEditor>,
+    /// Copy of picked files into the content store, while running
+    pub(crate) import_job: Option<ImportJob>,
+    /// Song being renamed and the name typed for it
+    pub(crate) renaming: Option<(PathBuf, String)>,
+    /// Announcement being spoken, with the queued song it announces
+    /// (`None` for a test from Settings)
+    pub(crate) announcement: Option<(Option<PathBuf>, Announcement)>,
+    /// Songs selected in the library, for exporting to a device
+    pub(crate) selected_songs: BTreeSet<PathBuf>,
+    /// "Export to device" dialog, while open
+    pub(crate) device_export: Option<DeviceExportDialog>,
+    /// Online lyrics lookup for the library, while running
+    pub(crate) lyrics_fetch: Option<LyricsFetchJob>,
+    /// Difficulty rating of songs with lyrics, while running
+    pub(crate) difficulty_job: Option<DifficultyJob>,
+    /// Language detection of songs without one, while running, and
+    /// whether songs were added since it started
+    pub(crate) language_job: Option<LanguageJob>,
+    pub(crate) languages_stale: bool,
+    /// Library filter: only show songs rated easy
+    pub(crate) easy_only: bool,
+    /// Show the library in one section per language
+    pub(crate) group_by_language: bool,
+    /// Music video of the current song, while it is shown behind the
+    /// lyrics
+    pub(crate) video: Option<VideoPlayback>,
+    /// CD+G graphics of the current song, while they are shown
+    pub(crate) cdg: Option<CdgPlayback>,
+    /// Blurred cover of the song in the karaoke view
+    pub(crate) cover_art: Option<CoverArt>,
+    /// Downloads of this session, running or finished
+    pub(crate) downloads: Vec<DownloadJob>,
+    /// URL typed for the next download
+    pub(crate) download_url: String,
+    /// Kind given to the next section marker
+    pub(crate) section_kind: SectionKind,
+    /// Name typed for the next cue point
+    pub(crate) cue_name: String,
+    /// Octave of the reference keyboard
+    pub(crate) reference_octave: i8,
+    pub(crate) show_diagnostics: bool,
+    /// Output spectrum feed, subscribed while the diagnostics HUD is open
+    pub(crate) spectrum: Option<Receiver<Arc<SpectrumFrame>>>,
+    pub(crate) last_spectrum: Option<Arc<SpectrumFrame>>,
+    /// Clip count last seen and when it last went up, for the clip indicator
+    pub(crate) clips_seen: u64,
+    pub(crate) last_clip: Option<Instant>,
+    /// Keep the mic open for the input meter in Settings
+    pub(crate) mic_test: bool,
+    /// Same as `clips_seen` / `last_clip`, for feedback being suppressed
+    pub(crate) feedback_seen: u64,
+    pub(crate) last_feedback: Option<Instant>,
+    /// Same as `clips_seen` / `last_clip`, for each mic input meter
+    pub(crate) mic_clips_seen: [u64; MIC_COUNT],
+    pub(crate) last_mic_clip: [Option<Instant>; MIC_COUNT],
+    pub(crate) status: Option<String>,
+    /// When quitting began; the window closes once everything is saved
+    pub(crate) shutdown: Option<Instant>,
+    /// Settings changed by a slider drag or a text field still being
+    /// edited, saved once the edit is over
+    pub(crate) config_unsaved: bool,
+}
+
+impl KaraokeApp {
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let config = AppConfig::load();
+        theme::apply_theme(&cc.egui_ctx, &config.display);
+        fonts::install_fonts(&cc.egui_ctx, config.display.lyric_font.as_deref());
+        let params = Arc::new(ProcessorParams::new(&config.audio));
+        let storage = LibraryStorage::load();
+
+        let mut status = None;
+        let player = match AudioPlayer::new(params.clone()) {
+            Ok(player) => {
+                player.set_volume(config.audio.master_volume);
+                Some(player)
+            },
+            Err(e) => {
+                tracing::error!("{e}");
+                status = Some(format!("No audio output: {e}"));
+                None
+            },
+        };
+
+        let mut app = Self {
+            show_diagnostics: config.display.show_diagnostics,
+            config,
+            params,
+            player,
+            mic: None,
+            duet_mic: None,
+            recording: None,
+            saving_recordings: Vec::new(),
+            last_performance: None,
+            export_job: None,
+            session_log: SessionLog::new(),
+            viewed_session: None,
+            data_migration: None,
+            moved_data: None,
+            spectrum: None,
+            last_spectrum: None,
+            clips_seen: 0,
+            last_clip: None,
+            mic_test: false,
+            feedback_seen: 0,
+            last_feedback: None,
+            mic_clips_seen: [0; MIC_COUNT],
+            last_mic_clip: [None; MIC_COUNT],
+            view: View::Library,
+            queue: storage.queue().iter().cloned().collect(),
+            storage,
+            separator: Separator::default(),
+            key_detection: None,
+            warmup: None,
+            waveform: None,
+            waveform_job: None,
+            melody: None,
+            melody_job: None,
+            pitch_trails: Default::default(),
+            run: None,
+            run_results: None,
+            song_started: None,
+            song_summary: None,
+            profiles: Profiles::load(),
+            current_singer: None,
+            new_profile_name: String::new(),
+            chord_sheet: None,
+            lyrics: None,
+            plain_lyrics: None,
+            teleprompter: None,
+            translation_languages: None,
+            translation: None,
+            romaji: None,
+            lyric_line_shown: (None, 0.0),
+            adjustments: SongAdjustments::default(),
+            singer_screen_placed: None,
+            remote: None,
+            queue_votes: QueueVotes::default(),
+            guest_label: String::new(),
+            warmup_options: WarmupOptions::default(),
+            lrc_import: None,
+            lyrics_editor: None,
+            import_job: None,
+            announcement: None,
+            renaming: None,
+            selected_songs: BTreeSet::new(),
+            device_export: None,
+            lyrics_fetch: None,
+            difficulty_job: None,
+            language_job: None,
+            languages_stale: false,
+            easy_only: false,
+            group_by_language: false,
+            video: None,
+            cdg: None,
+            cover_art: None,
+            downloads: Vec::new(),
+            download_url: String::new(),
+            section_kind: SectionKind::Verse,
+            cue_name: String::new(),
+            reference_octave: 4,
+            status,
+            shutdown: None,
+            config_unsaved: false,
+        };
+        app.apply_song_settings();
+        app.precache_upcoming();
+        app.update_mic();
+        app.update_remote_server();
+        app.detect_languages();
+        app
+    }
+
+    /// Reopen the output when its device went away or playback got stuck,
+    /// resuming the song and reattaching the microphone to the new mix bus.
+    fn update_output(&mut self) {
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        if player.output_stalled() {
+            tracing::warn!("Audio output stopped, reopening the default device");
+        } else if player.playback_stalled() {
+            let position = player.get_position();
+            tracing::warn!(
+                "Playback stuck at {position:?} on {}, reopening the default device",
+                player.device_name()
+            );
+            self.session_log.record(SessionEvent::PlaybackStalled {
+                device: player.device_name().to_string(),
+                position_secs: position.as_secs_f32(),
+            });
+        } else {
+            return;
+        }
+        match player.recover() {
+            Ok(()) => {
+                self.status = Some(format!(
+                    "Audio output stalled; now playing on {}",
+                    player.device_name()
+                ));
+                self.session_log.record(SessionEvent::OutputRecovered {
+                    device: player.device_name().to_string(),
+                });
+                self.mic = None;
+                self.duet_mic = None;
+                self.update_mic();
+            },
+            Err(e) => {
+                tracing::error!("{e}");
+                self.status = Some(format!("Audio output lost: {e}"));
+            },
+        }
+    }
+
+    /// Start or stop the microphones: they run for passthrough (per the
+    /// settings), while a warm-up or the pitch guide needs the sung pitch,
+    /// while a performance is recorded, or while the input meter in
+    /// Settings is testing them. The second one only runs in duet mode.
+    pub(crate) fn update_mic(&mut self) {
+        if !self.config.audio.mic_passthrough
+            && !self.warmup_running()
+            && !self.params.pitch_guide()
+            && self.recording.is_none()
+            && !self.mic_test
+        {
+            self.mic = None;
+            self.duet_mic = None;
+            return;
+        }
+        let audio = &self.config.audio;
+        if !audio.duet.enabled {
+            self.duet_mic = None;
+        }
+        let Some(player) = &self.player else {
+            return;
+        };
+        if self.mic.is_none() {
+            match open_mic(
+                player,
+                &self.params,
+                0,
+                audio.input_device.as_deref(),
+                audio.input_channel,
+                audio,
+            ) {
+                Ok(mic) => self.mic = Some(mic),
+                Err(e) => {
+                    tracing::error!("{e}");
+                    self.status = Some(format!("No microphone: {e}"));
+                },
+            }
+        }
+        let duet = &audio.duet;
+        if duet.enabled && self.duet_mic.is_none() {
+            match open_mic(
+                player,
+                &self.params,
+                1,
+                duet.input_device.as_deref(),
+                duet.input_channel,
+                audio,
+            ) {
+                Ok(mic) => self.duet_mic = Some(mic),
+                Err(e) => {
+                    tracing::error!("{e}");
+                    self.status = Some(format!("No second microphone: {e}"));
+                },
+            }
+        }
+    }
+
+    /// Load and start playing a song, switching to the Karaoke view.
+    pub(crate) fn play_song(&mut self, path: &Path) {
+        self.start_song(path, None);
+    }
+
+    /// Load `path` and play it, from `resume` when reloading the current
+    /// song, otherwise from its beginning (after any leading silence) with
+    /// the count-in.
+    fn start_song(&mut self, path: &Path, resume: Option<Duration>) {
+        let instrumental = self.cached_instrumental(path);
+        let audio = &self.config.audio;
+        let entry = self.storage.entry(path);
+        let start = resume
+            .or_else(|| {
+                let (start, _) = entry.filter(|_| audio.skip_silence)?.audible?;
+                Some(Duration::from_secs_f32(start))
+            })
+            .unwrap_or_default();
+        let count_in = (resume.is_none() && audio.count_in).then(|| {
+            let bpm = entry.and_then(|entry| entry.bpm).unwrap_or(DEFAULT_BPM);
+            generator::create_count_in(bpm, audio.count_in_beats)
+        });
+
+        let Some(player) = &mut self.player else {
+            return;
+        };
+        player.set_instrumental(entry.is_some_and(|entry| entry.instrumental));
+        match player.load(path, instrumental.as_deref(), start, count_in) {
+            Ok(()) => {
+                self.status = None;
+                self.view = View::Karaoke;
+                if resume.is_none() {
+                    self.close_song_summary();
+                    self.song_started = Some(Instant::now());
+                    let singer = self
+                        .session_log
+                        .take_requester(path)
+                        .or_else(|| self.profiles.active().map(|profile| profile.name.clone()));
+                    self.current_singer = singer.clone();
+                    self.adjustments = self.stored_adjustments(path);
+                    let key = self
+                        .storage
+                        .entry(path)
+                        .and_then(|entry| entry.key)
+                        .map(|key| key.to_string());
+                    self.start_run(path, singer.clone());
+                    self.session_log.record(SessionEvent::SongStarted {
+                        song: song_title(path),
+                        singer,
+                        key,
+                    });
+                }
+                self.apply_song_settings();
+                self.detect_keys(vec![path.to_path_buf()], true);
+                self.request_waveform(path);
+                self.request_melody(path);
+                self.request_difficulty(path);
+                if resume.is_none() && self.config.recording.enabled {
+                    self.start_recording(path);
+                }
+            },
+            Err(e) => {
+                tracing::error!("{e}");
+                self.status = Some(e.to_string());
+            },
+        }
+    }
+
+    /// Record the performance of `song`, replacing any running recording.
+    pub(crate) fn start_recording(&mut self, song: &Path) {
+        self.stop_recording();
+        let Some(player) = &self.player else {
+            return;
+        };
+        let backing = Backing {
+            path: player.backing_path().unwrap_or(song).to_path_buf(),
+            start: player.get_position(),
+            gain: self.params.music_gain(),
+            vocal_removal: self.params.vocal_removal(),
+        };
+        let options = &self.config.recording;
+        let mics = if self.config.audio.duet.enabled { 2 } else { 1 };
+        match Recorder::start(
+            self.params.clone(),
+            &options.folder(),
+            &song_title(song),
+            mics,
+            options.separate_music,
+            options.include_mix,
+            options.format,
+        ) {
+            Ok(recorder) => self.recording = Some((song.to_path_buf(), backing, recorder)),
+            Err(e) => {
+                tracing::error!("{e}");
+                self.status = Some(e.to_string());
+            },
+        }
+        self.update_mic();
+    }
+
+    /// Stop recording; the files are saved in the background.
+    pub(crate) fn stop_recording(&mut self) {
+        if let Some((_, backing, mut recorder)) = self.recording.take() {
+            recorder.stop();
+            let latency = [self.mic_latency(0), self.mic_latency(1)];
+            self.saving_recordings.push((backing, latency, recorder));
+            self.update_mic();
+        }
+    }
+
+    /// End the recording with the song it records, and report saved files.
+    fn update_recording(&mut self) {
+        let current = self
+            .player
+            .as_ref()
+            .filter(|player| !player.is_finished())
+            .and_then(AudioPlayer::current_path);
+        if self
+            .recording
+            .as_ref()
+            .is_some_and(|(song, _, _)| current != Some(song.as_path()))
+        {
+            self.stop_recording();
+        }
+
+        let mut saved = Vec::new();
+        self.saving_recordings
+            .retain_mut(|(backing, latency, recorder)| match recorder.try_finish() {
+                Some(recording) => {
+                    saved.push((backing.clone(), *latency, recording));
+                    false
+                },
+                None => true,
+            });
+        for (backing, mic_latency, recording) in saved {
+            for e in &recording.errors {
+                tracing::warn!("{e}");
+            }
+            for path in recording.files() {
+                tracing::info!("Recording saved to {}", path.display());
+                self.status = Some(format!("Recording saved to {}", path.display()));
+                self.session_log
+                    .record(SessionEvent::RecordingSaved { path: path.clone() });
+            }
+            if !recording.vocals.is_empty() {
+                self.last_performance = Some(Performance {
+                    backing,
+                    vocals: recording.vocals,
+                    mic_latency,
+                    music: recording.music,
+                });
+            }
+        }
+
+        if let Some(result) = self.export_job.as_mut().and_then(ExportJob::try_finish) {
+            self.export_job = None;
+            match result {
+                Ok(path) => {
+                    tracing::info!("Performance exported to {}", path.display());
+                    self.status = Some(format!("Performance exported to {}", path.display()));
+                },
+                Err(e) => {
+                    tracing::error!("{e}");
+                    self.status = Some(e.to_string());
+                },
+            }
+        }
+    }
+
+    /// Ask where to save the last performance, and render it there mixed
+    /// with its backing track.
+    pub(crate) fn export_performance(&mut self, preset: MixPreset) {
+        let Some(performance) = self.last_performance.clone() else {
+            return;
+        };
+        let format = self.config.recording.format;
+        let name = performance
+            .vocals
+            .first()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().to_string())
+            .and_then(|stem| {
+                stem.rsplit_once(" (")
+                    .map(|(name, _)| format!("{name} (performance)"))
+            })
+            .unwrap_or_else(|| "performance".to_string());
+        let Some(output) = rfd::FileDialog::new()
+            .set_directory(self.config.recording.folder())
+            .set_file_name(format!("{name}.{}", format.extension()))
+            .add_filter(format.label(), &[format.extension()])
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(format!("Exporting to {}…", output.display()));
+        self.export_job = Some(ExportJob::start(performance, output, format, preset));
+    }
+
+    /// Hold a close request until the audio has faded out and everything is
+    /// saved: stop recording, cancel background jobs and save the state,
+    /// then close once the recordings and cancelled jobs have finished.
+    fn update_shutdown(&mut self, ctx: &egui::Context) {
+        if ctx.input(|i| i.viewport().close_requested()) && !self.shutdown_done() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            if self.shutdown.is_none() {
+                self.begin_shutdown();
+            }
+        }
+        let Some(started) = self.shutdown else {
+            return;
+        };
+
+        let fade = 1.0 - started.elapsed().as_secs_f32() / SHUTDOWN_FADE.as_secs_f32();
+        if let Some(player) = &self.player {
+            player.set_volume(self.config.audio.master_volume * fade.max(0.0));
+            if fade <= 0.0 {
+                player.pause();
+            }
+        }
+        if fade <= 0.0 {
+            self.mic = None;
+            self.duet_mic = None;
+        }
+        if self.shutdown_done() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    fn begin_shutdown(&mut self) {
+        tracing::info!("Shutting down");
+        self.shutdown = Some(Instant::now());
+        self.stop_recording();
+        if let Some(job) = &self.lyrics_fetch {
+            job.cancel();
+        }
+        if let Some(job) = &self.difficulty_job {
+            job.cancel();
+        }
+        if let Some(detection) = &self.key_detection {
+            detection.cancel();
+        }
+        if let Some(migration) = &self.data_migration {
+            migration.cancel();
+        }
+        self.config.display.show_diagnostics = self.show_diagnostics;
+        self.save_config();
+        self.save_library();
+    }
+
+    /// What quitting still waits for, for the overlay.
+    fn shutdown_pending(&self) -> Vec<&'static str> {
+        let mut pending = Vec::new();
+        if !self.saving_recordings.is_empty() {
+            pending.push("Finishing recordings");
+        }
+        if self.export_job.is_some() {
+            pending.push("Exporting the performance");
+        }
+        if self.data_migration.is_some() {
+            pending.push("Removing the unfinished data copy");
+        }
+        if self.key_detection.is_some()
+            || self.difficulty_job.is_some()
+            || self.lyrics_fetch.is_some()
+        {
+            pending.push("Stopping background analysis");
+        }
+        pending
+    }
+
+    /// Whether the window may close: not quitting yet counts as done, so
+    /// the first close request starts the shutdown.
+    fn shutdown_done(&self) -> bool {
+        let Some(started) = self.shutdown else {
+            return false;
+        };
+        let elapsed = started.elapsed();
+        elapsed >= SHUTDOWN_TIMEOUT
+            || (elapsed >= SHUTDOWN_FADE && self.shutdown_pending().is_empty())
+    }
+
+    /// Dim the window with what is still being saved on top.
+    fn shutdown_overlay(&self, ctx: &egui::Context) {
+        let dim = egui::LayerId::new(egui::Order::Foreground, egui::Id::new("shutdown_dim"));
+        ctx.layer_painter(dim).rect_filled(
+            ctx.screen_rect(),
+            0.0,
+            egui::Color32::from_black_alpha(160),
+        );
+        egui::Area::new(egui::Id::new("shutdown_overlay"))
+            .order(egui::Order::Tooltip)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.heading("Saving…");
+                    });
+                    for step in self.shutdown_pending() {
+                        ui.weak(step);
+                    }
+                });
+            });
+    }
+
+    /// Whether something is writing into the data directory that a move
+    /// would miss: recordings and downloads.
+    pub(crate) fn data_in_use(&self) -> bool {
+        self.recording.is_some()
+            || !self.saving_recordings.is_empty()
+            || self.downloads.iter().any(DownloadJob::is_running)
+    }
+
+    /// Start moving the data directory to `to`.
+    pub(crate) fn start_data_migration(&mut self, to: PathBuf) {
+        if let Err(e) = migration::check_target(&config::data_dir(), &to) {
+            self.status = Some(format!("{e:#}"));
+            return;
+        }
+        tracing::info!("Moving the data to {}", to.display());
+        self.moved_data = None;
+        self.data_migration = Some(DataMigration::start(to));
+    }
+
+    /// Switch over to the new data directory once everything was copied
+    /// and checked, saving the library and settings there again so nothing
+    /// changed during the copy is lost.
+    fn update_data_migration(&mut self) {
+        let Some(migration) = &mut self.data_migration else {
+            return;
+        };
+        let Some(result) = migration.try_finish() else {
+            return;
+        };
+        let (from, to) = (migration.from().to_path_buf(), migration.to().to_path_buf());
+        self.data_migration = None;
+        let switched = result.and_then(|files| {
+            config::set_data_dir(&to)?;
+            Ok(files)
+        });
+        match switched {
+            Ok(files) => {
+                tracing::info!("Data moved to {}", to.display());
+                self.relocate_stored_songs(&from, &to);
+                self.save_config();
+                self.save_library();
+                self.session_log.relocate();
+                self.status = Some(format!("Data moved to {}", to.display()));
+                self.moved_data = Some((from, files));
+            },
+            Err(e) => {
+                tracing::error!("Moving the data failed: {e:#}");
+                self.status = Some(format!("Moving the data failed: {e:#}"));
+            },
+        }
+    }
+
+    /// Point the songs of the content store at its copy in the data
+    /// directory `to`.
+    fn relocate_stored_songs(&mut self, from: &Path, to: &Path) {
+        let Ok(relative) = content_store::songs_dir()
+            .strip_prefix(to)
+            .map(Path::to_path_buf)
+        else {
+            return;
+        };
+        let (old, new) = (from.join(&relative), to.join(&relative));
+        self.storage.relocate(&old, &new);
+        for queued in &mut self.queue {
+            if let Ok(rest) = queued.strip_prefix(&old) {
+                *queued = new.join(rest);
+            }
+        }
+    }
+
+    /// Copy `files` into the content store and add them to the library.
+    pub(crate) fn import_songs(&mut self, files: Vec<PathBuf>) {
+        if self.import_job.is_none() && !files.is_empty() {
+            self.import_job = Some(ImportJob::start(files, self.storage.stored_contents()));
+        }
+    }
+
+    /// Add the imported songs once the import has run.
+    fn update_import(&mut self) {
+        let Some(result) = self.import_job.as_mut().and_then(ImportJob::try_finish) else {
+            return;
+        };
+        self.import_job = None;
+        let results = match result {
+            Ok(results) => results,
+            Err(e) => {
+                tracing::error!("{e:#}");
+                self.status = Some(format!("{e:#}"));
+                return;
+            },
+        };
+        let mut songs = Vec::new();
+        let mut deduplicated = 0;
+        let mut failed = 0;
+        for result in results {
+            match result {
+                Ok(imported) => {
+                    deduplicated += usize::from(imported.deduplicated);
+                    self.storage.entry_mut(&imported.path).content = Some(imported.content);
+                    songs.push(imported.path);
+                },
+                Err(e) => {
+                    tracing::warn!("Import failed: {e:#}");
+                    failed += 1;
+                },
+            }
+        }
+        self.status = Some(match failed {
+            0 => format!(
+                "Imported {} songs, {deduplicated} of them stored already",
+                songs.len()
+            ),
+            _ => format!(
+                "Imported {} songs; {failed} failed, see the log",
+                songs.len()
+            ),
+        });
+        self.detect_languages();
+        self.detect_keys(songs, false);
+        self.save_library();
+    }
+
+    /// Give `song` the file name `name`, keeping its extension.
+    pub(crate) fn rename_song(&mut self, song: &Path, name: &str) -> anyhow::Result<()> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(['/', '\\']) {
+            anyhow::bail!("\"{name}\" is not a valid file name");
+        }
+        let playing = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .is_some_and(|current| current == song);
+        if playing {
+            anyhow::bail!("Stop the song before renaming it");
+        }
+        let mut file_name = name.to_string();
+        if let Some(extension) = song.extension() {
+            file_name = format!("{file_name}.{}", extension.to_string_lossy());
+        }
+        let renamed = song.with_file_name(file_name);
+        self.storage.rename_song(song, &renamed)?;
+        for queued in &mut self.queue {
+            if queued == song {
+                *queued = renamed.clone();
+            }
+        }
+        if self.selected_songs.remove(song) {
+            self.selected_songs.insert(renamed);
+        }
+        self.save_library();
+        Ok(())
+    }
+
+    /// Start the next queued song when the current one ends, and preload it
+    /// near the end of the current one so the transition is instant.
+    fn update_queue(&mut self) {
+        self.update_announcement();
+        let next = self.queue.front().cloned();
+        let audible_end = self.audible_range().map(|(_, end)| end);
+        let Some(player) = &mut self.player else {
+            return;
+        };
+
+        if player.is_finished() {
+            if self.announcement.is_some() {
+                return;
+            }
+            let song = player.current_path().map(Path::to_path_buf);
+            if self.song_summary.is_none()
+                && self.config.display.song_summary
+                && self.view == View::Karaoke
+            {
+                self.song_summary = song.map(|song| SongSummary {
+                    song,
+                    singer: self.current_singer.clone(),
+                    time_sung: self
+                        .song_started
+                        .map(|started| started.elapsed())
+                        .unwrap_or_default(),
+                });
+            }
+            if self.song_summary.is_none() {
+                self.start_next_queued();
+            }
+            return;
+        }
+
+        if audible_end.is_some_and(|end| player.get_position() >= end) {
+            player.skip_to_end();
+            return;
+        }
+
+        let (Some(next), Some(duration)) = (next, player.duration()) else {
+            return;
+        };
+        if player.get_position().as_secs_f32() >= duration.as_secs_f32() * PRELOAD_AT {
+            player.preload(&next);
+        }
+    }
+
+    /// Start the first queued song, after announcing it when the announcer
+    /// is on.
+    pub(crate) fn start_next_queued(&mut self) {
+        let Some(next) = self.queue.pop_front() else {
+            return;
+        };
+        if !self.config.announcer.enabled || !self.announce(&next) {
+            self.play_song(&next);
+        }
+        self.precache_upcoming();
+    }
+
+    /// Start announcing `song` as next up. Returns false when it cannot be
+    /// spoken, and the song should just start.
+    fn announce(&mut self, song: &Path) -> bool {
+        let singer = self
+            .session_log
+            .requester(song)
+            .map(str::to_string)
+            .or_else(|| self.profiles.active().map(|profile| profile.name.clone()));
+        let text = announcer::next_up(singer.as_deref(), &display_title(&self.storage, song));
+        match Announcement::speak(&text, self.config.announcer.voice.as_deref()) {
+            Ok(announcement) => {
+                self.announcement = Some((Some(song.to_path_buf()), announcement));
+                true
+            },
+            Err(e) => {
+                tracing::warn!("{e:#}");
+                self.status = Some(format!("Cannot announce the next song: {e:#}"));
+                false
+            },
+        }
+    }
+
+    /// Start the announced song once its announcement has been spoken. If
+    /// another song was started meanwhile, the announced one goes back to
+    /// the front of the queue.
+    fn update_announcement(&mut self) {
+        let Some((song, announcement)) = &mut self.announcement else {
+            return;
+        };
+        if !announcement.is_finished() {
+            return;
+        }
+        let song = song.take();
+        self.announcement = None;
+        let Some(song) = song else {
+            return;
+        };
+        if self.player.as_ref().is_some_and(AudioPlayer::is_finished) {
+            self.play_song(&song);
+        } else {
+            self.queue.push_front(song);
+        }
+    }
+
+    /// Audible part of the current song when silence skipping is on and the
+    /// song has been analysed.
+    fn audible_range(&self) -> Option<(Duration, Duration)> {
+        if !self.config.audio.skip_silence {
+            return None;
+        }
+        let song = self.player.as_ref()?.current_path()?;
+        let (start, end) = self.storage.entry(song)?.audible?;
+        Some((Duration::from_secs_f32(start), Duration::from_secs_f32(end)))
+    }
+
+    /// Jump over the leading silence of the current song if it has not been
+    /// passed yet.
+    fn skip_leading_silence(&mut self) {
+        let (Some((start, _)), Some(player)) = (self.audible_range(), &self.player) else {
+            return;
+        };
+        if player.get_position() < start && !player.is_counting_in() {
+            if let Err(e) = player.seek(start) {
+                tracing::warn!("{e}");
+            }
+        }
+    }
+
+    /// The separated instrumental of `song`, if there is one. It is loaded
+    /// next to the original so the two can be switched at any moment.
+    fn cached_instrumental(&self, song: &Path) -> Option<PathBuf> {
+        Some(separation::instrumental_path(song)).filter(|path| path.exists())
+    }
+
+    /// Reload the current song at the same position, e.g. after switching
+    /// between the original and the instrumental.
+    pub(crate) fn reload_song(&mut self) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        let Some(path) = player.current_path().map(Path::to_path_buf) else {
+            return;
+        };
+        let position = player.get_position();
+        self.start_song(&path, Some(position));
+    }
+
+    pub(crate) fn warmup_running(&self) -> bool {
+        self.warmup
+            .as_ref()
+            .is_some_and(|session| !session.is_finished())
+    }
+
+    /// Latency offset set for the device of mic `slot`, zero while it is
+    /// closed.
+    pub(crate) fn mic_latency(&self, slot: usize) -> Duration {
+        let mic = if slot == 0 { &self.mic } else { &self.duet_mic };
+        mic.as_ref().map_or(Duration::ZERO, |mic| {
+            self.config.audio.mic_latency_offset(mic.device_name())
+        })
+    }
+
+    /// Score the running warm-up, releasing the mic once it ends.
+    fn update_warmup(&mut self) {
+        let pitches = [self.params.mic(0).pitch(), self.params.mic(1).pitch()];
+        let latency = [self.mic_latency(0), self.mic_latency(1)];
+        match &mut self.warmup {
+            Some(session) if !session.is_finished() => session.update(&pitches, &latency),
+            _ if self.params.pitch_tracking() && self.run.is_none() => {
+                if let Some(session) = &self.warmup {
+                    let accuracy = (0..session.singers)
+                        .filter_map(|singer| session.total_score(singer).accuracy())
+                        .collect();
+                    self.session_log.record(SessionEvent::WarmupScored {
+                        exercise: session.exercise.label().to_string(),
+                        accuracy,
+                    });
+                }
+                self.params.set_pitch_tracking(false);
+                self.update_mic();
+            },
+            _ => {},
+        }
+    }
+
+    /// Score the song starting to play when a mic is open; `singer` sings on
+    /// the first mic.
+    fn start_run(&mut self, song: &Path, singer: Option<String>) {
+        self.run = None;
+        if self.mic.is_none() {
+            return;
+        }
+        let mut singers = vec![singer.unwrap_or_else(|| "Singer 1".to_string())];
+        if self.duet_mic.is_some() {
+            singers.push("Singer 2".to_string());
+        }
+        self.run = Some(KaraokeRun::new(song, singers));
+        self.params.set_pitch_tracking(true);
+    }
+
+    /// Score the sung pitch against the melody, and show the results once
+    /// the song has played to the end. Runs before the queue moves on.
+    fn update_run(&mut self) {
+        let pitches = [self.params.mic(0).pitch(), self.params.mic(1).pitch()];
+        let latency = [self.mic_latency(0), self.mic_latency(1)];
+        let key_shift = self.key_shift();
+        let (Some(run), Some(player)) = (&mut self.run, &self.player) else {
+            return;
+        };
+        if player.current_path() != Some(run.song()) {
+            self.end_run();
+            return;
+        }
+        if player.is_finished() {
+            self.finish_run();
+            return;
+        }
+        if !player.is_playing() || player.is_counting_in() {
+            return;
+        }
+        if let Some((_, melody)) = self.melody.as_ref().filter(|(song, _)| song == run.song()) {
+            run.update(melody, key_shift, player.get_position(), pitches, latency);
+        }
+    }
+
+    /// Stop scoring without a result.
+    fn end_run(&mut self) -> Option<KaraokeRun> {
+        self.params.set_pitch_tracking(self.warmup_running());
+        self.run.take()
+    }
+
+    /// Keep the results of the run with the song and show them.
+    fn finish_run(&mut self) {
+        let Some(run) = self.end_run() else {
+            return;
+        };
+        let song = run.song().to_path_buf();
+        let entry = self.storage.entry_mut(&song);
+        let Some(mut singers) = run.finish(&entry.sections) else {
+            return;
+        };
+        let previous_best = entry.best_result().map(|best| best.score);
+        for singer in &mut singers {
+            singer.previous_best = previous_best;
+            entry.add_result(singer.result.clone());
+        }
+        for singer in &singers {
+            self.session_log.record(SessionEvent::SongScored {
+                song: song_title(&song),
+                singer: singer.result.singer.clone(),
+                score: singer.result.score,
+            });
+        }
+        self.save_library();
+        self.run_results = Some(RunResults { song, singers });
+    }
+
+    /// Load the waveform of `song` in the background unless it is shown already.
+    fn request_waveform(&mut self, song: &Path) {
+        let shown = self.waveform.as_ref().is_some_and(|(path, _)| path == song);
+        let loading = self
+            .waveform_job
+            .as_ref()
+            .is_some_and(|job| job.song() == song);
+        if !shown && !loading {
+            self.waveform = None;
+            self.waveform_job = Some(WaveformJob::start(song));
+        }
+    }
+
+    fn update_waveform(&mut self) {
+        let Some(job) = &mut self.waveform_job else {
+            return;
+        };
+        let Some(result) = job.try_finish() else {
+            return;
+        };
+        let song = job.song().to_path_buf();
+        self.waveform_job = None;
+        match result {
+            Ok(waveform) => {
+                let entry = self.storage.entry_mut(&song);
+                if entry.audible.is_none() {
+                    entry.audible = waveform
+                        .audible_range()
+                        .map(|(start, end)| (start.as_secs_f32(), end.as_secs_f32()));
+                    self.save_library();
+                }
+                self.waveform = Some((song, waveform));
+                self.skip_leading_silence();
+            },
+            Err(e) => tracing::warn!("{e}"),
+        }
+    }
+
+    /// Track the melody of `song` in the background unless it is known
+    /// already.
+    pub(crate) fn request_melody(&mut self, song: &Path) {
+        let shown = self.melody.as_ref().is_some_and(|(path, _)| path == song);
+        let loading = self
+            .melody_job
+            .as_ref()
+            .is_some_and(|job| job.song() == song);
+        if !shown && !loading {
+            self.melody = None;
+            self.melody_job = Some(MelodyJob::start(song));
+        }
+    }
+
+    fn update_melody(&mut self) {
+        let Some(job) = &mut self.melody_job else {
+            return;
+        };
+        let Some(result) = job.try_finish() else {
+            return;
+        };
+        let song = job.song().to_path_buf();
+        self.melody_job = None;
+        match result {
+            Ok(melody) => self.melody = Some((song, melody)),
+            Err(e) => tracing::warn!("{e}"),
+        }
+    }
+
+    /// Report the outcome of a lyrics lookup once it ends.
+    fn update_lyrics_fetch(&mut self) {
+        let Some(progress) = self
+            .lyrics_fetch
+            .as_mut()
+            .and_then(LyricsFetchJob::try_finish)
+        else {
+            return;
+        };
+        self.lyrics_fetch = None;
+        for (song, provider) in &progress.supplied {
+            self.storage.entry_mut(song).lyrics_provider = Some(*provider);
+        }
+        self.detect_languages();
+        if !progress.supplied.is_empty() {
+            self.save_library();
+        }
+        let mut status = format!(
+            "Lyrics found for {} of {} songs",
+            progress.found, progress.total
+        );
+        if progress.cached > 0 {
+            status += &format!(", {} answered from the cache", progress.cached);
+        }
+        if progress.failed > 0 {
+            status += &format!(", {} failed", progress.failed);
+        }
+        if progress.manual > 0 {
+            status += &format!(", {} left for manual entry", progress.manual);
+        }
+        self.status = Some(status);
+    }
+
+    /// Rate `path` when it has lyrics but no rating, unless a rating job
+    /// is already running.
+    fn request_difficulty(&mut self, path: &Path) {
+        let rated = self
+            .storage
+            .entry(path)
+            .is_some_and(|entry| entry.difficulty.is_some());
+        if !rated && self.difficulty_job.is_none() {
+            self.difficulty_job = Some(DifficultyJob::start(vec![path.to_path_buf()]));
+        }
+    }
+
+    /// Store the ratings finished by the difficulty job.
+    fn update_difficulty(&mut self) {
+        let Some(job) = &mut self.difficulty_job else {
+            return;
+        };
+        let finished = job.is_finished();
+        let results = job.take_results();
+        if finished {
+            self.difficulty_job = None;
+        }
+        if results.is_empty() {
+            return;
+        }
+        for (song, difficulty) in results {
+            self.storage.entry_mut(&song).difficulty = Some(difficulty);
+        }
+        self.save_library();
+    }
+
+    /// Finish downloads whose pipeline ended: the library import step runs
+    /// here because it changes the library.
+    fn update_downloads(&mut self) {
+        let mut imported = false;
+        for job in &mut self.downloads {
+            let Some(state) = job.try_finish() else {
+                continue;
+            };
+            let Some(file) = state
+                .file
+                .filter(|_| self.config.downloads.import_to_library)
+            else {
+                continue;
+            };
+            let entry = self.storage.entry_mut(&file);
+            if let Some(offset) = state.volume_offset_db {
+                entry.volume_offset_db = offset;
+            }
+            if let Some(provider) = state.lyrics_provider {
+                entry.lyrics_provider = Some(provider);
+            }
+            job.set_step(
+                PipelineStep::LibraryImport,
+                StepStatus::Done(song_title(&file)),
+            );
+            imported = true;
+        }
+        if imported {
+            self.detect_languages();
+            self.save_library();
+        }
+    }
+
+    /// Guess the language of the songs not detected yet, in the
+    /// background. While a detection runs, another follows it.
+    pub(crate) fn detect_languages(&mut self) {
+        if self.language_job.is_some() {
+            self.languages_stale = true;
+            return;
+        }
+        let pending: Vec<(PathBuf, Option<SystemTime>)> = self
+            .storage
+            .songs()
+            .filter_map(|path| {
+                let entry = self.storage.entry(path)?;
+                entry
+                    .detected_language
+                    .is_none()
+                    .then(|| (path.to_path_buf(), entry.language_checked))
+            })
+            .collect();
+        if !pending.is_empty() {
+            self.language_job = Some(LanguageJob::start(pending));
+        }
+    }
+
+    /// Store the languages found by the detection job.
+    fn update_languages(&mut self) {
+        let Some(detections) = self.language_job.as_mut().and_then(LanguageJob::try_finish) else {
+            return;
+        };
+        self.language_job = None;
+        if !detections.is_empty() {
+            for (song, detection) in detections {
+                let entry = self.storage.entry_mut(&song);
+                match detection {
+                    Ok(language) => entry.detected_language = Some(language.to_string()),
+                    Err(checked) => entry.language_checked = Some(checked),
+                }
+            }
+            self.save_library();
+            self.publish_library();
+        }
+        if std::mem::take(&mut self.languages_stale) {
+            self.detect_languages();
+        }
+    }
+
+    /// Queue key detection of the `songs` whose key is not known yet,
+    /// ahead of the songs already queued when `first`.
+    pub(crate) fn detect_keys(&mut self, songs: Vec<PathBuf>, first: bool) {
+        let songs: Vec<PathBuf> = songs
+            .into_iter()
+            .filter(|song| {
+                self.storage
+                    .entry(song)
+                    .is_none_or(|entry| entry.key.is_none())
+            })
+            .collect();
+        if songs.is_empty() {
+            return;
+        }
+        let queued = self
+            .key_detection
+            .as_ref()
+            .is_some_and(|detection| detection.add(&songs, first));
+        if !queued {
+            self.key_detection = Some(KeyDetection::start(songs));
+        }
+    }
+
+    /// Store the keys found by the key detection, applying the current
+    /// song's to autotune.
+    fn update_key_detection(&mut self) {
+        let Some(detection) = &mut self.key_detection else {
+            return;
+        };
+        let finished = detection.is_finished();
+        let results = detection.take_results();
+        if finished {
+            self.key_detection = None;
+        }
+        if results.is_empty() {
+            return;
+        }
+        let current = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .map(Path::to_path_buf);
+        for (song, key) in results {
+            if current.as_ref() == Some(&song) {
+                tracing::info!("{} is in {key}", song_title(&song));
+                self.session_log.record(SessionEvent::KeyDetected {
+                    song: song_title(&song),
+                    key: key.to_string(),
+                });
+            }
+            self.storage.entry_mut(&song).key = Some(key);
+        }
+        self.save_library();
+        self.apply_song_settings();
+    }
+
+    /// Collect finished separations, switching the current song over when
+    /// its instrumental was the one waited for.
+    fn update_separation(&mut self) {
+        for outcome in self.separator.poll() {
+            match outcome.result {
+                Ok(_) => {
+                    self.status =
+                        Some(format!("Instrumental ready: {}", song_title(&outcome.song)));
+                    let is_current = self
+                        .player
+                        .as_ref()
+                        .and_then(AudioPlayer::current_path)
+                        .is_some_and(|path| path == outcome.song);
+                    let wanted = self
+                        .storage
+                        .entry(&outcome.song)
+                        .is_some_and(|entry| entry.instrumental);
+                    if is_current && wanted {
+                        self.reload_song();
+                    }
+                },
+                Err(e) => {
+                    self.status = Some(format!("{}: {e}", song_title(&outcome.song)));
+                },
+            }
+        }
+    }
+
+    /// Push the settings of the current song (with its overrides) to the engine.
+    pub(crate) fn apply_song_settings(&self) {
+        let audio = &self.config.audio;
+        let default_strength = if audio.vocal_removal {
+            audio.vocal_removal_strength
+        } else {
+            0.0
+        };
+        let entry = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .and_then(|path| self.storage.entry(path));
+
+        let vocal_removal = entry
+            .and_then(|entry| entry.vocal_removal)
+            .unwrap_or(default_strength);
+        self.params.set_vocal_removal(vocal_removal);
+        self.params.set_music_gain_db(
+            audio.music_level_db + entry.map_or(0.0, |entry| entry.volume_offset_db),
+        );
+
+        // Pitch correction snaps to the key the music is heard in
+        let key_shift = self.key_shift();
+        self.params.set_key_shift(key_shift);
+        self.params.set_song_key(
+            entry
+                .and_then(|entry| entry.key)
+                .map(|key| key.transposed(key_shift)),
+        );
+
+        if let Some(player) = &self.player {
+            player.set_speed(self.adjustments.playback_rate.unwrap_or(1.0));
+        }
+
+        let voice_effects = entry
+            .and_then(|entry| entry.voice_effects.as_deref())
+            .unwrap_or(&audio.voice_effects);
+        self.params.mic(0).set_voice_effects(voice_effects);
+        self.params
+            .mic(1)
+            .set_voice_effects(&audio.duet.voice_effects);
+    }
+
+    /// Adjustments kept for `song` under the adjustment policy, for the
+    /// current singer.
+    fn stored_adjustments(&self, song: &Path) -> SongAdjustments {
+        match self.config.audio.adjustments {
+            AdjustmentPolicy::PerSong => {
+                let entry = self.storage.entry(song);
+                SongAdjustments {
+                    playback_rate: entry.and_then(|entry| entry.playback_rate),
+                    key_shift: entry.and_then(|entry| entry.key_shift),
+                    lyric_offset_ms: entry.map_or(0, |entry| entry.lyric_offset_ms),
+                }
+            },
+            AdjustmentPolicy::PerSinger => self
+                .current_singer
+                .as_deref()
+                .and_then(|name| self.profiles.get(name))
+                .and_then(|profile| profile.adjustments.get(song))
+                .cloned()
+                .unwrap_or_default(),
+            AdjustmentPolicy::Discard => SongAdjustments::default(),
+        }
+    }
+
+    /// Apply the current song's adjustments after they changed, and keep
+    /// them where the adjustment policy says.
+    pub(crate) fn adjustments_changed(&mut self) {
+        self.apply_song_settings();
+        let Some(song) = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .map(Path::to_path_buf)
+        else {
+            return;
+        };
+        let adjustments = self.adjustments.clone();
+        match self.config.audio.adjustments {
+            AdjustmentPolicy::PerSong => {
+                let entry = self.storage.entry_mut(&song);
+                entry.playback_rate = adjustments.playback_rate;
+                entry.key_shift = adjustments.key_shift;
+                entry.lyric_offset_ms = adjustments.lyric_offset_ms;
+                self.save_library();
+            },
+            AdjustmentPolicy::PerSinger => {
+                let Some(profile) = self
+                    .current_singer
+                    .as_deref()
+                    .and_then(|name| self.profiles.get_mut(name))
+                else {
+                    return;
+                };
+                if adjustments.is_default() {
+                    profile.adjustments.remove(&song);
+                } else {
+                    profile.adjustments.insert(song, adjustments);
+                }
+                self.save_profiles();
+            },
+            AdjustmentPolicy::Discard => {},
+        }
+    }
+
+    /// Semitones the current song is shifted from its key: as adjusted, or
+    /// else the key the singer prefers.
+    pub(crate) fn key_shift(&self) -> i8 {
+        self.adjustments.key_shift.unwrap_or_else(|| {
+            self.current_singer
+                .as_deref()
+                .and_then(|name| self.profiles.get(name))
+                .map_or(0, |profile| profile.key_offset)
+        })
+    }
+
+    /// Where adjustments to the current song end up, for the indicator next
+    /// to the controls making them.
+    pub(crate) fn adjustments_destination(&self) -> String {
+        match (self.config.audio.adjustments, &self.current_singer) {
+            (AdjustmentPolicy::PerSinger, Some(singer)) => format!("Saved for {singer}"),
+            (AdjustmentPolicy::PerSinger, None) => "Reset after the song (no singer)".to_string(),
+            (policy, _) => policy.label().to_string(),
+        }
+    }
+
+    /// Persist the singer profiles, reporting failures in the status line.
+    pub(crate) fn save_profiles(&mut self) {
+        if let Err(e) = self.profiles.save() {
+            tracing::error!("{e:#}");
+            self.status = Some(format!("Failed to save singer profiles: {e}"));
+        }
+    }
+
+    /// Queue `song`, for the active singer profile if there is one.
+    pub(crate) fn enqueue(&mut self, song: &Path) {
+        if let Some(profile) = self.profiles.active() {
+            let name = profile.name.clone();
+            self.session_log
+                .record_request(song, &name, song_title(song));
+        }
+        self.queue.push_back(song.to_path_buf());
+        self.precache_upcoming();
+    }
+
+    /// Keep the queue after it was edited, and decode what is now next.
+    pub(crate) fn queue_changed(&mut self) {
+        self.save_library();
+        self.precache_upcoming();
+    }
+
+    /// Decode the next queued songs and their instrumentals into memory
+    /// ahead, when they are short enough, so they seek and restart
+    /// instantly. Songs already decoded or being decoded are skipped.
+    pub(crate) fn precache_upcoming(&self) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        let audio = &self.config.audio;
+        for song in self.queue.iter().take(PRECACHE_AHEAD) {
+            let instrumental = self.cached_instrumental(song);
+            for path in std::iter::once(song.as_path()).chain(instrumental.as_deref()) {
+                player.precache(path, audio.precache_song_mb, audio.precache_total_mb);
+            }
+        }
+    }
+
+    /// Persist the library, reporting failures in the status line.
+    pub(crate) fn save_library(&mut self) {
+        self.storage.set_queue(self.queue.iter().cloned());
+        if let Err(e) = self.storage.save() {
+            tracing::error!("{e:#}");
+            self.status = Some(format!("Failed to save library: {e}"));
+        }
+        self.publish_library();
+    }
+
+    /// Start or stop the web remote to match the settings.
+    pub(crate) fn update_remote_server(&mut self) {
+        let remote = &self.config.remote;
+        let running_on = self.remote.as_ref().map(RemoteServer::port);
+        if !remote.enabled {
+            self.remote = None;
+            return;
+        }
+        if running_on == Some(remote.port) {
+            return;
+        }
+
+        // Free the old port first when only the port changed
+        self.remote = None;
+        match RemoteServer::start(remote.port) {
+            Ok(server) => {
+                self.remote = Some(server);
+                self.publish_library();
+            },
+            Err(e) => {
+                tracing::error!("{e}");
+                self.status = Some(e.to_string());
+            },
+        }
+    }
+
+    /// Give the web remote the current song list.
+    fn publish_library(&self) {
+        if let Some(remote) = &self.remote {
+            remote.state().songs = self
+                .storage
+                .songs()
+                .map(|path| {
+                    let language = self
+                        .storage
+                        .entry(path)
+                        .and_then(SongEntry::language)
+                        .map(str::to_string);
+                    RemoteSong::new(path, song_title(path), language)
+                })
+                .collect();
+        }
+    }
+
+    /// Log what the player did since the last frame.
+    fn update_session_log(&mut self) {
+        let state = self.player.as_ref().and_then(|player| {
+            Some(PlaybackState {
+                song: player.current_path()?,
+                playing: player.is_playing(),
+                finished: player.is_finished(),
+                position: player.get_position(),
+            })
+        });
+        self.session_log.observe(state, song_title);
+    }
+
+    /// Queue the guests' requests and publish the queue back to them.
+    fn update_remote(&mut self) {
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let (requests, votes) = {
+            let mut state = remote.state();
+            (
+                std::mem::take(&mut state.requests),
+                std::mem::take(&mut state.votes),
+            )
+        };
+        let mut changed = false;
+        for request in requests {
+            self.status = Some(format!(
+                "{} requested {}",
+                request.guest,
+                song_title(&request.path)
+            ));
+            self.session_log.record_request(
+                &request.path,
+                &request.guest,
+                song_title(&request.path),
+            );
+            self.queue.push_back(request.path);
+            changed = true;
+        }
+        for vote in votes {
+            if self.queue_votes.add(&vote.path, &vote.voter) {
+                self.status = Some(format!(
+                    "{} voted for {}",
+                    vote.guest,
+                    song_title(&vote.path)
+                ));
+                changed = true;
+            }
+        }
+        self.queue_votes.retain_queued(&self.queue);
+        if changed && self.config.remote.sort_queue_by_votes {
+            self.queue = votes::fair_order(&self.queue, &self.queue_votes, |song| {
+                self.session_log.requester(song)
+            });
+        }
+        if changed {
+            self.queue_changed();
+        }
+        let Some(remote) = &self.remote else {
+            return;
+        };
+        let mut state = remote.state();
+        state.queue = self
+            .queue
+            .iter()
+            .map(|path| QueuedSong {
+                id: song_id(path),
+                path: path.clone(),
+                title: song_title(path),
+                voters: self.queue_votes.voters(path).map(str::to_string).collect(),
+            })
+            .collect();
+        state.now_playing = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .map(song_title);
+    }
+
+    /// Persist the config, reporting failures in the status line.
+    pub(crate) fn save_config(&mut self) {
+        if let Err(e) = self.config.save() {
+            tracing::error!("{e:#}");
+            self.status = Some(format!("Failed to save settings: {e}"));
+        }
+    }
+
+    fn top_panel(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.heading("PWE Karaoke");
+                ui.separator();
+                ui.selectable_value(&mut self.view, View::Library, "📚 Library");
+                ui.selectable_value(&mut self.view, View::Karaoke, "🎤 Karaoke");
+                ui.selectable_value(&mut self.view, View::WarmUp, "🎵 Warm-up");
+                ui.selectable_value(&mut self.view, View::Practice, "🎸 Practice");
+                ui.selectable_value(&mut self.view, View::Sessions, "🕒 Sessions");
+                ui.selectable_value(&mut self.view, View::Editor, "✏ Lyrics");
+                ui.selectable_value(&mut self.view, View::Settings, "⚙ Settings");
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.toggle_value(&mut self.show_diagnostics, "🩺")
+                        .on_hover_text("Diagnostics HUD (F3)");
+                    if ui
+                        .toggle_value(&mut self.config.singer_screen.enabled, "📺")
+                        .on_hover_text("Singer screen: lyrics on a second display")
+                        .changed()
+                    {
+                        self.save_config();
+                    }
+                    if ui
+                        .toggle_value(&mut self.config.display.show_queue_panel, "📋")
+                        .on_hover_text("Queue sidebar")
+                        .changed()
+                    {
+                        self.config.display.layout = None;
+                        self.save_config();
+                    }
+
+                    self.layout_menu(ui);
+                    self.profile_picker(ui);
+
+                    let mode = self.config.display.theme_mode;
+                    if ui
+                        .button(mode.icon())
+                        .on_hover_text(format!("Theme: {}", mode.label()))
+                        .clicked()
+                    {
+                        self.config.display.theme_mode = mode.next();
+                        theme::apply_theme(ui.ctx(), &self.config.display);
+                        self.save_config();
+                    }
+
+                    self.clip_indicator(ui);
+
+                    let notches = self.params.active_notches().len();
+                    if notches > 0 {
+                        let text = egui::RichText::new(format!("🔔 Feedback: {notches} notch(es)"))
+                            .color(ui.visuals().warn_fg_color);
+                        let label = ui
+                            .add(egui::Label::new(text).sense(egui::Sense::click()))
+                            .on_hover_text("Click to open the diagnostics HUD");
+                        if label.clicked() {
+                            self.show_diagnostics = true;
+                        }
+                    }
+                });
+            });
+        });
+    }
+
+    /// Resizable queue sidebar. Its width is saved once a resize ends, and
+    /// it can be collapsed entirely from its header or the top panel.
+    fn queue_panel(&mut self, ctx: &egui::Context) {
+        if !self.config.display.show_queue_panel {
+            return;
+        }
+        let mut queue_edited = false;
+        let panel = egui::SidePanel::right(QUEUE_PANEL_ID)
+            .resizable(true)
+            .default_width(self.config.display.queue_panel_width)
+            .width_range(QUEUE_PANEL_WIDTH)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Queue");
+                    if !self.queue.is_empty() && ui.small_button("Clear").clicked() {
+                        self.queue.clear();
+                        queue_edited = true;
+                    }
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui
+                            .small_button("»")
+                            .on_hover_text("Hide the queue")
+                            .clicked()
+                        {
+                            self.config.display.show_queue_panel = false;
+                            self.config.display.layout = None;
+                        }
+                    });
+                });
+                ui.separator();
+
+                if self.queue.is_empty() {
+                    ui.weak("Queue is empty");
+                    return;
+                }
+
+                let mut remove = None;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (index, path) in self.queue.iter().enumerate() {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("✖").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.label(format!(
+                                "{}. {}",
+                                index + 1,
+                                display_title(&self.storage, path)
+                            ));
+                            self.singer_label(ui, self.session_log.requester(path));
+                            let votes = self.queue_votes.count(path);
+                            if votes > 0 {
+                                ui.weak(format!("👍 {votes}"))
+                                    .on_hover_text("Votes from guests on the web remote");
+                            }
+                        });
+                    }
+                });
+                if let Some(index) = remove {
+                    self.queue.remove(index);
+                    queue_edited = true;
+                }
+            });
+        if queue_edited {
+            self.queue_changed();
+        }
+
+        let width = panel.response.rect.width();
+        let resizing = ctx.input(|i| i.pointer.any_down());
+        let display = &mut self.config.display;
+        let resized = !resizing && (width - display.queue_panel_width).abs() >= 1.0;
+        if resized {
+            display.queue_panel_width = width;
+            display.layout = None;
+        }
+        if resized || !display.show_queue_panel {
+            self.save_config();
+        }
+    }
+
+    fn bottom_panel(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let Some(player) = &mut self.player else {
+                    ui.label("🔇 No audio output");
+                    return;
+                };
+
+                if player.is_playing() {
+                    if ui.button("⏸").clicked() {
+                        player.pause();
+                    }
+                } else if ui.button("▶").clicked() {
+                    player.play();
+                }
+                if ui
+                    .button("👏")
+                    .on_hover_text("Mark applause in the session log")
+                    .clicked()
+                {
+                    self.session_log.record(SessionEvent::Applause);
+                }
+                if ui.button("⏹").clicked() {
+                    player.stop();
+                }
+
+                let position = player.get_position();
+                let sections = player
+                    .current_path()
+                    .and_then(|path| self.storage.entry(path))
+                    .map_or(&[][..], |entry| entry.sections.as_slice());
+                let waveform = self
+                    .waveform
+                    .as_ref()
+                    .filter(|(path, _)| Some(path.as_path()) == player.current_path())
+                    .map(|(_, waveform)| waveform);
+                let duration = player
+                    .duration()
+                    .or_else(|| waveform.map(Waveform::duration));
+                match duration {
+                    Some(duration) => {
+                        let target = match waveform {
+                            Some(waveform) => {
+                                waveform_seek_bar(ui, waveform, sections, position, duration, 240.0)
+                            },
+                            None => {
+                                let mut secs = position.as_secs_f32();
+                                let seek_bar =
+                                    egui::Slider::new(&mut secs, 0.0..=duration.as_secs_f32())
+                                        .show_value(false);
+                                ui.add(seek_bar)
+                                    .changed()
+                                    .then(|| Duration::from_secs_f32(secs))
+                            },
+                        };
+                        if let Some(target) = target {
+                            if let Err(e) = player.seek(target) {
+                                self.status = Some(e.to_string());
+                            }
+                        }
+                        ui.label(format!(
+                            "{} / {}",
+                            format_time(position),
+                            format_time(duration)
+                        ));
+                    },
+                    None => {
+                        ui.label(format_time(position));
+                    },
+                }
+                if let Some(section) = sections::section_at(sections, position) {
+                    ui.colored_label(section_color(section.kind), "■")
+                        .on_hover_text("Current section");
+                    ui.strong(section.kind.label());
+                }
+
+                if let Some(path) = player.current_path() {
+                    ui.separator();
+                    ui.label(display_title(&self.storage, path));
+                }
+                ui.separator();
+                self.queue_popover(ui);
+                self.mixer_popover(ui);
+                self.ticker_popover(ui);
+
+                if let Some(status) = &self.status {
+                    ui.separator();
+                    ui.colored_label(ui.visuals().warn_fg_color, status);
+                }
+            });
+        });
+    }
+}
+
+impl eframe::App for KaraokeApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.key_pressed(egui::Key::F3)) {
+            self.show_diagnostics = !self.show_diagnostics;
+        }
+        if self.view == View::Karaoke && !ctx.wants_keyboard_input() {
+            let nudge = ctx.input(|i| {
+                let earlier = i.key_pressed(egui::Key::Plus) || i.key_pressed(egui::Key::Equals);
+                let later = i.key_pressed(egui::Key::Minus);
+                i64::from(earlier) - i64::from(later)
+            });
+            if nudge != 0 {
+                // Lyrics without timestamps have no offset; the keys set
+                // the pace of the teleprompter instead
+                match &mut self.teleprompter {
+                    Some(teleprompter) => teleprompter.nudge(nudge),
+                    None => {
+                        self.adjustments.lyric_offset_ms += nudge * LYRIC_NUDGE_MS;
+                        self.adjustments_changed();
+                    },
+                }
+            }
+        }
+
+        self.update_shutdown(ctx);
+        self.update_output();
+        self.update_run();
+        self.update_queue();
+        self.update_separation();
+        self.update_key_detection();
+        self.update_lyrics_fetch();
+        self.update_difficulty();
+        self.update_languages();
+        self.update_downloads();
+        self.update_warmup();
+        self.update_recording();
+        self.update_waveform();
+        self.update_melody();
+        self.update_remote();
+        self.update_session_log();
+        self.update_data_migration();
+        self.update_import();
+
+        // The mic test only lasts while Settings is open
+        if self.mic_test && self.view != View::Settings {
+            self.mic_test = false;
+            self.update_mic();
+        }
+        // The pitch guide only tracks the singer while it is on screen
+        let pitch_guide = self.config.display.show_pitch_guide && self.view == View::Karaoke;
+        if pitch_guide != self.params.pitch_guide() {
+            self.params.set_pitch_guide(pitch_guide);
+            self.update_mic();
+        }
+
+        self.top_panel(ctx);
+        self.bottom_panel(ctx);
+        self.queue_panel(ctx);
+
+        egui::CentralPanel::default().show(ctx, |ui| match self.view {
+            View::Library => self.library_view(ui),
+            View::Karaoke => self.karaoke_view(ui),
+            View::WarmUp => self.warmup_view(ui),
+            View::Practice => self.practice_view(ui),
+            View::Sessions => self.session_view(ui),
+            View::Editor => self.lyrics_editor_view(ui),
+            View::Settings => self.settings_view(ui),
+        });
+
+        self.lrc_import_window(ctx);
+        self.results_window(ctx);
+        self.device_export_window(ctx);
+        self.feedback_toast(ctx);
+        self.rename_window(ctx);
+        self.singer_screen(ctx);
+        if self.shutdown.is_some() {
+            self.shutdown_overlay(ctx);
+        }
+
+        if self.show_diagnostics {
+            self.diagnostics_hud(ctx);
+        } else {
+            self.spectrum = None;
+            self.last_spectrum = None;
+        }
+
+        // Saved when the slider is let go or the field left, not on every
+        // value passed on the way
+        if self.config_unsaved && ctx.dragged_id().is_none() && !ctx.wants_keyboard_input() {
+            self.config_unsaved = false;
+            self.save_config();
+        }
+
+        if self.warmup_running() || self.shutdown.is_some() {
+            ctx.request_repaint();
+        } else if self
+            .player
+            .as_ref()
+            .is_some_and(|player| player.is_playing() || player.is_finished())
+        {
+            ctx.request_repaint_after(Duration::from_millis(33));
+        } else if self.separator.is_busy()
+            || self.key_detection.is_some()
+            || self.lyrics_fetch.is_some()
+            || self.difficulty_job.is_some()
+            || self.melody_job.is_some()
+            || !self.saving_recordings.is_empty()
+            || self.export_job.is_some()
+            || self.data_migration.is_some()
+            || self.import_job.is_some()
+            || self.announcement.is_some()
+            || self.downloads.iter().any(DownloadJob::is_running)
+            || self.remote.is_some()
+            // Scheduled ticker messages come and go on their own
+            || self
+                .config
+                .ticker
+                .messages
+                .iter()
+                .any(|message| message.from.is_some() || message.until.is_some())
+        {
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
+    }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // Unfinished WAV files would be unreadable
+        self.stop_recording();
+        for (_, _, recorder) in &mut self.saving_recordings {
+            for e in recorder.wait().errors {
+                tracing::warn!("{e}");
+            }
+        }
+        self.config.display.show_diagnostics = self.show_diagnostics;
+        if let Err(e) = self.config.save() {
+            tracing::error!("{e:#}");
+        }
+    }
+}
+
+/// Open mic `slot`, on the low-latency monitor when it is on and the
+/// output device allows it, through the player's mix otherwise.
+fn open_mic(
+    player: &AudioPlayer,
+    params: &Arc<ProcessorParams>,
+    slot: usize,
+    device: Option<&str>,
+    channel: Option<u16>,
+    audio: &AudioConfig,
+) -> Result<MicInput, AudioError> {
+    if audio.low_latency_monitor {
+        match MicInput::start(
+            MonitorOutput::Direct,
+            params.clone(),
+            slot,
+            device,
+            channel,
+            audio.mic_latency_ms,
+        ) {
+            Ok(mic) => return Ok(mic),
+            Err(e) => tracing::warn!("Low-latency monitor unavailable, using the mix: {e}"),
+        }
+    }
+    MicInput::start(
+        MonitorOutput::Mix(player.mixer()),
+        params.clone(),
+        slot,
+        device,
+        channel,
+        audio.mic_latency_ms,
+    )
+}
+
+/// Format a duration as `m:ss`.
+pub fn format_time(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
+
+/// Display name for a song file (its file stem).
+pub fn song_title(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Title of `path` as drawn in lists, reordered for right-to-left scripts
+/// per the song's text direction.
+pub(crate) fn display_title(storage: &LibraryStorage, path: &Path) -> String {
+    let direction = storage
+        .entry(path)
+        .map(|entry| entry.text_direction)
+        .unwrap_or_default();
+    bidi::visual_line(&song_title(path), direction)
+}
@@ -0,0 +1,22 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// An `f32` that can be shared between the UI thread and the audio thread.
+///
+/// Stored as raw bits in an [`AtomicU32`]; relaxed ordering is enough because
+/// every value is independent (a parameter or a meter reading).
+#[derive(Debug, Default)]
+pub struct AtomicF32(AtomicU32);
+
+impl AtomicF32 {
+    pub fn new(value: f32) -> Self {
+        Self(AtomicU32::new(value.to_bits()))
+    }
+
+    pub fn load(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    pub fn store(&self, value: f32) {
+        self.0.store(value.to_bits(), Ordering::Relaxed);
+    }
+}
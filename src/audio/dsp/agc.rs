@@ -0,0 +1,60 @@
+use super::{db_to_linear, linear_to_db, time_coefficient};
+
+/// Averaging time of the level detector, about a sung syllable.
+const DETECTOR_MS: f32 = 300.0;
+/// Time to pull a loud singer down, and to bring a quiet one up.
+const ATTACK_MS: f32 = 400.0;
+const RELEASE_MS: f32 = 2500.0;
+/// Below this RMS level the signal is pauses or gated noise: the gain is
+/// held instead of raised.
+const FLOOR_DB: f32 = -50.0;
+/// Most the gain is lowered.
+const MAX_CUT_DB: f32 = -18.0;
+
+/// Automatic gain control: slowly steers a mono signal's RMS level toward a
+/// target, so quiet and loud singers end up at a similar level.
+#[derive(Debug)]
+pub struct AutoGain {
+    detector_coeff: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    floor: f32,
+    /// Mean square of the input
+    power: f32,
+    gain_db: f32,
+}
+
+impl AutoGain {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            detector_coeff: time_coefficient(DETECTOR_MS, sample_rate),
+            attack_coeff: time_coefficient(ATTACK_MS, sample_rate),
+            release_coeff: time_coefficient(RELEASE_MS, sample_rate),
+            floor: db_to_linear(FLOOR_DB),
+            power: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Current gain in dB, for display.
+    pub fn gain_db(&self) -> f32 {
+        self.gain_db
+    }
+
+    /// Process one sample, aiming for `target_db` RMS with at most
+    /// `max_gain_db` of boost.
+    pub fn process(&mut self, input: f32, target_db: f32, max_gain_db: f32) -> f32 {
+        self.power = input * input + (self.power - input * input) * self.detector_coeff;
+        let rms = self.power.sqrt();
+        if rms > self.floor {
+            let wanted = (target_db - linear_to_db(rms)).clamp(MAX_CUT_DB, max_gain_db.max(0.0));
+            let coeff = if wanted < self.gain_db {
+                self.attack_coeff
+            } else {
+                self.release_coeff
+            };
+            self.gain_db = wanted + (self.gain_db - wanted) * coeff;
+        }
+        input * db_to_linear(self.gain_db)
+    }
+}
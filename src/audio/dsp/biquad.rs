@@ -0,0 +1,80 @@
+use std::f32::consts::PI;
+
+/// Second-order IIR filter (RBJ audio EQ cookbook), transposed direct form II.
+#[derive(Debug, Clone, Copy)]
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    z1: f32,
+    z2: f32,
+}
+
+impl Biquad {
+    /// Peaking EQ boosting or cutting `gain_db` around `freq`.
+    pub fn peaking(sample_rate: u32, freq: f32, q: f32, gain_db: f32) -> Self {
+        let a = 10.0_f32.powf(gain_db / 40.0);
+        let (cos, alpha) = Self::prewarp(sample_rate, freq, q);
+        Self::normalized(
+            1.0 + alpha * a,
+            -2.0 * cos,
+            1.0 - alpha * a,
+            1.0 + alpha / a,
+            -2.0 * cos,
+            1.0 - alpha / a,
+        )
+    }
+
+    /// Second-order low-pass.
+    pub fn low_pass(sample_rate: u32, freq: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, freq, q);
+        Self::normalized(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    /// Second-order high-pass.
+    pub fn high_pass(sample_rate: u32, freq: f32, q: f32) -> Self {
+        let (cos, alpha) = Self::prewarp(sample_rate, freq, q);
+        Self::normalized(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let output = self.b0 * input + self.z1;
+        self.z1 = self.b1 * input - self.a1 * output + self.z2;
+        self.z2 = self.b2 * input - self.a2 * output;
+        output
+    }
+
+    fn prewarp(sample_rate: u32, freq: f32, q: f32) -> (f32, f32) {
+        let nyquist = sample_rate as f32 / 2.0;
+        let w0 = 2.0 * PI * freq.clamp(1.0, nyquist * 0.99) / sample_rate as f32;
+        (w0.cos(), w0.sin() / (2.0 * q.max(0.01)))
+    }
+
+    fn normalized(b0: f32, b1: f32, b2: f32, a0: f32, a1: f32, a2: f32) -> Self {
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+            z1: 0.0,
+            z2: 0.0,
+        }
+    }
+}
@@ -0,0 +1,33 @@
+use super::biquad::Biquad;
+
+/// Feedback delay (echo) for mono signals. Each repeat is low-passed so
+/// the echoes darken as they fade, like a tape delay. Returns the wet
+/// signal only.
+#[derive(Debug)]
+pub struct Delay {
+    buffer: Vec<f32>,
+    index: usize,
+    feedback: f32,
+    tone: Biquad,
+}
+
+impl Delay {
+    /// `feedback` (0.0 - 0.95) sets how many repeats are heard; 0.0 gives
+    /// a single one.
+    pub fn new(sample_rate: u32, time_ms: f32, feedback: f32, tone_hz: f32) -> Self {
+        let len = (time_ms / 1000.0 * sample_rate as f32) as usize;
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+            feedback: feedback.clamp(0.0, 0.95),
+            tone: Biquad::low_pass(sample_rate, tone_hz, std::f32::consts::FRAC_1_SQRT_2),
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.tone.process(self.buffer[self.index]);
+        self.buffer[self.index] = input + delayed * self.feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed
+    }
+}
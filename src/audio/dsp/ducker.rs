@@ -0,0 +1,64 @@
+use super::time_coefficient;
+
+/// Time to pull the music down once the mic is hot.
+const ATTACK_MS: f32 = 30.0;
+/// Time the music stays down after the mic goes quiet, bridging short pauses.
+const HOLD_MS: f32 = 400.0;
+/// Time to bring the music back up.
+const RELEASE_MS: f32 = 600.0;
+
+/// Sidechain gain for ducking one signal under another.
+///
+/// Feed it whether the key signal is above its threshold each frame; it
+/// returns a smoothed gain that falls to the duck depth while the key is
+/// active and recovers after a hold time once it stops.
+#[derive(Debug)]
+pub struct Ducker {
+    attack_coeff: f32,
+    release_coeff: f32,
+    hold_frames: u32,
+    /// Frames since the key was last active
+    idle_frames: u32,
+    gain: f32,
+}
+
+impl Ducker {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            attack_coeff: time_coefficient(ATTACK_MS, sample_rate),
+            release_coeff: time_coefficient(RELEASE_MS, sample_rate),
+            hold_frames: (HOLD_MS / 1000.0 * sample_rate as f32) as u32,
+            idle_frames: u32::MAX,
+            gain: 1.0,
+        }
+    }
+
+    pub fn configure(&mut self, sample_rate: u32) {
+        *self = Self {
+            gain: self.gain,
+            ..Self::new(sample_rate)
+        };
+    }
+
+    /// Current gain (1.0 = no ducking).
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Advance one frame and return the gain to apply to it.
+    pub fn next_gain(&mut self, key_active: bool, depth: f32) -> f32 {
+        if key_active {
+            self.idle_frames = 0;
+        } else {
+            self.idle_frames = self.idle_frames.saturating_add(1);
+        }
+
+        let (target, coeff) = if self.idle_frames <= self.hold_frames {
+            (depth, self.attack_coeff)
+        } else {
+            (1.0, self.release_coeff)
+        };
+        self.gain = target + (self.gain - target) * coeff;
+        self.gain
+    }
+}
@@ -0,0 +1,358 @@
+use std::collections::VecDeque;
+
+use super::biquad::Biquad;
+use super::fft;
+
+/// Maximum number of simultaneous notch filters.
+pub const MAX_NOTCHES: usize = 6;
+
+const FFT_SIZE: usize = 2048;
+const HOP_SIZE: usize = FFT_SIZE / 2;
+/// Search range for feedback tones.
+const MIN_FREQ: f32 = 80.0;
+const MAX_FREQ: f32 = 10_000.0;
+/// Peaks quieter than this (dBFS) are ignored.
+const LEVEL_FLOOR_DB: f32 = -45.0;
+/// How far a peak must stand above its neighbourhood to look like a pure tone.
+const PEAK_TO_NEIGHBOURS_DB: f32 = 20.0;
+/// More isolated peaks than this in one spectrum means music, not feedback.
+const MAX_CANDIDATES: usize = 2;
+/// How long a tone must persist before it is notched.
+const DETECT_SECS: f32 = 0.8;
+/// A notch is released after its tone has been absent this long.
+const RELEASE_SECS: f32 = 8.0;
+const NOTCH_Q: f32 = 30.0;
+const NOTCH_GAIN_DB: f32 = -18.0;
+/// A tone still ringing this long through its notch gets the output ducked.
+const DUCK_AFTER_SECS: f32 = 1.5;
+/// How long the output stays ducked after the last feedback that needed it.
+const DUCK_HOLD_SECS: f32 = 4.0;
+const DUCK_GAIN_DB: f32 = -12.0;
+/// Time constant of the duck gain, fast enough to stop a howl building up.
+const DUCK_SMOOTHING_SECS: f32 = 0.05;
+
+#[derive(Debug)]
+struct Candidate {
+    bin: usize,
+    hits: u32,
+    misses: u32,
+}
+
+#[derive(Debug)]
+struct Notch {
+    frequency: f32,
+    filter: Biquad,
+    /// Analysis hops since the tone was last detected
+    idle_hops: u32,
+    /// Analysis hops the tone has kept ringing through the notch
+    ringing_hops: u32,
+}
+
+/// Detects sustained narrow-band tones (howling) in a mic signal and
+/// notches them out.
+///
+/// The input is analysed with overlapping FFTs. A peak that stands far above
+/// its neighbouring bins, in a spectrum with few such peaks, and that persists
+/// for [`DETECT_SECS`] gets a narrow peaking cut at its frequency. Each notch
+/// releases on its own once its tone has been gone for [`RELEASE_SECS`].
+///
+/// When a notch is not enough, because every slot is taken or the tone
+/// keeps ringing through it for [`DUCK_AFTER_SECS`], the suppressor asks for
+/// the output to be ducked ([`Self::wants_duck`]) until the feedback has
+/// stopped for [`DUCK_HOLD_SECS`]. The duck itself is an [`OutputDuck`] on
+/// the master.
+#[derive(Debug)]
+pub struct FeedbackSuppressor {
+    sample_rate: u32,
+    window: Vec<f32>,
+    history: VecDeque<f32>,
+    since_analysis: usize,
+    re: Vec<f32>,
+    im: Vec<f32>,
+    magnitudes: Vec<f32>,
+    candidates: Vec<Candidate>,
+    notches: [Option<Notch>; MAX_NOTCHES],
+    /// Analysis hops left to keep the output ducked
+    duck_hops: u32,
+    /// Notches engaged and ducks started so far
+    events: u64,
+}
+
+impl FeedbackSuppressor {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            window: fft::hann_window(FFT_SIZE),
+            history: VecDeque::with_capacity(FFT_SIZE),
+            since_analysis: 0,
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+            magnitudes: vec![0.0; FFT_SIZE / 2],
+            candidates: Vec::new(),
+            notches: Default::default(),
+            duck_hops: 0,
+            events: 0,
+        }
+    }
+
+    /// Frequencies of the active notches, by slot.
+    pub fn notch_frequencies(&self) -> [Option<f32>; MAX_NOTCHES] {
+        std::array::from_fn(|slot| self.notches[slot].as_ref().map(|notch| notch.frequency))
+    }
+
+    /// Whether the feedback is more than the notches can stop and the
+    /// output should be ducked.
+    pub fn wants_duck(&self) -> bool {
+        self.duck_hops > 0
+    }
+
+    /// Number of times feedback was acted on so far, by a new notch or by
+    /// ducking.
+    pub fn events(&self) -> u64 {
+        self.events
+    }
+
+    /// Remove the notch in `slot` immediately.
+    pub fn release(&mut self, slot: usize) {
+        if let Some(notch) = self.notches.get_mut(slot).and_then(Option::take) {
+            tracing::info!("Released feedback notch at {:.0} Hz", notch.frequency);
+        }
+    }
+
+    /// Process one mono sample.
+    pub fn process(&mut self, input: f32) -> f32 {
+        if self.history.len() == FFT_SIZE {
+            self.history.pop_front();
+        }
+        self.history.push_back(input);
+        self.since_analysis += 1;
+        if self.since_analysis >= HOP_SIZE && self.history.len() == FFT_SIZE {
+            self.since_analysis = 0;
+            self.analyse();
+        }
+
+        self.notches
+            .iter_mut()
+            .flatten()
+            .fold(input, |sample, notch| notch.filter.process(sample))
+    }
+
+    /// Ask for the output to be ducked, or kept ducked, for
+    /// [`DUCK_HOLD_SECS`].
+    fn duck(&mut self) {
+        if self.duck_hops == 0 {
+            tracing::warn!("Feedback not stopped by notches, ducking the output");
+            self.events += 1;
+        }
+        self.duck_hops = self.hops_for(DUCK_HOLD_SECS);
+    }
+
+    fn hops_for(&self, secs: f32) -> u32 {
+        (secs * self.sample_rate as f32 / HOP_SIZE as f32).ceil() as u32
+    }
+
+    fn analyse(&mut self) {
+        for (i, sample) in self.history.iter().enumerate() {
+            self.re[i] = sample * self.window[i];
+            self.im[i] = 0.0;
+        }
+        fft::fft(&mut self.re, &mut self.im);
+        self.duck_hops = self.duck_hops.saturating_sub(1);
+
+        // A full-scale sine reads 0 dB through a Hann window
+        let scale = 4.0 / FFT_SIZE as f32;
+        for (bin, magnitude) in self.magnitudes.iter_mut().enumerate() {
+            let amplitude = (self.re[bin].powi(2) + self.im[bin].powi(2)).sqrt() * scale;
+            *magnitude = super::linear_to_db(amplitude);
+        }
+
+        let peaks = self.find_peaks();
+        let detected = if peaks.len() <= MAX_CANDIDATES {
+            peaks
+        } else {
+            Vec::new()
+        };
+
+        self.track_candidates(&detected);
+        self.age_notches(&detected);
+    }
+
+    /// Bins that are local maxima standing well above their neighbourhood.
+    fn find_peaks(&self) -> Vec<usize> {
+        const GUARD: usize = 2;
+        const SPAN: usize = 10;
+
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+        let first = ((MIN_FREQ / bin_width) as usize).max(GUARD + SPAN);
+        let last = ((MAX_FREQ / bin_width) as usize).min(self.magnitudes.len() - GUARD - SPAN - 1);
+
+        let mags = &self.magnitudes;
+        (first..last)
+            .filter(|&bin| {
+                let level = mags[bin];
+                if level < LEVEL_FLOOR_DB || level < mags[bin - 1] || level < mags[bin + 1] {
+                    return false;
+                }
+                let below = &mags[bin - GUARD - SPAN..bin - GUARD];
+                let above = &mags[bin + GUARD + 1..=bin + GUARD + SPAN];
+                let neighbourhood =
+                    below.iter().chain(above).sum::<f32>() / (below.len() + above.len()) as f32;
+                level - neighbourhood >= PEAK_TO_NEIGHBOURS_DB
+            })
+            .collect()
+    }
+
+    fn track_candidates(&mut self, detected: &[usize]) {
+        for candidate in &mut self.candidates {
+            if detected.iter().any(|&bin| bin.abs_diff(candidate.bin) <= 1) {
+                candidate.hits += 1;
+                candidate.misses = 0;
+            } else {
+                candidate.misses += 1;
+            }
+        }
+        self.candidates.retain(|candidate| candidate.misses <= 2);
+
+        for &bin in detected {
+            if !self
+                .candidates
+                .iter()
+                .any(|candidate| bin.abs_diff(candidate.bin) <= 1)
+            {
+                self.candidates.push(Candidate {
+                    bin,
+                    hits: 1,
+                    misses: 0,
+                });
+            }
+        }
+
+        let required = self.hops_for(DETECT_SECS);
+        let confirmed: Vec<usize> = self
+            .candidates
+            .iter()
+            .filter(|candidate| candidate.hits >= required)
+            .map(|candidate| candidate.bin)
+            .collect();
+        self.candidates
+            .retain(|candidate| candidate.hits < required);
+        for bin in confirmed {
+            self.add_notch(bin);
+        }
+    }
+
+    fn add_notch(&mut self, bin: usize) {
+        let frequency = self.refine_frequency(bin);
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+        if self
+            .notches
+            .iter()
+            .flatten()
+            .any(|notch| (notch.frequency - frequency).abs() < bin_width * 1.5)
+        {
+            return;
+        }
+
+        let Some(slot) = self.notches.iter().position(Option::is_none) else {
+            tracing::warn!("Feedback at {frequency:.0} Hz but all notches are in use");
+            self.duck();
+            return;
+        };
+        self.notches[slot] = Some(Notch {
+            frequency,
+            filter: Biquad::peaking(self.sample_rate, frequency, NOTCH_Q, NOTCH_GAIN_DB),
+            idle_hops: 0,
+            ringing_hops: 0,
+        });
+        self.events += 1;
+        tracing::warn!("Feedback detected at {frequency:.0} Hz, notch engaged");
+    }
+
+    /// Parabolic interpolation of the peak position between bins.
+    fn refine_frequency(&self, bin: usize) -> f32 {
+        let (left, centre, right) = (
+            self.magnitudes[bin - 1],
+            self.magnitudes[bin],
+            self.magnitudes[bin + 1],
+        );
+        let denominator = left - 2.0 * centre + right;
+        let offset = if denominator.abs() > f32::EPSILON {
+            0.5 * (left - right) / denominator
+        } else {
+            0.0
+        };
+        (bin as f32 + offset) * self.sample_rate as f32 / FFT_SIZE as f32
+    }
+
+    fn age_notches(&mut self, detected: &[usize]) {
+        let bin_width = self.sample_rate as f32 / FFT_SIZE as f32;
+        let release_after = self.hops_for(RELEASE_SECS);
+        let duck_after = self.hops_for(DUCK_AFTER_SECS);
+        let mut duck = false;
+
+        for slot in &mut self.notches {
+            let Some(notch) = slot else {
+                continue;
+            };
+            let still_ringing = detected
+                .iter()
+                .any(|&bin| (bin as f32 * bin_width - notch.frequency).abs() < bin_width * 1.5);
+            if still_ringing {
+                notch.idle_hops = 0;
+                notch.ringing_hops += 1;
+                duck |= notch.ringing_hops >= duck_after;
+            } else {
+                notch.idle_hops += 1;
+                notch.ringing_hops = 0;
+            }
+            if notch.idle_hops >= release_after {
+                tracing::info!("Feedback notch at {:.0} Hz released", notch.frequency);
+                *slot = None;
+            }
+        }
+        if duck {
+            self.duck();
+        }
+    }
+}
+
+/// Turns the whole output down while a [`FeedbackSuppressor`] asks for it,
+/// by [`DUCK_GAIN_DB`] with a fast fade either way.
+#[derive(Debug)]
+pub struct OutputDuck {
+    gain: f32,
+    coefficient: f32,
+}
+
+impl OutputDuck {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            gain: 1.0,
+            coefficient: 1.0 - (-1.0 / (DUCK_SMOOTHING_SECS * sample_rate as f32)).exp(),
+        }
+    }
+
+    pub fn configure(&mut self, sample_rate: u32) {
+        *self = Self::new(sample_rate);
+    }
+
+    /// Current output reduction in dB (positive = reducing).
+    pub fn ducking_db(&self) -> f32 {
+        -super::linear_to_db(self.gain)
+    }
+
+    /// Process one interleaved frame in place, ducked while `ducking`.
+    pub fn process_frame(&mut self, frame: &mut [f32], ducking: bool) {
+        let target = if ducking {
+            super::db_to_linear(DUCK_GAIN_DB)
+        } else {
+            1.0
+        };
+        self.gain += (target - self.gain) * self.coefficient;
+        if self.gain < 1.0 {
+            for sample in frame.iter_mut() {
+                *sample *= self.gain;
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+
+use super::time_coefficient;
+
+/// Lookahead before a peak reaches the output.
+const LOOKAHEAD_MS: f32 = 5.0;
+/// Time to recover after gain reduction.
+const RELEASE_MS: f32 = 120.0;
+
+/// Stereo-linked lookahead peak limiter.
+///
+/// Incoming frames are delayed by [`LOOKAHEAD_MS`] so the gain can already be
+/// lowered when a peak leaves the delay line. A final hard clamp guarantees
+/// the output never exceeds the threshold, even on the very first frames.
+#[derive(Debug)]
+pub struct Limiter {
+    threshold: f32,
+    channels: usize,
+    lookahead: usize,
+    attack_coeff: f32,
+    release_coeff: f32,
+    /// Delayed interleaved samples, `lookahead` frames long once primed
+    delay: VecDeque<f32>,
+    /// Monotonic queue of (frame index, required gain) for the sliding minimum
+    required: VecDeque<(u64, f32)>,
+    frame_index: u64,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(threshold: f32, channels: u16, sample_rate: u32) -> Self {
+        let mut limiter = Self {
+            threshold,
+            channels: 0,
+            lookahead: 0,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            delay: VecDeque::new(),
+            required: VecDeque::new(),
+            frame_index: 0,
+            gain: 1.0,
+        };
+        limiter.configure(channels, sample_rate);
+        limiter
+    }
+
+    /// Adapt to a new stream format. Clears the internal state.
+    pub fn configure(&mut self, channels: u16, sample_rate: u32) {
+        self.channels = usize::from(channels.max(1));
+        self.lookahead = ((LOOKAHEAD_MS / 1000.0) * sample_rate as f32)
+            .round()
+            .max(1.0) as usize;
+        // Reach most of the required reduction within the lookahead window
+        self.attack_coeff = time_coefficient(LOOKAHEAD_MS / 5.0, sample_rate);
+        self.release_coeff = time_coefficient(RELEASE_MS, sample_rate);
+        self.reset();
+    }
+
+    /// Drop buffered audio, e.g. after a seek.
+    pub fn reset(&mut self) {
+        self.delay.clear();
+        self.required.clear();
+        self.frame_index = 0;
+        self.gain = 1.0;
+    }
+
+    /// Set the ceiling as a linear amplitude (0.0 - 1.0).
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold.clamp(0.01, 1.0);
+    }
+
+    /// Current gain applied to the output (1.0 = no reduction).
+    pub fn gain(&self) -> f32 {
+        self.gain
+    }
+
+    /// Process one interleaved frame in place.
+    ///
+    /// The output lags the input by the lookahead; frames produced while the
+    /// delay line is still filling are silent.
+    pub fn process_frame(&mut self, frame: &mut [f32]) {
+        if frame.len() != self.channels {
+            // Partial frame at the end of a stream: just keep it under the ceiling
+            for sample in frame.iter_mut() {
+                *sample = sample.clamp(-self.threshold, self.threshold);
+            }
+            return;
+        }
+
+        let peak = frame.iter().fold(0.0_f32, |peak, s| peak.max(s.abs()));
+        let required = if peak > self.threshold {
+            self.threshold / peak
+        } else {
+            1.0
+        };
+
+        // Sliding minimum over the frames currently inside the delay line
+        while self
+            .required
+            .back()
+            .is_some_and(|&(_, gain)| gain >= required)
+        {
+            self.required.pop_back();
+        }
+        self.required.push_back((self.frame_index, required));
+        let window_start = self.frame_index.saturating_sub(self.lookahead as u64);
+        while self
+            .required
+            .front()
+            .is_some_and(|&(index, _)| index < window_start)
+        {
+            self.required.pop_front();
+        }
+        self.frame_index += 1;
+
+        let target = self.required.front().map_or(1.0, |&(_, gain)| gain);
+        let coeff = if target < self.gain {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.gain = target + (self.gain - target) * coeff;
+
+        self.delay.extend(frame.iter().copied());
+        if self.delay.len() <= self.lookahead * self.channels {
+            frame.fill(0.0);
+            return;
+        }
+
+        for sample in frame.iter_mut() {
+            let delayed = self.delay.pop_front().unwrap_or(0.0);
+            *sample = (delayed * self.gain).clamp(-self.threshold, self.threshold);
+        }
+    }
+}
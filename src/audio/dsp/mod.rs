@@ -0,0 +1,46 @@
+//! Sample-level DSP stages used by the processing chain.
+//!
+//! Stages work on interleaved `f32` frames (one sample per channel) so they can
+//! be linked across channels and stay independent of rodio.
+
+pub mod agc;
+pub mod biquad;
+pub mod delay;
+pub mod ducker;
+pub mod feedback;
+pub mod fft;
+pub mod limiter;
+pub mod noise_gate;
+pub mod pitch_shifter;
+pub mod reverb;
+pub mod vocal_remover;
+
+pub use agc::AutoGain;
+pub use delay::Delay;
+pub use ducker::Ducker;
+pub use feedback::{FeedbackSuppressor, OutputDuck};
+pub use limiter::Limiter;
+pub use noise_gate::NoiseGate;
+pub use pitch_shifter::PitchShifter;
+pub use reverb::Reverb;
+pub use vocal_remover::VocalRemover;
+
+/// Convert decibels to a linear amplitude factor.
+pub fn db_to_linear(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
+/// Convert a linear amplitude factor to decibels.
+pub fn linear_to_db(linear: f32) -> f32 {
+    20.0 * linear.max(1e-9).log10()
+}
+
+/// One-pole smoothing coefficient reaching ~63% of a step after `time_ms`.
+pub fn time_coefficient(time_ms: f32, sample_rate: u32) -> f32 {
+    let samples = time_ms / 1000.0 * sample_rate as f32;
+    if samples <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / samples).exp()
+    }
+}
@@ -0,0 +1,57 @@
+use super::time_coefficient;
+
+/// Time to open fully once the signal crosses the threshold.
+const ATTACK_MS: f32 = 1.0;
+/// Time the gate stays open after the signal drops, so word endings and
+/// short breaths are not chopped.
+const HOLD_MS: f32 = 80.0;
+/// Time to close.
+const RELEASE_MS: f32 = 120.0;
+/// Decay of the level detector.
+const DETECTOR_RELEASE_MS: f32 = 20.0;
+
+/// Mutes a mono signal while it stays below a threshold, removing room
+/// noise and spill between phrases.
+#[derive(Debug)]
+pub struct NoiseGate {
+    attack_coeff: f32,
+    release_coeff: f32,
+    detector_coeff: f32,
+    hold_samples: u32,
+    /// Samples since the level was last above the threshold
+    idle_samples: u32,
+    level: f32,
+    gain: f32,
+}
+
+impl NoiseGate {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            attack_coeff: time_coefficient(ATTACK_MS, sample_rate),
+            release_coeff: time_coefficient(RELEASE_MS, sample_rate),
+            detector_coeff: time_coefficient(DETECTOR_RELEASE_MS, sample_rate),
+            hold_samples: (HOLD_MS / 1000.0 * sample_rate as f32) as u32,
+            idle_samples: u32::MAX,
+            level: 0.0,
+            gain: 0.0,
+        }
+    }
+
+    /// Gate one sample against `threshold` (linear peak level).
+    pub fn process(&mut self, input: f32, threshold: f32) -> f32 {
+        self.level = input.abs().max(self.level * self.detector_coeff);
+        if self.level > threshold {
+            self.idle_samples = 0;
+        } else {
+            self.idle_samples = self.idle_samples.saturating_add(1);
+        }
+
+        let (target, coeff) = if self.idle_samples <= self.hold_samples {
+            (1.0, self.attack_coeff)
+        } else {
+            (0.0, self.release_coeff)
+        };
+        self.gain = target + (self.gain - target) * coeff;
+        input * self.gain
+    }
+}
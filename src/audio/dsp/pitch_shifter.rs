@@ -0,0 +1,61 @@
+use std::f32::consts::PI;
+
+/// Length of the crossfaded delay window.
+const WINDOW_MS: f32 = 40.0;
+
+/// Delay-line pitch shifter for mono signals.
+///
+/// Two read taps sweep through a short delay window half a window apart; the
+/// sweep speed sets the pitch ratio and a sin² crossfade hides each tap's
+/// wrap-around. Cheap and latency-free enough for live vocals.
+#[derive(Debug)]
+pub struct PitchShifter {
+    buffer: Vec<f32>,
+    write: usize,
+    window: f32,
+    phase: f32,
+    ratio: f32,
+}
+
+impl PitchShifter {
+    pub fn new(sample_rate: u32) -> Self {
+        let window = WINDOW_MS / 1000.0 * sample_rate as f32;
+        Self {
+            buffer: vec![0.0; window as usize * 2 + 2],
+            write: 0,
+            window,
+            phase: 0.0,
+            ratio: 1.0,
+        }
+    }
+
+    /// Set the pitch ratio (2.0 = one octave up).
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(0.5, 2.0);
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.write] = input;
+
+        self.phase = (self.phase + (1.0 - self.ratio) / self.window).rem_euclid(1.0);
+        let second = (self.phase + 0.5) % 1.0;
+
+        let output = self.tap(self.phase) * (PI * self.phase).sin().powi(2)
+            + self.tap(second) * (PI * second).sin().powi(2);
+
+        self.write = (self.write + 1) % len;
+        output
+    }
+
+    /// Read the delay line `phase * window` samples behind the write head.
+    fn tap(&self, phase: f32) -> f32 {
+        let len = self.buffer.len();
+        let delay = phase * self.window;
+        let position = (self.write as f32 - delay).rem_euclid(len as f32);
+        let index = position as usize % len;
+        let next = (index + 1) % len;
+        let fraction = position.fract();
+        self.buffer[index] * (1.0 - fraction) + self.buffer[next] * fraction
+    }
+}
@@ -0,0 +1,98 @@
+/// Comb filter delays of the Freeverb tuning, in samples at 44.1 kHz.
+const COMB_TUNING: [usize; 4] = [1116, 1277, 1422, 1557];
+/// All-pass delays diffusing the comb output.
+const ALLPASS_TUNING: [usize; 2] = [556, 225];
+const ALLPASS_FEEDBACK: f32 = 0.5;
+/// Input scaling keeping the summed combs near unity.
+const INPUT_GAIN: f32 = 0.03;
+
+/// Mono Schroeder/Freeverb-style reverb: parallel damped comb filters
+/// followed by series all-passes. Returns the wet signal only.
+#[derive(Debug)]
+pub struct Reverb {
+    combs: Vec<Comb>,
+    allpasses: Vec<AllPass>,
+    feedback: f32,
+    damping: f32,
+}
+
+impl Reverb {
+    /// `size` (0.0 - 1.0) sets the decay time, `damping` (0.0 - 1.0) how
+    /// quickly high frequencies die away.
+    pub fn new(sample_rate: u32, size: f32, damping: f32) -> Self {
+        let scale = |samples: usize| (samples as f32 * sample_rate as f32 / 44_100.0) as usize;
+        Self {
+            combs: COMB_TUNING
+                .iter()
+                .map(|&samples| Comb::new(scale(samples)))
+                .collect(),
+            allpasses: ALLPASS_TUNING
+                .iter()
+                .map(|&samples| AllPass::new(scale(samples)))
+                .collect(),
+            feedback: 0.7 + 0.28 * size.clamp(0.0, 1.0),
+            damping: damping.clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn process(&mut self, input: f32) -> f32 {
+        let input = input * INPUT_GAIN;
+        let mut wet: f32 = self
+            .combs
+            .iter_mut()
+            .map(|comb| comb.process(input, self.feedback, self.damping))
+            .sum();
+        for allpass in &mut self.allpasses {
+            wet = allpass.process(wet);
+        }
+        wet
+    }
+}
+
+#[derive(Debug)]
+struct Comb {
+    buffer: Vec<f32>,
+    index: usize,
+    /// Low-passed feedback, for damping
+    filtered: f32,
+}
+
+impl Comb {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+            filtered: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filtered = output * (1.0 - damping) + self.filtered * damping;
+        self.buffer[self.index] = input + self.filtered * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Debug)]
+struct AllPass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl AllPass {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len.max(1)],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        self.buffer[self.index] = input + delayed * ALLPASS_FEEDBACK;
+        self.index = (self.index + 1) % self.buffer.len();
+        delayed - input
+    }
+}
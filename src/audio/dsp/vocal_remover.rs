@@ -0,0 +1,48 @@
+use super::biquad::Biquad;
+
+/// Centre content below this frequency (bass, kick) is kept.
+const BASS_CUTOFF_HZ: f32 = 150.0;
+
+/// Centre-channel cancellation for stereo tracks.
+///
+/// Lead vocals are usually mixed dead centre, so attenuating the mid signal
+/// (L + R) removes most of them while side content survives. The low end of
+/// the mid signal is kept so bass and kick drum don't vanish with the voice.
+#[derive(Debug)]
+pub struct VocalRemover {
+    sample_rate: u32,
+    bass: Biquad,
+}
+
+impl VocalRemover {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            bass: Biquad::low_pass(sample_rate, BASS_CUTOFF_HZ, std::f32::consts::FRAC_1_SQRT_2),
+        }
+    }
+
+    /// Adapt to a new sample rate. Clears the filter state.
+    pub fn configure(&mut self, sample_rate: u32) {
+        if sample_rate != self.sample_rate {
+            *self = Self::new(sample_rate);
+        }
+    }
+
+    /// Attenuate the centre of a stereo frame by `strength` (0.0 - 1.0).
+    ///
+    /// Frames that are not stereo are left untouched.
+    pub fn process_frame(&mut self, frame: &mut [f32], strength: f32) {
+        let [left, right] = frame else {
+            return;
+        };
+
+        let mid = (*left + *right) * 0.5;
+        let side = (*left - *right) * 0.5;
+        let bass = self.bass.process(mid);
+        let mid = bass + (mid - bass) * (1.0 - strength.clamp(0.0, 1.0));
+
+        *left = mid + side;
+        *right = mid - side;
+    }
+}
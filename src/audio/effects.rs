@@ -0,0 +1,324 @@
+//! Voice effects rack for the microphone.
+//!
+//! A rack is an ordered list of [`VoiceEffect`] settings (serializable, so it
+//! can be stored as a default or per song). [`EffectsRack`] turns the list into
+//! running processors and applies them to the mono mic signal, after the
+//! optional key-aware autotune.
+
+use serde::{Deserialize, Serialize};
+
+use super::dsp::biquad::Biquad;
+use super::dsp::{Delay, PitchShifter, Reverb};
+use super::key::Key;
+use super::pitch::{self, PitchTracker};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VoiceEffectKind {
+    /// Light autotune: pulls the sung pitch toward the nearest semitone
+    PitchCorrection,
+    Chorus,
+    /// Band-limited, slightly overdriven "AM radio" voice
+    Radio,
+    /// Narrow band and heavy drive
+    Megaphone,
+    /// Short, bright reverb of a small room
+    Room,
+    /// Long, dark reverb of a concert hall
+    Hall,
+    /// One quick repeat, the 1950s vocal sound
+    Slapback,
+    /// Repeating karaoke-machine echo
+    Echo,
+}
+
+impl VoiceEffectKind {
+    pub const ALL: [Self; 8] = [
+        Self::PitchCorrection,
+        Self::Chorus,
+        Self::Radio,
+        Self::Megaphone,
+        Self::Room,
+        Self::Hall,
+        Self::Slapback,
+        Self::Echo,
+    ];
+    /// Reverb and echo presets, offered as one live choice in the karaoke
+    /// view.
+    pub const AMBIENCE: [Self; 4] = [Self::Room, Self::Hall, Self::Slapback, Self::Echo];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PitchCorrection => "Pitch correction",
+            Self::Chorus => "Chorus",
+            Self::Radio => "Radio",
+            Self::Megaphone => "Megaphone",
+            Self::Room => "Room reverb",
+            Self::Hall => "Hall reverb",
+            Self::Slapback => "Slapback echo",
+            Self::Echo => "Echo",
+        }
+    }
+
+    pub fn is_ambience(self) -> bool {
+        Self::AMBIENCE.contains(&self)
+    }
+}
+
+/// One slot of the rack.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VoiceEffect {
+    pub kind: VoiceEffectKind,
+    pub enabled: bool,
+    /// Wet mix, or correction strength for pitch correction (0.0 - 1.0)
+    pub amount: f32,
+}
+
+impl VoiceEffect {
+    pub fn new(kind: VoiceEffectKind) -> Self {
+        Self {
+            kind,
+            enabled: true,
+            amount: 0.5,
+        }
+    }
+}
+
+/// Running processors for a rack, applied in order.
+pub struct EffectsRack {
+    sample_rate: u32,
+    /// Key of the current song, used by every pitch corrector
+    key: Option<Key>,
+    /// Autotune strength and its corrector, ahead of the rack
+    autotune: Option<(f32, PitchCorrector)>,
+    slots: Vec<(VoiceEffect, EffectState)>,
+    /// Scale on every effect's amount; pitch correction is not an effect
+    /// heard as such and ignores it
+    send: f32,
+}
+
+impl EffectsRack {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            key: None,
+            autotune: None,
+            slots: Vec::new(),
+            send: 1.0,
+        }
+    }
+
+    /// Snap pitch corrections to this key's scale, or chromatically if `None`.
+    pub fn set_key(&mut self, key: Option<Key>) {
+        self.key = key;
+        if let Some((_, corrector)) = &mut self.autotune {
+            corrector.key = key;
+        }
+        for (_, state) in &mut self.slots {
+            if let EffectState::PitchCorrection(corrector) = state {
+                corrector.key = key;
+            }
+        }
+    }
+
+    /// Set the autotune strength (0.0 = off).
+    pub fn set_autotune(&mut self, strength: f32) {
+        if strength <= 0.0 {
+            self.autotune = None;
+            return;
+        }
+        let (current, _) = self.autotune.get_or_insert_with(|| {
+            let mut corrector = PitchCorrector::new(self.sample_rate);
+            corrector.key = self.key;
+            (strength, corrector)
+        });
+        *current = strength.min(1.0);
+    }
+
+    /// Rebuild the processors for a new rack configuration.
+    pub fn set_effects(&mut self, effects: &[VoiceEffect]) {
+        self.slots = effects
+            .iter()
+            .filter(|effect| effect.enabled)
+            .map(|effect| {
+                (
+                    effect.clone(),
+                    EffectState::new(effect.kind, self.sample_rate),
+                )
+            })
+            .collect();
+        self.set_key(self.key);
+    }
+
+    /// Set the effects send (0.0 = dry voice only).
+    pub fn set_send(&mut self, send: f32) {
+        self.send = send.clamp(0.0, 1.0);
+    }
+
+    pub fn process(&mut self, mut sample: f32) -> f32 {
+        if let Some((strength, corrector)) = &mut self.autotune {
+            sample = corrector.process(sample, *strength);
+        }
+        for (effect, state) in &mut self.slots {
+            let send = match state {
+                EffectState::PitchCorrection(_) => 1.0,
+                _ => self.send,
+            };
+            sample = state.process(sample, effect.amount.clamp(0.0, 1.0) * send);
+        }
+        sample
+    }
+}
+
+enum EffectState {
+    PitchCorrection(PitchCorrector),
+    Chorus(Chorus),
+    Filter(DriveFilter),
+    Reverb(Reverb),
+    Delay(Delay),
+}
+
+impl EffectState {
+    fn new(kind: VoiceEffectKind, sample_rate: u32) -> Self {
+        match kind {
+            VoiceEffectKind::PitchCorrection => {
+                Self::PitchCorrection(PitchCorrector::new(sample_rate))
+            },
+            VoiceEffectKind::Chorus => Self::Chorus(Chorus::new(sample_rate)),
+            VoiceEffectKind::Radio => {
+                Self::Filter(DriveFilter::new(sample_rate, 400.0, 3000.0, 2.0))
+            },
+            VoiceEffectKind::Megaphone => {
+                Self::Filter(DriveFilter::new(sample_rate, 700.0, 2500.0, 6.0))
+            },
+            VoiceEffectKind::Room => Self::Reverb(Reverb::new(sample_rate, 0.4, 0.2)),
+            VoiceEffectKind::Hall => Self::Reverb(Reverb::new(sample_rate, 0.9, 0.5)),
+            VoiceEffectKind::Slapback => Self::Delay(Delay::new(sample_rate, 110.0, 0.0, 6000.0)),
+            VoiceEffectKind::Echo => Self::Delay(Delay::new(sample_rate, 320.0, 0.45, 3500.0)),
+        }
+    }
+
+    fn process(&mut self, input: f32, amount: f32) -> f32 {
+        match self {
+            Self::PitchCorrection(corrector) => corrector.process(input, amount),
+            Self::Chorus(chorus) => {
+                let wet = chorus.process(input);
+                input * (1.0 - amount * 0.5) + wet * amount * 0.5
+            },
+            Self::Filter(filter) => {
+                let wet = filter.process(input);
+                input * (1.0 - amount) + wet * amount
+            },
+            // Added on top of the dry voice, like a send on a mixing desk
+            Self::Reverb(reverb) => input + reverb.process(input) * amount,
+            Self::Delay(delay) => input + delay.process(input) * amount * 0.7,
+        }
+    }
+}
+
+/// Snaps the detected pitch toward the nearest note of the key, or the
+/// nearest semitone when the key is unknown.
+struct PitchCorrector {
+    key: Option<Key>,
+    tracker: PitchTracker,
+    shifter: PitchShifter,
+    target_ratio: f32,
+    ratio: f32,
+    smoothing: f32,
+}
+
+impl PitchCorrector {
+    fn new(sample_rate: u32) -> Self {
+        Self {
+            key: None,
+            tracker: PitchTracker::new(sample_rate, 512),
+            shifter: PitchShifter::new(sample_rate),
+            target_ratio: 1.0,
+            ratio: 1.0,
+            // ~20 ms glide between corrections
+            smoothing: super::dsp::time_coefficient(20.0, sample_rate),
+        }
+    }
+
+    fn process(&mut self, input: f32, strength: f32) -> f32 {
+        if let Some(estimate) = self.tracker.push(input) {
+            self.target_ratio = estimate.map_or(1.0, |frequency| {
+                let note = pitch::frequency_to_midi(frequency);
+                let nearest = match self.key {
+                    Some(key) => key.nearest_note(note),
+                    None => note.round(),
+                };
+                let target = pitch::midi_to_frequency(nearest);
+                (target / frequency).powf(strength)
+            });
+        }
+        self.ratio = self.target_ratio + (self.ratio - self.target_ratio) * self.smoothing;
+        self.shifter.set_ratio(self.ratio);
+        self.shifter.process(input)
+    }
+}
+
+/// Single-voice chorus: a delay line modulated by a slow LFO.
+struct Chorus {
+    buffer: Vec<f32>,
+    write: usize,
+    sample_rate: f32,
+    lfo_phase: f32,
+}
+
+impl Chorus {
+    const BASE_DELAY_MS: f32 = 15.0;
+    const DEPTH_MS: f32 = 4.0;
+    const RATE_HZ: f32 = 0.8;
+
+    fn new(sample_rate: u32) -> Self {
+        let max_delay = (Self::BASE_DELAY_MS + Self::DEPTH_MS) / 1000.0 * sample_rate as f32;
+        Self {
+            buffer: vec![0.0; max_delay as usize + 2],
+            write: 0,
+            sample_rate: sample_rate as f32,
+            lfo_phase: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[self.write] = input;
+
+        let modulation = (self.lfo_phase * std::f32::consts::TAU).sin();
+        self.lfo_phase = (self.lfo_phase + Self::RATE_HZ / self.sample_rate).fract();
+        let delay_ms = Self::BASE_DELAY_MS + Self::DEPTH_MS * modulation;
+        let delay = delay_ms / 1000.0 * self.sample_rate;
+
+        let position = (self.write as f32 - delay).rem_euclid(len as f32);
+        let index = position as usize % len;
+        let fraction = position.fract();
+        let output =
+            self.buffer[index] * (1.0 - fraction) + self.buffer[(index + 1) % len] * fraction;
+
+        self.write = (self.write + 1) % len;
+        output
+    }
+}
+
+/// Band-pass followed by soft clipping, for lo-fi voices.
+struct DriveFilter {
+    high_pass: Biquad,
+    low_pass: Biquad,
+    drive: f32,
+}
+
+impl DriveFilter {
+    fn new(sample_rate: u32, low_hz: f32, high_hz: f32, drive: f32) -> Self {
+        let q = std::f32::consts::FRAC_1_SQRT_2;
+        Self {
+            high_pass: Biquad::high_pass(sample_rate, low_hz, q),
+            low_pass: Biquad::low_pass(sample_rate, high_hz, q),
+            drive,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let band = self.low_pass.process(self.high_pass.process(input));
+        (band * self.drive).tanh() / self.drive.tanh()
+    }
+}
@@ -0,0 +1,277 @@
+//! Offline render of a recorded performance into a single file.
+//!
+//! The vocal tracks already carry the voice effects as they were heard. The
+//! music comes from the separately recorded backing track when there is one;
+//! otherwise it is decoded again from the file that was playing, from the
+//! song position where the recording started, with the same volume and vocal
+//! removal. Both are balanced by a [`MixPreset`] and mixed through a limiter
+//! so loud passages do not clip.
+
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rodio::Source;
+
+use super::dsp::{self, Limiter, VocalRemover};
+use super::processor::MIC_COUNT;
+use super::recorder::{self, RecordingFormat};
+use super::{player, AudioError};
+
+/// Ceiling of the rendered mix.
+const LIMIT_DB: f32 = -1.0;
+
+/// Balance between the voice and the music in an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MixPreset {
+    VocalForward,
+    #[default]
+    Balanced,
+    MusicForward,
+}
+
+impl MixPreset {
+    pub const ALL: [Self; 3] = [Self::VocalForward, Self::Balanced, Self::MusicForward];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::VocalForward => "Vocal-forward",
+            Self::Balanced => "Balanced",
+            Self::MusicForward => "Music-forward",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::VocalForward => "The voice on top, the music a step back",
+            Self::Balanced => "Voice and music as heard while singing",
+            Self::MusicForward => "The music up front, the voice blended in",
+        }
+    }
+
+    /// Gains of the voice and the music, in dB.
+    fn gains_db(self) -> (f32, f32) {
+        match self {
+            Self::VocalForward => (3.0, -4.0),
+            Self::Balanced => (0.0, 0.0),
+            Self::MusicForward => (-4.0, 2.0),
+        }
+    }
+}
+
+/// The backing track as it was heard while recording.
+#[derive(Debug, Clone)]
+pub struct Backing {
+    /// File played: the song, or its instrumental
+    pub path: PathBuf,
+    /// Song position when the recording started
+    pub start: Duration,
+    /// Linear gain of the song's volume offset
+    pub gain: f32,
+    pub vocal_removal: f32,
+}
+
+/// A finished recording that can be rendered.
+#[derive(Debug, Clone)]
+pub struct Performance {
+    pub backing: Backing,
+    /// One file per singer
+    pub vocals: Vec<PathBuf>,
+    /// How late each singer's mic delivered the voice; taken off the
+    /// start of their track
+    pub mic_latency: [Duration; MIC_COUNT],
+    /// The backing track as played, when it was recorded separately
+    pub music: Option<PathBuf>,
+}
+
+/// Interleaved music samples for the render.
+struct Music {
+    channels: u16,
+    sample_rate: u32,
+    samples: Box<dyn Iterator<Item = f32>>,
+    /// Decoded from the song file, so the song's volume and vocal removal
+    /// are still to be applied
+    from_song: bool,
+}
+
+impl Music {
+    fn open(performance: &Performance) -> Result<Self, AudioError> {
+        if let Some(path) = &performance.music {
+            let reader = hound::WavReader::open(path)
+                .map_err(|e| AudioError::LoadError(format!("{}: {e}", path.display())))?;
+            let spec = reader.spec();
+            return Ok(Self {
+                channels: spec.channels.max(1),
+                sample_rate: spec.sample_rate,
+                samples: Box::new(
+                    reader
+                        .into_samples::<i16>()
+                        .map(|sample| sample.map_or(0.0, recorder::from_i16)),
+                ),
+                from_song: false,
+            });
+        }
+        let backing = &performance.backing;
+        let decoder = player::open_decoder(&backing.path)?;
+        Ok(Self {
+            channels: decoder.channels().max(1),
+            sample_rate: decoder.sample_rate(),
+            samples: Box::new(
+                decoder
+                    .skip_duration(backing.start)
+                    .convert_samples::<f32>(),
+            ),
+            from_song: true,
+        })
+    }
+}
+
+/// Mix `performance` down to `output` balanced by `preset`, converted to
+/// `format`. Returns the file written, which has the extension of the
+/// format actually used.
+pub fn render(
+    performance: &Performance,
+    output: &Path,
+    format: RecordingFormat,
+    preset: MixPreset,
+) -> Result<PathBuf, AudioError> {
+    let backing = &performance.backing;
+    let mut music = Music::open(performance)?;
+    let (channels, sample_rate) = (music.channels, music.sample_rate);
+    let vocals = performance
+        .vocals
+        .iter()
+        .zip(performance.mic_latency)
+        .map(|(path, latency)| {
+            let mut samples = read_vocals(path, sample_rate)?;
+            let late = (latency.as_secs_f64() * f64::from(sample_rate)) as usize;
+            samples.drain(..late.min(samples.len()));
+            Ok(samples)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let frames = vocals.iter().map(Vec::len).max().unwrap_or(0);
+    if frames == 0 {
+        return Err(AudioError::RecordingError(
+            "The recording has no vocals to mix".to_string(),
+        ));
+    }
+
+    let wav = output.with_extension("wav");
+    let error = |e: hound::Error| AudioError::RecordingError(format!("{}: {e}", wav.display()));
+    let spec = hound::WavSpec {
+        channels,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&wav, spec).map_err(error)?;
+    let (voice_db, music_db) = preset.gains_db();
+    let voice_gain = dsp::db_to_linear(voice_db);
+    let mut music_gain = dsp::db_to_linear(music_db);
+    let vocal_removal = if music.from_song {
+        music_gain *= backing.gain;
+        backing.vocal_removal
+    } else {
+        0.0
+    };
+    let mut vocal_remover = VocalRemover::new(sample_rate);
+    let mut limiter = Limiter::new(dsp::db_to_linear(LIMIT_DB), channels, sample_rate);
+    let mut frame = vec![0.0; usize::from(channels)];
+    for index in 0..frames {
+        for sample in &mut frame {
+            *sample = music.samples.next().unwrap_or(0.0);
+        }
+        if vocal_removal > 0.0 {
+            vocal_remover.process_frame(&mut frame, vocal_removal);
+        }
+        let voice: f32 = vocals.iter().filter_map(|track| track.get(index)).sum();
+        for sample in &mut frame {
+            *sample = *sample * music_gain + voice * voice_gain;
+        }
+        limiter.process_frame(&mut frame);
+        for &sample in &frame {
+            writer
+                .write_sample(recorder::to_i16(sample))
+                .map_err(error)?;
+        }
+    }
+    writer.finalize().map_err(error)?;
+
+    match format {
+        RecordingFormat::Wav => Ok(wav),
+        RecordingFormat::Flac => Ok(recorder::convert_to_flac(&wav)),
+    }
+}
+
+/// A recorded vocal track as mono samples at `sample_rate`.
+fn read_vocals(path: &Path, sample_rate: u32) -> Result<Vec<f32>, AudioError> {
+    let error = |e: hound::Error| AudioError::LoadError(format!("{}: {e}", path.display()));
+    let reader = hound::WavReader::open(path).map_err(error)?;
+    let spec = reader.spec();
+    let samples = reader
+        .into_samples::<i16>()
+        .map(|sample| sample.map(recorder::from_i16))
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(error)?;
+    let mono: Vec<f32> = samples
+        .chunks(usize::from(spec.channels.max(1)))
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    Ok(resample(&mono, spec.sample_rate, sample_rate))
+}
+
+/// Linear interpolation from `from` Hz to `to` Hz; good enough for a voice.
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || from == 0 || to == 0 {
+        return samples.to_vec();
+    }
+    let step = f64::from(from) / f64::from(to);
+    let len = (samples.len() as f64 / step) as usize;
+    (0..len)
+        .map(|index| {
+            let position = index as f64 * step;
+            let before = position as usize;
+            let fraction = (position - before as f64) as f32;
+            let a = samples.get(before).copied().unwrap_or(0.0);
+            let b = samples.get(before + 1).copied().unwrap_or(a);
+            a + (b - a) * fraction
+        })
+        .collect()
+}
+
+/// A performance being rendered on a background thread.
+pub struct ExportJob {
+    output: PathBuf,
+    handle: Option<JoinHandle<Result<PathBuf, AudioError>>>,
+}
+
+impl ExportJob {
+    pub fn start(
+        performance: Performance,
+        output: PathBuf,
+        format: RecordingFormat,
+        preset: MixPreset,
+    ) -> Self {
+        let target = output.clone();
+        Self {
+            output,
+            handle: Some(thread::spawn(move || {
+                render(&performance, &target, format, preset)
+            })),
+        }
+    }
+
+    /// The file written once the render has finished; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<Result<PathBuf, AudioError>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| {
+            Err(AudioError::RecordingError(format!(
+                "{}: export crashed",
+                self.output.display()
+            )))
+        }))
+    }
+}
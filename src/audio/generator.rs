@@ -0,0 +1,153 @@
+//! Synthesized reference tones and count-in clicks.
+
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use rodio::Source;
+
+use super::pitch;
+
+/// Sample rate of generated tones; the mixer resamples as needed.
+const SAMPLE_RATE: u32 = 44_100;
+/// Fade in/out so notes start and stop without clicks.
+const FADE_MS: f32 = 10.0;
+const AMPLITUDE: f32 = 0.3;
+
+/// A mono sine tone with short fades at both ends.
+#[derive(Debug, Clone)]
+pub struct Tone {
+    /// Phase increment per sample, in cycles
+    step: f32,
+    phase: f32,
+    position: usize,
+    length: usize,
+    fade: usize,
+}
+
+/// A tone at `frequency` Hz lasting `duration`. A frequency of 0 gives silence.
+pub fn create_tone(frequency: f32, duration: Duration) -> Tone {
+    let length = (duration.as_secs_f32() * SAMPLE_RATE as f32) as usize;
+    Tone {
+        step: frequency / SAMPLE_RATE as f32,
+        phase: 0.0,
+        position: 0,
+        length,
+        fade: ((FADE_MS / 1000.0 * SAMPLE_RATE as f32) as usize).min(length / 2),
+    }
+}
+
+/// The tone of pitch class `note` (0 = C) in scientific pitch `octave`,
+/// so `create_note(9, 4, ..)` is A4 at 440 Hz.
+pub fn create_note(note: u8, octave: i8, duration: Duration) -> Tone {
+    let midi = (i32::from(octave) + 1) * 12 + i32::from(note % 12);
+    create_tone(pitch::midi_to_frequency(midi as f32), duration)
+}
+
+impl Iterator for Tone {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.length {
+            return None;
+        }
+        let from_edge = self.position.min(self.length - 1 - self.position);
+        let envelope = if from_edge < self.fade {
+            from_edge as f32 / self.fade as f32
+        } else {
+            1.0
+        };
+        let sample = (self.phase * TAU).sin() * AMPLITUDE * envelope;
+        self.phase = (self.phase + self.step).fract();
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for Tone {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.length - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.length as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
+
+/// Length of one click.
+const CLICK_MS: f32 = 40.0;
+/// Click pitch: higher on the first beat of the bar.
+const ACCENT_HZ: f32 = 1_600.0;
+const CLICK_HZ: f32 = 1_000.0;
+
+/// Metronome clicks for counting a song in: short decaying blips, one per
+/// beat, the first one accented.
+#[derive(Debug, Clone)]
+pub struct CountIn {
+    /// Samples per beat
+    beat_length: usize,
+    click_length: usize,
+    position: usize,
+    length: usize,
+}
+
+/// `beats` clicks at `bpm` beats per minute.
+pub fn create_count_in(bpm: f32, beats: u8) -> CountIn {
+    let beat_length = (60.0 / bpm.clamp(30.0, 300.0) * SAMPLE_RATE as f32) as usize;
+    CountIn {
+        beat_length,
+        click_length: ((CLICK_MS / 1000.0 * SAMPLE_RATE as f32) as usize).min(beat_length),
+        position: 0,
+        length: beat_length * usize::from(beats),
+    }
+}
+
+impl Iterator for CountIn {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.length {
+            return None;
+        }
+        let beat = self.position / self.beat_length;
+        let offset = self.position % self.beat_length;
+        self.position += 1;
+        if offset >= self.click_length {
+            return Some(0.0);
+        }
+
+        let frequency = if beat == 0 { ACCENT_HZ } else { CLICK_HZ };
+        let t = offset as f32 / SAMPLE_RATE as f32;
+        let decay = (-(offset as f32) / (self.click_length as f32 / 5.0)).exp();
+        Some((t * frequency * TAU).sin() * AMPLITUDE * decay)
+    }
+}
+
+impl Source for CountIn {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.length - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        SAMPLE_RATE
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        Some(Duration::from_secs_f32(
+            self.length as f32 / SAMPLE_RATE as f32,
+        ))
+    }
+}
@@ -0,0 +1,555 @@
+//! Microphone capture: live passthrough into the output mix and pitch tracking.
+//!
+//! A cpal input stream downmixes the device to mono (or takes one of its
+//! channels) and hands chunks to a [`MicSource`] through a bounded channel,
+//! in buffers the source hands back once played, so the capture callback
+//! does not allocate. The source runs the voice effects rack and is added
+//! to the player's mixer, so the mic goes through the same master chain
+//! (feedback ducking, limiter) as the music. Howling is notched out of each
+//! mic before its effects, whichever way it is monitored. For a tighter
+//! monitor the source can instead drive an output stream of its own with a
+//! small buffer, which the system mixes with the music at the device
+//! ([`MonitorOutput::Direct`]). Up to [`MIC_COUNT`] mics run at once for
+//! duets, each with its own gain, effects and pitch tracking in
+//! [`ProcessorParams::mic`].
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SizedSample};
+use rodio::dynamic_mixer::DynamicMixerController;
+use rodio::Source;
+
+use super::dsp::feedback::MAX_NOTCHES;
+use super::dsp::{self, AutoGain, FeedbackSuppressor, NoiseGate};
+use super::effects::EffectsRack;
+use super::pitch::PitchTracker;
+use super::processor::MIC_COUNT;
+use super::recorder::RecordBuffer;
+use super::{AudioError, ProcessorParams};
+
+/// Bounds of the monitoring latency: audio buffered beyond it is dropped
+/// so the monitor never drifts late.
+pub const LATENCY_RANGE_MS: std::ops::RangeInclusive<u32> = 10..=250;
+/// Capacity of the capture channel, in device callbacks.
+const CHANNEL_CHUNKS: usize = 64;
+/// Samples each capture buffer is allocated for; a larger device callback
+/// grows the buffer once, and it is reused at that size.
+const CHUNK_CAPACITY: usize = 4096;
+/// Decay of the level meter used for ducking.
+const LEVEL_RELEASE_MS: f32 = 100.0;
+/// Fall time of the input meter's peak, and the RMS averaging time.
+const METER_PEAK_RELEASE_MS: f32 = 600.0;
+const METER_RMS_MS: f32 = 300.0;
+/// Level counted as clipping: the converter is at, or very near, full scale.
+const CLIP_LEVEL: f32 = 0.999;
+/// Buffer of the direct monitor stream, in frames (about 3 ms at 48 kHz).
+const MONITOR_BUFFER_FRAMES: u32 = 128;
+
+/// Where a monitored mic is heard.
+#[derive(Clone, Copy)]
+pub enum MonitorOutput<'a> {
+    /// Through the player's mix and master chain
+    Mix(&'a DynamicMixerController<f32>),
+    /// On a low-latency stream of its own on the default output device,
+    /// bypassing the master chain
+    Direct,
+}
+
+/// A running microphone capture. Dropping it stops the passthrough.
+pub struct MicInput {
+    _stream: cpal::Stream,
+    /// Output stream of a direct monitor
+    _monitor: Option<cpal::Stream>,
+    active: Arc<AtomicBool>,
+    device_name: String,
+}
+
+impl MicInput {
+    /// Open the input device called `device` (the default one if `None` or
+    /// no longer present) as mic `slot` and play it to `output`, buffering
+    /// at most `latency_ms` of audio. `channel` picks one input of the
+    /// device instead of mixing them all, so two singers can share an
+    /// interface.
+    ///
+    /// The mic is only heard while monitoring is on in the params; otherwise
+    /// it is captured for pitch tracking alone.
+    pub fn start(
+        output: MonitorOutput<'_>,
+        params: Arc<ProcessorParams>,
+        slot: usize,
+        device: Option<&str>,
+        channel: Option<u16>,
+        latency_ms: u32,
+    ) -> Result<Self, AudioError> {
+        let device = find_input_device(device)
+            .ok_or_else(|| AudioError::DeviceError("No input device available".to_string()))?;
+        let device_name = device
+            .name()
+            .unwrap_or_else(|_| "Unknown device".to_string());
+        let supported = device
+            .default_input_config()
+            .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+
+        let channels = usize::from(supported.channels());
+        let channel = channel.map(usize::from).filter(|&channel| {
+            let present = channel < channels;
+            if !present {
+                tracing::warn!(
+                    "{device_name} has no input channel {}; mixing all channels",
+                    channel + 1
+                );
+            }
+            present
+        });
+        let sample_rate = supported.sample_rate().0;
+        let config = supported.config();
+
+        let (sender, receiver) = mpsc::sync_channel(CHANNEL_CHUNKS);
+        // Buffers go back to the callback once played, so it never
+        // allocates: one for each chunk in the channel and the one playing
+        let (recycle, pool) = mpsc::sync_channel(CHANNEL_CHUNKS + 1);
+        for _ in 0..CHANNEL_CHUNKS {
+            let _ = recycle.try_send(Vec::with_capacity(CHUNK_CAPACITY));
+        }
+        let buffered = Arc::new(AtomicUsize::new(0));
+        let capture = Capture {
+            channels,
+            channel,
+            sender,
+            pool,
+            buffered: buffered.clone(),
+        };
+
+        let stream = match supported.sample_format() {
+            cpal::SampleFormat::F32 => build_stream::<f32>(&device, &config, capture),
+            cpal::SampleFormat::I16 => build_stream::<i16>(&device, &config, capture),
+            cpal::SampleFormat::U16 => build_stream::<u16>(&device, &config, capture),
+            other => {
+                return Err(AudioError::UnsupportedFormat(format!(
+                    "Microphone sample format {other}"
+                )))
+            },
+        }
+        .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+        stream
+            .play()
+            .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+
+        let latency_ms = latency_ms.clamp(*LATENCY_RANGE_MS.start(), *LATENCY_RANGE_MS.end());
+        let active = Arc::new(AtomicBool::new(true));
+        let source = MicSource {
+            receiver,
+            recycle,
+            buffered,
+            max_buffered: (sample_rate * latency_ms / 1000) as usize,
+            chunk: Vec::with_capacity(CHUNK_CAPACITY),
+            position: 0,
+            sample_rate,
+            active: active.clone(),
+            input_gain: 1.0,
+            gate: NoiseGate::new(sample_rate),
+            gate_threshold: None,
+            feedback: FeedbackSuppressor::new(sample_rate),
+            suppress_feedback: false,
+            agc: AutoGain::new(sample_rate),
+            agc_settings: None,
+            rack: EffectsRack::new(sample_rate),
+            rack_version: 0,
+            tracker: PitchTracker::new(sample_rate, 512),
+            level: 0.0,
+            level_release: dsp::time_coefficient(LEVEL_RELEASE_MS, sample_rate),
+            meter_peak: 0.0,
+            meter_power: 0.0,
+            meter_release: dsp::time_coefficient(METER_PEAK_RELEASE_MS, sample_rate),
+            meter_smoothing: dsp::time_coefficient(METER_RMS_MS, sample_rate),
+            monitor: false,
+            track_pitch: false,
+            recording: false,
+            record: RecordBuffer::default(),
+            slot: slot.min(MIC_COUNT - 1),
+            params,
+        };
+        let monitor = match output {
+            MonitorOutput::Mix(mixer) => {
+                mixer.add(source);
+                None
+            },
+            MonitorOutput::Direct => Some(start_monitor(source)?),
+        };
+
+        tracing::info!("Microphone {} started on {device_name}", slot + 1);
+        Ok(Self {
+            _stream: stream,
+            _monitor: monitor,
+            active,
+            device_name,
+        })
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+}
+
+/// The input device called `name`, or the default one if `None` or no
+/// longer present.
+fn find_input_device(name: Option<&str>) -> Option<cpal::Device> {
+    let host = cpal::default_host();
+    let named = name.and_then(|name| {
+        let found = host
+            .input_devices()
+            .ok()?
+            .find(|device| device.name().is_ok_and(|n| n == name));
+        if found.is_none() {
+            tracing::warn!("Microphone {name} not found; using the default input");
+        }
+        found
+    });
+    named.or_else(|| host.default_input_device())
+}
+
+/// Number of input channels of the device called `name` (the default one
+/// if `None`), 0 when it cannot be opened.
+pub fn input_channel_count(name: Option<&str>) -> u16 {
+    find_input_device(name)
+        .and_then(|device| device.default_input_config().ok())
+        .map_or(0, |config| config.channels())
+}
+
+/// Names of the available input devices.
+pub fn input_device_names() -> Vec<String> {
+    match cpal::default_host().input_devices() {
+        Ok(devices) => devices.filter_map(|device| device.name().ok()).collect(),
+        Err(e) => {
+            tracing::warn!("Cannot list input devices: {e}");
+            Vec::new()
+        },
+    }
+}
+
+impl Drop for MicInput {
+    fn drop(&mut self) {
+        self.active.store(false, Ordering::Relaxed);
+    }
+}
+
+/// State moved into the cpal capture callback.
+struct Capture {
+    channels: usize,
+    /// Single channel to take, `None` to downmix them all
+    channel: Option<usize>,
+    sender: SyncSender<Vec<f32>>,
+    /// Empty buffers to fill
+    pool: Receiver<Vec<f32>>,
+    buffered: Arc<AtomicUsize>,
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    capture: Capture,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            // All buffers queued means the output side stalled; drop the audio
+            let Ok(mut chunk) = capture.pool.try_recv() else {
+                return;
+            };
+            chunk.clear();
+            chunk.extend(
+                data.chunks(capture.channels)
+                    .map(|frame| match capture.channel {
+                        Some(channel) => frame.get(channel).map_or(0.0, |&s| f32::from_sample(s)),
+                        None => {
+                            frame.iter().map(|&s| f32::from_sample(s)).sum::<f32>()
+                                / frame.len() as f32
+                        },
+                    }),
+            );
+            let len = chunk.len();
+            // Never full, with no more buffers than it holds; fails once
+            // the source is gone
+            if capture.sender.try_send(chunk).is_ok() {
+                capture.buffered.fetch_add(len, Ordering::Relaxed);
+            }
+        },
+        |e| tracing::error!("Microphone stream error: {e}"),
+        None,
+    )
+}
+
+/// Play `source` on the default output device with a small buffer. The
+/// stream runs at the mic's rate, so the device must support it.
+fn start_monitor(source: MicSource) -> Result<cpal::Stream, AudioError> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| AudioError::DeviceError("No output device available".to_string()))?;
+    let sample_rate = cpal::SampleRate(source.sample_rate);
+    let supported = device
+        .supported_output_configs()
+        .map_err(|e| AudioError::DeviceError(e.to_string()))?
+        .filter(|range| (range.min_sample_rate()..=range.max_sample_rate()).contains(&sample_rate))
+        .max_by_key(|range| range.sample_format() == cpal::SampleFormat::F32)
+        .map(|range| range.with_sample_rate(sample_rate))
+        .ok_or_else(|| {
+            AudioError::DeviceError(format!(
+                "The output device cannot play the microphone's {} Hz directly",
+                sample_rate.0
+            ))
+        })?;
+
+    let channels = usize::from(supported.channels().max(1));
+    let mut config = supported.config();
+    config.buffer_size = match supported.buffer_size() {
+        cpal::SupportedBufferSize::Range { min, max } => {
+            cpal::BufferSize::Fixed(MONITOR_BUFFER_FRAMES.clamp(*min, *max))
+        },
+        cpal::SupportedBufferSize::Unknown => cpal::BufferSize::Default,
+    };
+    let stream = match supported.sample_format() {
+        cpal::SampleFormat::F32 => build_monitor_stream::<f32>(&device, &config, channels, source),
+        cpal::SampleFormat::I16 => build_monitor_stream::<i16>(&device, &config, channels, source),
+        cpal::SampleFormat::U16 => build_monitor_stream::<u16>(&device, &config, channels, source),
+        other => {
+            return Err(AudioError::UnsupportedFormat(format!(
+                "Monitor sample format {other}"
+            )))
+        },
+    }
+    .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+    stream
+        .play()
+        .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+    tracing::info!("Direct monitor on {:?} frames", config.buffer_size);
+    Ok(stream)
+}
+
+fn build_monitor_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    channels: usize,
+    mut source: MicSource,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            for frame in data.chunks_mut(channels) {
+                // No limiter on this path; clamp so a hot mic cannot wrap
+                let sample = source.next().unwrap_or(0.0).clamp(-1.0, 1.0);
+                frame.fill(T::from_sample(sample));
+            }
+        },
+        |e| tracing::error!("Monitor stream error: {e}"),
+        None,
+    )
+}
+
+/// Mono source playing the captured mic signal through the effects rack.
+struct MicSource {
+    receiver: Receiver<Vec<f32>>,
+    /// Played buffers, back to the capture callback
+    recycle: SyncSender<Vec<f32>>,
+    buffered: Arc<AtomicUsize>,
+    max_buffered: usize,
+    chunk: Vec<f32>,
+    position: usize,
+    sample_rate: u32,
+    active: Arc<AtomicBool>,
+    /// Input gain and gate threshold from the params, refreshed per chunk
+    input_gain: f32,
+    gate: NoiseGate,
+    gate_threshold: Option<f32>,
+    feedback: FeedbackSuppressor,
+    /// Feedback suppression is on and the mic is heard, refreshed per chunk
+    suppress_feedback: bool,
+    agc: AutoGain,
+    /// Target and most boost in dB, `None` when the AGC is off
+    agc_settings: Option<(f32, f32)>,
+    rack: EffectsRack,
+    rack_version: u64,
+    tracker: PitchTracker,
+    /// Peak follower of the monitored signal
+    level: f32,
+    level_release: f32,
+    /// Input meter after the gain: peak follower and mean square
+    meter_peak: f32,
+    meter_power: f32,
+    meter_release: f32,
+    meter_smoothing: f32,
+    /// Flags from the params, refreshed once per chunk
+    monitor: bool,
+    track_pitch: bool,
+    recording: bool,
+    record: RecordBuffer,
+    /// Index of this mic in [`ProcessorParams::mic`]
+    slot: usize,
+    params: Arc<ProcessorParams>,
+}
+
+impl MicSource {
+    fn next_chunk(&mut self) {
+        self.position = 0;
+        self.chunk.clear();
+        let mic = self.params.mic(self.slot);
+        mic.set_level(if self.monitor { self.level } else { 0.0 });
+        mic.set_meter(self.meter_peak, self.meter_power.sqrt());
+
+        if let Some(effects) = mic.voice_effects_since(&mut self.rack_version) {
+            self.rack.set_effects(&effects);
+        }
+        self.input_gain = mic.input_gain();
+        self.recording = mic.record_tap().is_active();
+        self.rack.set_autotune(self.params.autotune());
+        self.rack.set_send(self.params.effects_send());
+        self.rack.set_key(self.params.song_key());
+        self.gate_threshold = self.params.noise_gate_threshold();
+        self.agc_settings = self.params.agc();
+        mic.set_agc_gain_db(if self.agc_settings.is_some() {
+            self.agc.gain_db()
+        } else {
+            0.0
+        });
+        self.monitor = self.params.mic_monitor();
+        self.track_pitch = self.params.pitch_tracking() || self.params.pitch_guide();
+        self.update_feedback();
+
+        // Skip stale audio so latency stays bounded after a hiccup
+        while self.buffered.load(Ordering::Relaxed) > self.max_buffered {
+            match self.receiver.try_recv() {
+                Ok(stale) => {
+                    self.buffered.fetch_sub(stale.len(), Ordering::Relaxed);
+                    let _ = self.recycle.try_send(stale);
+                },
+                Err(_) => break,
+            }
+        }
+
+        if let Ok(chunk) = self.receiver.try_recv() {
+            self.buffered.fetch_sub(chunk.len(), Ordering::Relaxed);
+            let played = std::mem::replace(&mut self.chunk, chunk);
+            let _ = self.recycle.try_send(played);
+        }
+    }
+
+    /// Apply notch releases asked for by the UI and report the notches. A
+    /// mic nobody hears cannot feed back, so its notches are dropped.
+    fn update_feedback(&mut self) {
+        let mic = self.params.mic(self.slot);
+        self.suppress_feedback = self.params.feedback_enabled() && self.monitor;
+        let release = if self.suppress_feedback {
+            mic.take_notch_release()
+        } else {
+            u32::MAX
+        };
+        for slot in (0..MAX_NOTCHES).filter(|slot| release & (1 << slot) != 0) {
+            self.feedback.release(slot);
+        }
+        mic.set_feedback(
+            self.feedback.notch_frequencies(),
+            self.feedback.events(),
+            self.suppress_feedback && self.feedback.wants_duck(),
+        );
+    }
+
+    /// Feed the input meter, ahead of the gate so the noise floor shows.
+    fn meter(&mut self, sample: f32) {
+        let magnitude = sample.abs();
+        if magnitude >= CLIP_LEVEL {
+            self.params.mic(self.slot).report_clip();
+        }
+        self.meter_peak = magnitude.max(self.meter_peak * self.meter_release);
+        self.meter_power =
+            sample * sample + (self.meter_power - sample * sample) * self.meter_smoothing;
+    }
+}
+
+impl Iterator for MicSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if !self.active.load(Ordering::Relaxed) {
+            let mic = self.params.mic(self.slot);
+            mic.set_level(0.0);
+            mic.set_meter(0.0, 0.0);
+            return None;
+        }
+        if self.position >= self.chunk.len() {
+            self.next_chunk();
+        }
+        // Underrun: keep the stream alive with silence
+        let Some(&sample) = self.chunk.get(self.position) else {
+            if self.recording {
+                self.record.push(
+                    self.params.mic(self.slot).record_tap(),
+                    &[0.0],
+                    self.sample_rate,
+                );
+            }
+            return Some(0.0);
+        };
+        self.position += 1;
+
+        let mut sample = sample * self.input_gain;
+        self.meter(sample);
+        // Ahead of the gate and AGC, so a howl is not boosted on its way
+        if self.suppress_feedback {
+            sample = self.feedback.process(sample);
+        }
+        if let Some(threshold) = self.gate_threshold {
+            sample = self.gate.process(sample, threshold);
+        }
+        // After the gate, so pauses hold the gain rather than raise it
+        if let Some((target_db, max_gain_db)) = self.agc_settings {
+            sample = self.agc.process(sample, target_db, max_gain_db);
+        }
+
+        if self.track_pitch {
+            if let Some(estimate) = self.tracker.push(sample) {
+                self.params.mic(self.slot).set_pitch(estimate);
+            }
+        }
+        self.level = sample.abs().max(self.level * self.level_release);
+        if !self.monitor && !self.recording {
+            return Some(0.0);
+        }
+        let voice = self.rack.process(sample);
+        if self.recording {
+            self.record.push(
+                self.params.mic(self.slot).record_tap(),
+                &[voice],
+                self.sample_rate,
+            );
+        }
+        Some(if self.monitor { voice } else { 0.0 })
+    }
+}
+
+impl Source for MicSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        1
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -0,0 +1,344 @@
+//! Musical key detection.
+//!
+//! The track is decoded once, folded into a 12-bin chromagram and correlated
+//! against the Krumhansl-Kessler key profiles; the best of the 24 major and
+//! minor keys wins.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use super::dsp::fft;
+use super::pitch;
+use super::{player, AudioError};
+
+/// Furthest a backing track is shifted from its key, in semitones; further
+/// than this the shifted music sounds artificial.
+pub const MAX_SHIFT: i8 = 6;
+
+const MAJOR_SCALE: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+const MINOR_SCALE: [u8; 7] = [0, 2, 3, 5, 7, 8, 10];
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+const FFT_SIZE: usize = 4096;
+/// Frequency range folded into the chromagram.
+const MIN_CHROMA_HZ: f32 = 55.0;
+const MAX_CHROMA_HZ: f32 = 2000.0;
+/// Only the start of long tracks is analysed.
+const MAX_ANALYSIS_SECS: u32 = 240;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Mode {
+    Major,
+    Minor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Key {
+    /// Pitch class of the tonic, 0 = C
+    pub tonic: u8,
+    pub mode: Mode,
+}
+
+impl Key {
+    fn scale(self) -> &'static [u8; 7] {
+        match self.mode {
+            Mode::Major => &MAJOR_SCALE,
+            Mode::Minor => &MINOR_SCALE,
+        }
+    }
+
+    /// Whether the pitch class (0 = C) belongs to the key's scale.
+    pub fn contains(self, pitch_class: u8) -> bool {
+        let degree = (pitch_class + 12 - self.tonic % 12) % 12;
+        self.scale().contains(&degree)
+    }
+
+    /// The in-key MIDI note closest to a (fractional) MIDI note.
+    pub fn nearest_note(self, note: f32) -> f32 {
+        let base = note.round();
+        [0.0, -1.0, 1.0, -2.0, 2.0]
+            .into_iter()
+            .map(|offset| base + offset)
+            .filter(|candidate| self.contains(candidate.rem_euclid(12.0) as u8))
+            .min_by(|a, b| (a - note).abs().total_cmp(&(b - note).abs()))
+            .unwrap_or(base)
+    }
+
+    /// The key `semitones` higher, or lower when negative.
+    pub fn transposed(self, semitones: i8) -> Self {
+        Self {
+            tonic: (i16::from(self.tonic) + i16::from(semitones)).rem_euclid(12) as u8,
+            mode: self.mode,
+        }
+    }
+
+    /// Compact encoding for sharing through an atomic: 0 means no key.
+    pub fn encode(key: Option<Self>) -> u32 {
+        key.map_or(0, |key| {
+            1 + u32::from(key.tonic % 12) * 2 + u32::from(key.mode == Mode::Minor)
+        })
+    }
+
+    pub fn decode(value: u32) -> Option<Self> {
+        let index = value.checked_sub(1)?;
+        Some(Self {
+            tonic: ((index / 2) % 12) as u8,
+            mode: if index % 2 == 1 {
+                Mode::Minor
+            } else {
+                Mode::Major
+            },
+        })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mode = match self.mode {
+            Mode::Major => "major",
+            Mode::Minor => "minor",
+        };
+        write!(
+            f,
+            "{} {mode}",
+            pitch::NOTE_NAMES[usize::from(self.tonic % 12)]
+        )
+    }
+}
+
+/// Decode `path` and estimate its key.
+pub fn detect_key(path: &Path) -> Result<Key, AudioError> {
+    let decoder = player::open_decoder(path)?;
+    let channels = usize::from(decoder.channels().max(1));
+    let sample_rate = decoder.sample_rate();
+    let max_samples = (sample_rate * MAX_ANALYSIS_SECS) as usize * channels;
+
+    let window = fft::hann_window(FFT_SIZE);
+    let mut chroma = [0.0_f32; 12];
+    let mut block = Vec::with_capacity(FFT_SIZE);
+    let mut frame = Vec::with_capacity(channels);
+    let mut re = vec![0.0; FFT_SIZE];
+    let mut im = vec![0.0; FFT_SIZE];
+
+    for sample in decoder.convert_samples::<f32>().take(max_samples) {
+        frame.push(sample);
+        if frame.len() < channels {
+            continue;
+        }
+        block.push(frame.drain(..).sum::<f32>() / channels as f32);
+        if block.len() < FFT_SIZE {
+            continue;
+        }
+
+        for (i, sample) in block.drain(..).enumerate() {
+            re[i] = sample * window[i];
+            im[i] = 0.0;
+        }
+        fft::fft(&mut re, &mut im);
+        accumulate_chroma(&re, &im, sample_rate, &mut chroma);
+    }
+
+    best_key(&chroma).ok_or_else(|| {
+        AudioError::LoadError(format!(
+            "{}: too short or silent for key detection",
+            path.display()
+        ))
+    })
+}
+
+fn accumulate_chroma(re: &[f32], im: &[f32], sample_rate: u32, chroma: &mut [f32; 12]) {
+    let bin_width = sample_rate as f32 / FFT_SIZE as f32;
+    let first = (MIN_CHROMA_HZ / bin_width).ceil() as usize;
+    let last = ((MAX_CHROMA_HZ / bin_width) as usize).min(FFT_SIZE / 2 - 1);
+    for bin in first..=last {
+        let magnitude = (re[bin].powi(2) + im[bin].powi(2)).sqrt();
+        let note = pitch::frequency_to_midi(bin as f32 * bin_width).round();
+        chroma[note.rem_euclid(12.0) as usize] += magnitude;
+    }
+}
+
+/// The key whose profile correlates best with the chromagram.
+fn best_key(chroma: &[f32; 12]) -> Option<Key> {
+    if chroma.iter().sum::<f32>() <= f32::EPSILON {
+        return None;
+    }
+    (0..12_u8)
+        .flat_map(|tonic| {
+            [
+                (
+                    Key {
+                        tonic,
+                        mode: Mode::Major,
+                    },
+                    &MAJOR_PROFILE,
+                ),
+                (
+                    Key {
+                        tonic,
+                        mode: Mode::Minor,
+                    },
+                    &MINOR_PROFILE,
+                ),
+            ]
+        })
+        .map(|(key, profile)| {
+            let rotated: [f32; 12] =
+                std::array::from_fn(|pc| profile[(pc + 12 - usize::from(key.tonic)) % 12]);
+            (key, correlation(chroma, &rotated))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(key, _)| key)
+}
+
+/// Pearson correlation of two 12-bin vectors.
+fn correlation(a: &[f32; 12], b: &[f32; 12]) -> f32 {
+    let mean_a = a.iter().sum::<f32>() / 12.0;
+    let mean_b = b.iter().sum::<f32>() / 12.0;
+    let (mut covariance, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    covariance / (var_a * var_b).sqrt().max(f32::EPSILON)
+}
+
+/// Queue of a running [`KeyDetection`].
+#[derive(Debug, Default)]
+struct Queue {
+    pending: VecDeque<PathBuf>,
+    current: Option<PathBuf>,
+    done: usize,
+    results: Vec<(PathBuf, Key)>,
+    cancelled: bool,
+    /// Set by the thread when it stops taking songs
+    closed: bool,
+}
+
+/// Detects the keys of a queue of songs on a background thread. Songs can
+/// be added while it runs, e.g. the one just started ahead of a library
+/// scan.
+pub struct KeyDetection {
+    queue: Arc<Mutex<Queue>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl KeyDetection {
+    /// Start analysing `songs` in order.
+    pub fn start(songs: Vec<PathBuf>) -> Self {
+        let queue = Arc::new(Mutex::new(Queue {
+            pending: songs.into(),
+            ..Queue::default()
+        }));
+        let handle = {
+            let queue = queue.clone();
+            thread::spawn(move || loop {
+                let song = {
+                    let mut queue = lock(&queue);
+                    let next = queue.pending.pop_front().filter(|_| !queue.cancelled);
+                    let Some(song) = next else {
+                        queue.closed = true;
+                        break;
+                    };
+                    queue.current = Some(song.clone());
+                    song
+                };
+                let result = detect_key(&song);
+                let mut queue = lock(&queue);
+                queue.current = None;
+                queue.done += 1;
+                match result {
+                    Ok(key) => queue.results.push((song, key)),
+                    Err(e) => tracing::warn!("{e}"),
+                }
+            })
+        };
+        Self {
+            queue,
+            handle: Some(handle),
+        }
+    }
+
+    /// Add `songs` to the queue, ahead of the others when `first`. Returns
+    /// false when the job has stopped taking songs; start a new one then.
+    pub fn add(&self, songs: &[PathBuf], first: bool) -> bool {
+        let mut queue = lock(&self.queue);
+        if queue.closed {
+            return false;
+        }
+        queue.pending.retain(|pending| !songs.contains(pending));
+        let new = songs
+            .iter()
+            .filter(|song| queue.current.as_ref() != Some(*song))
+            .cloned()
+            .collect::<Vec<_>>();
+        if first {
+            for song in new.into_iter().rev() {
+                queue.pending.push_front(song);
+            }
+        } else {
+            queue.pending.extend(new);
+        }
+        true
+    }
+
+    /// Whether `song` is being or still to be analysed.
+    pub fn is_pending(&self, song: &Path) -> bool {
+        let queue = lock(&self.queue);
+        queue.current.as_deref() == Some(song) || queue.pending.iter().any(|path| path == song)
+    }
+
+    /// Songs analysed so far and songs in total.
+    pub fn progress(&self) -> (usize, usize) {
+        let queue = lock(&self.queue);
+        let total = queue.done + queue.pending.len() + usize::from(queue.current.is_some());
+        (queue.done, total)
+    }
+
+    /// Stop after the song being analysed.
+    pub fn cancel(&self) {
+        lock(&self.queue).cancelled = true;
+    }
+
+    /// Keys found since the last call.
+    pub fn take_results(&self) -> Vec<(PathBuf, Key)> {
+        std::mem::take(&mut lock(&self.queue).results)
+    }
+
+    /// Whether the job has ended; the last results may still be waiting in
+    /// [`Self::take_results`].
+    pub fn is_finished(&mut self) -> bool {
+        let Some(handle) = &self.handle else {
+            return true;
+        };
+        if !handle.is_finished() {
+            return false;
+        }
+        if self
+            .handle
+            .take()
+            .is_some_and(|handle| handle.join().is_err())
+        {
+            tracing::error!("Key detection crashed");
+        }
+        true
+    }
+}
+
+/// Lock ignoring poisoning: the results stay usable after a panic.
+fn lock(queue: &Mutex<Queue>) -> MutexGuard<'_, Queue> {
+    queue
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
@@ -0,0 +1,54 @@
+//! Whole-track loudness measurement, used to even out song volumes.
+//!
+//! Loudness is the mean power of 400 ms blocks in dBFS, ignoring blocks
+//! quieter than [`GATE_DB`] so long silent intros and outros do not drag
+//! it down (a simplified, unweighted take on EBU R128 gating).
+
+use std::path::Path;
+
+use rodio::Source;
+
+use super::{dsp, player, AudioError};
+
+const BLOCK_MS: u32 = 400;
+/// Blocks below this level are silence and left out of the average.
+const GATE_DB: f32 = -60.0;
+/// Loudness songs are evened out to.
+pub const TARGET_DB: f32 = -18.0;
+/// Largest correction suggested, matching the per-song volume offset range.
+const MAX_OFFSET_DB: f32 = 12.0;
+
+/// Decode `path` and measure its gated loudness in dBFS. `None` for a
+/// silent track.
+pub fn measure(path: &Path) -> Result<Option<f32>, AudioError> {
+    let decoder = player::open_decoder(path)?;
+    let block_len =
+        (decoder.sample_rate() * BLOCK_MS / 1000) as usize * usize::from(decoder.channels().max(1));
+    let gate = dsp::db_to_linear(GATE_DB).powi(2);
+
+    let (mut total, mut blocks) = (0.0_f64, 0_u64);
+    let (mut sum, mut count) = (0.0_f64, 0);
+    for sample in decoder.convert_samples::<f32>() {
+        sum += f64::from(sample * sample);
+        count += 1;
+        if count == block_len {
+            let power = sum / count as f64;
+            if power > f64::from(gate) {
+                total += power;
+                blocks += 1;
+            }
+            (sum, count) = (0.0, 0);
+        }
+    }
+
+    if blocks == 0 {
+        return Ok(None);
+    }
+    let power = (total / blocks as f64) as f32;
+    Ok(Some(dsp::linear_to_db(power.sqrt())))
+}
+
+/// Volume offset bringing a track of `loudness` dBFS to [`TARGET_DB`].
+pub fn volume_offset(loudness: f32) -> f32 {
+    (TARGET_DB - loudness).clamp(-MAX_OFFSET_DB, MAX_OFFSET_DB)
+}
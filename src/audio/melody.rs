@@ -0,0 +1,246 @@
+//! Vocal melody estimated from the audio, for the pitch guide.
+//!
+//! The song is pitch tracked offline, on the separated vocals when an
+//! instrumental stem is cached and on the full mix otherwise, and the
+//! estimates are grouped into notes. The notes are cached in the data
+//! directory like waveforms; a melody taken from the mix is extracted again
+//! once a stem exists, as the vocals alone track far better.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use super::{pitch, player, separation, AudioError};
+use crate::config;
+
+const CACHE_DIR_NAME: &str = "melodies";
+/// Pitch estimates per second of audio.
+const PITCH_RATE_HZ: u32 = 20;
+/// Estimates further than this from the note so far start a new note.
+const NOTE_TOLERANCE: f32 = 0.75;
+/// Longest unvoiced gap inside one note.
+const MAX_GAP_SECS: f32 = 0.15;
+/// Shorter notes are tracking noise more often than sung notes.
+const MIN_NOTE_SECS: f32 = 0.12;
+
+/// A sung note.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct MelodyNote {
+    /// Seconds into the song
+    pub start: f32,
+    pub end: f32,
+    /// Fractional MIDI note
+    pub note: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Melody {
+    /// Sorted by start
+    pub notes: Vec<MelodyNote>,
+}
+
+impl Melody {
+    /// Notes overlapping `start..end` seconds.
+    pub fn notes_between(&self, start: f32, end: f32) -> &[MelodyNote] {
+        let first = self.notes.partition_point(|note| note.end < start);
+        let last = self.notes.partition_point(|note| note.start <= end);
+        &self.notes[first..last.max(first)]
+    }
+
+    /// The note due `secs` into the song.
+    pub fn note_at(&self, secs: f32) -> Option<&MelodyNote> {
+        self.notes_between(secs, secs)
+            .iter()
+            .find(|note| note.start <= secs && secs < note.end)
+    }
+
+    /// Lowest and highest note, `None` for an empty melody.
+    pub fn range(&self) -> Option<(f32, f32)> {
+        let notes = self.notes.iter().map(|note| note.note);
+        let low = notes.clone().min_by(f32::total_cmp)?;
+        let high = notes.max_by(f32::total_cmp)?;
+        Some((low, high))
+    }
+}
+
+/// Cache file contents; the key guards against hash collisions and edits.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    path: PathBuf,
+    modified: SystemTime,
+    /// Tracked on the separated vocals rather than the mix
+    from_stem: bool,
+    melody: Melody,
+}
+
+/// The melody of `song`, from the cache when it is still valid.
+pub fn load_or_extract(song: &Path) -> Result<Melody, AudioError> {
+    let modified = fs::metadata(song)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| AudioError::LoadError(format!("{}: {e}", song.display())))?;
+    let cache = cache_path(song, modified);
+    let stem = separation::instrumental_path(song).exists();
+
+    if let Ok(contents) = fs::read_to_string(&cache) {
+        match serde_json::from_str::<CacheEntry>(&contents) {
+            Ok(entry)
+                if entry.path == song && entry.modified == modified && entry.from_stem == stem =>
+            {
+                return Ok(entry.melody);
+            },
+            Ok(_) => {},
+            Err(e) => tracing::warn!("Invalid melody cache {}: {e}", cache.display()),
+        }
+    }
+
+    let melody = Melody {
+        notes: group_notes(&track(song)?),
+    };
+    let entry = CacheEntry {
+        path: song.to_path_buf(),
+        modified,
+        from_stem: stem,
+        melody,
+    };
+    if let Err(e) = write_cache(&cache, &entry) {
+        tracing::warn!("Failed to cache melody {}: {e}", cache.display());
+    }
+    Ok(entry.melody)
+}
+
+/// Sung pitch of the song as `(seconds, fractional MIDI note)`, voiced
+/// estimates only.
+pub fn track(song: &Path) -> Result<Vec<(f32, f32)>, AudioError> {
+    let decoder = player::open_decoder(song)?;
+    let channels = usize::from(decoder.channels().max(1));
+    let sample_rate = decoder.sample_rate();
+    // The vocals alone are what the original has over the instrumental
+    let instrumental = player::open_decoder(&separation::instrumental_path(song))
+        .ok()
+        .filter(|stem| {
+            stem.sample_rate() == sample_rate && usize::from(stem.channels()) == channels
+        });
+    let samples: Box<dyn Iterator<Item = f32>> = match instrumental {
+        Some(stem) => Box::new(
+            decoder
+                .convert_samples::<f32>()
+                .zip(stem.convert_samples::<f32>())
+                .map(|(original, stem)| original - stem),
+        ),
+        None => Box::new(decoder.convert_samples::<f32>()),
+    };
+
+    let hop = (sample_rate / PITCH_RATE_HZ) as usize;
+    let mut tracker = pitch::PitchTracker::new(sample_rate, hop);
+    let mut melody = Vec::new();
+    let mut frame = Vec::with_capacity(channels);
+    let mut position = 0_u64;
+    for sample in samples {
+        frame.push(sample);
+        if frame.len() < channels {
+            continue;
+        }
+        let mono = frame.drain(..).sum::<f32>() / channels as f32;
+        position += 1;
+        if let Some(Some(frequency)) = tracker.push(mono) {
+            let time = position as f32 / sample_rate as f32;
+            melody.push((time, pitch::frequency_to_midi(frequency)));
+        }
+    }
+    Ok(melody)
+}
+
+/// Runs of close, nearly contiguous estimates as notes at their median
+/// pitch.
+fn group_notes(pitches: &[(f32, f32)]) -> Vec<MelodyNote> {
+    let hop = 1.0 / PITCH_RATE_HZ as f32;
+    let mut notes = Vec::new();
+    let mut run: Vec<f32> = Vec::new();
+    let mut start = 0.0;
+    let mut last = f32::NEG_INFINITY;
+    for &(time, note) in pitches {
+        let continues = time - last <= MAX_GAP_SECS + hop
+            && median(&run).is_some_and(|median| (note - median).abs() <= NOTE_TOLERANCE);
+        if !continues {
+            push_note(&mut notes, start, last + hop, &run);
+            run.clear();
+            start = time;
+        }
+        run.push(note);
+        last = time;
+    }
+    push_note(&mut notes, start, last + hop, &run);
+    notes
+}
+
+fn push_note(notes: &mut Vec<MelodyNote>, start: f32, end: f32, run: &[f32]) {
+    if end - start < MIN_NOTE_SECS {
+        return;
+    }
+    if let Some(note) = median(run) {
+        notes.push(MelodyNote { start, end, note });
+    }
+}
+
+fn median(values: &[f32]) -> Option<f32> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    sorted.get(sorted.len() / 2).copied()
+}
+
+fn cache_path(song: &Path, modified: SystemTime) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    song.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    config::data_dir()
+        .join(CACHE_DIR_NAME)
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn write_cache(cache: &Path, entry: &CacheEntry) -> anyhow::Result<()> {
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(cache, serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Melody extraction on a background thread.
+pub struct MelodyJob {
+    song: PathBuf,
+    handle: Option<JoinHandle<Result<Melody, AudioError>>>,
+}
+
+impl MelodyJob {
+    pub fn start(song: &Path) -> Self {
+        let owned = song.to_path_buf();
+        Self {
+            song: song.to_path_buf(),
+            handle: Some(thread::spawn(move || load_or_extract(&owned))),
+        }
+    }
+
+    pub fn song(&self) -> &Path {
+        &self.song
+    }
+
+    /// The result once extraction has finished; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<Result<Melody, AudioError>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| {
+            Err(AudioError::LoadError(format!(
+                "{}: melody extraction crashed",
+                self.song.display()
+            )))
+        }))
+    }
+}
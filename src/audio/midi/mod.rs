@@ -0,0 +1,422 @@
+//! Standard MIDI files and `.kar` karaoke files.
+//!
+//! The notes of every track are merged and timed through the tempo map,
+//! for the [built-in synthesizer](synth) to play as the backing track.
+//! Lyrics come from the lyric events, or in `.kar` files from the text
+//! events, where `/` starts a new line and `\` a new paragraph; `@` lines
+//! are file headers. Each event is a syllable, so the lines get word
+//! timestamps and highlight as they are sung.
+
+pub mod synth;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use super::AudioError;
+use crate::lrc::encoding;
+use crate::lrc::{LyricSegment, TimedLine};
+
+/// Extensions of MIDI songs.
+pub const MIDI_EXTENSIONS: [&str; 3] = ["mid", "midi", "kar"];
+/// Tempo until the file sets one: 120 beats per minute.
+const DEFAULT_TEMPO: u32 = 500_000;
+/// Channel of the General MIDI drum kit.
+const DRUM_CHANNEL: u8 = 9;
+/// Controllers the synthesizer follows.
+const CONTROL_VOLUME: u8 = 7;
+const CONTROL_PAN: u8 = 10;
+const CONTROL_SUSTAIN: u8 = 64;
+
+/// Meta events read from the tracks.
+const META_TEXT: u8 = 0x01;
+const META_LYRIC: u8 = 0x05;
+const META_END_OF_TRACK: u8 = 0x2F;
+const META_TEMPO: u8 = 0x51;
+
+pub fn is_midi(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| MIDI_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// A note as the synthesizer plays it, timed in seconds.
+#[derive(Debug, Clone)]
+pub struct Note {
+    pub start: f64,
+    pub end: f64,
+    pub channel: u8,
+    pub key: u8,
+    pub velocity: u8,
+    /// Instrument of the channel when the note started
+    pub program: u8,
+    /// Channel volume and pan when the note started, 0 to 127
+    pub volume: u8,
+    pub pan: u8,
+}
+
+impl Note {
+    pub fn is_drum(&self) -> bool {
+        self.channel == DRUM_CHANNEL
+    }
+}
+
+/// The notes and lyrics of a MIDI file.
+#[derive(Debug, Default)]
+pub struct MidiSong {
+    pub notes: Vec<Note>,
+    /// Lyric syllables in order, with the time they are sung in seconds
+    pub syllables: Vec<(f64, String)>,
+    /// Time of the last event in seconds
+    pub length: f64,
+}
+
+impl MidiSong {
+    pub fn read(path: &Path) -> Result<Self, AudioError> {
+        let bytes = fs::read(path)
+            .map_err(|e| AudioError::LoadError(format!("{}: {e}", path.display())))?;
+        let karaoke = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("kar"));
+        parse(&bytes, karaoke)
+            .map_err(|e| AudioError::UnsupportedFormat(format!("{}: {e}", path.display())))
+    }
+
+    /// The syllables gathered into lines, each syllable a word timestamp.
+    pub fn lyric_lines(&self) -> Vec<TimedLine> {
+        let mut lines = Vec::new();
+        let mut line: Option<TimedLine> = None;
+        let mut finish = |line: &mut Option<TimedLine>| {
+            if let Some(line) = line.take().filter(|line| !line.text.trim().is_empty()) {
+                lines.push(line);
+            }
+        };
+        for (time, syllable) in &self.syllables {
+            let mut text = syllable.as_str();
+            if text.starts_with(['/', '\\', '\r', '\n']) {
+                finish(&mut line);
+                text = text.trim_start_matches(['/', '\\', '\r', '\n']);
+            }
+            let ends_line = text.ends_with(['\r', '\n']);
+            let text = text.trim_end_matches(['\r', '\n']);
+            if !text.is_empty() {
+                let start = Duration::from_secs_f64(*time);
+                let line = line.get_or_insert_with(|| TimedLine {
+                    start,
+                    text: String::new(),
+                    segments: Vec::new(),
+                });
+                line.segments.push(LyricSegment {
+                    start,
+                    offset: line.text.chars().count(),
+                });
+                line.text.push_str(text);
+            }
+            if ends_line {
+                finish(&mut line);
+            }
+        }
+        finish(&mut line);
+        lines
+    }
+}
+
+/// Lyrics of the MIDI song at `path` as timed lines; empty when it has none
+/// or cannot be read.
+pub fn lyrics(path: &Path) -> Vec<TimedLine> {
+    match MidiSong::read(path) {
+        Ok(song) => song.lyric_lines(),
+        Err(e) => {
+            tracing::warn!("{e}");
+            Vec::new()
+        },
+    }
+}
+
+enum Event {
+    Tempo(u32),
+    NoteOn { channel: u8, key: u8, velocity: u8 },
+    NoteOff { channel: u8, key: u8 },
+    Program { channel: u8, program: u8 },
+    Control { channel: u8, control: u8, value: u8 },
+    Text(Vec<u8>),
+    Lyric(Vec<u8>),
+}
+
+/// Reads the big-endian fields of a MIDI file.
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, count: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .data
+            .get(self.position..self.position + count)
+            .ok_or("file ends early")?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Variable-length quantity: seven bits per byte, high bit set on all
+    /// but the last.
+    fn varlen(&mut self) -> Result<u32, String> {
+        let mut value = 0_u32;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = (value << 7) | u32::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("malformed length".to_string())
+    }
+
+    fn done(&self) -> bool {
+        self.position >= self.data.len()
+    }
+}
+
+fn parse(bytes: &[u8], karaoke: bool) -> Result<MidiSong, String> {
+    let mut reader = Reader {
+        data: bytes,
+        position: 0,
+    };
+    if reader.take(4)? != b"MThd" {
+        return Err("not a MIDI file".to_string());
+    }
+    let header_length = reader.u32()? as usize;
+    let header = reader.take(header_length.max(6))?;
+    let tracks = u16::from_be_bytes([header[2], header[3]]);
+    let division = u16::from_be_bytes([header[4], header[5]]);
+
+    // Events of all tracks by tick; the sort keeps each track's order
+    let mut events: Vec<(u64, Event)> = Vec::new();
+    for _ in 0..tracks {
+        if reader.done() {
+            break;
+        }
+        let kind = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        let chunk = reader.take(length)?;
+        if kind == b"MTrk" {
+            read_track(chunk, &mut events)?;
+        }
+    }
+    events.sort_by_key(|(tick, _)| *tick);
+
+    // Seconds per tick: from the tempo, or fixed with SMPTE timing
+    let smpte = (division & 0x8000 != 0).then(|| {
+        let frames = f64::from(-((division >> 8) as u8 as i8));
+        1.0 / (frames * f64::from(division & 0xFF)).max(1.0)
+    });
+    let ticks_per_beat = f64::from(division.max(1));
+    let mut tempo = DEFAULT_TEMPO;
+
+    let mut song = MidiSong::default();
+    let (mut time, mut last_tick) = (0.0, 0);
+    let mut programs = [0_u8; 16];
+    let mut volumes = [100_u8; 16];
+    let mut pans = [64_u8; 16];
+    let mut sustain = [false; 16];
+    // Sounding notes by channel and key, as indices into `song.notes`
+    let mut sounding: HashMap<(u8, u8), Vec<usize>> = HashMap::new();
+    // Released while the sustain pedal was down
+    let mut held: Vec<usize> = Vec::new();
+    let mut texts = Vec::new();
+    let mut lyrics = Vec::new();
+    for (tick, event) in events {
+        let seconds_per_tick = smpte.unwrap_or(f64::from(tempo) / 1_000_000.0 / ticks_per_beat);
+        time += (tick - last_tick) as f64 * seconds_per_tick;
+        last_tick = tick;
+        match event {
+            Event::Tempo(value) => tempo = value,
+            Event::NoteOn {
+                channel,
+                key,
+                velocity,
+            } => {
+                let index = usize::from(channel);
+                sounding
+                    .entry((channel, key))
+                    .or_default()
+                    .push(song.notes.len());
+                song.notes.push(Note {
+                    start: time,
+                    end: f64::INFINITY,
+                    channel,
+                    key,
+                    velocity,
+                    program: programs[index],
+                    volume: volumes[index],
+                    pan: pans[index],
+                });
+            },
+            Event::NoteOff { channel, key } => {
+                let Some(note) = sounding
+                    .get_mut(&(channel, key))
+                    .and_then(|notes| (!notes.is_empty()).then(|| notes.remove(0)))
+                else {
+                    continue;
+                };
+                if sustain[usize::from(channel)] {
+                    held.push(note);
+                } else {
+                    song.notes[note].end = time;
+                }
+            },
+            Event::Program { channel, program } => programs[usize::from(channel)] = program,
+            Event::Control {
+                channel,
+                control,
+                value,
+            } => {
+                let index = usize::from(channel);
+                match control {
+                    CONTROL_VOLUME => volumes[index] = value,
+                    CONTROL_PAN => pans[index] = value,
+                    CONTROL_SUSTAIN => {
+                        sustain[index] = value >= 64;
+                        if !sustain[index] {
+                            held.retain(|&note| {
+                                let released = song.notes[note].channel == channel;
+                                if released {
+                                    song.notes[note].end = time;
+                                }
+                                !released
+                            });
+                        }
+                    },
+                    _ => {},
+                }
+            },
+            Event::Text(text) => texts.push((time, text)),
+            Event::Lyric(text) => lyrics.push((time, text)),
+        }
+    }
+    song.length = time;
+    for note in &mut song.notes {
+        note.end = note.end.min(time);
+    }
+
+    // `.kar` files keep their lyrics in text events, with `@` headers
+    texts.retain(|(_, text)| !text.starts_with(b"@"));
+    let syllables = if karaoke && !texts.is_empty() {
+        texts
+    } else {
+        lyrics
+    };
+    // Old files use a regional code page; guess it from all the text
+    let all: Vec<u8> = syllables
+        .iter()
+        .flat_map(|(_, text)| text.clone())
+        .collect();
+    let (_, text_encoding) = encoding::decode(&all);
+    song.syllables = syllables
+        .into_iter()
+        .map(|(time, text)| {
+            let (text, _) = text_encoding.decode_without_bom_handling(&text);
+            (time, text.into_owned())
+        })
+        .collect();
+    Ok(song)
+}
+
+/// Add the events of one track chunk to `events`.
+fn read_track(chunk: &[u8], events: &mut Vec<(u64, Event)>) -> Result<(), String> {
+    let mut reader = Reader {
+        data: chunk,
+        position: 0,
+    };
+    let mut tick = 0_u64;
+    let mut running_status = 0_u8;
+    while !reader.done() {
+        tick += u64::from(reader.varlen()?);
+        let mut status = reader.byte()?;
+        match status {
+            0xFF => {
+                let kind = reader.byte()?;
+                let length = reader.varlen()? as usize;
+                let data = reader.take(length)?;
+                match kind {
+                    META_TEMPO if data.len() == 3 => {
+                        let tempo = u32::from_be_bytes([0, data[0], data[1], data[2]]);
+                        events.push((tick, Event::Tempo(tempo)));
+                    },
+                    META_TEXT => events.push((tick, Event::Text(data.to_vec()))),
+                    META_LYRIC => events.push((tick, Event::Lyric(data.to_vec()))),
+                    META_END_OF_TRACK => break,
+                    _ => {},
+                }
+                continue;
+            },
+            0xF0 | 0xF7 => {
+                let length = reader.varlen()? as usize;
+                reader.take(length)?;
+                continue;
+            },
+            _ => {},
+        }
+
+        // Running status: the data byte follows the last status
+        let first = if status & 0x80 == 0 {
+            let data = status;
+            status = running_status;
+            data
+        } else {
+            running_status = status;
+            reader.byte()?
+        };
+        let channel = status & 0x0F;
+        let event = match status & 0xF0 {
+            0x80 => {
+                reader.byte()?;
+                Event::NoteOff {
+                    channel,
+                    key: first,
+                }
+            },
+            0x90 => match reader.byte()? {
+                0 => Event::NoteOff {
+                    channel,
+                    key: first,
+                },
+                velocity => Event::NoteOn {
+                    channel,
+                    key: first,
+                    velocity,
+                },
+            },
+            0xB0 => Event::Control {
+                channel,
+                control: first,
+                value: reader.byte()?,
+            },
+            0xC0 => Event::Program {
+                channel,
+                program: first,
+            },
+            // Channel pressure has one data byte
+            0xD0 => continue,
+            // Key pressure and pitch bend have two
+            0xA0 | 0xE0 => {
+                reader.byte()?;
+                continue;
+            },
+            _ => return Err(format!("unknown status byte {status:#04x}")),
+        };
+        events.push((tick, event));
+    }
+    Ok(())
+}
@@ -0,0 +1,227 @@
+//! A small built-in synthesizer for the backing track of MIDI songs.
+//!
+//! Each General MIDI instrument family gets a simple additive timbre and
+//! envelope, and the drum kit is made of noise bursts and pitched thumps.
+//! It will not pass for a sound module, but it keeps the time and the
+//! harmony to sing along to, with nothing else to install.
+
+use std::f32::consts::TAU;
+
+use super::{MidiSong, Note};
+use crate::audio::pcm_cache::DecodedPcm;
+
+const SAMPLE_RATE: u32 = 44_100;
+/// Level of a note at full velocity and channel volume.
+const NOTE_GAIN: f32 = 0.12;
+/// The mix is scaled down to peak here when it would go above.
+const HEADROOM: f32 = 0.9;
+
+/// Sound of an instrument family.
+struct Timbre {
+    /// Level of each harmonic, the fundamental first
+    harmonics: &'static [f32],
+    attack: f32,
+    /// Time constant of the fall from the peak to the sustain level
+    decay: f32,
+    sustain: f32,
+    release: f32,
+}
+
+/// Timbre of a General MIDI program, by its family of eight.
+fn timbre(program: u8) -> Timbre {
+    let (harmonics, attack, decay, sustain, release): (&'static [f32], _, _, _, _) =
+        match program / 8 {
+            // Piano, chromatic percussion
+            0 | 1 => (&[1.0, 0.5, 0.25, 0.12], 0.005, 0.8, 0.0, 0.15),
+            // Organ
+            2 => (&[1.0, 0.6, 0.4, 0.3], 0.01, 1.0, 1.0, 0.08),
+            // Guitar
+            3 => (&[1.0, 0.6, 0.3, 0.2], 0.005, 0.5, 0.1, 0.1),
+            // Bass
+            4 => (&[1.0, 0.4, 0.1], 0.005, 0.6, 0.3, 0.08),
+            // Strings, ensemble
+            5 | 6 => (&[1.0, 0.5, 0.33, 0.25, 0.2], 0.08, 1.0, 1.0, 0.25),
+            // Brass, reed
+            7 | 8 => (&[1.0, 0.7, 0.5, 0.3], 0.03, 0.2, 0.8, 0.1),
+            // Pipe
+            9 => (&[1.0, 0.1, 0.05], 0.04, 1.0, 1.0, 0.1),
+            // Synth lead
+            10 => (&[1.0, 0.5, 0.33, 0.25, 0.2, 0.16], 0.01, 0.1, 0.8, 0.1),
+            // Synth pad
+            11 => (&[1.0, 0.3, 0.1], 0.3, 1.0, 1.0, 0.5),
+            _ => (&[1.0, 0.3], 0.01, 0.3, 0.5, 0.15),
+        };
+    Timbre {
+        harmonics,
+        attack,
+        decay,
+        sustain,
+        release,
+    }
+}
+
+/// Pieces of the drum kit.
+#[derive(Clone, Copy)]
+enum Drum {
+    Kick,
+    Snare,
+    /// Noise, brighter and ringing for `decay` seconds
+    Metal {
+        decay: f32,
+    },
+    Tom {
+        frequency: f32,
+    },
+    Other,
+}
+
+fn drum(key: u8) -> Drum {
+    match key {
+        35 | 36 => Drum::Kick,
+        38 | 40 => Drum::Snare,
+        42 | 44 => Drum::Metal { decay: 0.04 },
+        46 => Drum::Metal { decay: 0.3 },
+        51 | 53 | 59 => Drum::Metal { decay: 0.4 },
+        49 | 52 | 55 | 57 => Drum::Metal { decay: 0.9 },
+        41 | 43 | 45 | 47 | 48 | 50 => Drum::Tom {
+            frequency: 80.0 + f32::from(key - 41) * 15.0,
+        },
+        _ => Drum::Other,
+    }
+}
+
+/// Render `song` to stereo samples.
+pub fn render(song: &MidiSong) -> DecodedPcm {
+    let rate = SAMPLE_RATE as f32;
+    let length = song.length + 1.0;
+    let mut samples = vec![0.0_f32; (length * f64::from(SAMPLE_RATE)) as usize * 2];
+    let mut noise = Noise(0x2545_F491);
+    for note in &song.notes {
+        let voice = if note.is_drum() {
+            drum_voice(note, rate, &mut noise)
+        } else {
+            note_voice(note, rate)
+        };
+        let gain = NOTE_GAIN * f32::from(note.velocity) / 127.0 * f32::from(note.volume) / 127.0;
+        // Equal power pan
+        let pan = f32::from(note.pan) / 127.0 * TAU / 4.0;
+        let (left, right) = (pan.cos() * gain, pan.sin() * gain);
+        let first = ((note.start * f64::from(SAMPLE_RATE)) as usize * 2).min(samples.len());
+        for (frame, sample) in samples[first..].chunks_exact_mut(2).zip(voice) {
+            frame[0] += sample * left;
+            frame[1] += sample * right;
+        }
+    }
+
+    let peak = samples
+        .iter()
+        .fold(0.0_f32, |peak, sample| peak.max(sample.abs()));
+    if peak > HEADROOM {
+        let scale = HEADROOM / peak;
+        samples.iter_mut().for_each(|sample| *sample *= scale);
+    }
+    DecodedPcm::new(2, SAMPLE_RATE, samples)
+}
+
+/// Samples of a pitched note, release included.
+fn note_voice(note: &Note, rate: f32) -> Vec<f32> {
+    let timbre = timbre(note.program);
+    let held = (note.end - note.start) as f32;
+    let length = ((held + timbre.release) * rate) as usize;
+    let frequency = 440.0 * 2_f32.powf((f32::from(note.key) - 69.0) / 12.0);
+    let step = frequency / rate;
+    // Harmonics above half the sample rate would fold back as noise
+    let harmonics = timbre
+        .harmonics
+        .iter()
+        .take((0.5 / step) as usize)
+        .collect::<Vec<_>>();
+    let level = |t: f32| {
+        if t < timbre.attack {
+            t / timbre.attack
+        } else {
+            let fall = (-(t - timbre.attack) / timbre.decay).exp();
+            timbre.sustain + (1.0 - timbre.sustain) * fall
+        }
+    };
+    let released_at = level(held);
+    let mut phase = 0.0_f32;
+    (0..length)
+        .map(|index| {
+            let t = index as f32 / rate;
+            let envelope = if t < held {
+                level(t)
+            } else {
+                released_at * (1.0 - (t - held) / timbre.release).max(0.0)
+            };
+            let sample: f32 = harmonics
+                .iter()
+                .enumerate()
+                .map(|(harmonic, amplitude)| {
+                    *amplitude * (TAU * phase * (harmonic + 1) as f32).sin()
+                })
+                .sum();
+            phase = (phase + step).fract();
+            sample * envelope * 0.5
+        })
+        .collect()
+}
+
+/// Samples of a drum hit, which lasts as long as it rings.
+fn drum_voice(note: &Note, rate: f32, noise: &mut Noise) -> Vec<f32> {
+    let kind = drum(note.key);
+    let decay = match kind {
+        Drum::Kick => 0.3,
+        Drum::Snare => 0.15,
+        Drum::Metal { decay } => decay,
+        Drum::Tom { .. } => 0.25,
+        Drum::Other => 0.1,
+    };
+    let length = (decay * 5.0 * rate) as usize;
+    let mut phase = 0.0_f32;
+    let mut previous = 0.0_f32;
+    (0..length)
+        .map(|index| {
+            let t = index as f32 / rate;
+            let envelope = (-t / decay).exp();
+            let white = noise.next();
+            let sample = match kind {
+                Drum::Kick => {
+                    // A thump falling in pitch
+                    phase += (50.0 + 90.0 * (-t / 0.03).exp()) / rate;
+                    (TAU * phase).sin()
+                },
+                Drum::Snare => {
+                    // Mostly the rattle of the snares
+                    phase += 190.0 / rate;
+                    0.3 * (TAU * phase).sin() + 0.7 * white
+                },
+                Drum::Metal { .. } => {
+                    // Differences of white noise keep its highs
+                    let bright = white - previous;
+                    previous = white;
+                    bright * 0.5
+                },
+                Drum::Tom { frequency } => {
+                    phase += frequency / rate;
+                    (TAU * phase).sin()
+                },
+                Drum::Other => white * 0.5,
+            };
+            phase = phase.fract();
+            sample * envelope
+        })
+        .collect()
+}
+
+/// Xorshift white noise, -1.0 to 1.0.
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as f32 / u32::MAX as f32 * 2.0 - 1.0
+    }
+}
@@ -0,0 +1,46 @@
+//! Audio engine: playback, the real-time processing chain and DSP building blocks.
+
+pub mod dsp;
+pub mod effects;
+pub mod export;
+pub mod generator;
+pub mod input;
+pub mod key;
+pub mod loudness;
+pub mod melody;
+pub mod midi;
+pub mod pcm_cache;
+pub mod pitch;
+pub mod player;
+pub mod processor;
+pub mod recorder;
+pub mod scoring;
+pub mod separation;
+pub mod spectrum;
+pub mod warmup;
+pub mod waveform;
+
+mod atomic;
+
+pub use atomic::AtomicF32;
+pub use input::MicInput;
+pub use player::AudioPlayer;
+pub use processor::ProcessorParams;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AudioError {
+    #[error("Failed to load audio file: {0}")]
+    LoadError(String),
+
+    #[error("Playback error: {0}")]
+    PlaybackError(String),
+
+    #[error("Unsupported format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Audio device error: {0}")]
+    DeviceError(String),
+
+    #[error("Recording failed: {0}")]
+    RecordingError(String),
+}
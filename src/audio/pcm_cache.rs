@@ -0,0 +1,219 @@
+//! Songs decoded into memory ahead of time, so seeking, looping and
+//! restarting them never wait on the decoder.
+//!
+//! Songs are decoded on a background thread when they are queued. Only
+//! songs that decode to at most the per-song limit are kept; decoding a
+//! longer one is abandoned as soon as it outgrows the limit, and it streams
+//! from disk as usual. The cache holds decoded songs up to a total memory
+//! limit, dropping the least recently used first.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+use super::player::open_decoder;
+use super::AudioError;
+
+const MB: usize = 1024 * 1024;
+
+/// A whole song as interleaved samples.
+pub struct DecodedPcm {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+impl DecodedPcm {
+    /// `samples` interleaved over `channels`.
+    pub fn new(channels: u16, sample_rate: u32, samples: Vec<f32>) -> Self {
+        Self {
+            channels,
+            sample_rate,
+            samples,
+        }
+    }
+
+    /// Memory the samples take, in bytes.
+    fn size(&self) -> usize {
+        self.samples.len() * std::mem::size_of::<f32>()
+    }
+}
+
+/// Shared by the player and the decoding threads.
+#[derive(Default)]
+struct Inner {
+    /// Least recently used first
+    songs: VecDeque<(PathBuf, Arc<DecodedPcm>)>,
+    /// Being decoded
+    pending: HashSet<PathBuf>,
+    /// Larger than the per-song limit they were tried with, in bytes
+    too_large: Vec<(PathBuf, usize)>,
+    /// Total memory limit in bytes
+    total_limit: usize,
+}
+
+impl Inner {
+    /// Drop least recently used songs until the total fits the limit.
+    fn evict(&mut self) {
+        let mut total: usize = self.songs.iter().map(|(_, pcm)| pcm.size()).sum();
+        while total > self.total_limit {
+            let Some((path, pcm)) = self.songs.pop_front() else {
+                break;
+            };
+            total -= pcm.size();
+            tracing::debug!("Dropped decoded {} from memory", path.display());
+        }
+    }
+}
+
+/// Decoded songs, most recently used last.
+#[derive(Clone, Default)]
+pub struct PcmCache {
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl PcmCache {
+    /// Decode `path` in the background if it fits in `song_mb` megabytes
+    /// and is not cached yet. Up to `total_mb` megabytes of songs are kept;
+    /// a `song_mb` of 0 turns the cache off.
+    pub fn request(&self, path: &Path, song_mb: u32, total_mb: u32) {
+        let song_limit = (song_mb as usize * MB).min(total_mb as usize * MB);
+        let mut inner = self.lock();
+        inner.total_limit = total_mb as usize * MB;
+        inner.evict();
+        let known = inner.songs.iter().any(|(song, _)| song == path)
+            || inner.pending.contains(path)
+            || inner
+                .too_large
+                .iter()
+                .any(|(song, limit)| song == path && *limit >= song_limit);
+        if song_limit == 0 || known {
+            return;
+        }
+        inner.pending.insert(path.to_path_buf());
+        drop(inner);
+
+        let cache = self.clone();
+        let path = path.to_path_buf();
+        thread::spawn(move || {
+            let decoded = decode(&path, song_limit);
+            let mut inner = cache.lock();
+            inner.pending.remove(&path);
+            match decoded {
+                Ok(Some(pcm)) => {
+                    tracing::debug!(
+                        "Decoded {} into memory ({} MB)",
+                        path.display(),
+                        pcm.size() / MB
+                    );
+                    inner.songs.push_back((path, Arc::new(pcm)));
+                    inner.evict();
+                },
+                Ok(None) => {
+                    tracing::debug!("{} is too long to keep decoded", path.display());
+                    inner.too_large.retain(|(song, _)| *song != path);
+                    inner.too_large.push((path, song_limit));
+                },
+                Err(e) => tracing::warn!("Cannot decode {} ahead: {e}", path.display()),
+            }
+        });
+    }
+
+    /// The decoded `path`, if it is cached, marking it as just used.
+    pub fn get(&self, path: &Path) -> Option<Arc<DecodedPcm>> {
+        let mut inner = self.lock();
+        let index = inner.songs.iter().position(|(song, _)| song == path)?;
+        let entry = inner.songs.remove(index)?;
+        let pcm = entry.1.clone();
+        inner.songs.push_back(entry);
+        Some(pcm)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// All of `path`, or `None` once it decodes to more than `limit` bytes.
+fn decode(path: &Path, limit: usize) -> Result<Option<DecodedPcm>, AudioError> {
+    let decoder = open_decoder(path)?;
+    let channels = decoder.channels();
+    let sample_rate = decoder.sample_rate();
+    let max_samples = limit / std::mem::size_of::<f32>();
+    let expected = decoder.total_duration().map(|duration| {
+        (duration.as_secs_f64() * f64::from(sample_rate) * f64::from(channels)) as usize
+    });
+    if expected.is_some_and(|expected| expected > max_samples) {
+        return Ok(None);
+    }
+
+    let mut samples = Vec::with_capacity(expected.unwrap_or(0));
+    for sample in decoder.convert_samples::<f32>() {
+        if samples.len() == max_samples {
+            return Ok(None);
+        }
+        samples.push(sample);
+    }
+    samples.shrink_to_fit();
+    Ok(Some(DecodedPcm {
+        channels,
+        sample_rate,
+        samples,
+    }))
+}
+
+/// Plays a decoded song. Seeking only moves an index.
+pub struct PcmSource {
+    pcm: Arc<DecodedPcm>,
+    position: usize,
+}
+
+impl PcmSource {
+    pub fn new(pcm: Arc<DecodedPcm>) -> Self {
+        Self { pcm, position: 0 }
+    }
+}
+
+impl Iterator for PcmSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let sample = self.pcm.samples.get(self.position).copied()?;
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for PcmSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        Some(self.pcm.samples.len() - self.position)
+    }
+
+    fn channels(&self) -> u16 {
+        self.pcm.channels
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.pcm.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        let frames = self.pcm.samples.len() / usize::from(self.pcm.channels.max(1));
+        Some(Duration::from_secs_f64(
+            frames as f64 / f64::from(self.pcm.sample_rate.max(1)),
+        ))
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        let channels = usize::from(self.pcm.channels.max(1));
+        // Keep the channel order: land on the start of a frame
+        let frame = (position.as_secs_f64() * f64::from(self.pcm.sample_rate)) as usize;
+        self.position = (frame * channels).min(self.pcm.samples.len());
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+//! Pitch detection and note helpers.
+
+use std::collections::VecDeque;
+
+/// Lowest and highest pitch the detector looks for (covers bass to soprano).
+pub const MIN_PITCH_HZ: f32 = 70.0;
+pub const MAX_PITCH_HZ: f32 = 1000.0;
+
+/// Pitch class names, starting at C.
+pub const NOTE_NAMES: [&str; 12] = [
+    "C", "C♯", "D", "E♭", "E", "F", "F♯", "G", "A♭", "A", "B♭", "B",
+];
+
+/// YIN aperiodicity threshold; lower is stricter.
+const YIN_THRESHOLD: f32 = 0.15;
+/// Samples used for the difference function, beyond the longest period.
+const INTEGRATION_WINDOW: usize = 512;
+/// Mean square level below which the input counts as silence (~ -50 dBFS).
+const SILENCE_ENERGY: f32 = 1e-5;
+
+/// Estimate the fundamental frequency of `samples` with the YIN algorithm.
+///
+/// Returns `None` for silence or unvoiced input. `samples` must hold at least
+/// one longest period plus the integration window. `scratch` holds the
+/// difference function, so a caller running this every few milliseconds
+/// can keep reusing one buffer.
+pub fn detect_pitch(samples: &[f32], sample_rate: u32, scratch: &mut Vec<f32>) -> Option<f32> {
+    let min_tau = (sample_rate as f32 / MAX_PITCH_HZ) as usize;
+    let max_tau = (sample_rate as f32 / MIN_PITCH_HZ) as usize;
+    let window = samples
+        .len()
+        .checked_sub(max_tau + 1)?
+        .min(INTEGRATION_WINDOW);
+    if window < min_tau.max(1) {
+        return None;
+    }
+
+    let energy = samples[..window].iter().map(|s| s * s).sum::<f32>() / window as f32;
+    if energy < SILENCE_ENERGY {
+        return None;
+    }
+
+    // Cumulative mean normalized difference function
+    let cmnd = scratch;
+    cmnd.clear();
+    cmnd.resize(max_tau + 1, 1.0);
+    let mut running = 0.0;
+    for tau in 1..=max_tau {
+        let difference: f32 = samples[..window]
+            .iter()
+            .zip(&samples[tau..tau + window])
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum();
+        running += difference;
+        cmnd[tau] = if running > 0.0 {
+            difference * tau as f32 / running
+        } else {
+            1.0
+        };
+    }
+
+    let mut tau = min_tau.max(2);
+    while tau < max_tau {
+        if cmnd[tau] < YIN_THRESHOLD {
+            while tau + 1 < max_tau && cmnd[tau + 1] < cmnd[tau] {
+                tau += 1;
+            }
+            break;
+        }
+        tau += 1;
+    }
+    if tau >= max_tau {
+        return None;
+    }
+
+    // Parabolic interpolation around the minimum
+    let (left, centre, right) = (cmnd[tau - 1], cmnd[tau], cmnd[tau + 1]);
+    let denominator = left - 2.0 * centre + right;
+    let offset = if denominator.abs() > f32::EPSILON {
+        0.5 * (left - right) / denominator
+    } else {
+        0.0
+    };
+    Some(sample_rate as f32 / (tau as f32 + offset))
+}
+
+/// Runs [`detect_pitch`] on a sliding window of a sample stream.
+#[derive(Debug)]
+pub struct PitchTracker {
+    sample_rate: u32,
+    window: VecDeque<f32>,
+    capacity: usize,
+    hop: usize,
+    since_estimate: usize,
+    /// Reused by [`detect_pitch`] for every estimate
+    scratch: Vec<f32>,
+}
+
+impl PitchTracker {
+    /// Track pitch in a stream, producing an estimate every `hop` samples.
+    pub fn new(sample_rate: u32, hop: usize) -> Self {
+        let capacity = (sample_rate as f32 / MIN_PITCH_HZ) as usize + 1 + INTEGRATION_WINDOW;
+        Self {
+            sample_rate,
+            window: VecDeque::with_capacity(capacity),
+            capacity,
+            hop: hop.max(1),
+            since_estimate: 0,
+            scratch: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Feed one sample. Returns `Some(estimate)` every hop once enough audio
+    /// has been seen; the estimate itself is `None` when nothing is sung.
+    pub fn push(&mut self, sample: f32) -> Option<Option<f32>> {
+        if self.window.len() == self.capacity {
+            self.window.pop_front();
+        }
+        self.window.push_back(sample);
+        self.since_estimate += 1;
+        if self.since_estimate < self.hop || self.window.len() < self.capacity {
+            return None;
+        }
+        self.since_estimate = 0;
+        Some(detect_pitch(
+            self.window.make_contiguous(),
+            self.sample_rate,
+            &mut self.scratch,
+        ))
+    }
+}
+
+/// Fractional MIDI note number of a frequency (A4 = 440 Hz = 69).
+pub fn frequency_to_midi(frequency: f32) -> f32 {
+    69.0 + 12.0 * (frequency / 440.0).log2()
+}
+
+/// Frequency of a (fractional) MIDI note number.
+pub fn midi_to_frequency(note: f32) -> f32 {
+    440.0 * 2.0_f32.powf((note - 69.0) / 12.0)
+}
+
+/// Name of a MIDI note with its octave, e.g. `A4` for 69.
+pub fn note_name(note: u8) -> String {
+    let octave = i32::from(note / 12) - 1;
+    format!("{}{octave}", NOTE_NAMES[usize::from(note % 12)])
+}
@@ -0,0 +1,574 @@
+//! Audio playback control built on rodio.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use rodio::dynamic_mixer::{self, DynamicMixerController};
+use rodio::source::{SeekError, Zero};
+use rodio::{Decoder, OutputStream, Sink, Source};
+use serde::{Deserialize, Serialize};
+
+use super::generator::{self, CountIn};
+use super::midi::{self, synth, MidiSong};
+use super::pcm_cache::{PcmCache, PcmSource};
+use super::processor::{FrameSource, MasterChain, MusicChain, ProcessorParams};
+use super::AudioError;
+
+/// A song's samples, decoded from memory or from the file as it plays.
+pub(crate) type SongSource = Box<dyn Source<Item = f32> + Send>;
+
+/// An output that pulls no audio for this long is considered dead.
+const STALL_TIMEOUT: Duration = Duration::from_secs(1);
+/// A playing track whose position does not move for this long is stuck,
+/// even when the device still pulls (silent) audio.
+const POSITION_STALL_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A track being opened on a background thread ahead of time.
+struct Preload {
+    path: PathBuf,
+    handle: JoinHandle<Result<SongSource, AudioError>>,
+}
+
+/// Plays one track at a time through the processing chains.
+///
+/// Everything audible goes through a mix bus: the track's sink and the
+/// microphone are inputs of a dynamic mixer whose output runs the master
+/// chain and feeds the device. The output stream must stay alive for as long
+/// as anything plays, so the player owns it. `OutputStream` is not `Send`,
+/// which keeps the player on the UI thread.
+///
+/// If the device disappears (a USB interface unplugged mid-song) the stream
+/// just stops pulling audio. [`output_stalled`](Self::output_stalled)
+/// notices that and [`recover`](Self::recover) reopens the default device.
+/// A device can also stall while still being pulled, which
+/// [`playback_stalled`](Self::playback_stalled) notices from the song
+/// position standing still.
+pub struct AudioPlayer {
+    _stream: OutputStream,
+    device_name: String,
+    mixer: Arc<DynamicMixerController<f32>>,
+    sink: Sink,
+    /// Reference note being played, next to the track
+    reference: Option<Sink>,
+    params: Arc<ProcessorParams>,
+    /// Output frame count last seen and when it last moved
+    output_check: (u64, Instant),
+    /// Song position last seen while playing and when it last moved
+    position_check: (Duration, Instant),
+    /// Song position of the current track in microseconds, kept by its
+    /// music chain
+    clock: Arc<AtomicU64>,
+    current: Option<PathBuf>,
+    /// Instrumental decoded in lockstep with the current song
+    instrumental: Option<PathBuf>,
+    /// Play the instrumental instead of the original, when it is loaded
+    use_instrumental: Arc<AtomicBool>,
+    /// Both renditions are playing side by side, so switching is instant
+    ab_live: bool,
+    duration: Option<Duration>,
+    preload: Option<Preload>,
+    /// Songs decoded ahead into memory
+    pcm_cache: PcmCache,
+}
+
+impl AudioPlayer {
+    /// Open the default output device.
+    pub fn new(params: Arc<ProcessorParams>) -> Result<Self, AudioError> {
+        let (stream, device_name, mixer) = open_output(&params)?;
+        let (sink, queue) = Sink::new_idle();
+        mixer.add(queue);
+
+        Ok(Self {
+            _stream: stream,
+            device_name,
+            mixer,
+            sink,
+            reference: None,
+            output_check: (params.output_frames(), Instant::now()),
+            position_check: (Duration::ZERO, Instant::now()),
+            params,
+            clock: Arc::new(AtomicU64::new(0)),
+            current: None,
+            instrumental: None,
+            use_instrumental: Arc::new(AtomicBool::new(false)),
+            ab_live: false,
+            duration: None,
+            preload: None,
+            pcm_cache: PcmCache::default(),
+        })
+    }
+
+    /// Replace the current track with `song` and start playing it from
+    /// `start`, after the count-in clicks if given.
+    ///
+    /// With an `instrumental`, both files are decoded side by side and
+    /// [`set_instrumental`](Self::set_instrumental) picks the one heard, so
+    /// switching keeps the exact position. Files whose formats differ
+    /// cannot run in lockstep; then only the chosen one is decoded.
+    pub fn load(
+        &mut self,
+        song: &Path,
+        instrumental: Option<&Path>,
+        start: Duration,
+        count_in: Option<CountIn>,
+    ) -> Result<(), AudioError> {
+        let preloaded = self.take_preloaded(song);
+        let mut decoder = match (self.pcm_cache.get(song), preloaded) {
+            (Some(pcm), _) => Box::new(PcmSource::new(pcm)) as SongSource,
+            (None, Some(result)) => result?,
+            (None, None) => open_decoder(song)?,
+        };
+        let mut alternate = instrumental.and_then(|path| match self.pcm_cache.get(path) {
+            Some(pcm) => Some(Box::new(PcmSource::new(pcm)) as SongSource),
+            None => open_decoder(path)
+                .inspect_err(|e| tracing::warn!("Cannot open {}: {e}", path.display()))
+                .ok(),
+        });
+        if let Some(other) = alternate.take_if(|other| {
+            other.channels() != decoder.channels() || other.sample_rate() != decoder.sample_rate()
+        }) {
+            tracing::warn!("Original and instrumental formats differ; switching reloads");
+            if self.use_instrumental.load(Ordering::Relaxed) {
+                decoder = other;
+            }
+        }
+
+        let duration = decoder.total_duration();
+        self.ab_live = alternate.is_some();
+        // A fresh clock so the old track's chain cannot move the new position
+        self.clock = Arc::new(AtomicU64::new(0));
+        let mut source = FrameSource::new(
+            AbSource::new(
+                decoder,
+                alternate,
+                self.use_instrumental.clone(),
+                self.params.clone(),
+            ),
+            MusicChain::new(self.params.clone(), self.clock.clone()),
+        );
+        if !start.is_zero() {
+            if let Err(e) = source.try_seek(start) {
+                tracing::warn!("Cannot start {} at {start:?}: {e}", song.display());
+            }
+        }
+
+        // Dropping the old sink ends its queue, which removes it from the mixer
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
+        sink.set_volume(self.sink.volume());
+        sink.set_speed(self.sink.speed());
+        if let Some(count_in) = count_in {
+            sink.append(count_in);
+        }
+        sink.append(source);
+        self.sink.stop();
+        self.sink = sink;
+
+        self.current = Some(song.to_path_buf());
+        self.instrumental = instrumental.map(Path::to_path_buf);
+        self.duration = duration;
+        tracing::info!("Loaded {}", song.display());
+        Ok(())
+    }
+
+    /// Choose between the original and the instrumental. Returns true when
+    /// the switch was heard instantly; otherwise the song has to be
+    /// reloaded for it to take effect.
+    pub fn set_instrumental(&self, instrumental: bool) -> bool {
+        self.use_instrumental.store(instrumental, Ordering::Relaxed);
+        self.ab_live
+    }
+
+    /// True when the output device stopped pulling audio for a while, which
+    /// means it is gone. Reports each stall once per [`STALL_TIMEOUT`].
+    pub fn output_stalled(&mut self) -> bool {
+        let frames = self.params.output_frames();
+        let (seen, since) = self.output_check;
+        if frames != seen {
+            self.output_check = (frames, Instant::now());
+            return false;
+        }
+        if since.elapsed() < STALL_TIMEOUT {
+            return false;
+        }
+        self.output_check = (frames, Instant::now());
+        true
+    }
+
+    /// True when the track is playing but its position has not moved for
+    /// [`POSITION_STALL_TIMEOUT`]. Count-ins and pauses do not count.
+    pub fn playback_stalled(&mut self) -> bool {
+        let position = self.get_position();
+        let (seen, since) = self.position_check;
+        if !self.is_playing() || self.is_counting_in() || position != seen {
+            self.position_check = (position, Instant::now());
+            return false;
+        }
+        if since.elapsed() < POSITION_STALL_TIMEOUT {
+            return false;
+        }
+        self.position_check = (position, Instant::now());
+        true
+    }
+
+    /// Reopen the default output device and resume the current track where
+    /// it was. Live inputs of the old mix bus, such as the microphone, have
+    /// to be added to the new one by the caller.
+    pub fn recover(&mut self) -> Result<(), AudioError> {
+        let (stream, device_name, mixer) = open_output(&self.params)?;
+        let position = self.get_position();
+        let playing = self.is_playing();
+
+        let (sink, queue) = Sink::new_idle();
+        mixer.add(queue);
+        sink.set_volume(self.sink.volume());
+        sink.set_speed(self.sink.speed());
+        self.sink.stop();
+        self.sink = sink;
+        self.reference = None;
+        self.mixer = mixer;
+        self._stream = stream;
+        self.output_check = (self.params.output_frames(), Instant::now());
+        self.position_check = (position, Instant::now());
+        tracing::info!("Audio output reopened on {device_name}");
+        self.device_name = device_name;
+
+        if let Some(song) = self.current.clone() {
+            let instrumental = self.instrumental.clone();
+            self.load(&song, instrumental.as_deref(), position, None)?;
+            if !playing {
+                self.pause();
+            }
+        }
+        Ok(())
+    }
+
+    /// Name of the output device in use.
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    /// Open and probe `path` in the background so a later [`load`](Self::load)
+    /// of the same file starts without delay. Replaces any previous preload.
+    pub fn preload(&mut self, path: &Path) {
+        if self
+            .preload
+            .as_ref()
+            .is_some_and(|preload| preload.path == path)
+        {
+            return;
+        }
+
+        let owned = path.to_path_buf();
+        let handle = thread::spawn(move || open_decoder(&owned));
+        self.preload = Some(Preload {
+            path: path.to_path_buf(),
+            handle,
+        });
+        tracing::debug!("Preloading {}", path.display());
+    }
+
+    /// Decode `path` into memory in the background when it fits in
+    /// `song_mb` megabytes, keeping up to `total_mb` megabytes of songs, so
+    /// it seeks and restarts instantly once loaded. Longer songs stream from
+    /// the file as usual.
+    pub fn precache(&self, path: &Path, song_mb: u32, total_mb: u32) {
+        self.pcm_cache.request(path, song_mb, total_mb);
+    }
+
+    /// Take the preloaded decoder if it belongs to `path`.
+    fn take_preloaded(&mut self, path: &Path) -> Option<Result<SongSource, AudioError>> {
+        let preload = self.preload.take()?;
+        if preload.path != path {
+            return None;
+        }
+        preload.handle.join().ok()
+    }
+
+    pub fn play(&self) {
+        self.sink.play();
+    }
+
+    pub fn pause(&self) {
+        self.sink.pause();
+    }
+
+    /// Stop playback and unload the current track.
+    pub fn stop(&mut self) {
+        self.sink.stop();
+        self.current = None;
+        self.instrumental = None;
+        self.ab_live = false;
+        self.duration = None;
+    }
+
+    /// Play pitch class `note` (0 = C) in `octave` for `duration` on top of
+    /// whatever else plays, so a singer can find their starting note. A new
+    /// reference note cuts off the previous one.
+    pub fn play_reference_note(&mut self, note: u8, octave: i8, duration: Duration) {
+        let (sink, queue) = Sink::new_idle();
+        self.mixer.add(queue);
+        sink.append(generator::create_note(note, octave, duration));
+        if let Some(previous) = self.reference.replace(sink) {
+            previous.stop();
+        }
+    }
+
+    /// End the current track now; it then counts as finished.
+    pub fn skip_to_end(&self) {
+        self.sink.stop();
+    }
+
+    /// True while count-in clicks play ahead of the track.
+    pub fn is_counting_in(&self) -> bool {
+        self.sink.len() > 1
+    }
+
+    pub fn is_playing(&self) -> bool {
+        !self.sink.is_paused() && !self.sink.empty()
+    }
+
+    /// True once a loaded track has played to the end.
+    pub fn is_finished(&self) -> bool {
+        self.current.is_some() && self.sink.empty()
+    }
+
+    /// Seek to `position` in the song, whatever the playback rate.
+    pub fn seek(&self, position: Duration) -> Result<(), AudioError> {
+        // The speed stage scales seek targets by its factor; undo that
+        self.sink
+            .try_seek(position.div_f32(self.sink.speed()))
+            .map_err(|e| AudioError::PlaybackError(e.to_string()))
+    }
+
+    /// Set the master volume (0.0 - 1.0).
+    pub fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume.clamp(0.0, 1.0));
+    }
+
+    /// Set the playback rate (1.0 = normal). The track is resampled, so
+    /// its pitch follows the rate.
+    pub fn set_speed(&self, rate: f32) {
+        self.sink.set_speed(rate.clamp(0.25, 2.0));
+    }
+
+    /// Playback position in the current song. Unlike the sink's own
+    /// position this is song time, so it stays valid at any playback rate.
+    pub fn get_position(&self) -> Duration {
+        Duration::from_micros(self.clock.load(Ordering::Relaxed))
+    }
+
+    /// Total length of the current track, when the decoder knows it.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    pub fn current_path(&self) -> Option<&Path> {
+        self.current.as_deref()
+    }
+
+    /// The file heard: the instrumental when it is playing, the song
+    /// otherwise.
+    pub fn backing_path(&self) -> Option<&Path> {
+        match &self.instrumental {
+            Some(instrumental) if self.use_instrumental.load(Ordering::Relaxed) => {
+                Some(instrumental)
+            },
+            _ => self.current_path(),
+        }
+    }
+
+    /// The mix bus, for adding live inputs such as the microphone.
+    pub fn mixer(&self) -> &DynamicMixerController<f32> {
+        &self.mixer
+    }
+}
+
+/// Open the current default output device with a mix bus feeding it
+/// through the master chain.
+fn open_output(
+    params: &Arc<ProcessorParams>,
+) -> Result<(OutputStream, String, Arc<DynamicMixerController<f32>>), AudioError> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| AudioError::DeviceError("No output device available".to_string()))?;
+    let device_name = device
+        .name()
+        .unwrap_or_else(|_| "Unknown device".to_string());
+    let (stream, handle) = OutputStream::try_from_device(&device)
+        .map_err(|e| AudioError::PlaybackError(e.to_string()))?;
+
+    // Mix at the device format so the bus output needs no conversion
+    let (channels, sample_rate) = device
+        .default_output_config()
+        .map(|config| (config.channels(), config.sample_rate().0))
+        .unwrap_or((2, 48_000));
+    let (mixer, mix) = dynamic_mixer::mixer::<f32>(channels, sample_rate);
+    // The mixer ends once it has no inputs; a silent input keeps it running
+    mixer.add(Zero::<f32>::new(channels, sample_rate));
+    handle
+        .play_raw(FrameSource::new(mix, MasterChain::new(params.clone())))
+        .map_err(|e| AudioError::PlaybackError(e.to_string()))?;
+    Ok((stream, device_name, mixer))
+}
+
+pub(crate) fn open_decoder(path: &Path) -> Result<SongSource, AudioError> {
+    if midi::is_midi(path) {
+        let song = MidiSong::read(path)?;
+        return Ok(Box::new(PcmSource::new(Arc::new(synth::render(&song)))));
+    }
+    let file =
+        File::open(path).map_err(|e| AudioError::LoadError(format!("{}: {e}", path.display())))?;
+    let decoder = Decoder::new(BufReader::new(file))
+        .map_err(|e| AudioError::UnsupportedFormat(format!("{}: {e}", path.display())))?;
+    Ok(Box::new(decoder.convert_samples()))
+}
+
+/// Length of the crossfade when switching renditions, so it does not click.
+const AB_FADE_MS: u32 = 15;
+
+/// Channel the guide vocals are routed to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideEar {
+    #[default]
+    Left,
+    Right,
+}
+
+impl GuideEar {
+    pub const ALL: [Self; 2] = [Self::Left, Self::Right];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Left => "Left",
+            Self::Right => "Right",
+        }
+    }
+
+    /// Index of the channel in an interleaved frame.
+    pub fn channel(self) -> u16 {
+        match self {
+            Self::Left => 0,
+            Self::Right => 1,
+        }
+    }
+}
+
+/// Two renditions of a song decoded in lockstep, one of them heard.
+///
+/// Both inputs advance on every sample whichever is chosen, so a switch
+/// lands on the very same sample of the other rendition. Being aligned,
+/// their difference is the vocal stem: while the instrumental plays, it
+/// can be fed quietly into one channel as a guide for the performer's
+/// earpiece.
+struct AbSource<S> {
+    original: S,
+    alternate: Option<S>,
+    use_alternate: Arc<AtomicBool>,
+    params: Arc<ProcessorParams>,
+    /// Guide vocal gain and channel, refreshed every frame
+    guide: Option<(f32, u16)>,
+    /// Share of the alternate in the output, 0.0 - 1.0
+    mix: f32,
+    /// Change of `mix` per frame while fading
+    fade_step: f32,
+    channels: u16,
+    /// Channel of the next sample within its frame
+    channel: u16,
+}
+
+impl<S: Source<Item = f32>> AbSource<S> {
+    fn new(
+        original: S,
+        alternate: Option<S>,
+        use_alternate: Arc<AtomicBool>,
+        params: Arc<ProcessorParams>,
+    ) -> Self {
+        let channels = original.channels().max(1);
+        let fade_frames = (original.sample_rate() * AB_FADE_MS / 1000).max(1);
+        let mix = if use_alternate.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+        Self {
+            original,
+            alternate,
+            use_alternate,
+            params,
+            guide: None,
+            mix,
+            fade_step: 1.0 / fade_frames as f32,
+            channels,
+            channel: 0,
+        }
+    }
+}
+
+impl<S: Source<Item = f32>> Iterator for AbSource<S> {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        let Some(alternate) = &mut self.alternate else {
+            return self.original.next();
+        };
+        if self.channel == 0 {
+            let target = if self.use_alternate.load(Ordering::Relaxed) {
+                1.0
+            } else {
+                0.0
+            };
+            self.mix = if self.mix < target {
+                (self.mix + self.fade_step).min(target)
+            } else {
+                (self.mix - self.fade_step).max(target)
+            };
+            self.guide = self.params.guide_vocals();
+        }
+        let channel = self.channel;
+        self.channel = (self.channel + 1) % self.channels;
+
+        let (original, alternate) = match (self.original.next(), alternate.next()) {
+            (None, None) => return None,
+            (original, alternate) => (original.unwrap_or(0.0), alternate.unwrap_or(0.0)),
+        };
+        let mut sample = original * (1.0 - self.mix) + alternate * self.mix;
+        if let Some((gain, _)) = self.guide.filter(|&(_, ear)| ear == channel) {
+            sample += (original - alternate) * gain * self.mix;
+        }
+        Some(sample)
+    }
+}
+
+impl<S: Source<Item = f32>> Source for AbSource<S> {
+    fn current_frame_len(&self) -> Option<usize> {
+        self.original.current_frame_len()
+    }
+
+    fn channels(&self) -> u16 {
+        self.original.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.original.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.original.total_duration()
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.original.try_seek(position)?;
+        if let Some(alternate) = &mut self.alternate {
+            alternate.try_seek(position)?;
+        }
+        self.channel = 0;
+        Ok(())
+    }
+}
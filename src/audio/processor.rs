@@ -0,0 +1,833 @@
+//! Real-time processing chains.
+//!
+//! [`FrameSource`] wraps a rodio source and runs each interleaved frame
+//! through a [`FrameProcessor`]. Two chains exist: [`MusicChain`] on the
+//! backing track only, and [`MasterChain`] on the final mix (music + mic).
+//! Parameters live in [`ProcessorParams`], shared with the UI through an
+//! `Arc` so settings changes are heard immediately and meter values can be
+//! read back every frame. The final mix is also published as live spectrum
+//! data through [`ProcessorParams::subscribe_spectrum`].
+
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use rodio::source::SeekError;
+use rodio::Source;
+
+use super::dsp::feedback::MAX_NOTCHES;
+use super::dsp::{self, Ducker, Limiter, OutputDuck, PitchShifter, VocalRemover};
+use super::effects::VoiceEffect;
+use super::key::{self, Key};
+use super::recorder::{RecordBuffer, RecordTap};
+use super::spectrum::{SpectrumAnalyzer, SpectrumFrame, SpectrumTap};
+use super::AtomicF32;
+use crate::config::AudioConfig;
+
+/// Microphones that can be open at once: two for duets.
+pub const MIC_COUNT: usize = 2;
+
+/// Gain, effects and meters of one microphone, shared with its capture.
+#[derive(Debug)]
+pub struct MicChannel {
+    /// Linear gain on the raw mic signal
+    input_gain: AtomicF32,
+    /// Effects rack; the version is bumped on every change
+    voice_effects: Mutex<Vec<VoiceEffect>>,
+    voice_effects_version: AtomicU64,
+    /// Latest sung pitch in Hz, 0.0 when unvoiced
+    pitch: AtomicF32,
+    /// Peak level of the monitored mic (linear)
+    level: AtomicF32,
+    /// Input meter after the input gain (linear peak and RMS)
+    peak: AtomicF32,
+    rms: AtomicF32,
+    /// Samples that reached full scale after the input gain
+    clips: AtomicU64,
+    /// Gain currently applied by the automatic gain control, in dB
+    agc_gain_db: AtomicF32,
+    /// Active feedback notch frequency per slot, 0.0 when the slot is free
+    notch_frequencies: [AtomicF32; MAX_NOTCHES],
+    /// Bit mask of notch slots the UI asked to release
+    notch_release: AtomicU32,
+    /// Times feedback was notched or ducked, for the warning toast
+    feedback_events: AtomicU64,
+    /// Feedback on this mic is more than its notches can stop
+    feedback_duck: AtomicBool,
+    /// The voice as heard, while a performance is recorded
+    record: RecordTap,
+}
+
+impl Default for MicChannel {
+    fn default() -> Self {
+        Self {
+            input_gain: AtomicF32::new(1.0),
+            voice_effects: Mutex::new(Vec::new()),
+            voice_effects_version: AtomicU64::new(0),
+            pitch: AtomicF32::new(0.0),
+            level: AtomicF32::new(0.0),
+            peak: AtomicF32::new(0.0),
+            rms: AtomicF32::new(0.0),
+            clips: AtomicU64::new(0),
+            agc_gain_db: AtomicF32::new(0.0),
+            notch_frequencies: Default::default(),
+            notch_release: AtomicU32::new(0),
+            feedback_events: AtomicU64::new(0),
+            feedback_duck: AtomicBool::new(false),
+            record: RecordTap::default(),
+        }
+    }
+}
+
+impl MicChannel {
+    pub fn input_gain(&self) -> f32 {
+        self.input_gain.load()
+    }
+
+    pub fn set_pitch(&self, frequency: Option<f32>) {
+        self.pitch.store(frequency.unwrap_or(0.0));
+    }
+
+    /// Latest pitch sung into the mic, `None` when silent or not tracked.
+    pub fn pitch(&self) -> Option<f32> {
+        Some(self.pitch.load()).filter(|&frequency| frequency > 0.0)
+    }
+
+    /// Report the monitored mic level (linear peak) for ducking.
+    pub fn set_level(&self, level: f32) {
+        self.level.store(level);
+    }
+
+    /// Report the input meter (linear peak and RMS).
+    pub fn set_meter(&self, peak: f32, rms: f32) {
+        self.peak.store(peak);
+        self.rms.store(rms);
+    }
+
+    /// Input level as `(peak, rms)`, linear.
+    pub fn meter(&self) -> (f32, f32) {
+        (self.peak.load(), self.rms.load())
+    }
+
+    pub fn report_clip(&self) {
+        self.clips.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of samples that clipped so far, compared by the UI like
+    /// [`ProcessorParams::clip_count`].
+    pub fn clip_count(&self) -> u64 {
+        self.clips.load(Ordering::Relaxed)
+    }
+
+    pub fn set_agc_gain_db(&self, db: f32) {
+        self.agc_gain_db.store(db);
+    }
+
+    pub fn agc_gain_db(&self) -> f32 {
+        self.agc_gain_db.load()
+    }
+
+    /// Report the feedback suppressor's notches, event count and whether
+    /// it wants the output ducked.
+    pub fn set_feedback(
+        &self,
+        frequencies: [Option<f32>; MAX_NOTCHES],
+        events: u64,
+        wants_duck: bool,
+    ) {
+        for (shared, frequency) in self.notch_frequencies.iter().zip(frequencies) {
+            shared.store(frequency.unwrap_or(0.0));
+        }
+        self.feedback_events.store(events, Ordering::Relaxed);
+        self.feedback_duck.store(wants_duck, Ordering::Relaxed);
+    }
+
+    /// Active feedback notches as `(slot, frequency in Hz)`.
+    pub fn active_notches(&self) -> Vec<(usize, f32)> {
+        self.notch_frequencies
+            .iter()
+            .enumerate()
+            .map(|(slot, frequency)| (slot, frequency.load()))
+            .filter(|&(_, frequency)| frequency > 0.0)
+            .collect()
+    }
+
+    /// Ask the audio thread to drop the notch in `slot`.
+    pub fn release_notch(&self, slot: usize) {
+        self.notch_release.fetch_or(1 << slot, Ordering::Relaxed);
+    }
+
+    /// Notch slots the UI asked to release since the last call.
+    pub fn take_notch_release(&self) -> u32 {
+        self.notch_release.swap(0, Ordering::Relaxed)
+    }
+
+    pub fn record_tap(&self) -> &RecordTap {
+        &self.record
+    }
+
+    /// Replace the effects rack.
+    pub fn set_voice_effects(&self, effects: &[VoiceEffect]) {
+        if let Ok(mut current) = self.voice_effects.lock() {
+            if current.as_slice() != effects {
+                *current = effects.to_vec();
+                self.voice_effects_version.fetch_add(1, Ordering::Release);
+            }
+        }
+    }
+
+    /// The effects rack if it changed since version `seen`.
+    ///
+    /// Called from the audio thread, so it never blocks: a rack being edited
+    /// right now is simply picked up on a later call.
+    pub fn voice_effects_since(&self, seen: &mut u64) -> Option<Vec<VoiceEffect>> {
+        let version = self.voice_effects_version.load(Ordering::Acquire);
+        if version == *seen {
+            return None;
+        }
+        let effects = self.voice_effects.try_lock().ok()?.clone();
+        *seen = version;
+        Some(effects)
+    }
+}
+
+/// Processing parameters and meters shared with the audio thread.
+#[derive(Debug)]
+pub struct ProcessorParams {
+    /// Output balance, -1.0 = left only, 1.0 = right only
+    pan: AtomicF32,
+    /// Centre cancellation strength for the current song, 0.0 = off
+    vocal_removal: AtomicF32,
+    /// Linear gain on the current song's track
+    music_gain: AtomicF32,
+    /// Key shift of the current song's track in semitones
+    key_shift: AtomicF32,
+    /// Level of the guide vocals (linear, 0.0 = off) and their channel
+    guide_level: AtomicF32,
+    guide_channel: AtomicU32,
+    limiter_enabled: AtomicBool,
+    limiter_threshold_db: AtomicF32,
+    /// Current limiter gain reduction in dB (positive = reducing)
+    gain_reduction_db: AtomicF32,
+    /// Frames where the mix went over 0 dBFS ahead of the limiter
+    clips: AtomicU64,
+    /// Frames delivered to the output device; stops moving when the stream dies
+    output_frames: AtomicU64,
+    feedback_enabled: AtomicBool,
+    /// Current output reduction from feedback ducking in dB
+    feedback_duck_db: AtomicF32,
+    /// Per-microphone gain, effects and meters: the lead singer, then the
+    /// second singer in duet mode
+    mics: [MicChannel; MIC_COUNT],
+    /// Autotune strength on the mic, 0.0 = off
+    autotune: AtomicF32,
+    /// Scale on the amount of every voice effect, like an aux send
+    effects_send: AtomicF32,
+    /// Key of the current song, encoded with [`Key::encode`]
+    song_key: AtomicU32,
+    /// Noise gate threshold on the mic (linear), 0.0 = gate off
+    noise_gate_threshold: AtomicF32,
+    /// Automatic gain control target and most boost in dB
+    agc_enabled: AtomicBool,
+    agc_target_db: AtomicF32,
+    agc_max_gain_db: AtomicF32,
+    /// Play the mic through the mix (off: capture for pitch tracking only)
+    mic_monitor: AtomicBool,
+    pitch_tracking: AtomicBool,
+    /// Track the sung pitch for the pitch guide lane
+    pitch_guide: AtomicBool,
+    ducking_enabled: AtomicBool,
+    ducking_threshold_db: AtomicF32,
+    ducking_depth_db: AtomicF32,
+    /// Current music reduction from ducking in dB (positive = reducing)
+    ducking_db: AtomicF32,
+    /// Spectrum frames of the final mix
+    spectrum: SpectrumTap,
+    /// The final mix, while a performance is recorded with it
+    mix_record: RecordTap,
+    /// The backing track alone, while a performance is recorded with it
+    music_record: RecordTap,
+}
+
+impl ProcessorParams {
+    pub fn new(config: &AudioConfig) -> Self {
+        let params = Self {
+            pan: AtomicF32::new(0.0),
+            vocal_removal: AtomicF32::new(0.0),
+            music_gain: AtomicF32::new(1.0),
+            key_shift: AtomicF32::new(0.0),
+            guide_level: AtomicF32::new(0.0),
+            guide_channel: AtomicU32::new(0),
+            limiter_enabled: AtomicBool::new(true),
+            limiter_threshold_db: AtomicF32::new(0.0),
+            gain_reduction_db: AtomicF32::new(0.0),
+            clips: AtomicU64::new(0),
+            output_frames: AtomicU64::new(0),
+            feedback_enabled: AtomicBool::new(true),
+            feedback_duck_db: AtomicF32::new(0.0),
+            mics: Default::default(),
+            autotune: AtomicF32::new(0.0),
+            effects_send: AtomicF32::new(1.0),
+            song_key: AtomicU32::new(0),
+            noise_gate_threshold: AtomicF32::new(0.0),
+            agc_enabled: AtomicBool::new(false),
+            agc_target_db: AtomicF32::new(0.0),
+            agc_max_gain_db: AtomicF32::new(0.0),
+            mic_monitor: AtomicBool::new(false),
+            pitch_tracking: AtomicBool::new(false),
+            pitch_guide: AtomicBool::new(false),
+            ducking_enabled: AtomicBool::new(false),
+            ducking_threshold_db: AtomicF32::new(0.0),
+            ducking_depth_db: AtomicF32::new(0.0),
+            ducking_db: AtomicF32::new(0.0),
+            spectrum: SpectrumTap::default(),
+            mix_record: RecordTap::default(),
+            music_record: RecordTap::default(),
+        };
+        params.apply_config(config);
+        params
+    }
+
+    /// Push the current settings to the audio thread.
+    pub fn apply_config(&self, config: &AudioConfig) {
+        self.pan.store(config.pan.clamp(-1.0, 1.0));
+        self.limiter_enabled
+            .store(config.limiter_enabled, Ordering::Relaxed);
+        self.limiter_threshold_db.store(config.limiter_threshold_db);
+        self.feedback_enabled
+            .store(config.feedback_suppression, Ordering::Relaxed);
+        self.mic_monitor
+            .store(config.mic_passthrough, Ordering::Relaxed);
+        self.guide_level.store(if config.guide_vocals {
+            dsp::db_to_linear(config.guide_vocals_level_db)
+        } else {
+            0.0
+        });
+        self.guide_channel.store(
+            u32::from(config.guide_vocals_ear.channel()),
+            Ordering::Relaxed,
+        );
+        self.mics[0]
+            .input_gain
+            .store(dsp::db_to_linear(config.input_gain_db));
+        self.mics[1]
+            .input_gain
+            .store(dsp::db_to_linear(config.duet.input_gain_db));
+        self.noise_gate_threshold
+            .store(if config.noise_gate_enabled {
+                dsp::db_to_linear(config.noise_gate_threshold_db)
+            } else {
+                0.0
+            });
+        self.agc_enabled
+            .store(config.agc_enabled, Ordering::Relaxed);
+        self.agc_target_db.store(config.agc_target_db);
+        self.agc_max_gain_db.store(config.agc_max_gain_db);
+        self.ducking_enabled
+            .store(config.ducking, Ordering::Relaxed);
+        self.ducking_threshold_db.store(config.ducking_threshold_db);
+        self.ducking_depth_db
+            .store(config.ducking_depth_db.max(0.0));
+        self.autotune.store(if config.autotune {
+            config.autotune_strength.clamp(0.0, 1.0)
+        } else {
+            0.0
+        });
+        self.effects_send.store(config.effects_send.clamp(0.0, 1.0));
+    }
+
+    /// Set the vocal removal strength for the song being played.
+    pub fn set_vocal_removal(&self, strength: f32) {
+        self.vocal_removal.store(strength.clamp(0.0, 1.0));
+    }
+
+    pub fn vocal_removal(&self) -> f32 {
+        self.vocal_removal.load()
+    }
+
+    /// Set the music level with the song's volume offset, in dB.
+    pub fn set_music_gain_db(&self, db: f32) {
+        self.music_gain.store(dsp::db_to_linear(db));
+    }
+
+    /// Linear gain of the music level and the song's volume offset.
+    pub fn music_gain(&self) -> f32 {
+        self.music_gain.load()
+    }
+
+    /// Shift the backing track up or down by `semitones`.
+    pub fn set_key_shift(&self, semitones: i8) {
+        let semitones = semitones.clamp(-key::MAX_SHIFT, key::MAX_SHIFT);
+        self.key_shift.store(f32::from(semitones));
+    }
+
+    /// Guide vocal gain (linear) and channel index, `None` when off.
+    pub fn guide_vocals(&self) -> Option<(f32, u16)> {
+        let level = self.guide_level.load();
+        let channel = self.guide_channel.load(Ordering::Relaxed) as u16;
+        (level > 0.0).then_some((level, channel))
+    }
+
+    /// Set the key pitch correction snaps to, `None` for chromatic.
+    pub fn set_song_key(&self, key: Option<Key>) {
+        self.song_key.store(Key::encode(key), Ordering::Relaxed);
+    }
+
+    pub fn song_key(&self) -> Option<Key> {
+        Key::decode(self.song_key.load(Ordering::Relaxed))
+    }
+
+    pub fn autotune(&self) -> f32 {
+        self.autotune.load()
+    }
+
+    pub fn effects_send(&self) -> f32 {
+        self.effects_send.load()
+    }
+
+    /// Microphone `index`: 0 is the lead singer, 1 the second singer in
+    /// duet mode.
+    pub fn mic(&self, index: usize) -> &MicChannel {
+        &self.mics[index.min(MIC_COUNT - 1)]
+    }
+
+    /// Noise gate threshold (linear), `None` when the gate is off.
+    pub fn noise_gate_threshold(&self) -> Option<f32> {
+        Some(self.noise_gate_threshold.load()).filter(|&threshold| threshold > 0.0)
+    }
+
+    /// Automatic gain control target and most boost in dB, `None` when off.
+    pub fn agc(&self) -> Option<(f32, f32)> {
+        self.agc_enabled
+            .load(Ordering::Relaxed)
+            .then(|| (self.agc_target_db.load(), self.agc_max_gain_db.load()))
+    }
+
+    pub fn mic_monitor(&self) -> bool {
+        self.mic_monitor.load(Ordering::Relaxed)
+    }
+
+    /// Track the sung pitch while something (warm-up, scoring) needs it.
+    pub fn set_pitch_tracking(&self, enabled: bool) {
+        self.pitch_tracking.store(enabled, Ordering::Relaxed);
+        if !enabled && !self.pitch_guide() {
+            for mic in &self.mics {
+                mic.pitch.store(0.0);
+            }
+        }
+    }
+
+    pub fn pitch_tracking(&self) -> bool {
+        self.pitch_tracking.load(Ordering::Relaxed)
+    }
+
+    /// Track the sung pitch while the pitch guide lane is on screen. Kept
+    /// apart from [`Self::set_pitch_tracking`] so the lane does not look
+    /// like a warm-up or a scored run.
+    pub fn set_pitch_guide(&self, enabled: bool) {
+        self.pitch_guide.store(enabled, Ordering::Relaxed);
+        if !enabled && !self.pitch_tracking() {
+            for mic in &self.mics {
+                mic.pitch.store(0.0);
+            }
+        }
+    }
+
+    pub fn pitch_guide(&self) -> bool {
+        self.pitch_guide.load(Ordering::Relaxed)
+    }
+
+    pub fn ducking_db(&self) -> f32 {
+        self.ducking_db.load()
+    }
+
+    /// Live spectrum of the output mix, until the receiver is dropped.
+    pub fn subscribe_spectrum(&self) -> Receiver<Arc<SpectrumFrame>> {
+        self.spectrum.subscribe()
+    }
+
+    pub fn mix_record_tap(&self) -> &RecordTap {
+        &self.mix_record
+    }
+
+    pub fn music_record_tap(&self) -> &RecordTap {
+        &self.music_record
+    }
+
+    pub fn gain_reduction_db(&self) -> f32 {
+        self.gain_reduction_db.load()
+    }
+
+    pub fn feedback_enabled(&self) -> bool {
+        self.feedback_enabled.load(Ordering::Relaxed)
+    }
+
+    /// Active feedback notches on every mic as `(mic, slot, frequency in
+    /// Hz)`.
+    pub fn active_notches(&self) -> Vec<(usize, usize, f32)> {
+        self.mics
+            .iter()
+            .enumerate()
+            .flat_map(|(mic, channel)| {
+                channel
+                    .active_notches()
+                    .into_iter()
+                    .map(move |(slot, frequency)| (mic, slot, frequency))
+            })
+            .collect()
+    }
+
+    /// Number of times feedback was notched or ducked so far, on any mic.
+    /// The UI compares it with the last value it saw to show a warning.
+    pub fn feedback_events(&self) -> u64 {
+        self.mics
+            .iter()
+            .map(|mic| mic.feedback_events.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Whether feedback on any mic needs the output ducked.
+    fn feedback_duck_wanted(&self) -> bool {
+        self.mics
+            .iter()
+            .any(|mic| mic.feedback_duck.load(Ordering::Relaxed))
+    }
+
+    /// Current output reduction from feedback ducking in dB.
+    pub fn feedback_duck_db(&self) -> f32 {
+        self.feedback_duck_db.load()
+    }
+
+    /// Number of frames that went over full scale so far. The UI compares
+    /// it with the last value it saw to light the clip indicator.
+    pub fn clip_count(&self) -> u64 {
+        self.clips.load(Ordering::Relaxed)
+    }
+
+    /// Number of frames the output device has pulled so far.
+    pub fn output_frames(&self) -> u64 {
+        self.output_frames.load(Ordering::Relaxed)
+    }
+}
+
+/// A processing stage working on interleaved `f32` frames.
+pub trait FrameProcessor {
+    /// Called before the first frame and whenever the stream format changes.
+    fn configure(&mut self, channels: u16, sample_rate: u32);
+
+    fn process_frame(&mut self, frame: &mut [f32]);
+
+    /// Called after the input seeked to `position`; drop buffered state.
+    fn seek(&mut self, _position: Duration) {}
+}
+
+/// Processing applied to the backing track only.
+///
+/// It also keeps the track's clock: the position in the song, counted in
+/// source frames so it stays right whatever the playback rate.
+pub struct MusicChain {
+    params: Arc<ProcessorParams>,
+    vocal_remover: VocalRemover,
+    /// One per channel, all at the ratio of `key_shift`
+    key_shifters: Vec<PitchShifter>,
+    key_shift: f32,
+    ducker: Ducker,
+    record: RecordBuffer,
+    clock: Arc<AtomicU64>,
+    sample_rate: u32,
+    start: Duration,
+    frames: u64,
+}
+
+impl MusicChain {
+    /// `clock` receives the song position in microseconds.
+    pub fn new(params: Arc<ProcessorParams>, clock: Arc<AtomicU64>) -> Self {
+        Self {
+            params,
+            vocal_remover: VocalRemover::new(44_100),
+            key_shifters: Vec::new(),
+            key_shift: 0.0,
+            ducker: Ducker::new(44_100),
+            record: RecordBuffer::default(),
+            clock,
+            sample_rate: 44_100,
+            start: Duration::ZERO,
+            frames: 0,
+        }
+    }
+
+    fn advance_clock(&mut self) {
+        self.frames += 1;
+        let played = Duration::from_secs_f64(self.frames as f64 / f64::from(self.sample_rate));
+        self.clock
+            .store((self.start + played).as_micros() as u64, Ordering::Relaxed);
+    }
+
+    /// Move the track to another key. Left out at the original key, where
+    /// the shifter would only smear the sound.
+    fn process_key_shift(&mut self, frame: &mut [f32]) {
+        let semitones = self.params.key_shift.load();
+        if semitones != self.key_shift {
+            self.key_shift = semitones;
+            let ratio = 2.0_f32.powf(semitones / 12.0);
+            for shifter in &mut self.key_shifters {
+                shifter.set_ratio(ratio);
+            }
+        }
+        if semitones == 0.0 {
+            return;
+        }
+        for (sample, shifter) in frame.iter_mut().zip(&mut self.key_shifters) {
+            *sample = shifter.process(*sample);
+        }
+    }
+
+    /// Lower the track while the mic is above the ducking threshold.
+    fn process_ducking(&mut self, frame: &mut [f32]) {
+        let params = &self.params;
+        let enabled = params.ducking_enabled.load(Ordering::Relaxed);
+        if !enabled && self.ducker.gain() >= 1.0 {
+            return;
+        }
+
+        let threshold = dsp::db_to_linear(params.ducking_threshold_db.load());
+        let hot = enabled && params.mics.iter().any(|mic| mic.level.load() > threshold);
+        let depth = dsp::db_to_linear(-params.ducking_depth_db.load());
+        let gain = self.ducker.next_gain(hot, depth);
+        for sample in frame.iter_mut() {
+            *sample *= gain;
+        }
+        params.ducking_db.store(-dsp::linear_to_db(gain));
+    }
+}
+
+impl FrameProcessor for MusicChain {
+    fn configure(&mut self, channels: u16, sample_rate: u32) {
+        self.vocal_remover.configure(sample_rate);
+        let ratio = 2.0_f32.powf(self.key_shift / 12.0);
+        self.key_shifters = (0..channels)
+            .map(|_| {
+                let mut shifter = PitchShifter::new(sample_rate);
+                shifter.set_ratio(ratio);
+                shifter
+            })
+            .collect();
+        self.ducker.configure(sample_rate);
+        // Keep the time played so far when the rate changes mid-stream
+        self.start += Duration::from_secs_f64(self.frames as f64 / f64::from(self.sample_rate));
+        self.frames = 0;
+        self.sample_rate = sample_rate.max(1);
+    }
+
+    fn process_frame(&mut self, frame: &mut [f32]) {
+        let vocal_removal = self.params.vocal_removal.load();
+        if vocal_removal > 0.0 {
+            self.vocal_remover.process_frame(frame, vocal_removal);
+        }
+        self.process_key_shift(frame);
+        let gain = self.params.music_gain.load();
+        if gain != 1.0 {
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+        // Recorded before ducking, which depends on the voice and is
+        // redone when the performance is mixed
+        self.record
+            .push(&self.params.music_record, frame, self.sample_rate);
+        self.process_ducking(frame);
+        self.advance_clock();
+    }
+
+    fn seek(&mut self, position: Duration) {
+        self.start = position;
+        self.frames = 0;
+        self.clock
+            .store(position.as_micros() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Processing applied to the final mix before it reaches the device.
+pub struct MasterChain {
+    params: Arc<ProcessorParams>,
+    feedback_duck: OutputDuck,
+    limiter: Limiter,
+    spectrum: SpectrumAnalyzer,
+    record: RecordBuffer,
+    sample_rate: u32,
+}
+
+impl MasterChain {
+    pub fn new(params: Arc<ProcessorParams>) -> Self {
+        let threshold = dsp::db_to_linear(params.limiter_threshold_db.load());
+        Self {
+            params,
+            feedback_duck: OutputDuck::new(44_100),
+            limiter: Limiter::new(threshold, 2, 44_100),
+            spectrum: SpectrumAnalyzer::new(44_100),
+            record: RecordBuffer::default(),
+            sample_rate: 44_100,
+        }
+    }
+
+    /// Turn the output down while feedback on a mic is more than its
+    /// notches can stop. The notches themselves are on each mic.
+    fn process_feedback_duck(&mut self, frame: &mut [f32]) {
+        let params = &self.params;
+        let ducking = params.feedback_enabled() && params.feedback_duck_wanted();
+        self.feedback_duck.process_frame(frame, ducking);
+        params
+            .feedback_duck_db
+            .store(self.feedback_duck.ducking_db());
+    }
+
+    /// Balance control: attenuate the opposite side, never boost.
+    fn process_pan(&mut self, frame: &mut [f32]) {
+        let pan = self.params.pan.load();
+        if pan == 0.0 || frame.len() < 2 {
+            return;
+        }
+        frame[0] *= (1.0 - pan).min(1.0);
+        frame[1] *= (1.0 + pan).min(1.0);
+    }
+
+    /// Brick-wall limiter, the last stage that changes the signal.
+    fn process_limiter(&mut self, frame: &mut [f32]) {
+        let params = &self.params;
+        if frame.iter().any(|sample| sample.abs() > 1.0) {
+            params.clips.fetch_add(1, Ordering::Relaxed);
+        }
+        if params.limiter_enabled.load(Ordering::Relaxed) {
+            self.limiter
+                .set_threshold(dsp::db_to_linear(params.limiter_threshold_db.load()));
+            self.limiter.process_frame(frame);
+            params
+                .gain_reduction_db
+                .store(-dsp::linear_to_db(self.limiter.gain()));
+        } else {
+            params.gain_reduction_db.store(0.0);
+        }
+    }
+}
+
+impl FrameProcessor for MasterChain {
+    fn configure(&mut self, channels: u16, sample_rate: u32) {
+        self.feedback_duck.configure(sample_rate);
+        self.limiter.configure(channels, sample_rate);
+        self.spectrum.configure(sample_rate);
+        self.sample_rate = sample_rate;
+    }
+
+    fn process_frame(&mut self, frame: &mut [f32]) {
+        self.process_feedback_duck(frame);
+        self.process_pan(frame);
+        self.process_limiter(frame);
+        self.spectrum.process_frame(frame, &self.params.spectrum);
+        self.record
+            .push(&self.params.mix_record, frame, self.sample_rate);
+        self.params.output_frames.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A rodio source running a [`FrameProcessor`] on an inner `f32` source.
+pub struct FrameSource<S, P> {
+    input: S,
+    processor: P,
+    channels: u16,
+    sample_rate: u32,
+    frame: Vec<f32>,
+    position: usize,
+}
+
+impl<S, P> FrameSource<S, P>
+where
+    S: Source<Item = f32>,
+    P: FrameProcessor,
+{
+    pub fn new(input: S, mut processor: P) -> Self {
+        let channels = input.channels();
+        let sample_rate = input.sample_rate();
+        processor.configure(channels, sample_rate);
+        Self {
+            input,
+            processor,
+            channels,
+            sample_rate,
+            frame: Vec::with_capacity(usize::from(channels)),
+            position: 0,
+        }
+    }
+
+    /// Pull the next frame from the input and run it through the processor.
+    fn fill_frame(&mut self) {
+        self.frame.clear();
+        self.position = 0;
+
+        let channels = self.input.channels();
+        let sample_rate = self.input.sample_rate();
+        if channels != self.channels || sample_rate != self.sample_rate {
+            self.channels = channels;
+            self.sample_rate = sample_rate;
+            self.processor.configure(channels, sample_rate);
+        }
+
+        for _ in 0..channels {
+            match self.input.next() {
+                Some(sample) => self.frame.push(sample),
+                None => break,
+            }
+        }
+        if !self.frame.is_empty() {
+            self.processor.process_frame(&mut self.frame);
+        }
+    }
+}
+
+impl<S, P> Iterator for FrameSource<S, P>
+where
+    S: Source<Item = f32>,
+    P: FrameProcessor,
+{
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position >= self.frame.len() {
+            self.fill_frame();
+        }
+        let sample = self.frame.get(self.position).copied();
+        self.position += 1;
+        sample
+    }
+}
+
+impl<S, P> Source for FrameSource<S, P>
+where
+    S: Source<Item = f32>,
+    P: FrameProcessor,
+{
+    fn current_frame_len(&self) -> Option<usize> {
+        let buffered = self.frame.len().saturating_sub(self.position);
+        self.input.current_frame_len().map(|len| len + buffered)
+    }
+
+    fn channels(&self) -> u16 {
+        self.input.channels()
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.input.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.input.total_duration()
+    }
+
+    fn try_seek(&mut self, pos: Duration) -> Result<(), SeekError> {
+        self.input.try_seek(pos)?;
+        self.frame.clear();
+        self.position = 0;
+        self.processor.seek(pos);
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+//! Recording performances to file.
+//!
+//! The audio thread copies samples into a [`RecordBuffer`] and hands full
+//! blocks to a [`RecordTap`]; a writer thread per file drains the tap into
+//! a 16-bit WAV file. Nothing is copied while no [`Recorder`] is attached.
+//! FLAC files are converted from the finished WAV with `ffmpeg`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::processor::MIC_COUNT;
+use super::{AudioError, ProcessorParams};
+
+/// Samples per block handed to the writer.
+const BLOCK_SAMPLES: usize = 8192;
+/// Blocks a slow disk may fall behind before new ones are dropped.
+const BLOCK_BACKLOG: usize = 64;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RecordingFormat {
+    #[default]
+    Wav,
+    /// Converted from WAV with `ffmpeg`; kept as WAV when that fails
+    Flac,
+}
+
+impl RecordingFormat {
+    pub const ALL: [Self; 2] = [Self::Wav, Self::Flac];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Wav => "WAV",
+            Self::Flac => "FLAC",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Wav => "wav",
+            Self::Flac => "flac",
+        }
+    }
+}
+
+/// What a file of a recording holds.
+#[derive(Debug, Clone, Copy)]
+enum Track {
+    Vocals,
+    Music,
+    Mix,
+}
+
+/// Files of a finished recording.
+#[derive(Debug, Default)]
+pub struct Recording {
+    /// One per singer
+    pub vocals: Vec<PathBuf>,
+    /// The backing track alone
+    pub music: Option<PathBuf>,
+    pub mix: Option<PathBuf>,
+    pub errors: Vec<AudioError>,
+}
+
+impl Recording {
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.vocals.iter().chain(&self.music).chain(&self.mix)
+    }
+}
+
+/// A block of interleaved samples.
+#[derive(Debug)]
+pub struct RecordBlock {
+    channels: u16,
+    sample_rate: u32,
+    samples: Vec<f32>,
+}
+
+/// Hand-over point between the audio thread and a recording writer.
+#[derive(Debug, Default)]
+pub struct RecordTap {
+    sender: Mutex<Option<SyncSender<RecordBlock>>>,
+    active: AtomicBool,
+    /// Blocks dropped because the writer fell behind
+    dropped: AtomicU64,
+}
+
+impl RecordTap {
+    /// Start receiving blocks, replacing any previous receiver.
+    fn attach(&self) -> Receiver<RecordBlock> {
+        let (sender, receiver) = mpsc::sync_channel(BLOCK_BACKLOG);
+        if let Ok(mut current) = self.sender.lock() {
+            *current = Some(sender);
+            self.active.store(true, Ordering::Relaxed);
+        }
+        receiver
+    }
+
+    /// Stop sending; the writer ends once it has drained the channel.
+    fn detach(&self) {
+        self.active.store(false, Ordering::Relaxed);
+        if let Ok(mut current) = self.sender.lock() {
+            *current = None;
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Never blocks: the block is dropped if the UI holds the lock or the
+    /// writer is behind.
+    fn send(&self, block: RecordBlock) {
+        let Ok(sender) = self.sender.try_lock() else {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        };
+        if let Some(sender) = sender.as_ref() {
+            if let Err(TrySendError::Full(_)) = sender.try_send(block) {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Audio-thread side of a tap: collects samples into blocks.
+#[derive(Debug, Default)]
+pub struct RecordBuffer {
+    samples: Vec<f32>,
+}
+
+impl RecordBuffer {
+    /// Add one frame of `channels` interleaved samples, sending a block
+    /// whenever one is full.
+    pub fn push(&mut self, tap: &RecordTap, frame: &[f32], sample_rate: u32) {
+        if !tap.is_active() {
+            self.samples.clear();
+            return;
+        }
+        if self.samples.capacity() == 0 {
+            self.samples.reserve_exact(BLOCK_SAMPLES);
+        }
+        self.samples.extend_from_slice(frame);
+        if self.samples.len() >= BLOCK_SAMPLES {
+            tap.send(RecordBlock {
+                channels: frame.len() as u16,
+                sample_rate,
+                samples: std::mem::replace(&mut self.samples, Vec::with_capacity(BLOCK_SAMPLES)),
+            });
+        }
+    }
+}
+
+/// A running recording: one file per mic, and optionally the mix.
+pub struct Recorder {
+    params: Arc<ProcessorParams>,
+    started: Instant,
+    writers: Vec<(Track, JoinHandle<Result<PathBuf, AudioError>>)>,
+    stopped: bool,
+}
+
+impl Recorder {
+    /// Record the first `mics` microphones, the backing track on its own
+    /// when `include_music`, and the output mix when `include_mix`, into
+    /// `folder` as `<time> <name> (<track>)`.
+    pub fn start(
+        params: Arc<ProcessorParams>,
+        folder: &Path,
+        name: &str,
+        mics: usize,
+        include_music: bool,
+        include_mix: bool,
+        format: RecordingFormat,
+    ) -> Result<Self, AudioError> {
+        fs::create_dir_all(folder).map_err(|e| {
+            AudioError::RecordingError(format!("Cannot create {}: {e}", folder.display()))
+        })?;
+        let stem = format!("{} {}", timestamp(SystemTime::now()), file_safe(name));
+        let mut tracks: Vec<(Track, &RecordTap, String)> = (0..mics.min(MIC_COUNT))
+            .map(|slot| {
+                let name = match (slot, mics) {
+                    (_, 1) => "vocals".to_string(),
+                    (slot, _) => format!("singer {}", slot + 1),
+                };
+                (Track::Vocals, params.mic(slot).record_tap(), name)
+            })
+            .collect();
+        if include_music {
+            tracks.push((Track::Music, params.music_record_tap(), "music".to_string()));
+        }
+        if include_mix {
+            tracks.push((Track::Mix, params.mix_record_tap(), "mix".to_string()));
+        }
+
+        let writers = tracks
+            .into_iter()
+            .map(|(track, tap, name)| {
+                let path = folder.join(format!("{stem} ({name}).wav"));
+                let receiver = tap.attach();
+                let writer = thread::spawn(move || write_track(&path, &receiver, format));
+                (track, writer)
+            })
+            .collect();
+        tracing::info!("Recording {name} into {}", folder.display());
+        Ok(Self {
+            params,
+            started: Instant::now(),
+            writers,
+            stopped: false,
+        })
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Stop taking audio; the files are finished in the background, see
+    /// [`Self::try_finish`].
+    pub fn stop(&mut self) {
+        if self.stopped {
+            return;
+        }
+        self.stopped = true;
+        for slot in 0..MIC_COUNT {
+            self.params.mic(slot).record_tap().detach();
+        }
+        self.params.music_record_tap().detach();
+        self.params.mix_record_tap().detach();
+        let dropped = (0..MIC_COUNT)
+            .map(|slot| self.params.mic(slot).record_tap())
+            .chain([self.params.music_record_tap(), self.params.mix_record_tap()])
+            .map(|tap| tap.dropped.swap(0, Ordering::Relaxed))
+            .sum::<u64>();
+        if dropped > 0 {
+            tracing::warn!("Recording dropped {dropped} blocks; the disk was too slow");
+        }
+    }
+
+    /// The saved files once every writer has finished, `None` while they
+    /// run. Only meaningful after [`Self::stop`].
+    pub fn try_finish(&mut self) -> Option<Recording> {
+        if self.writers.iter().any(|(_, writer)| !writer.is_finished()) {
+            return None;
+        }
+        Some(self.wait())
+    }
+
+    /// Stop and wait for the files to be finished, e.g. before quitting.
+    pub fn wait(&mut self) -> Recording {
+        self.stop();
+        let mut recording = Recording::default();
+        for (track, writer) in self.writers.drain(..) {
+            let file = writer
+                .join()
+                .unwrap_or_else(|_| Err(AudioError::RecordingError("Writer crashed".to_string())));
+            match (file, track) {
+                (Ok(path), Track::Vocals) => recording.vocals.push(path),
+                (Ok(path), Track::Music) => recording.music = Some(path),
+                (Ok(path), Track::Mix) => recording.mix = Some(path),
+                (Err(e), _) => recording.errors.push(e),
+            }
+        }
+        recording
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Write the blocks from `receiver` to `path` until the tap is detached,
+/// then convert to `format`. Returns the final file.
+fn write_track(
+    path: &Path,
+    receiver: &Receiver<RecordBlock>,
+    format: RecordingFormat,
+) -> Result<PathBuf, AudioError> {
+    let error = |e: hound::Error| AudioError::RecordingError(format!("{}: {e}", path.display()));
+    // The format is only known from the first block
+    let Ok(first) = receiver.recv() else {
+        return Err(AudioError::RecordingError(format!(
+            "{}: no audio was recorded",
+            path.display()
+        )));
+    };
+    let spec = hound::WavSpec {
+        channels: first.channels,
+        sample_rate: first.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec).map_err(error)?;
+    for block in std::iter::once(first).chain(receiver.iter()) {
+        for sample in block.samples {
+            writer.write_sample(to_i16(sample)).map_err(error)?;
+        }
+    }
+    writer.finalize().map_err(error)?;
+
+    match format {
+        RecordingFormat::Wav => Ok(path.to_path_buf()),
+        RecordingFormat::Flac => Ok(convert_to_flac(path)),
+    }
+}
+
+pub(super) fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)).round() as i16
+}
+
+pub(super) fn from_i16(sample: i16) -> f32 {
+    f32::from(sample) / f32::from(i16::MAX)
+}
+
+/// Convert the WAV at `path` with `ffmpeg`, deleting it on success. Returns
+/// the file that was kept.
+pub(super) fn convert_to_flac(path: &Path) -> PathBuf {
+    let flac = path.with_extension("flac");
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(path)
+        .arg(&flac)
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            if let Err(e) = fs::remove_file(path) {
+                tracing::warn!("Failed to delete {}: {e}", path.display());
+            }
+            flac
+        },
+        Ok(output) => {
+            tracing::warn!(
+                "FLAC conversion failed, keeping the WAV: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            path.to_path_buf()
+        },
+        Err(e) => {
+            tracing::warn!("Cannot run ffmpeg, keeping the WAV: {e}");
+            path.to_path_buf()
+        },
+    }
+}
+
+/// `time` as `YYYY-MM-DD HH-MM-SS` (UTC), sortable and valid in file names.
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (days, rest) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}-{:02}-{:02}",
+        rest / 3600,
+        rest % 3600 / 60,
+        rest % 60
+    )
+}
+
+/// `name` without characters file systems reject.
+fn file_safe(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
@@ -0,0 +1,113 @@
+//! Pitch accuracy scoring against target notes.
+
+use super::melody::MelodyNote;
+use super::pitch;
+
+/// A sung pitch this close to the target (in cents) counts as a hit.
+pub const HIT_TOLERANCE_CENTS: f32 = 50.0;
+/// A note counts as hit when this much of it was sung on pitch.
+const NOTE_HIT_ACCURACY: f32 = 0.5;
+/// Notes a song run needs to be scored, so a skipped song is not.
+const MIN_RUN_NOTES: usize = 20;
+/// Highest score of a run.
+pub const MAX_SCORE: u32 = 10_000;
+
+/// Distance in cents from `frequency` to the MIDI note `target`, folded into
+/// ±600 so singing in another octave is not penalized.
+pub fn cents_off(frequency: f32, target: f32) -> f32 {
+    let cents = (pitch::frequency_to_midi(frequency) - target) * 100.0;
+    (cents + 600.0).rem_euclid(1200.0) - 600.0
+}
+
+/// Running accuracy over pitch estimates taken while a note is due.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PitchScore {
+    hits: u32,
+    samples: u32,
+}
+
+impl PitchScore {
+    /// Count one estimate; silence counts as a miss.
+    pub fn add(&mut self, sung: Option<f32>, target: f32) {
+        self.samples += 1;
+        if sung.is_some_and(|frequency| cents_off(frequency, target).abs() <= HIT_TOLERANCE_CENTS) {
+            self.hits += 1;
+        }
+    }
+
+    /// Merge another score into this one.
+    pub fn combine(&mut self, other: &Self) {
+        self.hits += other.hits;
+        self.samples += other.samples;
+    }
+
+    /// Fraction of estimates on pitch, `None` before any estimate.
+    pub fn accuracy(&self) -> Option<f32> {
+        (self.samples > 0).then(|| self.hits as f32 / self.samples as f32)
+    }
+}
+
+/// One singer's accuracy over a song, note by note.
+#[derive(Debug, Clone, Default)]
+pub struct RunScore {
+    /// Start in seconds and score of each note that was due, in order
+    notes: Vec<(f32, PitchScore)>,
+}
+
+impl RunScore {
+    /// Count one estimate taken while `note` is due.
+    pub fn add(&mut self, note: &MelodyNote, sung: Option<f32>) {
+        match self.notes.last_mut() {
+            Some((start, score)) if *start == note.start => score.add(sung, note.note),
+            _ => {
+                let mut score = PitchScore::default();
+                score.add(sung, note.note);
+                self.notes.push((note.start, score));
+            },
+        }
+    }
+
+    /// Start and score of each note, in order.
+    pub fn notes(&self) -> &[(f32, PitchScore)] {
+        &self.notes
+    }
+
+    /// Whether enough of the song was sung for a result.
+    pub fn is_complete(&self) -> bool {
+        self.notes.len() >= MIN_RUN_NOTES
+    }
+
+    /// Fraction of all estimates on pitch.
+    pub fn accuracy(&self) -> f32 {
+        let mut total = PitchScore::default();
+        for (_, score) in &self.notes {
+            total.combine(score);
+        }
+        total.accuracy().unwrap_or(0.0)
+    }
+
+    /// Score out of [`MAX_SCORE`].
+    pub fn score(&self) -> u32 {
+        (self.accuracy() * MAX_SCORE as f32).round() as u32
+    }
+
+    pub fn notes_hit(&self) -> usize {
+        self.notes.iter().filter(|(_, score)| is_hit(score)).count()
+    }
+
+    /// Most notes hit in a row.
+    pub fn longest_streak(&self) -> usize {
+        let (mut longest, mut streak) = (0, 0);
+        for (_, score) in &self.notes {
+            streak = if is_hit(score) { streak + 1 } else { 0 };
+            longest = longest.max(streak);
+        }
+        longest
+    }
+}
+
+fn is_hit(score: &PitchScore) -> bool {
+    score
+        .accuracy()
+        .is_some_and(|accuracy| accuracy >= NOTE_HIT_ACCURACY)
+}
@@ -0,0 +1,227 @@
+//! Stem separation with Spleeter or Demucs, run through PyO3.
+//!
+//! Separation is slow (tens of seconds per song) and holds the Python GIL, so
+//! jobs run one at a time on a background worker thread. The instrumental stem
+//! is cached as a WAV file next to the original, where later sessions find it
+//! without separating again.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+use pyo3::exceptions::PyModuleNotFoundError;
+use pyo3::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Suffix added to the song's file stem for the cached instrumental.
+const INSTRUMENTAL_SUFFIX: &str = ".instrumental.wav";
+
+/// Python side of the separation. Each backend writes the accompaniment
+/// (everything except vocals) to `target` as WAV.
+const SEPARATE_PY: &str = r#"
+import os
+import shutil
+import tempfile
+
+
+def separate(backend, source, target):
+    if backend == "spleeter":
+        from spleeter.audio.adapter import AudioAdapter
+        from spleeter.separator import Separator
+
+        adapter = AudioAdapter.default()
+        waveform, rate = adapter.load(source, sample_rate=44100)
+        stems = Separator("spleeter:2stems").separate(waveform)
+        adapter.save(target, stems["accompaniment"], rate, "wav")
+    elif backend == "demucs":
+        import demucs.separate
+
+        with tempfile.TemporaryDirectory() as out:
+            demucs.separate.main(["--two-stems", "vocals", "-o", out, source])
+            for root, _, files in os.walk(out):
+                if "no_vocals.wav" in files:
+                    shutil.move(os.path.join(root, "no_vocals.wav"), target)
+                    return
+            raise RuntimeError("demucs produced no instrumental stem")
+    else:
+        raise ValueError("unknown backend " + backend)
+"#;
+
+#[derive(thiserror::Error, Debug)]
+pub enum SeparationError {
+    #[error("Separation failed: {0}")]
+    SeparationFailed(String),
+
+    #[error("Python error: {0}")]
+    PythonError(String),
+
+    #[error("{0} is not installed in the Python environment")]
+    BackendMissing(&'static str),
+}
+
+/// Source separation tool used to produce instrumentals.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SeparationBackend {
+    #[default]
+    Spleeter,
+    Demucs,
+}
+
+impl SeparationBackend {
+    pub const ALL: [Self; 2] = [Self::Spleeter, Self::Demucs];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Spleeter => "Spleeter",
+            Self::Demucs => "Demucs",
+        }
+    }
+
+    /// Name understood by the Python script.
+    fn id(self) -> &'static str {
+        match self {
+            Self::Spleeter => "spleeter",
+            Self::Demucs => "demucs",
+        }
+    }
+}
+
+/// Where the instrumental stem of `song` is cached.
+pub fn instrumental_path(song: &Path) -> PathBuf {
+    let stem = song
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    song.with_file_name(format!("{stem}{INSTRUMENTAL_SUFFIX}"))
+}
+
+/// True for files written by the separator, which are not songs themselves.
+pub fn is_instrumental_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| name.to_string_lossy().ends_with(INSTRUMENTAL_SUFFIX))
+}
+
+struct Job {
+    song: PathBuf,
+    backend: SeparationBackend,
+}
+
+/// A finished separation job.
+pub struct Outcome {
+    pub song: PathBuf,
+    pub result: Result<PathBuf, SeparationError>,
+}
+
+/// Queue of background separation jobs.
+///
+/// The worker thread starts on the first request and lives as long as the
+/// separator. Finished jobs are collected with [`poll`](Self::poll).
+#[derive(Default)]
+pub struct Separator {
+    jobs: Option<Sender<Job>>,
+    outcomes: Option<Receiver<Outcome>>,
+    pending: BTreeSet<PathBuf>,
+}
+
+impl Separator {
+    /// Queue `song` for separation. Does nothing if it is already queued.
+    pub fn request(&mut self, song: &Path, backend: SeparationBackend) {
+        if !self.pending.insert(song.to_path_buf()) {
+            return;
+        }
+        let jobs = self.jobs.get_or_insert_with(|| {
+            let (jobs, job_receiver) = mpsc::channel();
+            let (outcome_sender, outcomes) = mpsc::channel();
+            thread::spawn(move || worker(job_receiver, outcome_sender));
+            self.outcomes = Some(outcomes);
+            jobs
+        });
+
+        let job = Job {
+            song: song.to_path_buf(),
+            backend,
+        };
+        if jobs.send(job).is_err() {
+            tracing::error!("Separation worker is gone");
+            self.pending.remove(song);
+        }
+    }
+
+    pub fn is_pending(&self, song: &Path) -> bool {
+        self.pending.contains(song)
+    }
+
+    pub fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Jobs that finished since the last call.
+    pub fn poll(&mut self) -> Vec<Outcome> {
+        let Some(outcomes) = &self.outcomes else {
+            return Vec::new();
+        };
+        let finished: Vec<Outcome> = outcomes.try_iter().collect();
+        for outcome in &finished {
+            self.pending.remove(&outcome.song);
+        }
+        finished
+    }
+}
+
+fn worker(jobs: Receiver<Job>, outcomes: Sender<Outcome>) {
+    for job in jobs {
+        tracing::info!(
+            "Separating {} with {}",
+            job.song.display(),
+            job.backend.label()
+        );
+        let result = separate(&job.song, job.backend);
+        match &result {
+            Ok(path) => tracing::info!("Instrumental written to {}", path.display()),
+            Err(e) => tracing::error!("{}: {e}", job.song.display()),
+        }
+        if outcomes
+            .send(Outcome {
+                song: job.song,
+                result,
+            })
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Separate `song` and write its instrumental to the cache path.
+fn separate(song: &Path, backend: SeparationBackend) -> Result<PathBuf, SeparationError> {
+    let target = instrumental_path(song);
+    // Write under a temporary name so a failed run never leaves a partial cache
+    let partial = target.with_extension("partial.wav");
+
+    Python::with_gil(|py| {
+        let module = PyModule::from_code_bound(py, SEPARATE_PY, "separation.py", "separation")
+            .map_err(|e| SeparationError::PythonError(e.to_string()))?;
+        module
+            .getattr("separate")
+            .and_then(|separate| {
+                separate.call1((
+                    backend.id(),
+                    song.to_string_lossy(),
+                    partial.to_string_lossy(),
+                ))
+            })
+            .map_err(|e| {
+                if e.is_instance_of::<PyModuleNotFoundError>(py) {
+                    SeparationError::BackendMissing(backend.label())
+                } else {
+                    SeparationError::SeparationFailed(e.to_string())
+                }
+            })?;
+        Ok(())
+    })?;
+
+    std::fs::rename(&partial, &target)
+        .map_err(|e| SeparationError::SeparationFailed(format!("{}: {e}", target.display())))?;
+    Ok(target)
+}
@@ -0,0 +1,138 @@
+//! Live spectrum of the output mix.
+//!
+//! The master chain feeds every output frame to a [`SpectrumAnalyzer`], which
+//! publishes windowed FFT magnitude frames to whoever subscribed through
+//! [`SpectrumTap::subscribe`]. No analysis runs while nobody listens.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+
+use super::dsp::{self, fft};
+
+/// FFT length; at 48 kHz one bin is ~23 Hz wide.
+pub const FFT_SIZE: usize = 2048;
+/// Samples between frames (50% overlap).
+const HOP_SIZE: usize = FFT_SIZE / 2;
+/// Frames a slow subscriber may fall behind before new ones are dropped.
+const SUBSCRIBER_BACKLOG: usize = 4;
+
+/// One analysis frame of the output mix.
+#[derive(Debug)]
+pub struct SpectrumFrame {
+    pub sample_rate: u32,
+    /// Magnitude per bin in dBFS, `FFT_SIZE / 2` bins from 0 Hz up
+    pub magnitudes: Vec<f32>,
+}
+
+impl SpectrumFrame {
+    /// Centre frequency of `bin` in Hz.
+    pub fn frequency(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate as f32 / FFT_SIZE as f32
+    }
+}
+
+/// Subscriber registry shared between the audio thread and the UI.
+#[derive(Debug, Default)]
+pub struct SpectrumTap {
+    subscribers: Mutex<Vec<SyncSender<Arc<SpectrumFrame>>>>,
+    count: AtomicUsize,
+}
+
+impl SpectrumTap {
+    /// Receive spectrum frames until the receiver is dropped.
+    pub fn subscribe(&self) -> Receiver<Arc<SpectrumFrame>> {
+        let (sender, receiver) = mpsc::sync_channel(SUBSCRIBER_BACKLOG);
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+            self.count.store(subscribers.len(), Ordering::Relaxed);
+        }
+        receiver
+    }
+
+    fn is_active(&self) -> bool {
+        self.count.load(Ordering::Relaxed) > 0
+    }
+
+    /// Send a frame to every subscriber, forgetting the ones that hung up.
+    /// Never blocks: a frame is skipped if the UI holds the lock.
+    fn publish(&self, frame: SpectrumFrame) {
+        let Ok(mut subscribers) = self.subscribers.try_lock() else {
+            return;
+        };
+        let frame = Arc::new(frame);
+        subscribers.retain(|subscriber| {
+            !matches!(
+                subscriber.try_send(frame.clone()),
+                Err(TrySendError::Disconnected(_))
+            )
+        });
+        self.count.store(subscribers.len(), Ordering::Relaxed);
+    }
+}
+
+/// Collects the mono downmix and runs an FFT every hop.
+pub struct SpectrumAnalyzer {
+    sample_rate: u32,
+    window: Vec<f32>,
+    history: Vec<f32>,
+    write: usize,
+    since_frame: usize,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            window: fft::hann_window(FFT_SIZE),
+            history: vec![0.0; FFT_SIZE],
+            write: 0,
+            since_frame: 0,
+            re: vec![0.0; FFT_SIZE],
+            im: vec![0.0; FFT_SIZE],
+        }
+    }
+
+    pub fn configure(&mut self, sample_rate: u32) {
+        *self = Self::new(sample_rate);
+    }
+
+    /// Feed one interleaved frame, publishing to `tap` when a hop completes.
+    pub fn process_frame(&mut self, frame: &[f32], tap: &SpectrumTap) {
+        if frame.is_empty() || !tap.is_active() {
+            return;
+        }
+        self.history[self.write] = frame.iter().sum::<f32>() / frame.len() as f32;
+        self.write = (self.write + 1) % FFT_SIZE;
+        self.since_frame += 1;
+        if self.since_frame >= HOP_SIZE {
+            self.since_frame = 0;
+            tap.publish(self.analyse());
+        }
+    }
+
+    fn analyse(&mut self) -> SpectrumFrame {
+        // Oldest sample first, starting at the write position
+        for i in 0..FFT_SIZE {
+            self.re[i] = self.history[(self.write + i) % FFT_SIZE] * self.window[i];
+            self.im[i] = 0.0;
+        }
+        fft::fft(&mut self.re, &mut self.im);
+
+        // A full-scale sine reads 0 dB through a Hann window
+        let scale = 4.0 / FFT_SIZE as f32;
+        let magnitudes = (0..FFT_SIZE / 2)
+            .map(|bin| {
+                let amplitude = (self.re[bin].powi(2) + self.im[bin].powi(2)).sqrt() * scale;
+                dsp::linear_to_db(amplitude)
+            })
+            .collect();
+
+        SpectrumFrame {
+            sample_rate: self.sample_rate,
+            magnitudes,
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Vocal warm-up exercises: note sequences played as guide tones and scored
+//! against the microphone pitch, per singer in duet mode.
+
+use std::time::{Duration, Instant};
+
+use rodio::Sink;
+
+use super::generator;
+use super::pitch;
+use super::processor::MIC_COUNT;
+use super::scoring::{self, PitchScore};
+use super::AudioPlayer;
+
+/// Pause between repetitions of an exercise, in note lengths.
+const GAP_NOTES: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exercise {
+    /// Five notes up and back down
+    FiveNoteScale,
+    /// Major scale over one octave, up and down
+    MajorScale,
+    /// Major triad arpeggio up to the octave and back
+    Arpeggio,
+}
+
+impl Exercise {
+    pub const ALL: [Self; 3] = [Self::FiveNoteScale, Self::MajorScale, Self::Arpeggio];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::FiveNoteScale => "Five-note scale",
+            Self::MajorScale => "Major scale",
+            Self::Arpeggio => "Arpeggio",
+        }
+    }
+
+    /// Intervals from the root, in semitones.
+    fn intervals(self) -> &'static [u8] {
+        match self {
+            Self::FiveNoteScale => &[0, 2, 4, 5, 7, 5, 4, 2, 0],
+            Self::MajorScale => &[0, 2, 4, 5, 7, 9, 11, 12, 11, 9, 7, 5, 4, 2, 0],
+            Self::Arpeggio => &[0, 4, 7, 12, 7, 4, 0],
+        }
+    }
+}
+
+/// One target note of a running exercise.
+#[derive(Debug, Clone)]
+pub struct WarmupNote {
+    pub note: u8,
+    pub start: Duration,
+    /// Accuracy of each singer on this note
+    pub scores: [PitchScore; MIC_COUNT],
+}
+
+/// A running exercise: the guide tones play on their own sink in the mix,
+/// the targets advance with the clock.
+pub struct WarmupSession {
+    pub exercise: Exercise,
+    pub notes: Vec<WarmupNote>,
+    pub note_length: Duration,
+    /// Singers scored: 1, or 2 in duet mode
+    pub singers: usize,
+    /// Sung pitch of each singer over time as fractional MIDI notes,
+    /// octave-folded onto the target that was due
+    pub trails: [Vec<(Duration, f32)>; MIC_COUNT],
+    /// Mic latency of each singer, last passed to [`Self::update`]
+    latency: [Duration; MIC_COUNT],
+    started: Instant,
+    _guide: Sink,
+}
+
+impl WarmupSession {
+    /// Play `exercise` from `root` (MIDI note), repeated a semitone higher
+    /// `repeats` times, for `singers` voices.
+    pub fn start(
+        player: &AudioPlayer,
+        exercise: Exercise,
+        root: u8,
+        repeats: u8,
+        note_length: Duration,
+        singers: usize,
+    ) -> Self {
+        let (guide, queue) = Sink::new_idle();
+        player.mixer().add(queue);
+
+        let mut notes = Vec::new();
+        let mut start = Duration::ZERO;
+        for repeat in 0..repeats.max(1) {
+            for interval in exercise.intervals() {
+                let note = root.saturating_add(repeat + interval).min(127);
+                let frequency = pitch::midi_to_frequency(f32::from(note));
+                guide.append(generator::create_tone(frequency, note_length));
+                notes.push(WarmupNote {
+                    note,
+                    start,
+                    scores: Default::default(),
+                });
+                start += note_length;
+            }
+            let gap = note_length * GAP_NOTES;
+            guide.append(generator::create_tone(0.0, gap));
+            start += gap;
+        }
+
+        Self {
+            exercise,
+            notes,
+            note_length,
+            singers: singers.clamp(1, MIC_COUNT),
+            trails: Default::default(),
+            latency: Default::default(),
+            started: Instant::now(),
+            _guide: guide,
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Index of the note due now, if any.
+    pub fn current(&self) -> Option<usize> {
+        self.note_at(self.elapsed())
+    }
+
+    fn note_at(&self, time: Duration) -> Option<usize> {
+        self.notes
+            .iter()
+            .position(|note| time >= note.start && time < note.start + self.note_length)
+    }
+
+    /// Whether the last note is over, and has reached every singer's mic.
+    pub fn is_finished(&self) -> bool {
+        let latency = self.latency.iter().max().copied().unwrap_or_default();
+        self.notes
+            .last()
+            .is_none_or(|note| self.elapsed() >= note.start + self.note_length + latency)
+    }
+
+    /// Score each singer's mic pitch against the note that was due when it
+    /// was sung: `latency` earlier, for mics that deliver the voice late.
+    pub fn update(&mut self, sung: &[Option<f32>; MIC_COUNT], latency: &[Duration; MIC_COUNT]) {
+        self.latency = *latency;
+        let elapsed = self.started.elapsed();
+        for singer in 0..self.singers {
+            let time = elapsed.saturating_sub(latency[singer]);
+            let Some(index) = self.note_at(time) else {
+                continue;
+            };
+            let note = &mut self.notes[index];
+            let target = f32::from(note.note);
+            note.scores[singer].add(sung[singer], target);
+            if let Some(frequency) = sung[singer] {
+                let folded = target + scoring::cents_off(frequency, target) / 100.0;
+                self.trails[singer].push((time, folded));
+            }
+        }
+    }
+
+    /// Accuracy of `singer` over the whole exercise so far.
+    pub fn total_score(&self, singer: usize) -> PitchScore {
+        let mut total = PitchScore::default();
+        for note in &self.notes {
+            total.combine(&note.scores[singer]);
+        }
+        total
+    }
+
+    /// Accuracy of all singers together on `note`.
+    pub fn note_score(&self, note: &WarmupNote) -> PitchScore {
+        let mut total = PitchScore::default();
+        for score in &note.scores[..self.singers] {
+            total.combine(score);
+        }
+        total
+    }
+}
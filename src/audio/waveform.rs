@@ -0,0 +1,168 @@
+//! Waveform overview for the seek bar.
+//!
+//! A track is decoded once into min/max peaks per fixed-length bucket. The
+//! peaks are cached in the data directory, keyed by path and modification
+//! time, so later loads of an unchanged file are instant.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, SystemTime};
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use super::{dsp, player, AudioError};
+use crate::config;
+
+const CACHE_DIR_NAME: &str = "waveforms";
+/// Length of audio summarized by one peak pair.
+const BUCKET_MS: u32 = 50;
+/// Peaks below this level count as silence.
+const SILENCE_THRESHOLD_DB: f32 = -48.0;
+/// Kept before the first and after the last audible bucket, so attacks and
+/// fade-outs are not cut.
+const SILENCE_PADDING: Duration = Duration::from_millis(150);
+
+/// Min/max peaks of a track, one pair per [`BUCKET_MS`] of audio.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Waveform {
+    pub peaks: Vec<(f32, f32)>,
+}
+
+impl Waveform {
+    pub fn bucket_duration() -> Duration {
+        Duration::from_millis(u64::from(BUCKET_MS))
+    }
+
+    pub fn duration(&self) -> Duration {
+        Self::bucket_duration() * self.peaks.len() as u32
+    }
+
+    /// Start and end of the audible part, without leading and trailing
+    /// silence. `None` when the whole track is silent.
+    pub fn audible_range(&self) -> Option<(Duration, Duration)> {
+        let threshold = dsp::db_to_linear(SILENCE_THRESHOLD_DB);
+        let audible = |&(min, max): &(f32, f32)| min.abs().max(max.abs()) > threshold;
+        let first = self.peaks.iter().position(audible)?;
+        let last = self.peaks.iter().rposition(audible)?;
+
+        let bucket = Self::bucket_duration();
+        let start = (bucket * first as u32).saturating_sub(SILENCE_PADDING);
+        let end = (bucket * (last + 1) as u32 + SILENCE_PADDING).min(self.duration());
+        Some((start, end))
+    }
+}
+
+/// Cache file contents; the key guards against hash collisions and edits.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    path: PathBuf,
+    modified: SystemTime,
+    waveform: Waveform,
+}
+
+/// The waveform of `path`, from the cache when it is still valid.
+pub fn load_or_extract(path: &Path) -> Result<Waveform, AudioError> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_err(|e| AudioError::LoadError(format!("{}: {e}", path.display())))?;
+    let cache = cache_path(path, modified);
+
+    if let Ok(contents) = fs::read_to_string(&cache) {
+        match serde_json::from_str::<CacheEntry>(&contents) {
+            Ok(entry) if entry.path == path && entry.modified == modified => {
+                return Ok(entry.waveform);
+            },
+            Ok(_) => {},
+            Err(e) => tracing::warn!("Invalid waveform cache {}: {e}", cache.display()),
+        }
+    }
+
+    let waveform = extract(path)?;
+    let entry = CacheEntry {
+        path: path.to_path_buf(),
+        modified,
+        waveform,
+    };
+    if let Err(e) = write_cache(&cache, &entry) {
+        tracing::warn!("Failed to cache waveform {}: {e}", cache.display());
+    }
+    Ok(entry.waveform)
+}
+
+/// Decode `path` and collect its peaks.
+pub fn extract(path: &Path) -> Result<Waveform, AudioError> {
+    let decoder = player::open_decoder(path)?;
+    let channels = usize::from(decoder.channels().max(1));
+    let bucket_len = (decoder.sample_rate() * BUCKET_MS / 1000) as usize * channels;
+
+    let mut peaks = Vec::new();
+    let (mut low, mut high, mut count) = (0.0_f32, 0.0_f32, 0);
+    for sample in decoder.convert_samples::<f32>() {
+        low = low.min(sample);
+        high = high.max(sample);
+        count += 1;
+        if count == bucket_len {
+            peaks.push((low, high));
+            (low, high, count) = (0.0, 0.0, 0);
+        }
+    }
+    if count > 0 {
+        peaks.push((low, high));
+    }
+    Ok(Waveform { peaks })
+}
+
+fn cache_path(path: &Path, modified: SystemTime) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    modified.hash(&mut hasher);
+    config::data_dir()
+        .join(CACHE_DIR_NAME)
+        .join(format!("{:016x}.json", hasher.finish()))
+}
+
+fn write_cache(cache: &Path, entry: &CacheEntry) -> anyhow::Result<()> {
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(cache, serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// Waveform loading on a background thread.
+pub struct WaveformJob {
+    song: PathBuf,
+    handle: Option<JoinHandle<Result<Waveform, AudioError>>>,
+}
+
+impl WaveformJob {
+    pub fn start(song: &Path) -> Self {
+        let owned = song.to_path_buf();
+        Self {
+            song: song.to_path_buf(),
+            handle: Some(thread::spawn(move || load_or_extract(&owned))),
+        }
+    }
+
+    pub fn song(&self) -> &Path {
+        &self.song
+    }
+
+    /// The result once loading has finished; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<Result<Waveform, AudioError>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| {
+            Err(AudioError::LoadError(format!(
+                "{}: waveform extraction crashed",
+                self.song.display()
+            )))
+        }))
+    }
+}
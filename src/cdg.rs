@@ -0,0 +1,243 @@
+//! CD+G graphics for MP3+G karaoke songs.
+//!
+//! A `.cdg` next to the audio holds the subcode graphics of a karaoke CD:
+//! 24-byte packets at 300 per second, drawing 6 × 12 tiles in a 16 colour
+//! palette on a 300 × 216 screen. The packets up to the song position are
+//! applied in order; seeking backwards replays them from the start, which
+//! is quick even for long songs.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Extensions of the graphics file, tried in order.
+const CDG_EXTENSIONS: [&str; 2] = ["cdg", "CDG"];
+const WIDTH: usize = 300;
+const HEIGHT: usize = 216;
+const TILE_WIDTH: usize = 6;
+const TILE_HEIGHT: usize = 12;
+const PACKET_SIZE: usize = 24;
+const PACKETS_PER_SECOND: f64 = 300.0;
+/// Subcode command of packets that carry graphics.
+const CDG_COMMAND: u8 = 0x09;
+
+/// Graphics instructions, from the low six bits of the packet's second byte.
+const MEMORY_PRESET: u8 = 1;
+const BORDER_PRESET: u8 = 2;
+const TILE_BLOCK: u8 = 6;
+const SCROLL_PRESET: u8 = 20;
+const SCROLL_COPY: u8 = 24;
+const LOAD_COLORS_LOW: u8 = 30;
+const LOAD_COLORS_HIGH: u8 = 31;
+const TILE_BLOCK_XOR: u8 = 38;
+
+/// The graphics kept for `song`, if there are any.
+pub fn cdg_path(song: &Path) -> Option<PathBuf> {
+    CDG_EXTENSIONS
+        .iter()
+        .map(|extension| song.with_extension(extension))
+        .find(|path| path.exists())
+}
+
+/// The pixels and palette the packets draw into.
+struct Screen {
+    /// Palette index of each pixel, row by row
+    pixels: Vec<u8>,
+    palette: [egui::Color32; 16],
+}
+
+impl Default for Screen {
+    fn default() -> Self {
+        Self {
+            pixels: vec![0; WIDTH * HEIGHT],
+            palette: [egui::Color32::BLACK; 16],
+        }
+    }
+}
+
+impl Screen {
+    /// Apply one packet; returns true when the picture changed.
+    fn apply(&mut self, packet: &[u8]) -> bool {
+        if packet[0] & 0x3F != CDG_COMMAND {
+            return false;
+        }
+        let data = &packet[4..20];
+        match packet[1] & 0x3F {
+            MEMORY_PRESET => self.pixels.fill(data[0] & 0x0F),
+            BORDER_PRESET => {
+                let color = data[0] & 0x0F;
+                for y in 0..HEIGHT {
+                    for x in 0..WIDTH {
+                        let inside = (TILE_WIDTH..WIDTH - TILE_WIDTH).contains(&x)
+                            && (TILE_HEIGHT..HEIGHT - TILE_HEIGHT).contains(&y);
+                        if !inside {
+                            self.pixels[y * WIDTH + x] = color;
+                        }
+                    }
+                }
+            },
+            TILE_BLOCK => self.tile(data, false),
+            TILE_BLOCK_XOR => self.tile(data, true),
+            SCROLL_PRESET => self.scroll(data, false),
+            SCROLL_COPY => self.scroll(data, true),
+            LOAD_COLORS_LOW => self.load_colors(data, 0),
+            LOAD_COLORS_HIGH => self.load_colors(data, 8),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Draw a tile of two colours, or XOR it onto what is there.
+    fn tile(&mut self, data: &[u8], xor: bool) {
+        let colors = [data[0] & 0x0F, data[1] & 0x0F];
+        let top = usize::from(data[2] & 0x1F) * TILE_HEIGHT;
+        let left = usize::from(data[3] & 0x3F) * TILE_WIDTH;
+        if top + TILE_HEIGHT > HEIGHT || left + TILE_WIDTH > WIDTH {
+            return;
+        }
+        for (row, bits) in data[4..16].iter().enumerate() {
+            for column in 0..TILE_WIDTH {
+                let on = bits >> (TILE_WIDTH - 1 - column) & 1;
+                let pixel = &mut self.pixels[(top + row) * WIDTH + left + column];
+                let color = colors[usize::from(on)];
+                *pixel = if xor { *pixel ^ color } else { color };
+            }
+        }
+    }
+
+    /// Move the picture a tile across and/or down. What scrolls off comes
+    /// back in on the other side when `wrap`, or else the space is filled
+    /// with the given colour.
+    fn scroll(&mut self, data: &[u8], wrap: bool) {
+        let fill = data[0] & 0x0F;
+        let dx: isize = match (data[1] & 0x30) >> 4 {
+            1 => TILE_WIDTH as isize,
+            2 => -(TILE_WIDTH as isize),
+            _ => 0,
+        };
+        let dy: isize = match (data[2] & 0x30) >> 4 {
+            1 => TILE_HEIGHT as isize,
+            2 => -(TILE_HEIGHT as isize),
+            _ => 0,
+        };
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        let source = self.pixels.clone();
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let from_x = x as isize - dx;
+                let from_y = y as isize - dy;
+                let inside =
+                    (0..WIDTH as isize).contains(&from_x) && (0..HEIGHT as isize).contains(&from_y);
+                self.pixels[y * WIDTH + x] = if inside || wrap {
+                    let from_x = from_x.rem_euclid(WIDTH as isize) as usize;
+                    let from_y = from_y.rem_euclid(HEIGHT as isize) as usize;
+                    source[from_y * WIDTH + from_x]
+                } else {
+                    fill
+                };
+            }
+        }
+    }
+
+    /// Set eight palette entries from `first` on, 4 bits per channel.
+    fn load_colors(&mut self, data: &[u8], first: usize) {
+        for (index, pair) in data.chunks_exact(2).enumerate() {
+            let (high, low) = (pair[0] & 0x3F, pair[1] & 0x3F);
+            let red = high >> 2;
+            let green = ((high & 0x03) << 2) | (low >> 4);
+            let blue = low & 0x0F;
+            self.palette[first + index] = egui::Color32::from_rgb(red * 17, green * 17, blue * 17);
+        }
+    }
+
+    fn image(&self) -> egui::ColorImage {
+        egui::ColorImage {
+            size: [WIDTH, HEIGHT],
+            pixels: self
+                .pixels
+                .iter()
+                .map(|&index| self.palette[usize::from(index)])
+                .collect(),
+        }
+    }
+}
+
+/// CD+G graphics following the song position.
+pub struct CdgPlayback {
+    path: PathBuf,
+    packets: Vec<u8>,
+    screen: Screen,
+    /// Packets applied to the screen so far
+    applied: usize,
+    texture: Option<egui::TextureHandle>,
+    error: Option<String>,
+}
+
+impl CdgPlayback {
+    /// Read the graphics in `path`.
+    pub fn open(path: &Path) -> Self {
+        let (packets, error) = match fs::read(path) {
+            Ok(packets) => (packets, None),
+            Err(e) => {
+                tracing::warn!("Cannot read {}: {e}", path.display());
+                (Vec::new(), Some(e.to_string()))
+            },
+        };
+        Self {
+            path: path.to_path_buf(),
+            packets,
+            screen: Screen::default(),
+            applied: 0,
+            texture: None,
+            error,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Why the graphics cannot be shown, if they cannot.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Draw the packets up to `position` and return the texture to show.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        position: Duration,
+    ) -> Option<&egui::TextureHandle> {
+        if self.error.is_some() {
+            return None;
+        }
+        let due = ((position.as_secs_f64() * PACKETS_PER_SECOND) as usize)
+            .min(self.packets.len() / PACKET_SIZE);
+        let mut changed = self.texture.is_none();
+        if due < self.applied {
+            self.screen = Screen::default();
+            self.applied = 0;
+            changed = true;
+        }
+        for packet in
+            self.packets[self.applied * PACKET_SIZE..due * PACKET_SIZE].chunks_exact(PACKET_SIZE)
+        {
+            changed |= self.screen.apply(packet);
+        }
+        self.applied = due;
+
+        if changed {
+            let image = self.screen.image();
+            match &mut self.texture {
+                Some(texture) => texture.set(image, egui::TextureOptions::NEAREST),
+                None => {
+                    self.texture =
+                        Some(ctx.load_texture("cdg", image, egui::TextureOptions::NEAREST));
+                },
+            }
+        }
+        self.texture.as_ref()
+    }
+}
@@ -0,0 +1,688 @@
+//! Persistent application settings.
+//!
+//! Settings are stored as JSON in the application data directory. Every
+//! section falls back to its defaults, so a missing or partially written
+//! file never prevents the app from starting.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::adjustments::AdjustmentPolicy;
+use crate::audio::effects::VoiceEffect;
+use crate::audio::player::GuideEar;
+use crate::audio::recorder::RecordingFormat;
+use crate::audio::separation::SeparationBackend;
+use crate::lyrics::LyricsProvider;
+use crate::ui::layout::LayoutPreset;
+use crate::ui::lyrics_layout::{Easing, LineTransition, TextEffect};
+use crate::ui::singer_screen::ScreenPlacement;
+use crate::ui::theme::{Palette, ThemeMode};
+
+pub const APP_DIR_NAME: &str = "pwe-karaoke";
+const CONFIG_FILE_NAME: &str = "config.json";
+/// In the default data directory: where the data was moved to, if it was
+pub const LOCATION_FILE_NAME: &str = "data-location.txt";
+
+/// The data directory in use, once read from the location file.
+static DATA_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Top-level settings, one field per Settings section.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    pub audio: AudioConfig,
+    pub display: DisplayConfig,
+    pub remote: RemoteConfig,
+    pub network: NetworkConfig,
+    pub lyrics: LyricsConfig,
+    pub recording: RecordingConfig,
+    pub downloads: DownloadConfig,
+    pub announcer: AnnouncerConfig,
+    pub ticker: TickerConfig,
+    pub singer_screen: SingerScreenConfig,
+}
+
+/// Settings → Audio System.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AudioConfig {
+    /// Master output volume (0.0 - 1.0)
+    pub master_volume: f32,
+    /// Music level in dB, on top of each song's own volume offset
+    pub music_level_db: f32,
+    /// Output balance, -1.0 (left only) to 1.0 (right only)
+    pub pan: f32,
+    /// Run the lookahead limiter on the final mix
+    pub limiter_enabled: bool,
+    /// Limiter ceiling in dBFS
+    pub limiter_threshold_db: f32,
+    /// Automatically notch out howling feedback tones
+    pub feedback_suppression: bool,
+    /// Attenuate centre-panned lead vocals
+    pub vocal_removal: bool,
+    /// Vocal removal strength (0.0 - 1.0)
+    pub vocal_removal_strength: f32,
+    /// Play the original vocals quietly in one channel while the
+    /// instrumental plays, for the performer's earpiece
+    pub guide_vocals: bool,
+    pub guide_vocals_ear: GuideEar,
+    /// Guide vocal level in dB
+    pub guide_vocals_level_db: f32,
+    /// Play the microphone through the speakers
+    pub mic_passthrough: bool,
+    /// Gain on the microphone before any processing, in dB
+    pub input_gain_db: f32,
+    /// Mute the microphone while it is below the gate threshold
+    pub noise_gate_enabled: bool,
+    /// Noise gate threshold in dBFS
+    pub noise_gate_threshold_db: f32,
+    /// Steer the mic level toward a target after the gate
+    pub agc_enabled: bool,
+    /// Automatic gain control target level (RMS, dBFS)
+    pub agc_target_db: f32,
+    /// Most the automatic gain control may boost, in dB
+    pub agc_max_gain_db: f32,
+    /// Name of the microphone device (`None` = system default)
+    pub input_device: Option<String>,
+    /// Input channel of the device to use (`None` = all channels mixed)
+    pub input_channel: Option<u16>,
+    /// Second microphone for duets
+    pub duet: DuetConfig,
+    /// Most captured audio buffered ahead of the mix, in milliseconds
+    pub mic_latency_ms: u32,
+    /// Monitor the mic on an output stream of its own with a small buffer
+    /// instead of through the music's mix
+    pub low_latency_monitor: bool,
+    /// How late each input device delivers the voice, by device name, in
+    /// milliseconds; taken off when scoring and mixing recordings
+    pub mic_latency_offsets_ms: BTreeMap<String, u32>,
+    /// Lower the music while the microphone is hot
+    pub ducking: bool,
+    /// Mic level (dBFS) above which the music is ducked
+    pub ducking_threshold_db: f32,
+    /// How far the music is lowered, in dB
+    pub ducking_depth_db: f32,
+    /// Default voice effects rack, used by songs without their own preset
+    pub voice_effects: Vec<VoiceEffect>,
+    /// How much of every voice effect reaches the mix (0.0 - 1.0)
+    pub effects_send: f32,
+    /// Pull the sung pitch toward the song's key
+    pub autotune: bool,
+    /// Autotune strength (0.0 - 1.0)
+    pub autotune_strength: f32,
+    /// Tool used to produce instrumental stems
+    pub separation_backend: SeparationBackend,
+    /// Start songs at the first audible frame and end them after the last
+    pub skip_silence: bool,
+    /// Play metronome clicks before each song starts
+    pub count_in: bool,
+    /// Number of count-in clicks
+    pub count_in_beats: u8,
+    /// Queued songs that decode to at most this many megabytes are
+    /// decoded into memory ahead (0 = never)
+    pub precache_song_mb: u32,
+    /// Memory kept for songs decoded ahead, in megabytes
+    pub precache_total_mb: u32,
+    /// Where key, tempo and lyric timing changes made during a song are kept
+    pub adjustments: AdjustmentPolicy,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            master_volume: 0.8,
+            music_level_db: 0.0,
+            pan: 0.0,
+            limiter_enabled: true,
+            limiter_threshold_db: -1.0,
+            feedback_suppression: true,
+            vocal_removal: false,
+            vocal_removal_strength: 0.8,
+            guide_vocals: false,
+            guide_vocals_ear: GuideEar::default(),
+            guide_vocals_level_db: -12.0,
+            mic_passthrough: false,
+            input_gain_db: 0.0,
+            noise_gate_enabled: false,
+            noise_gate_threshold_db: -50.0,
+            agc_enabled: false,
+            agc_target_db: -20.0,
+            agc_max_gain_db: 18.0,
+            input_device: None,
+            input_channel: None,
+            duet: DuetConfig::default(),
+            mic_latency_ms: 60,
+            low_latency_monitor: false,
+            mic_latency_offsets_ms: BTreeMap::new(),
+            ducking: false,
+            ducking_threshold_db: -30.0,
+            ducking_depth_db: 12.0,
+            voice_effects: Vec::new(),
+            effects_send: 1.0,
+            autotune: false,
+            autotune_strength: 0.5,
+            separation_backend: SeparationBackend::default(),
+            skip_silence: false,
+            count_in: false,
+            count_in_beats: 4,
+            precache_song_mb: 128,
+            precache_total_mb: 512,
+            adjustments: AdjustmentPolicy::default(),
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Latency offset of the input device called `device`.
+    pub fn mic_latency_offset(&self, device: &str) -> Duration {
+        let ms = self
+            .mic_latency_offsets_ms
+            .get(device)
+            .copied()
+            .unwrap_or(0);
+        Duration::from_millis(u64::from(ms))
+    }
+}
+
+/// Settings → Audio System → Duet: the second singer's microphone, either
+/// another device or another channel of the same interface.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DuetConfig {
+    /// Open the second microphone
+    pub enabled: bool,
+    /// Name of its device (`None` = system default)
+    pub input_device: Option<String>,
+    /// Input channel of the device to use (`None` = all channels mixed)
+    pub input_channel: Option<u16>,
+    /// Gain on the second microphone, in dB
+    pub input_gain_db: f32,
+    /// Voice effects of the second singer
+    pub voice_effects: Vec<VoiceEffect>,
+}
+
+/// Settings → Display.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// Lyric font size in points; long lines are shrunk below it to fit
+    pub font_size: f32,
+    /// Smallest size long lines are shrunk to before they wrap
+    pub min_font_size: f32,
+    /// Lyric lines shown after the one being sung, 0 to 4
+    pub upcoming_lines: usize,
+    /// How much the upcoming lines are dimmed, 0.0 (not at all) to 1.0
+    pub upcoming_dim: f32,
+    /// Count down to the next line after instrumental breaks longer than
+    /// this, in seconds (0 = never)
+    pub break_countdown_secs: f32,
+    /// Show how far through the sung line the song is, in a thin bar under
+    /// it
+    pub line_progress: bool,
+    /// Scroll through lyrics without timestamps like a teleprompter,
+    /// rather than highlighting a line
+    pub teleprompter: bool,
+    /// Show the lyrics translation in this language under the sung line,
+    /// for songs that have one (`None` = no translation)
+    pub translation_language: Option<String>,
+    /// Show the romaji reading above Japanese lyrics
+    pub show_romaji: bool,
+    /// Colour of the line being sung (`None` = the theme's)
+    pub active_color: Option<[u8; 3]>,
+    /// Colour of the previous line, romaji and translation (`None` = the
+    /// theme's)
+    pub inactive_color: Option<[u8; 3]>,
+    /// Colour of the part of the line already sung (`None` = the theme's)
+    pub highlight_color: Option<[u8; 3]>,
+    /// Outline or shadow drawn behind the lyrics
+    pub text_effect: TextEffect,
+    /// Colour of the outline or shadow
+    pub effect_color: [u8; 3],
+    /// Font file the lyrics are drawn in (`None` = the app's font)
+    pub lyric_font: Option<PathBuf>,
+    /// How the lyrics move on to the next line
+    pub line_transition: LineTransition,
+    /// Length of the line transition in seconds
+    pub transition_secs: f32,
+    pub transition_easing: Easing,
+    /// Play the song's music video behind the lyrics, when it has one
+    pub show_video: bool,
+    /// How strongly the song's blurred cover art shows behind the lyrics
+    /// when there is no video,
+    /// 0.0 (not at all) to 1.0
+    pub cover_background: f32,
+    /// Show a summary when a song ends in the karaoke view; the queue
+    /// waits on it to go on
+    pub song_summary: bool,
+    /// Show the diagnostics HUD on startup
+    pub show_diagnostics: bool,
+    pub theme_mode: ThemeMode,
+    /// Palette used in dark mode
+    pub dark_palette: Palette,
+    /// Palette used in light mode
+    pub light_palette: Palette,
+    /// Active layout preset, `None` once panels were changed by hand
+    pub layout: Option<LayoutPreset>,
+    /// Show the queue sidebar
+    pub show_queue_panel: bool,
+    /// Width of the queue sidebar in points
+    pub queue_panel_width: f32,
+    /// Show the mic input meter in the karaoke view
+    pub show_mic_meter: bool,
+    /// Show the song's melody and the sung pitch in the karaoke view
+    pub show_pitch_guide: bool,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            font_size: 32.0,
+            min_font_size: 20.0,
+            upcoming_lines: 1,
+            upcoming_dim: 0.5,
+            break_countdown_secs: 10.0,
+            line_progress: true,
+            teleprompter: false,
+            translation_language: None,
+            show_romaji: false,
+            active_color: None,
+            inactive_color: None,
+            highlight_color: None,
+            text_effect: TextEffect::None,
+            effect_color: [0, 0, 0],
+            lyric_font: None,
+            line_transition: LineTransition::Fade,
+            transition_secs: 0.3,
+            transition_easing: Easing::EaseOut,
+            show_video: true,
+            cover_background: 0.5,
+            song_summary: true,
+            show_diagnostics: false,
+            theme_mode: ThemeMode::Auto,
+            dark_palette: Palette::Tekkadan,
+            light_palette: Palette::Barbatos,
+            layout: Some(LayoutPreset::Host),
+            show_queue_panel: true,
+            queue_panel_width: 200.0,
+            show_mic_meter: false,
+            show_pitch_guide: true,
+        }
+    }
+}
+
+/// Settings → Web Remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Run the web remote server
+    pub enabled: bool,
+    pub port: u16,
+    /// Default lifetime of a new guest link, in hours
+    pub guest_link_hours: f32,
+    /// Default number of song requests per guest link
+    pub guest_request_limit: u32,
+    /// Keep the queue sorted by guests' votes, in rounds of one song per
+    /// singer
+    pub sort_queue_by_votes: bool,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 8080,
+            guest_link_hours: 6.0,
+            guest_request_limit: 5,
+            sort_queue_by_votes: false,
+        }
+    }
+}
+
+/// Settings → Network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkConfig {
+    /// Never go online; online features only use cached answers
+    pub offline: bool,
+    /// Give up on a request after this many seconds
+    pub timeout_secs: u32,
+    /// Extra attempts after a connection failure or server error
+    pub retries: u32,
+    /// How long a "no lyrics found" answer is trusted before asking again
+    pub negative_cache_hours: f32,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            offline: false,
+            timeout_secs: 15,
+            retries: 2,
+            negative_cache_hours: 24.0,
+        }
+    }
+}
+
+/// Settings → Lyrics: where "Fetch lyrics" looks, in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsConfig {
+    /// Providers in the order they are asked, each enabled or not
+    pub providers: Vec<(LyricsProvider, bool)>,
+    /// Folder searched by the local folder provider
+    pub local_folder: Option<PathBuf>,
+}
+
+impl Default for LyricsConfig {
+    fn default() -> Self {
+        Self {
+            providers: LyricsProvider::ALL
+                .into_iter()
+                .map(|provider| (provider, true))
+                .collect(),
+            local_folder: None,
+        }
+    }
+}
+
+impl LyricsConfig {
+    /// The enabled providers in order.
+    pub fn chain(&self) -> Vec<LyricsProvider> {
+        self.providers
+            .iter()
+            .filter(|(_, enabled)| *enabled)
+            .map(|&(provider, _)| provider)
+            .collect()
+    }
+
+    /// Add providers missing from an older config, disabled, and drop
+    /// duplicates.
+    pub fn normalize(&mut self) {
+        let mut seen = Vec::new();
+        self.providers.retain(|&(provider, _)| {
+            let first = !seen.contains(&provider);
+            seen.push(provider);
+            first
+        });
+        for provider in LyricsProvider::ALL {
+            if !seen.contains(&provider) {
+                self.providers.push((provider, false));
+            }
+        }
+    }
+}
+
+/// Settings → Recording.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RecordingConfig {
+    /// Record every song started in the karaoke view
+    pub enabled: bool,
+    /// Also record the backing track on its own, so exports can be
+    /// remixed
+    pub separate_music: bool,
+    /// Also record the mix with the backing track
+    pub include_mix: bool,
+    pub format: RecordingFormat,
+    /// Where recordings are saved (`None` = `Recordings` in the data
+    /// directory)
+    pub folder: Option<PathBuf>,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            separate_music: true,
+            include_mix: false,
+            format: RecordingFormat::default(),
+            folder: None,
+        }
+    }
+}
+
+impl RecordingConfig {
+    pub fn folder(&self) -> PathBuf {
+        self.folder
+            .clone()
+            .unwrap_or_else(|| data_dir().join("Recordings"))
+    }
+}
+
+/// Settings → Announcer.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnnouncerConfig {
+    /// Speak the next singer and song between queued songs
+    pub enabled: bool,
+    /// Voice of the text-to-speech command (`None` = system default)
+    pub voice: Option<String>,
+}
+
+/// Messages scrolling along the bottom of the karaoke view, edited from
+/// the bottom panel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickerConfig {
+    pub messages: Vec<TickerMessage>,
+    /// Scrolling speed in points per second
+    pub speed: f32,
+}
+
+impl Default for TickerConfig {
+    fn default() -> Self {
+        Self {
+            messages: Vec::new(),
+            speed: 80.0,
+        }
+    }
+}
+
+impl TickerConfig {
+    /// Text of the messages showing at `now`, in order.
+    pub fn live(&self, now: SystemTime) -> Vec<&str> {
+        self.messages
+            .iter()
+            .filter(|message| message.is_live(now))
+            .map(|message| message.text.trim())
+            .collect()
+    }
+}
+
+/// Settings → Singer Screen.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SingerScreenConfig {
+    /// Open the window with the lyrics for the singers
+    pub enabled: bool,
+    /// Which display the window opens on
+    pub placement: ScreenPlacement,
+    /// Where the window opens with [`ScreenPlacement::Custom`], in points
+    /// from the top left of the main display
+    pub position: [f32; 2],
+    /// Fill the display the window is on
+    pub fullscreen: bool,
+}
+
+impl Default for SingerScreenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            placement: ScreenPlacement::RightOfMain,
+            position: [1920.0, 0.0],
+            fullscreen: true,
+        }
+    }
+}
+
+/// One ticker message, shown while enabled and within its schedule.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TickerMessage {
+    pub text: String,
+    pub enabled: bool,
+    /// Shown from this time on, in seconds since the Unix epoch
+    pub from: Option<u64>,
+    /// Hidden from this time on, in seconds since the Unix epoch
+    pub until: Option<u64>,
+}
+
+impl TickerMessage {
+    pub fn is_live(&self, now: SystemTime) -> bool {
+        let now = now
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        self.enabled
+            && !self.text.trim().is_empty()
+            && self.from.is_none_or(|from| from <= now)
+            && self.until.is_none_or(|until| now < until)
+    }
+}
+
+/// Settings → Downloads.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloadConfig {
+    /// Where downloaded songs are saved (`None` = `downloads` in the data
+    /// directory)
+    pub folder: Option<PathBuf>,
+    /// Keep the music video next to the audio, for video karaoke
+    pub keep_video: bool,
+    /// Post-processing steps run after each download, in pipeline order
+    pub clean_titles: bool,
+    pub analyze_loudness: bool,
+    pub fetch_lyrics: bool,
+    pub convert_subtitles: bool,
+    pub embed_art: bool,
+    pub import_to_library: bool,
+}
+
+impl Default for DownloadConfig {
+    fn default() -> Self {
+        Self {
+            folder: None,
+            keep_video: false,
+            clean_titles: true,
+            analyze_loudness: true,
+            fetch_lyrics: true,
+            convert_subtitles: true,
+            embed_art: false,
+            import_to_library: true,
+        }
+    }
+}
+
+impl DownloadConfig {
+    pub fn folder(&self) -> PathBuf {
+        self.folder
+            .clone()
+            .unwrap_or_else(|| data_dir().join("downloads"))
+    }
+}
+
+impl NetworkConfig {
+    pub fn negative_cache_ttl(&self) -> Duration {
+        Duration::from_secs_f32(self.negative_cache_hours.max(0.0) * 3600.0)
+    }
+}
+
+impl AppConfig {
+    /// Load the config file, falling back to defaults when it is missing or invalid.
+    pub fn load() -> Self {
+        let path = config_path();
+        let mut config: Self = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!("Invalid config file {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tracing::warn!("Failed to read config file {}: {e}", path.display());
+                Self::default()
+            },
+        };
+        config.lyrics.normalize();
+        config
+    }
+
+    /// Write the config file, creating the data directory if needed.
+    pub fn save(&self) -> Result<()> {
+        let path = config_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+/// Directory holding the config file, library data and caches: the folder
+/// chosen in Settings → Data, or [`default_data_dir`].
+pub fn data_dir() -> PathBuf {
+    let mut dir = DATA_DIR.lock().unwrap_or_else(PoisonError::into_inner);
+    dir.get_or_insert_with(|| {
+        let default = default_data_dir();
+        match fs::read_to_string(default.join(LOCATION_FILE_NAME)) {
+            Ok(location) if !location.trim().is_empty() => PathBuf::from(location.trim()),
+            _ => default,
+        }
+    })
+    .clone()
+}
+
+/// Switch the data directory to `dir`, remembering it in the location file
+/// for the next start. Nothing is moved; see [`crate::migration`].
+pub fn set_data_dir(dir: &Path) -> Result<()> {
+    let default = default_data_dir();
+    let location = default.join(LOCATION_FILE_NAME);
+    if dir == default {
+        match fs::remove_file(&location) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to delete {}", location.display()));
+            },
+            _ => {},
+        }
+    } else {
+        let text = dir
+            .to_str()
+            .with_context(|| format!("{} is not a valid folder name", dir.display()))?;
+        fs::create_dir_all(&default)
+            .with_context(|| format!("Failed to create {}", default.display()))?;
+        fs::write(&location, text)
+            .with_context(|| format!("Failed to write {}", location.display()))?;
+    }
+    *DATA_DIR.lock().unwrap_or_else(PoisonError::into_inner) = Some(dir.to_path_buf());
+    Ok(())
+}
+
+/// Where the data lives unless it was moved.
+///
+/// Uses `%APPDATA%` on Windows, `~/Library/Application Support` on macOS and
+/// `$XDG_DATA_HOME` (or `~/.local/share`) elsewhere.
+pub fn default_data_dir() -> PathBuf {
+    let base = if cfg!(target_os = "windows") {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join("Library/Application Support"))
+    } else {
+        std::env::var_os("XDG_DATA_HOME")
+            .map(PathBuf::from)
+            .or_else(|| {
+                std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share"))
+            })
+    };
+
+    base.unwrap_or_else(|| PathBuf::from("."))
+        .join(APP_DIR_NAME)
+}
+
+fn config_path() -> PathBuf {
+    data_dir().join(CONFIG_FILE_NAME)
+}
@@ -0,0 +1,138 @@
+//! Cover art of a song, blurred for a background behind the lyrics.
+//!
+//! The picture embedded in the song is used, or else an image next to it
+//! (`song.jpg`, `cover.jpg`, `folder.jpg`, ...). `ffmpeg` decodes it straight
+//! to a tiny image, which is blurred further here; stretched over the view
+//! with linear filtering it shows only the colours of the cover.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Side of the decoded image in pixels.
+const SIZE: usize = 48;
+/// Box blur radius in pixels, applied in both directions.
+const BLUR_RADIUS: usize = 3;
+/// Image files looked for next to a song, after `song.<extension>`.
+const FOLDER_IMAGES: [&str; 4] = ["cover.jpg", "cover.png", "folder.jpg", "folder.png"];
+const IMAGE_EXTENSIONS: [&str; 3] = ["jpg", "jpeg", "png"];
+
+/// The blurred cover of one song, decoded on a background thread.
+pub struct CoverArt {
+    song: PathBuf,
+    receiver: Option<Receiver<Option<egui::ColorImage>>>,
+    texture: Option<egui::TextureHandle>,
+}
+
+impl CoverArt {
+    /// Start decoding the cover of `song`.
+    pub fn load(song: &Path) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let path = song.to_path_buf();
+        thread::spawn(move || {
+            let image = sources(&path).iter().find_map(|source| decode(source));
+            if image.is_none() {
+                tracing::debug!("No cover art for {}", path.display());
+            }
+            // The song may have changed since; nobody is waiting then
+            if sender.send(image).is_err() {
+                tracing::debug!("Cover art of {} no longer needed", path.display());
+            }
+        });
+        Self {
+            song: song.to_path_buf(),
+            receiver: Some(receiver),
+            texture: None,
+        }
+    }
+
+    pub fn song(&self) -> &Path {
+        &self.song
+    }
+
+    /// The blurred cover, once decoded; `None` while decoding and when the
+    /// song has no cover.
+    pub fn texture(&mut self, ctx: &egui::Context) -> Option<&egui::TextureHandle> {
+        if let Some(receiver) = &self.receiver {
+            match receiver.try_recv() {
+                Ok(image) => {
+                    self.texture = image.map(|image| {
+                        ctx.load_texture("cover_art", image, egui::TextureOptions::LINEAR)
+                    });
+                    self.receiver = None;
+                },
+                Err(TryRecvError::Empty) => {},
+                Err(TryRecvError::Disconnected) => self.receiver = None,
+            }
+        }
+        self.texture.as_ref()
+    }
+}
+
+/// Where the cover of `song` may be, in order of preference.
+fn sources(song: &Path) -> Vec<PathBuf> {
+    let mut sources = vec![song.to_path_buf()];
+    sources.extend(
+        IMAGE_EXTENSIONS
+            .iter()
+            .map(|extension| song.with_extension(extension)),
+    );
+    if let Some(folder) = song.parent() {
+        sources.extend(FOLDER_IMAGES.iter().map(|name| folder.join(name)));
+    }
+    sources.retain(|source| source.exists());
+    sources
+}
+
+/// The first picture in `source`, blurred, or `None` when it has none or
+/// `ffmpeg` cannot run.
+fn decode(source: &Path) -> Option<egui::ColorImage> {
+    let mut child = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(source)
+        .args(["-map", "0:v:0", "-frames:v", "1", "-vf"])
+        .arg(format!("scale={SIZE}:{SIZE}"))
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| tracing::debug!("Cannot run ffmpeg: {e}"))
+        .ok()?;
+    let mut pixels = vec![0; SIZE * SIZE * 4];
+    let read = child
+        .stdout
+        .take()
+        .is_some_and(|mut stdout| stdout.read_exact(&mut pixels).is_ok());
+    if let Err(e) = child.wait() {
+        tracing::debug!("ffmpeg for {} did not exit: {e}", source.display());
+    }
+    if !read {
+        return None;
+    }
+    blur(&mut pixels);
+    Some(egui::ColorImage::from_rgba_unmultiplied(
+        [SIZE, SIZE],
+        &pixels,
+    ))
+}
+
+/// Box blur the `SIZE` × `SIZE` RGBA `pixels` along rows, then columns.
+fn blur(pixels: &mut [u8]) {
+    for (step, stride) in [(4, SIZE * 4), (SIZE * 4, 4)] {
+        let source = pixels.to_vec();
+        for line in 0..SIZE {
+            for index in 0..SIZE {
+                let from = index.saturating_sub(BLUR_RADIUS);
+                let to = (index + BLUR_RADIUS).min(SIZE - 1);
+                for channel in 0..4 {
+                    let at = |i: usize| usize::from(source[line * stride + i * step + channel]);
+                    let sum: usize = (from..=to).map(at).sum();
+                    pixels[line * stride + index * step + channel] = (sum / (to - from + 1)) as u8;
+                }
+            }
+        }
+    }
+}
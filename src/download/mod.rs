@@ -0,0 +1,238 @@
+//! Downloading songs from video sites with the system `yt-dlp`.
+//!
+//! Each download runs on its own thread: `yt-dlp` extracts the audio into
+//! the download folder, then the post-processing [`pipeline`] runs the
+//! steps enabled in the settings. Progress is shared with the UI through a
+//! [`DownloadState`] snapshot.
+
+pub mod pipeline;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use self::pipeline::{PipelineStep, StepStatus};
+use crate::config::{DownloadConfig, LyricsConfig, NetworkConfig};
+use crate::lyrics::LyricsProvider;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stage {
+    Downloading,
+    Processing,
+    Finished,
+    Failed(String),
+}
+
+/// What the UI shows of a download.
+#[derive(Debug, Clone)]
+pub struct DownloadState {
+    pub stage: Stage,
+    /// The downloaded audio, once saved (renamed by title cleanup)
+    pub file: Option<PathBuf>,
+    pub steps: Vec<(PipelineStep, StepStatus)>,
+    /// Suggested per-song volume offset from the loudness analysis
+    pub volume_offset_db: Option<f32>,
+    /// Provider of the lyrics found for the song
+    pub lyrics_provider: Option<LyricsProvider>,
+}
+
+impl DownloadState {
+    /// Update the status of `step`.
+    pub fn set_step(&mut self, step: PipelineStep, status: StepStatus) {
+        if let Some((_, current)) = self.steps.iter_mut().find(|(s, _)| *s == step) {
+            *current = status;
+        }
+    }
+}
+
+/// One download and its post-processing.
+pub struct DownloadJob {
+    url: String,
+    state: Arc<Mutex<DownloadState>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DownloadJob {
+    pub fn start(
+        url: &str,
+        config: &DownloadConfig,
+        lyrics: &LyricsConfig,
+        network: &NetworkConfig,
+    ) -> Self {
+        let state = Arc::new(Mutex::new(DownloadState {
+            stage: Stage::Downloading,
+            file: None,
+            steps: PipelineStep::ALL
+                .into_iter()
+                .map(|step| {
+                    let status = if step.enabled(config) {
+                        StepStatus::Pending
+                    } else {
+                        StepStatus::Skipped
+                    };
+                    (step, status)
+                })
+                .collect(),
+            volume_offset_db: None,
+            lyrics_provider: None,
+        }));
+
+        let handle = {
+            let (url, config, lyrics, network, state) = (
+                url.to_string(),
+                config.clone(),
+                lyrics.clone(),
+                network.clone(),
+                state.clone(),
+            );
+            thread::spawn(move || {
+                let result = download(&url, &config, &network).map(|(file, video)| {
+                    {
+                        let mut state = lock(&state);
+                        state.stage = Stage::Processing;
+                        state.file = Some(file.clone());
+                    }
+                    pipeline::run(file, video, &config, &lyrics, &network, &state);
+                });
+                lock(&state).stage = match result {
+                    Ok(()) => Stage::Finished,
+                    Err(e) => {
+                        tracing::warn!("Download of {url} failed: {e}");
+                        Stage::Failed(e)
+                    },
+                };
+            })
+        };
+        Self {
+            url: url.to_string(),
+            state,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn state(&self) -> DownloadState {
+        lock(&self.state).clone()
+    }
+
+    pub fn set_step(&self, step: PipelineStep, status: StepStatus) {
+        lock(&self.state).set_step(step, status);
+    }
+
+    /// The final state once the thread has ended, returned only once;
+    /// `None` while running and afterwards.
+    pub fn try_finish(&mut self) -> Option<DownloadState> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        if handle.join().is_err() {
+            lock(&self.state).stage = Stage::Failed("Download crashed".to_string());
+        }
+        Some(self.state())
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.handle.is_some()
+    }
+}
+
+/// Run `yt-dlp` and return the path of the audio and, when kept, of the
+/// video it was extracted from.
+fn download(
+    url: &str,
+    config: &DownloadConfig,
+    network: &NetworkConfig,
+) -> Result<(PathBuf, Option<PathBuf>), String> {
+    if network.offline {
+        return Err("Offline mode is on".to_string());
+    }
+    let folder = config.folder();
+    fs::create_dir_all(&folder).map_err(|e| format!("Cannot create {}: {e}", folder.display()))?;
+
+    let mut command = Command::new("yt-dlp");
+    command
+        .args(["--no-playlist", "--no-simulate", "--no-progress"])
+        .args(["--socket-timeout", &network.timeout_secs.to_string()])
+        .args(["--retries", &network.retries.to_string()])
+        .arg("--paths")
+        .arg(&folder)
+        .args(["--output", "%(title)s.%(ext)s"])
+        // Only the final file path on stdout
+        .args(["--print", "after_move:filepath"]);
+    if config.keep_video {
+        command
+            .args([
+                "--format",
+                "bv*[height<=1080]+ba/b",
+                "--merge-output-format",
+                "mp4",
+            ])
+            .args(["--embed-subs", "--sub-langs", "en.*,en,-live_chat"]);
+    } else {
+        command.args(["--extract-audio", "--audio-format", "mp3"]);
+        if config.convert_subtitles {
+            // Saved next to the audio for the pipeline to turn into lyrics
+            command
+                .args(["--write-subs", "--sub-format", "vtt/srt/best"])
+                .args(["--sub-langs", "en.*,en,-live_chat"]);
+        }
+    }
+    if config.embed_art {
+        command.args(["--write-thumbnail", "--convert-thumbnails", "jpg"]);
+    }
+    let output = command
+        .arg(url)
+        .output()
+        .map_err(|e| format!("Cannot run yt-dlp: {e}"))?;
+    if !output.status.success() {
+        return Err(last_line(&output.stderr).unwrap_or_else(|| "yt-dlp failed".to_string()));
+    }
+    let file = last_line(&output.stdout)
+        .map(PathBuf::from)
+        .ok_or_else(|| "yt-dlp did not report the downloaded file".to_string())?;
+
+    if !config.keep_video {
+        return Ok((file, None));
+    }
+    let audio = extract_audio(&file)?;
+    Ok((audio, Some(file)))
+}
+
+/// Save the audio track of `video` as an MP3 next to it.
+fn extract_audio(video: &Path) -> Result<PathBuf, String> {
+    let audio = video.with_extension("mp3");
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-v", "error", "-i"])
+        .arg(video)
+        .args(["-vn", "-q:a", "2"])
+        .arg(&audio)
+        .output()
+        .map_err(|e| format!("Cannot run ffmpeg: {e}"))?;
+    if !output.status.success() {
+        return Err(
+            last_line(&output.stderr).unwrap_or_else(|| "Audio extraction failed".to_string())
+        );
+    }
+    Ok(audio)
+}
+
+/// Last non-empty line of a process output.
+fn last_line(output: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(output)
+        .lines()
+        .rfind(|line| !line.trim().is_empty())
+        .map(|line| line.trim().to_string())
+}
+
+/// Lock ignoring poisoning: the state stays readable after a panic.
+fn lock(state: &Mutex<DownloadState>) -> MutexGuard<'_, DownloadState> {
+    state
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
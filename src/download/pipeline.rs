@@ -0,0 +1,383 @@
+//! Post-processing run on every finished download.
+//!
+//! Steps run in [`PipelineStep::ALL`] order and each can be turned off in
+//! the settings. A failing step is reported and the pipeline carries on.
+//! Library import touches app state, so the UI thread performs it once the
+//! rest of the pipeline is done.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+
+use super::{lock, DownloadState};
+use crate::audio::loudness;
+use crate::config::{DownloadConfig, LyricsConfig, NetworkConfig};
+use crate::lrc::{self, subtitles};
+use crate::lyrics::{self, cache::LyricsCache};
+use crate::net::{HttpClient, NetError};
+
+/// Bracketed parts of video titles containing any of these are dropped.
+const TITLE_NOISE: &[&str] = &[
+    "official",
+    "video",
+    "audio",
+    "lyric",
+    "lyrics",
+    "visualizer",
+    "hd",
+    "4k",
+    "mv",
+    "live",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PipelineStep {
+    TitleCleanup,
+    Loudness,
+    LyricsFetch,
+    Subtitles,
+    ArtEmbed,
+    LibraryImport,
+}
+
+impl PipelineStep {
+    pub const ALL: [Self; 6] = [
+        Self::TitleCleanup,
+        Self::Loudness,
+        Self::LyricsFetch,
+        Self::Subtitles,
+        Self::ArtEmbed,
+        Self::LibraryImport,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TitleCleanup => "Clean up title",
+            Self::Loudness => "Analyse loudness",
+            Self::LyricsFetch => "Fetch lyrics",
+            Self::Subtitles => "Lyrics from subtitles",
+            Self::ArtEmbed => "Embed cover art",
+            Self::LibraryImport => "Add to library",
+        }
+    }
+
+    pub fn enabled(self, config: &DownloadConfig) -> bool {
+        match self {
+            Self::TitleCleanup => config.clean_titles,
+            Self::Loudness => config.analyze_loudness,
+            Self::LyricsFetch => config.fetch_lyrics,
+            Self::Subtitles => config.convert_subtitles,
+            Self::ArtEmbed => config.embed_art,
+            Self::LibraryImport => config.import_to_library,
+        }
+    }
+
+    pub fn enabled_mut(self, config: &mut DownloadConfig) -> &mut bool {
+        match self {
+            Self::TitleCleanup => &mut config.clean_titles,
+            Self::Loudness => &mut config.analyze_loudness,
+            Self::LyricsFetch => &mut config.fetch_lyrics,
+            Self::Subtitles => &mut config.convert_subtitles,
+            Self::ArtEmbed => &mut config.embed_art,
+            Self::LibraryImport => &mut config.import_to_library,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepStatus {
+    Pending,
+    Running,
+    /// Finished, with a short summary
+    Done(String),
+    Skipped,
+    Failed(String),
+}
+
+/// Run every enabled step but library import on the downloaded `file` and,
+/// when kept, the `video` it came from.
+pub(super) fn run(
+    mut file: PathBuf,
+    mut video: Option<PathBuf>,
+    config: &DownloadConfig,
+    lyrics: &LyricsConfig,
+    network: &NetworkConfig,
+    state: &Mutex<DownloadState>,
+) {
+    // yt-dlp names the thumbnail after the audio, before any renaming
+    let thumbnail = file.with_extension("jpg");
+    let subtitle_files = subtitles::sidecar_files(&file);
+
+    for step in PipelineStep::ALL {
+        if !step.enabled(config) || step == PipelineStep::LibraryImport {
+            continue;
+        }
+        lock(state).set_step(step, StepStatus::Running);
+        let status = match step {
+            PipelineStep::TitleCleanup => clean_up_title(&mut file, video.as_mut()),
+            PipelineStep::Loudness => match loudness::measure(&file) {
+                Ok(Some(level)) => {
+                    let offset = loudness::volume_offset(level);
+                    lock(state).volume_offset_db = Some(offset);
+                    StepStatus::Done(format!("{level:.1} dBFS, offset {offset:+.1} dB"))
+                },
+                Ok(None) => StepStatus::Done("Silent".to_string()),
+                Err(e) => StepStatus::Failed(e.to_string()),
+            },
+            PipelineStep::LyricsFetch => fetch_lyrics(&file, lyrics, network, state),
+            PipelineStep::Subtitles => {
+                convert_subtitles(&file, video.as_deref().unwrap_or(&file), &subtitle_files)
+            },
+            PipelineStep::ArtEmbed => embed_art(&file, &thumbnail),
+            PipelineStep::LibraryImport => continue,
+        };
+        let mut state = lock(state);
+        state.file = Some(file.clone());
+        state.set_step(step, status);
+    }
+}
+
+fn clean_up_title(file: &mut PathBuf, video: Option<&mut PathBuf>) -> StepStatus {
+    let Some(stem) = file.file_stem().and_then(|stem| stem.to_str()) else {
+        return StepStatus::Skipped;
+    };
+    let title = clean_title(stem);
+    if title.is_empty() || title == stem {
+        return StepStatus::Done("Already clean".to_string());
+    }
+
+    // The video keeps the audio's name, so it stays paired with the song
+    let mut files = vec![file];
+    files.extend(video);
+    let renamed: Vec<PathBuf> = files
+        .iter()
+        .map(|path| {
+            let mut renamed = path.with_file_name(&title);
+            if let Some(extension) = path.extension() {
+                renamed.set_extension(extension);
+            }
+            renamed
+        })
+        .collect();
+    if let Some(existing) = renamed.iter().find(|path| path.exists()) {
+        return StepStatus::Failed(format!("{} already exists", existing.display()));
+    }
+    for (path, renamed) in files.into_iter().zip(renamed) {
+        if let Err(e) = fs::rename(&*path, &renamed) {
+            return StepStatus::Failed(e.to_string());
+        }
+        *path = renamed;
+    }
+    StepStatus::Done(title)
+}
+
+/// `Artist - Song (Official Video) [HD]` → `Artist - Song`.
+fn clean_title(title: &str) -> String {
+    let mut cleaned = String::with_capacity(title.len());
+    let mut rest = title;
+    while let Some(open) = rest.find(['(', '[']) {
+        let close = if rest[open..].starts_with('(') {
+            ')'
+        } else {
+            ']'
+        };
+        let Some(length) = rest[open..].find(close) else {
+            break;
+        };
+        let inner = rest[open + 1..open + length].to_lowercase();
+        let noise = inner
+            .split(|c: char| !c.is_alphanumeric())
+            .any(|word| TITLE_NOISE.contains(&word));
+        cleaned.push_str(&rest[..open]);
+        if !noise {
+            cleaned.push_str(&rest[open..=open + length]);
+        }
+        rest = &rest[open + length + 1..];
+    }
+    cleaned.push_str(rest);
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn fetch_lyrics(
+    file: &Path,
+    config: &LyricsConfig,
+    network: &NetworkConfig,
+    state: &Mutex<DownloadState>,
+) -> StepStatus {
+    if lrc::lrc_path(file).exists() {
+        return StepStatus::Done("Already has lyrics".to_string());
+    }
+    let client = HttpClient::new(network);
+    let mut cache = LyricsCache::load();
+    let result = lyrics::look_up(
+        file,
+        config,
+        &client,
+        &mut cache,
+        network.negative_cache_ttl(),
+    );
+    if let Err(e) = cache.save() {
+        tracing::error!("{e:#}");
+    }
+    match result {
+        Ok(Some(found)) => match lyrics::save(file, &found.lyrics) {
+            Ok(()) => {
+                lock(state).lyrics_provider = Some(found.provider);
+                StepStatus::Done(format!("Synced lyrics from {}", found.provider.label()))
+            },
+            Err(e) => StepStatus::Failed(e.to_string()),
+        },
+        Ok(None) => StepStatus::Done("No synced lyrics found".to_string()),
+        Err(NetError::Offline) => StepStatus::Skipped,
+        Err(e) => StepStatus::Failed(e.to_string()),
+    }
+}
+
+/// Turn the subtitles downloaded with `song`, preferring English, into its
+/// `.lrc` file when it has no lyrics yet: the subtitle `files` saved next to
+/// it, else the first subtitle stream of the downloaded `media`. The files
+/// are removed once the song has lyrics, which then take their place; when
+/// nothing could be written they are kept for a retry or a manual fix.
+fn convert_subtitles(song: &Path, media: &Path, files: &[(PathBuf, String)]) -> StepStatus {
+    let lrc_path = lrc::lrc_path(song);
+    let status = if lrc_path.exists() {
+        StepStatus::Done("Already has lyrics".to_string())
+    } else if let Some((file, language)) = files
+        .iter()
+        .find(|(_, language)| language.starts_with("en"))
+        .or_else(|| files.first())
+    {
+        match subtitles::read_subtitle_file(file) {
+            Ok(cues) => write_subtitle_lyrics(&lrc_path, &cues, language),
+            Err(e) => StepStatus::Failed(e.to_string()),
+        }
+    } else {
+        convert_subtitle_stream(&lrc_path, media)
+    };
+    if matches!(status, StepStatus::Failed(_)) || !lrc_path.exists() {
+        return status;
+    }
+    for (file, _) in files {
+        if let Err(e) = fs::remove_file(file) {
+            tracing::warn!("Failed to delete {}: {e}", file.display());
+        }
+    }
+    status
+}
+
+/// Write the first subtitle stream of `media`, preferring English, to
+/// `lrc_path`.
+fn convert_subtitle_stream(lrc_path: &Path, media: &Path) -> StepStatus {
+    // One `index,language` line per subtitle stream
+    let probe = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "s"])
+        .args(["-show_entries", "stream=index:stream_tags=language"])
+        .args(["-of", "csv=p=0"])
+        .arg(media)
+        .output();
+    let streams = match probe {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (index, language) = line.split_once(',').unwrap_or((line, ""));
+                Some((
+                    index.trim().parse::<u32>().ok()?,
+                    language.trim().to_string(),
+                ))
+            })
+            .collect::<Vec<_>>(),
+        Ok(output) => {
+            return StepStatus::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        },
+        Err(e) => return StepStatus::Failed(format!("Cannot run ffprobe: {e}")),
+    };
+    let Some((index, language)) = streams
+        .iter()
+        .find(|(_, language)| language.starts_with("en"))
+        .or_else(|| streams.first())
+    else {
+        return StepStatus::Done("No subtitle streams".to_string());
+    };
+
+    let extracted = Command::new("ffmpeg")
+        .args(["-v", "error", "-i"])
+        .arg(media)
+        .args(["-map", &format!("0:{index}"), "-f", "webvtt", "-"])
+        .output();
+    let vtt = match extracted {
+        Ok(output) if output.status.success() => output.stdout,
+        Ok(output) => {
+            return StepStatus::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        },
+        Err(e) => return StepStatus::Failed(format!("Cannot run ffmpeg: {e}")),
+    };
+
+    let cues = subtitles::parse_subtitles(&String::from_utf8_lossy(&vtt));
+    write_subtitle_lyrics(lrc_path, &cues, language)
+}
+
+/// Save `cues` in `language` as the LRC file at `lrc_path`.
+fn write_subtitle_lyrics(
+    lrc_path: &Path,
+    cues: &[subtitles::SubtitleCue],
+    language: &str,
+) -> StepStatus {
+    if cues.is_empty() {
+        return StepStatus::Done("Subtitles are empty".to_string());
+    }
+    match fs::write(lrc_path, subtitles::cues_to_lrc(cues)) {
+        Ok(()) if language.is_empty() => {
+            StepStatus::Done(format!("{} lines from subtitles", cues.len()))
+        },
+        Ok(()) => StepStatus::Done(format!("{} lines from {language} subtitles", cues.len())),
+        Err(e) => StepStatus::Failed(e.to_string()),
+    }
+}
+
+/// Embed the thumbnail yt-dlp saved as the MP3's cover with `ffmpeg`, which
+/// yt-dlp needs for audio extraction anyway.
+fn embed_art(file: &Path, thumbnail: &Path) -> StepStatus {
+    if !thumbnail.exists() {
+        return StepStatus::Failed("No thumbnail was downloaded".to_string());
+    }
+    let temporary = file.with_extension("art.mp3");
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(file)
+        .arg("-i")
+        .arg(thumbnail)
+        .args([
+            "-map",
+            "0:a",
+            "-map",
+            "1",
+            "-c",
+            "copy",
+            "-id3v2_version",
+            "3",
+        ])
+        .args(["-disposition:v", "attached_pic"])
+        .arg(&temporary)
+        .output();
+
+    let status = match output {
+        Ok(output) if output.status.success() => match fs::rename(&temporary, file) {
+            Ok(()) => StepStatus::Done("Cover embedded".to_string()),
+            Err(e) => StepStatus::Failed(e.to_string()),
+        },
+        Ok(output) => {
+            StepStatus::Failed(String::from_utf8_lossy(&output.stderr).trim().to_string())
+        },
+        Err(e) => StepStatus::Failed(format!("Cannot run ffmpeg: {e}")),
+    };
+    if temporary.exists() {
+        if let Err(e) = fs::remove_file(&temporary) {
+            tracing::warn!("Failed to delete {}: {e}", temporary.display());
+        }
+    }
+    if let Err(e) = fs::remove_file(thumbnail) {
+        tracing::warn!("Failed to delete {}: {e}", thumbnail.display());
+    }
+    status
+}
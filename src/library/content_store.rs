@@ -0,0 +1,264 @@
+//! Songs imported into the data directory, stored once per content.
+//!
+//! Each imported file is kept under `store/objects`, named by a hash of its
+//! contents, and shown under `store/songs` by a readable name hard linked
+//! to the object (symlinked where hard links are not possible). Importing
+//! the same file again, from anywhere, finds the object already stored and
+//! takes no more space; renaming or moving a song only touches its link.
+//!
+//! The hash is FNV-1a, which unlike `DefaultHasher` is stable across
+//! builds; objects with the same hash are compared byte for byte before
+//! being treated as one.
+
+use std::fs::{self, File};
+use std::io::{BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{Context, Result};
+
+use crate::config;
+use crate::lrc;
+
+const STORE_DIR_NAME: &str = "store";
+const CHUNK_SIZE: usize = 1 << 16;
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// Folder of the readable song names, which is where imported songs live
+/// as far as the library is concerned.
+pub fn songs_dir() -> PathBuf {
+    config::data_dir().join(STORE_DIR_NAME).join("songs")
+}
+
+fn objects_dir() -> PathBuf {
+    config::data_dir().join(STORE_DIR_NAME).join("objects")
+}
+
+/// A file imported into the store.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    /// Name of the song in the store
+    pub path: PathBuf,
+    /// Content id, the object's file stem
+    pub content: String,
+    /// The contents were stored already and took no new space
+    pub deduplicated: bool,
+}
+
+/// Store `source` and give it a name, reusing `existing` when the content
+/// already has one there. The `.lrc` next to `source` is copied along.
+fn import(source: &Path, existing: impl Fn(&str) -> Option<PathBuf>) -> Result<Imported> {
+    let (hash, len) = hash_file(source)?;
+    let extension = source
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let objects = objects_dir();
+    fs::create_dir_all(&objects)
+        .with_context(|| format!("Failed to create {}", objects.display()))?;
+
+    // Numbered on the rare hash collision between different contents
+    let mut content = format!("{hash:016x}-{len:x}");
+    let mut number = 1;
+    let (object, stored) = loop {
+        let object = objects.join(format!("{content}.{extension}"));
+        if !object.exists() {
+            break (object, false);
+        }
+        if same_contents(source, &object)? {
+            break (object, true);
+        }
+        content = format!("{hash:016x}-{len:x}-{number}");
+        number += 1;
+    };
+    if !stored {
+        // Copy then rename, so an interrupted import leaves no partial object
+        let partial = object.with_extension("partial");
+        fs::copy(source, &partial)
+            .with_context(|| format!("Failed to copy {}", source.display()))?;
+        fs::rename(&partial, &object)
+            .with_context(|| format!("Failed to store {}", source.display()))?;
+    }
+
+    let path = match existing(&content).filter(|path| path.exists()) {
+        Some(path) => path,
+        None => {
+            let name = source.file_name().unwrap_or_default();
+            let path = free_name(&songs_dir().join(name));
+            link(&object, &path)?;
+            path
+        },
+    };
+    let lyrics = lrc::lrc_path(source);
+    let target = lrc::lrc_path(&path);
+    if lyrics.exists() && !target.exists() {
+        fs::copy(&lyrics, &target)
+            .with_context(|| format!("Failed to copy {}", lyrics.display()))?;
+    }
+    Ok(Imported {
+        path,
+        content,
+        deduplicated: stored,
+    })
+}
+
+/// Hash and length of the contents of `path`.
+fn hash_file(path: &Path) -> Result<(u64, u64)> {
+    let mut file = File::open(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    let mut buffer = vec![0; CHUNK_SIZE];
+    let (mut hash, mut len) = (FNV_OFFSET, 0_u64);
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .with_context(|| format!("Cannot read {}", path.display()))?;
+        if read == 0 {
+            return Ok((hash, len));
+        }
+        for &byte in &buffer[..read] {
+            hash = (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+        len += read as u64;
+    }
+}
+
+fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let open = |path: &Path| {
+        File::open(path)
+            .map(BufReader::new)
+            .with_context(|| format!("Cannot read {}", path.display()))
+    };
+    let (mut a, mut b) = (open(a)?.bytes(), open(b)?.bytes());
+    loop {
+        match (a.next().transpose()?, b.next().transpose()?) {
+            (None, None) => return Ok(true),
+            (Some(x), Some(y)) if x == y => {},
+            _ => return Ok(false),
+        }
+    }
+}
+
+/// `path`, or `path` numbered as `name (2).ext` when it is taken.
+fn free_name(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    let mut candidate = path.to_path_buf();
+    let mut number = 2;
+    while candidate.exists() || candidate.is_symlink() {
+        candidate = path.with_file_name(format!("{stem} ({number}){extension}"));
+        number += 1;
+    }
+    candidate
+}
+
+/// Give `object` the name `path`: a hard link, else a symlink relative to
+/// the store so it survives the data directory moving.
+fn link(object: &Path, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let Err(hard) = fs::hard_link(object, path) else {
+        return Ok(());
+    };
+    let relative = Path::new("..")
+        .join("objects")
+        .join(object.file_name().unwrap_or_default());
+    symlink(&relative, path).with_context(|| {
+        format!(
+            "Failed to link {} to {} ({hard})",
+            path.display(),
+            object.display()
+        )
+    })
+}
+
+#[cfg(unix)]
+fn symlink(object: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(object, path)
+}
+
+#[cfg(windows)]
+fn symlink(object: &Path, path: &Path) -> std::io::Result<()> {
+    std::os::windows::fs::symlink_file(object, path)
+}
+
+/// Imports of picked files running on a background thread.
+pub struct ImportJob {
+    total: usize,
+    done: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Vec<Result<Imported>>>>,
+}
+
+impl ImportJob {
+    /// Import `files`; `known` maps the content ids already in the library
+    /// to their song, so a file imported before keeps its one entry.
+    pub fn start(files: Vec<PathBuf>, known: Vec<(String, PathBuf)>) -> Self {
+        let total = files.len();
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (done, cancel) = (done.clone(), cancel.clone());
+            thread::spawn(move || {
+                let mut known = known;
+                let mut results = Vec::new();
+                for file in files {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let existing = |content: &str| {
+                        known
+                            .iter()
+                            .find(|(known, _)| known == content)
+                            .map(|(_, path)| path.clone())
+                    };
+                    let result = import(&file, existing);
+                    if let Ok(imported) = &result {
+                        known.push((imported.content.clone(), imported.path.clone()));
+                    }
+                    results.push(result);
+                    done.fetch_add(1, Ordering::Relaxed);
+                }
+                results
+            })
+        };
+        Self {
+            total,
+            done,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    /// Files imported so far and files to import.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done.load(Ordering::Relaxed), self.total)
+    }
+
+    /// Stop after the file being imported.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The import of each file once all have run; `None` while they do.
+    pub fn try_finish(&mut self) -> Option<Result<Vec<Result<Imported>>>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(
+            handle
+                .join()
+                .map_err(|_| anyhow::anyhow!("Importing the songs crashed")),
+        )
+    }
+}
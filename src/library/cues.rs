@@ -0,0 +1,34 @@
+//! Named cue points ("Verse 2", "Skip intro", …), stored per song.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CuePoint {
+    pub name: String,
+    /// Position in seconds
+    pub position: f32,
+}
+
+impl CuePoint {
+    pub fn position(&self) -> Duration {
+        Duration::from_secs_f32(self.position.max(0.0))
+    }
+}
+
+/// Add a cue at `position`, moving an existing cue of the same name (case
+/// insensitive). Keeps the list sorted by position.
+pub fn insert_cue(cues: &mut Vec<CuePoint>, name: &str, position: Duration) {
+    let name = name.trim();
+    cues.retain(|cue| !cue.name.eq_ignore_ascii_case(name));
+    let position = position.as_secs_f32();
+    let index = cues.partition_point(|cue| cue.position < position);
+    cues.insert(
+        index,
+        CuePoint {
+            name: name.to_string(),
+            position,
+        },
+    );
+}
@@ -0,0 +1,190 @@
+//! Copying songs with their lyrics to a folder or USB drive, e.g. for a car
+//! stereo or another karaoke machine.
+//!
+//! Each song is written next to its `.lrc` under the same file stem, which
+//! is what players reading sidecar lyrics expect. Songs can be converted to
+//! MP3 with `ffmpeg` for devices that play nothing else.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{bail, Context, Result};
+
+use crate::lrc;
+
+/// Format of the copied audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeviceFormat {
+    /// The files as they are
+    #[default]
+    Original,
+    /// Converted with `ffmpeg`
+    Mp3,
+}
+
+impl DeviceFormat {
+    pub const ALL: [Self; 2] = [Self::Original, Self::Mp3];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Original => "Keep the original format",
+            Self::Mp3 => "Convert to MP3",
+        }
+    }
+}
+
+/// What an export did.
+#[derive(Debug, Default)]
+pub struct ExportSummary {
+    /// Songs copied, and how many of them had lyrics
+    pub songs: usize,
+    pub lyrics: usize,
+    /// Songs that could not be copied, with why
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// An export running on a background thread.
+pub struct DeviceExport {
+    folder: PathBuf,
+    total: usize,
+    done: Arc<AtomicUsize>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<ExportSummary>>>,
+}
+
+impl DeviceExport {
+    pub fn start(songs: Vec<PathBuf>, folder: PathBuf, format: DeviceFormat) -> Self {
+        let total = songs.len();
+        let done = Arc::new(AtomicUsize::new(0));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let folder = folder.clone();
+            let (done, cancel) = (done.clone(), cancel.clone());
+            thread::spawn(move || export(&songs, &folder, format, &done, &cancel))
+        };
+        Self {
+            folder,
+            total,
+            done,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Songs copied so far and songs to copy.
+    pub fn progress(&self) -> (usize, usize) {
+        (self.done.load(Ordering::Relaxed), self.total)
+    }
+
+    /// Stop after the song being copied; the songs copied are kept.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// What was copied once the export has ended; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<Result<ExportSummary>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(
+            handle
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Exporting the songs crashed"))),
+        )
+    }
+}
+
+fn export(
+    songs: &[PathBuf],
+    folder: &Path,
+    format: DeviceFormat,
+    done: &AtomicUsize,
+    cancel: &AtomicBool,
+) -> Result<ExportSummary> {
+    fs::create_dir_all(folder).with_context(|| format!("Failed to create {}", folder.display()))?;
+    let mut summary = ExportSummary::default();
+    let mut stems = HashSet::new();
+    for song in songs {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Exporting the songs was cancelled");
+        }
+        let stem = unique_stem(song, &mut stems);
+        match export_song(song, folder, &stem, format) {
+            Ok(lyrics) => {
+                summary.songs += 1;
+                summary.lyrics += usize::from(lyrics);
+            },
+            Err(e) => {
+                tracing::warn!("{e:#}");
+                summary.failed.push((song.clone(), format!("{e:#}")));
+            },
+        }
+        done.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(summary)
+}
+
+/// File stem of `song`, numbered when another exported song took it.
+/// Devices often treat names case-insensitively, so the check does too.
+fn unique_stem(song: &Path, taken: &mut HashSet<String>) -> String {
+    let stem = song
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut candidate = stem.clone();
+    let mut number = 2;
+    while !taken.insert(candidate.to_lowercase()) {
+        candidate = format!("{stem} ({number})");
+        number += 1;
+    }
+    candidate
+}
+
+/// Write `song` and its lyrics to `folder` as `stem`. Returns whether it
+/// had lyrics.
+fn export_song(song: &Path, folder: &Path, stem: &str, format: DeviceFormat) -> Result<bool> {
+    match format {
+        DeviceFormat::Original => {
+            let extension = song.extension().unwrap_or_default().to_string_lossy();
+            let target = folder.join(format!("{stem}.{extension}"));
+            fs::copy(song, &target)
+                .with_context(|| format!("Failed to copy {}", song.display()))?;
+        },
+        DeviceFormat::Mp3 => convert_to_mp3(song, &folder.join(format!("{stem}.mp3")))?,
+    }
+    let lyrics = lrc::lrc_path(song);
+    if !lyrics.exists() {
+        return Ok(false);
+    }
+    let target = folder.join(format!("{stem}.lrc"));
+    fs::copy(&lyrics, &target).with_context(|| format!("Failed to copy {}", lyrics.display()))?;
+    Ok(true)
+}
+
+fn convert_to_mp3(song: &Path, target: &Path) -> Result<()> {
+    let output = Command::new("ffmpeg")
+        .args(["-y", "-loglevel", "error", "-i"])
+        .arg(song)
+        .args(["-vn", "-codec:a", "libmp3lame", "-q:a", "2"])
+        .arg(target)
+        .output()
+        .context("Cannot run ffmpeg")?;
+    if !output.status.success() {
+        bail!(
+            "Failed to convert {}: {}",
+            song.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,276 @@
+//! Difficulty rating of songs, from their lyrics and melody.
+//!
+//! Each timed LRC line gets a score from how fast its words come (words
+//! per second) and how wide the melody moves under it (pitch range in
+//! semitones). The melody is tracked on the separated vocals when an
+//! instrumental stem is cached, on the full mix otherwise, so it is an
+//! estimate. The song score weighs its hardest lines and its overall range.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use serde::{Deserialize, Serialize};
+
+use crate::audio::{melody, AudioError};
+use crate::lrc::{self, LrcEvent};
+
+/// A line lasts until the next one, but no longer than this.
+const MAX_LINE_SECS: f32 = 10.0;
+/// Words per second rated easy (score 0) and hard (score 1).
+const EASY_WORDS_PER_SEC: f32 = 1.5;
+const HARD_WORDS_PER_SEC: f32 = 4.0;
+/// Pitch range of a line rated hard, and of a whole song.
+const HARD_LINE_RANGE: f32 = 12.0;
+const HARD_SONG_RANGE: f32 = 24.0;
+/// Voiced estimates a line needs before its pitch range counts.
+const MIN_VOICED: usize = 3;
+/// Song scores below these are easy, then medium.
+const EASY_BELOW: f32 = 0.35;
+const MEDIUM_BELOW: f32 = 0.6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DifficultyLevel {
+    Easy,
+    Medium,
+    Hard,
+}
+
+impl DifficultyLevel {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Easy => "Easy",
+            Self::Medium => "Medium",
+            Self::Hard => "Hard",
+        }
+    }
+}
+
+/// Score of one lyric line, 0.0 (easy) to 1.0 (hard).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct LineDifficulty {
+    /// Start and end of the line in seconds
+    pub start: f32,
+    pub end: f32,
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Difficulty {
+    /// Whole-song score, 0.0 (easy) to 1.0 (hard)
+    pub overall: f32,
+    /// Sung lines in time order
+    pub lines: Vec<LineDifficulty>,
+}
+
+impl Difficulty {
+    pub fn level(&self) -> DifficultyLevel {
+        if self.overall < EASY_BELOW {
+            DifficultyLevel::Easy
+        } else if self.overall < MEDIUM_BELOW {
+            DifficultyLevel::Medium
+        } else {
+            DifficultyLevel::Hard
+        }
+    }
+}
+
+/// Rate `song` from its `.lrc` file and audio. `None` when the song has no
+/// synced lyrics.
+pub fn analyze(song: &Path) -> Result<Option<Difficulty>, AudioError> {
+    let path = lrc::lrc_path(song);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let events = lrc::parse_lrc_file(&path).map_err(|e| AudioError::LoadError(e.to_string()))?;
+    let lines = timed_lines(&events);
+    if lines.iter().all(|(_, _, text)| text.is_empty()) {
+        return Ok(None);
+    }
+    let melody = melody::track(song)?;
+
+    let scores: Vec<LineDifficulty> = lines
+        .iter()
+        .filter(|(_, _, text)| !text.is_empty())
+        .map(|&(start, end, text)| LineDifficulty {
+            start,
+            end,
+            score: line_score(text, end - start, &notes_between(&melody, start, end)),
+        })
+        .collect();
+
+    let mut sorted: Vec<f32> = scores.iter().map(|line| line.score).collect();
+    sorted.sort_by(f32::total_cmp);
+    let hardest = percentile(&sorted, 0.8).unwrap_or_default();
+    let mut notes: Vec<f32> = melody.iter().map(|&(_, note)| note).collect();
+    notes.sort_by(f32::total_cmp);
+    let range = match (percentile(&notes, 0.05), percentile(&notes, 0.95)) {
+        (Some(low), Some(high)) => high - low,
+        _ => 0.0,
+    };
+
+    Ok(Some(Difficulty {
+        overall: 0.7 * hardest + 0.3 * (range / HARD_SONG_RANGE).clamp(0.0, 1.0),
+        lines: scores,
+    }))
+}
+
+/// Every timed line as `(start, end, text)` in seconds, sorted by start and
+/// moved by the `[offset:]` tag to line up with the melody. A line ends
+/// where the next one starts, blank lines included.
+fn timed_lines(events: &[LrcEvent]) -> Vec<(f32, f32, &str)> {
+    let offset = lrc::offset_ms(events);
+    let mut starts: Vec<(f32, &str)> = events
+        .iter()
+        .filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps, text, ..
+            } => Some((timestamps, text.trim())),
+            LrcEvent::Metadata { .. } => None,
+        })
+        .flat_map(|(timestamps, text)| {
+            timestamps
+                .iter()
+                .map(move |&time| (lrc::shift(time, offset).as_secs_f32(), text))
+        })
+        .collect();
+    starts.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut lines = Vec::with_capacity(starts.len());
+    for (index, &(start, text)) in starts.iter().enumerate() {
+        let next = starts.get(index + 1).map_or(f32::MAX, |&(next, _)| next);
+        lines.push((start, next.min(start + MAX_LINE_SECS), text));
+    }
+    lines
+}
+
+fn line_score(text: &str, duration: f32, notes: &[f32]) -> f32 {
+    let words = text.split_whitespace().count() as f32;
+    let density = words / duration.max(0.5);
+    let density_score = ((density - EASY_WORDS_PER_SEC)
+        / (HARD_WORDS_PER_SEC - EASY_WORDS_PER_SEC))
+        .clamp(0.0, 1.0);
+    if notes.len() < MIN_VOICED {
+        return density_score;
+    }
+
+    let mut sorted = notes.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let range = match (percentile(&sorted, 0.1), percentile(&sorted, 0.9)) {
+        (Some(low), Some(high)) => high - low,
+        _ => 0.0,
+    };
+    0.5 * density_score + 0.5 * (range / HARD_LINE_RANGE).clamp(0.0, 1.0)
+}
+
+/// Notes of the melody from `start` to `end` seconds.
+fn notes_between(melody: &[(f32, f32)], start: f32, end: f32) -> Vec<f32> {
+    melody
+        .iter()
+        .filter(|&&(time, _)| time >= start && time < end)
+        .map(|&(_, note)| note)
+        .collect()
+}
+
+/// Value at `fraction` of the way through sorted `values`.
+fn percentile(values: &[f32], fraction: f32) -> Option<f32> {
+    let last = values.len().checked_sub(1)?;
+    values
+        .get((last as f32 * fraction).round() as usize)
+        .copied()
+}
+
+/// Songs rated by a running [`DifficultyJob`].
+#[derive(Debug, Default)]
+struct Rated {
+    done: usize,
+    results: Vec<(PathBuf, Difficulty)>,
+}
+
+/// Rates a list of songs on a background thread.
+pub struct DifficultyJob {
+    total: usize,
+    rated: Arc<Mutex<Rated>>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl DifficultyJob {
+    /// Rate the `songs` that have synced lyrics.
+    pub fn start(songs: Vec<PathBuf>) -> Self {
+        let songs: Vec<PathBuf> = songs
+            .into_iter()
+            .filter(|song| lrc::lrc_path(song).exists())
+            .collect();
+        let rated = Arc::new(Mutex::new(Rated::default()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let total = songs.len();
+
+        let handle = {
+            let (rated, cancel) = (rated.clone(), cancel.clone());
+            thread::spawn(move || {
+                for song in songs {
+                    if cancel.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    let result = analyze(&song);
+                    let mut rated = lock(&rated);
+                    rated.done += 1;
+                    match result {
+                        Ok(Some(difficulty)) => rated.results.push((song, difficulty)),
+                        Ok(None) => {},
+                        Err(e) => tracing::warn!("Difficulty rating failed: {e}"),
+                    }
+                }
+            })
+        };
+        Self {
+            total,
+            rated,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    /// Songs rated so far and songs to rate.
+    pub fn progress(&self) -> (usize, usize) {
+        (lock(&self.rated).done, self.total)
+    }
+
+    /// Stop after the song being rated.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// Ratings finished since the last call.
+    pub fn take_results(&self) -> Vec<(PathBuf, Difficulty)> {
+        std::mem::take(&mut lock(&self.rated).results)
+    }
+
+    /// Whether the job has ended; the last results may still be waiting in
+    /// [`Self::take_results`].
+    pub fn is_finished(&mut self) -> bool {
+        let Some(handle) = &self.handle else {
+            return true;
+        };
+        if !handle.is_finished() {
+            return false;
+        }
+        if self
+            .handle
+            .take()
+            .is_some_and(|handle| handle.join().is_err())
+        {
+            tracing::error!("Difficulty rating crashed");
+        }
+        true
+    }
+}
+
+/// Lock ignoring poisoning: the results stay usable after a panic.
+fn lock(rated: &Mutex<Rated>) -> MutexGuard<'_, Rated> {
+    rated
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
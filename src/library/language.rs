@@ -0,0 +1,258 @@
+//! Language of songs, guessed from their lyrics and title.
+//!
+//! Scripts other than Latin mostly give the language away on their own
+//! (kana is Japanese, Hangul is Korean). Latin-script lyrics are told apart
+//! by counting common short words of each language, which is reliable on
+//! a full set of lyrics and merely a hint on a title alone.
+//!
+//! Songs are looked at on a background thread. One where nothing stood out
+//! is not looked at again until its lyrics change.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::SystemTime;
+
+use super::artist_and_title;
+use crate::lrc::{self, LrcEvent};
+
+/// Languages offered when setting a song's language by hand.
+pub const LANGUAGES: [&str; 20] = [
+    "Arabic",
+    "Chinese",
+    "Dutch",
+    "English",
+    "French",
+    "German",
+    "Greek",
+    "Hebrew",
+    "Hindi",
+    "Indonesian",
+    "Italian",
+    "Japanese",
+    "Korean",
+    "Persian",
+    "Polish",
+    "Portuguese",
+    "Russian",
+    "Spanish",
+    "Thai",
+    "Turkish",
+];
+
+/// Frequent words of the Latin-script languages, lowercase.
+const COMMON_WORDS: [(&str, &[&str]); 9] = [
+    (
+        "English",
+        &[
+            "the", "and", "you", "i", "to", "my", "me", "it", "is", "of", "in", "your", "that",
+            "love", "don't", "i'm",
+        ],
+    ),
+    (
+        "Spanish",
+        &[
+            "el", "la", "que", "de", "y", "en", "mi", "tu", "no", "me", "te", "es", "por", "con",
+            "amor", "yo",
+        ],
+    ),
+    (
+        "French",
+        &[
+            "le", "la", "les", "et", "je", "tu", "de", "que", "pas", "est", "mon", "moi", "toi",
+            "une", "c'est", "j'ai",
+        ],
+    ),
+    (
+        "German",
+        &[
+            "der", "die", "das", "und", "ich", "du", "nicht", "ist", "mich", "dich", "mein", "ein",
+            "auf", "wir", "es", "zu",
+        ],
+    ),
+    (
+        "Portuguese",
+        &[
+            "o", "a", "que", "de", "e", "não", "eu", "você", "meu", "com", "um", "uma", "do", "da",
+            "em", "te",
+        ],
+    ),
+    (
+        "Italian",
+        &[
+            "il", "la", "che", "di", "e", "non", "io", "tu", "mi", "ti", "per", "un", "sei",
+            "sono", "amore", "con",
+        ],
+    ),
+    (
+        "Dutch",
+        &[
+            "de", "het", "een", "en", "ik", "je", "niet", "van", "is", "dat", "mijn", "jij", "wij",
+            "voor", "op", "zijn",
+        ],
+    ),
+    (
+        "Indonesian",
+        &[
+            "aku", "kau", "yang", "dan", "di", "ini", "itu", "tak", "cinta", "kamu", "ku",
+            "dengan", "untuk", "akan", "tidak", "ada",
+        ],
+    ),
+    (
+        "Turkish",
+        &[
+            "ve", "bir", "bu", "ben", "sen", "ne", "de", "da", "için", "gibi", "beni", "seni",
+            "çok", "var", "yok", "aşk",
+        ],
+    ),
+];
+/// Common-word hits a Latin-script guess needs, and how far ahead of the
+/// runner-up it must be.
+const MIN_HITS: usize = 3;
+const MIN_LEAD: f32 = 1.3;
+
+/// A song's language, or when nothing stood out, the modification time of
+/// the lyrics it was guessed from.
+pub type Detection = Result<&'static str, SystemTime>;
+
+/// Language detection of a list of songs on a background thread.
+pub struct LanguageJob {
+    handle: Option<JoinHandle<Vec<(PathBuf, Detection)>>>,
+}
+
+impl LanguageJob {
+    /// Detect the language of the `songs`, each with the time of the
+    /// lyrics nothing was found in before; a song whose lyrics have not
+    /// changed since is skipped.
+    pub fn start(songs: Vec<(PathBuf, Option<SystemTime>)>) -> Self {
+        let handle = thread::spawn(move || {
+            songs
+                .into_iter()
+                .filter_map(|(song, checked)| {
+                    let modified = lyrics_modified(&song);
+                    if checked == Some(modified) {
+                        return None;
+                    }
+                    let detection = detect_song(&song).ok_or(modified);
+                    Some((song, detection))
+                })
+                .collect()
+        });
+        Self {
+            handle: Some(handle),
+        }
+    }
+
+    /// The detections once the job has finished; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<Vec<(PathBuf, Detection)>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| {
+            tracing::error!("Language detection crashed");
+            Vec::new()
+        }))
+    }
+}
+
+/// Modification time of the `.lrc` of `song`, the epoch without one: the
+/// title a song without lyrics is guessed from does not change.
+fn lyrics_modified(song: &Path) -> SystemTime {
+    fs::metadata(lrc::lrc_path(song))
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
+}
+
+/// Guess the language of `song` from its `.lrc` lyrics, or its title when
+/// it has none. `None` when nothing stands out.
+fn detect_song(song: &Path) -> Option<&'static str> {
+    let (_, title) = artist_and_title(song);
+    let lyrics = lrc::parse_lrc_file(&lrc::lrc_path(song))
+        .map(|events| {
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    LrcEvent::Line { text, .. } => Some(text.as_str()),
+                    LrcEvent::Metadata { .. } => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+        .unwrap_or_default();
+    detect(&lyrics).or_else(|| detect(&title))
+}
+
+/// Guess the language of `text`.
+pub fn detect(text: &str) -> Option<&'static str> {
+    by_script(text).or_else(|| by_common_words(text))
+}
+
+/// Language of the dominant non-Latin script of `text`.
+fn by_script(text: &str) -> Option<&'static str> {
+    let mut counts = [0_usize; 10];
+    let mut persian = false;
+    for c in text.chars() {
+        let index = match u32::from(c) {
+            0x3040..=0x30FF => 0,                        // kana
+            0xAC00..=0xD7AF | 0x1100..=0x11FF => 1,      // Hangul
+            0x4E00..=0x9FFF => 2,                        // Han
+            0x0400..=0x04FF => 3,                        // Cyrillic
+            0x0600..=0x06FF | 0xFB50..=0xFEFF => 4,      // Arabic
+            0x0590..=0x05FF => 5,                        // Hebrew
+            0x0370..=0x03FF => 6,                        // Greek
+            0x0E00..=0x0E7F => 7,                        // Thai
+            0x0900..=0x097F => 8,                        // Devanagari
+            _ if c.is_alphabetic() && c.is_ascii() => 9, // Latin
+            0x00C0..=0x024F => 9,                        // Latin accents
+            _ => continue,
+        };
+        // Letters Persian has and Arabic does not
+        persian |= matches!(c, '\u{067E}' | '\u{0686}' | '\u{0698}' | '\u{06AF}');
+        counts[index] += 1;
+    }
+    let letters: usize = counts.iter().sum();
+    // Japanese mixes kanji with kana; any real amount of kana decides it
+    if counts[0] * 10 >= letters && counts[0] > 0 {
+        return Some("Japanese");
+    }
+    let (index, &count) = counts.iter().enumerate().max_by_key(|&(_, count)| *count)?;
+    if count == 0 || count * 2 < letters {
+        return None;
+    }
+    match index {
+        1 => Some("Korean"),
+        2 => Some("Chinese"),
+        3 => Some("Russian"),
+        4 if persian => Some("Persian"),
+        4 => Some("Arabic"),
+        5 => Some("Hebrew"),
+        6 => Some("Greek"),
+        7 => Some("Thai"),
+        8 => Some("Hindi"),
+        _ => None,
+    }
+}
+
+/// Latin-script language whose common words `text` uses most.
+fn by_common_words(text: &str) -> Option<&'static str> {
+    let words: Vec<String> = text
+        .split(|c: char| !(c.is_alphabetic() || c == '\'' || c == '’'))
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase().replace('’', "'"))
+        .collect();
+    let mut scores: Vec<(&str, usize)> = COMMON_WORDS
+        .iter()
+        .map(|&(language, common)| {
+            let hits = words
+                .iter()
+                .filter(|word| common.contains(&word.as_str()))
+                .count();
+            (language, hits)
+        })
+        .collect();
+    scores.sort_by_key(|&(_, hits)| std::cmp::Reverse(hits));
+    let (language, best) = *scores.first()?;
+    let runner_up = scores.get(1).map_or(0, |&(_, hits)| hits);
+    (best >= MIN_HITS && best as f32 >= runner_up as f32 * MIN_LEAD).then_some(language)
+}
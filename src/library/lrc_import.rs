@@ -0,0 +1,293 @@
+//! Batch import of `.lrc` files: pairing a folder of lyrics with library
+//! songs.
+//!
+//! Songs and lyrics are compared on title, artist and length. Titles and
+//! artists come from the LRC tags and from "Artist - Title" file names, and
+//! are compared with a character-bigram similarity after stripping case,
+//! punctuation and noise words such as "karaoke" or "official video".
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use encoding_rs::Encoding;
+use rodio::Source;
+use walkdir::WalkDir;
+
+use crate::audio::player;
+use crate::library;
+use crate::lrc::{self, LrcEvent, LrcMetadata};
+
+/// Candidates kept per lyrics file.
+const MAX_MATCHES: usize = 5;
+/// Best matches under this confidence need confirming.
+const CONFIDENT: f32 = 0.75;
+/// A runner-up this close to the best match makes the pairing ambiguous.
+const RUNNER_UP_MARGIN: f32 = 0.1;
+/// Length difference at which the length score reaches zero.
+const LENGTH_TOLERANCE_SECS: f32 = 10.0;
+/// Words that say nothing about which song a file is.
+const NOISE_WORDS: &[&str] = &[
+    "karaoke",
+    "instrumental",
+    "lyrics",
+    "lyric",
+    "official",
+    "video",
+    "audio",
+    "version",
+    "hq",
+    "remastered",
+];
+
+/// What is known about one lyrics file.
+#[derive(Debug, Clone)]
+pub struct LrcFile {
+    pub path: PathBuf,
+    title: String,
+    artist: Option<String>,
+    /// From the `[length:]` tag
+    length: Option<Duration>,
+    /// Timestamp of the last line; the song cannot be shorter
+    last_line: Option<Duration>,
+    /// First lyric line, to help the user recognise the song
+    pub first_line: Option<String>,
+    /// Character encoding the file was detected in
+    pub encoding: &'static Encoding,
+}
+
+impl LrcFile {
+    fn read(path: &Path) -> Result<Self> {
+        let (text, encoding) = lrc::read_lrc_text(path)?;
+        let events = lrc::parse_lrc(&text);
+        let (stem_artist, stem_title) = library::artist_and_title(path);
+        let tags = LrcMetadata::from_events(&events);
+        let title = tags.title.unwrap_or(stem_title);
+        let artist = tags.artist.or(stem_artist);
+        let length = tags.length;
+
+        let lines = events.iter().filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps, text, ..
+            } => Some((timestamps, text)),
+            LrcEvent::Metadata { .. } => None,
+        });
+        let last_line = lines
+            .clone()
+            .flat_map(|(timestamps, _)| timestamps.iter().copied())
+            .max()
+            .map(|last| lrc::shift(last, tags.offset_ms));
+        let first_line = lines
+            .map(|(_, text)| text)
+            .find(|text| !text.is_empty())
+            .cloned();
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            title,
+            artist,
+            length,
+            last_line,
+            first_line,
+            encoding,
+        })
+    }
+}
+
+/// A library song the lyrics may belong to.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub song: PathBuf,
+    /// 0.0 - 1.0
+    pub confidence: f32,
+}
+
+/// A lyrics file and its best candidate songs, best first.
+#[derive(Debug, Clone)]
+pub struct Pairing {
+    pub lrc: LrcFile,
+    pub matches: Vec<Match>,
+}
+
+impl Pairing {
+    pub fn best(&self) -> Option<&Match> {
+        self.matches.first()
+    }
+
+    /// True when the best match is weak or a runner-up is nearly as good,
+    /// so the user has to confirm the choice.
+    pub fn is_ambiguous(&self) -> bool {
+        match &self.matches[..] {
+            [] => true,
+            [best] => best.confidence < CONFIDENT,
+            [best, second, ..] => {
+                best.confidence < CONFIDENT
+                    || best.confidence - second.confidence < RUNNER_UP_MARGIN
+            },
+        }
+    }
+}
+
+/// What is known about one library song.
+struct SongInfo {
+    path: PathBuf,
+    stem: String,
+    title: String,
+    artist: Option<String>,
+    duration: Option<Duration>,
+}
+
+impl SongInfo {
+    fn read(path: &Path) -> Self {
+        let (artist, title) = library::artist_and_title(path);
+        Self {
+            path: path.to_path_buf(),
+            stem: normalize(&stem(path)),
+            title: normalize(&title),
+            artist: artist.as_deref().map(normalize),
+            duration: player::open_decoder(path)
+                .ok()
+                .and_then(|decoder| decoder.total_duration()),
+        }
+    }
+
+    /// Weighted similarity; parts that cannot be compared are left out.
+    fn confidence(&self, lrc: &LrcFile) -> f32 {
+        let lrc_title = normalize(&lrc.title);
+        let lrc_stem = normalize(&stem(&lrc.path));
+        let title = similarity(&lrc_title, &self.title)
+            .max(similarity(&lrc_stem, &self.stem))
+            .max(similarity(&lrc_title, &self.stem));
+
+        let mut score = title * 0.6;
+        let mut weight = 0.6;
+        if let (Some(lrc_artist), Some(artist)) = (&lrc.artist, &self.artist) {
+            score += similarity(&normalize(lrc_artist), artist) * 0.25;
+            weight += 0.25;
+        }
+        if let Some(duration) = self.duration {
+            let length_score = match (lrc.length, lrc.last_line) {
+                (Some(length), _) => Some(
+                    1.0 - (length.as_secs_f32() - duration.as_secs_f32()).abs()
+                        / LENGTH_TOLERANCE_SECS,
+                ),
+                // Lyrics running past the end cannot belong to this song
+                (None, Some(last)) if last > duration + Duration::from_secs(2) => Some(0.0),
+                _ => None,
+            };
+            if let Some(length_score) = length_score {
+                score += length_score.clamp(0.0, 1.0) * 0.15;
+                weight += 0.15;
+            }
+        }
+        score / weight
+    }
+}
+
+/// Read every `.lrc` file under `folder` and rank `songs` for each.
+pub fn pair_folder(folder: &Path, songs: &[PathBuf]) -> Vec<Pairing> {
+    let songs: Vec<SongInfo> = songs.iter().map(|song| SongInfo::read(song)).collect();
+    WalkDir::new(folder)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && lrc::is_lrc_file(entry.path()))
+        .filter_map(|entry| match LrcFile::read(entry.path()) {
+            Ok(lrc) => Some(lrc),
+            Err(e) => {
+                tracing::warn!("{e:#}");
+                None
+            },
+        })
+        .map(|lrc| {
+            let mut matches: Vec<Match> = songs
+                .iter()
+                .map(|song| Match {
+                    song: song.path.clone(),
+                    confidence: song.confidence(&lrc),
+                })
+                .filter(|candidate| candidate.confidence > 0.0)
+                .collect();
+            matches.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+            matches.truncate(MAX_MATCHES);
+            Pairing { lrc, matches }
+        })
+        .collect()
+}
+
+/// Copy `lrc` next to `song` as its lyrics file, replacing any existing
+/// one, converted to UTF-8 when `to_utf8` is set.
+pub fn import(lrc: &Path, song: &Path, to_utf8: bool) -> Result<()> {
+    let target = lrc::lrc_path(song);
+    fs::copy(lrc, &target)
+        .with_context(|| format!("Failed to copy {} to {}", lrc.display(), target.display()))?;
+    if to_utf8 {
+        lrc::resave_as_utf8(&target)?;
+    }
+    Ok(())
+}
+
+/// File name without extension.
+fn stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// `mm:ss` as used by the `[length:]` tag.
+/// Lowercase words without punctuation or noise words.
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !NOISE_WORDS.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Sørensen-Dice coefficient over character bigrams, 0.0 - 1.0.
+fn similarity(a: &str, b: &str) -> f32 {
+    if a == b {
+        return if a.is_empty() { 0.0 } else { 1.0 };
+    }
+    let bigrams = |text: &str| {
+        let chars: Vec<char> = text.chars().collect();
+        chars
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .collect::<HashSet<_>>()
+    };
+    let (a, b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    2.0 * a.intersection(&b).count() as f32 / (a.len() + b.len()) as f32
+}
+
+/// Pairing on a background thread; probing song lengths takes a while.
+pub struct LrcImportJob {
+    handle: Option<JoinHandle<Vec<Pairing>>>,
+}
+
+impl LrcImportJob {
+    pub fn start(folder: &Path, songs: Vec<PathBuf>) -> Self {
+        let folder = folder.to_path_buf();
+        Self {
+            handle: Some(thread::spawn(move || pair_folder(&folder, &songs))),
+        }
+    }
+
+    /// The pairings once done; `None` while the job runs.
+    pub fn try_finish(&mut self) -> Option<Vec<Pairing>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(handle.join().unwrap_or_else(|_| {
+            tracing::error!("Lyrics pairing crashed");
+            Vec::new()
+        }))
+    }
+}
@@ -0,0 +1,27 @@
+//! Song library management.
+
+pub mod content_store;
+pub mod cues;
+pub mod device_export;
+pub mod difficulty;
+pub mod language;
+pub mod lrc_import;
+pub mod results;
+pub mod scanner;
+pub mod sections;
+pub mod storage;
+
+use std::path::Path;
+
+/// Artist and title from an "Artist - Title" file name; the whole file stem
+/// is the title otherwise.
+pub fn artist_and_title(path: &Path) -> (Option<String>, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match stem.split_once(" - ") {
+        Some((artist, title)) => (Some(artist.trim().to_string()), title.trim().to_string()),
+        None => (None, stem),
+    }
+}
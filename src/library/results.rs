@@ -0,0 +1,194 @@
+//! Scores of karaoke runs.
+//!
+//! While a song plays with the mic on, each singer's pitch is scored
+//! against the song's estimated melody. A run sung far enough to the end
+//! becomes a [`SongResult`] per singer, kept with the song in the library
+//! for its best score badge, and shown on the results screen with a
+//! breakdown by section.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::sections::{self, SongSection};
+use crate::audio::melody::{Melody, MelodyNote};
+use crate::audio::processor::MIC_COUNT;
+use crate::audio::scoring::{PitchScore, RunScore};
+
+/// One singer's run of a song.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SongResult {
+    pub singer: String,
+    /// Out of [`crate::audio::scoring::MAX_SCORE`]
+    pub score: u32,
+    /// Fraction of the time on pitch
+    pub accuracy: f32,
+    pub notes_hit: u32,
+    pub notes: u32,
+    /// Most notes hit in a row
+    pub longest_streak: u32,
+    /// Seconds since the Unix epoch
+    pub sung_at: u64,
+}
+
+/// A song being sung and scored.
+#[derive(Debug)]
+pub struct KaraokeRun {
+    song: PathBuf,
+    /// Names of the singers, one per mic in use
+    singers: Vec<String>,
+    scores: [RunScore; MIC_COUNT],
+}
+
+impl KaraokeRun {
+    pub fn new(song: &Path, singers: Vec<String>) -> Self {
+        Self {
+            song: song.to_path_buf(),
+            singers,
+            scores: Default::default(),
+        }
+    }
+
+    pub fn song(&self) -> &Path {
+        &self.song
+    }
+
+    /// Score what each singer sings at `position`: `sung` in Hz, heard
+    /// `latency` late, against the melody moved `key_shift` semitones
+    /// along with the backing track.
+    pub fn update(
+        &mut self,
+        melody: &Melody,
+        key_shift: i8,
+        position: Duration,
+        sung: [Option<f32>; MIC_COUNT],
+        latency: [Duration; MIC_COUNT],
+    ) {
+        let singers = self.singers.len();
+        for ((score, sung), latency) in self.scores.iter_mut().zip(sung).zip(latency).take(singers)
+        {
+            let due = position.saturating_sub(latency).as_secs_f32();
+            if let Some(note) = melody.note_at(due) {
+                let note = MelodyNote {
+                    note: note.note + f32::from(key_shift),
+                    ..*note
+                };
+                score.add(&note, sung);
+            }
+        }
+    }
+
+    /// The results of each singer, `None` when too little was sung for a
+    /// result, e.g. a skipped song.
+    pub fn finish(self, sections: &[SongSection]) -> Option<Vec<SingerResults>> {
+        let sung_at = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let results: Vec<SingerResults> = self
+            .singers
+            .into_iter()
+            .zip(self.scores)
+            .filter(|(_, score)| score.is_complete())
+            .map(|(singer, score)| SingerResults {
+                result: SongResult {
+                    singer,
+                    score: score.score(),
+                    accuracy: score.accuracy(),
+                    notes_hit: score.notes_hit() as u32,
+                    notes: score.notes().len() as u32,
+                    longest_streak: score.longest_streak() as u32,
+                    sung_at,
+                },
+                sections: section_accuracy(&score, sections),
+                previous_best: None,
+            })
+            .collect();
+        (!results.is_empty()).then_some(results)
+    }
+}
+
+/// A singer's result with its breakdown, for the results screen.
+#[derive(Debug, Clone)]
+pub struct SingerResults {
+    pub result: SongResult,
+    /// Accuracy in each section, in singing order, when the song has
+    /// section markers
+    pub sections: Vec<(String, f32)>,
+    /// Best score of the song before this run
+    pub previous_best: Option<u32>,
+}
+
+/// A finished run, shown on the results screen until dismissed.
+#[derive(Debug, Clone)]
+pub struct RunResults {
+    pub song: PathBuf,
+    pub singers: Vec<SingerResults>,
+}
+
+/// Accuracy of each stretch of the song between section markers, named by
+/// the section.
+fn section_accuracy(score: &RunScore, sections: &[SongSection]) -> Vec<(String, f32)> {
+    let mut stretches: Vec<(f32, &str, PitchScore)> = Vec::new();
+    for (start, note) in score.notes() {
+        let Some(section) = sections::section_at(sections, Duration::from_secs_f32(*start)) else {
+            continue;
+        };
+        match stretches.last_mut() {
+            Some((section_start, _, total)) if *section_start == section.start => {
+                total.combine(note);
+            },
+            _ => stretches.push((section.start, section.kind.label(), *note)),
+        }
+    }
+    stretches
+        .into_iter()
+        .filter_map(|(_, label, score)| Some((label.to_string(), score.accuracy()?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::pitch;
+
+    /// Notes hit by a singer who sings every note of a C4 melody
+    /// `sung_shift` semitones up, scored with the track at `key_shift`.
+    fn notes_hit(key_shift: i8, sung_shift: f32) -> Option<u32> {
+        let melody = Melody {
+            notes: (0..30)
+                .map(|index| MelodyNote {
+                    start: index as f32,
+                    end: index as f32 + 1.0,
+                    note: 60.0,
+                })
+                .collect(),
+        };
+        let sung = pitch::midi_to_frequency(60.0 + sung_shift);
+        let mut run = KaraokeRun::new(Path::new("song.mp3"), vec!["Singer".to_string()]);
+        for tenth in 0..300 {
+            run.update(
+                &melody,
+                key_shift,
+                Duration::from_millis(tenth * 100),
+                [Some(sung), None],
+                [Duration::ZERO; MIC_COUNT],
+            );
+        }
+        run.finish(&[])
+            .map(|results| results.iter().map(|singer| singer.result.notes_hit).sum())
+    }
+
+    #[test]
+    fn singing_in_the_shifted_key_hits_the_notes() {
+        assert_eq!(notes_hit(0, 0.0), Some(30));
+        assert_eq!(notes_hit(3, 3.0), Some(30));
+        assert_eq!(notes_hit(-2, -2.0), Some(30));
+    }
+
+    #[test]
+    fn singing_in_the_original_key_misses_a_shifted_track() {
+        assert_eq!(notes_hit(3, 0.0), Some(0));
+        assert_eq!(notes_hit(0, 3.0), Some(0));
+    }
+}
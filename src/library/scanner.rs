@@ -0,0 +1,42 @@
+//! File system scanning for playable songs.
+
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use crate::audio::separation;
+
+/// File extensions the player can decode; MIDI songs are synthesized.
+pub const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "wav", "mid", "midi", "kar"];
+
+/// Whether `path` has one of the supported audio extensions.
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Recursively collect audio files under `root`, sorted by path.
+///
+/// Cached instrumentals written by the separator are skipped.
+pub fn scan_folder(root: &Path) -> Vec<PathBuf> {
+    let mut songs: Vec<PathBuf> = WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) => Some(entry),
+            Err(e) => {
+                tracing::warn!("Skipping unreadable entry: {e}");
+                None
+            },
+        })
+        .filter(|entry| {
+            entry.file_type().is_file()
+                && is_audio_file(entry.path())
+                && !separation::is_instrumental_file(entry.path())
+        })
+        .map(walkdir::DirEntry::into_path)
+        .collect();
+    songs.sort();
+    songs
+}
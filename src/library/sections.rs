@@ -0,0 +1,77 @@
+//! Song section markers (verse, chorus, …), stored per song.
+//!
+//! A marker starts a section that runs until the next marker or the end of
+//! the song.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Markers closer than this are treated as the same one.
+const MERGE_DISTANCE_SECS: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SectionKind {
+    Intro,
+    Verse,
+    PreChorus,
+    Chorus,
+    Bridge,
+    Solo,
+    Outro,
+}
+
+impl SectionKind {
+    pub const ALL: [Self; 7] = [
+        Self::Intro,
+        Self::Verse,
+        Self::PreChorus,
+        Self::Chorus,
+        Self::Bridge,
+        Self::Solo,
+        Self::Outro,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Intro => "Intro",
+            Self::Verse => "Verse",
+            Self::PreChorus => "Pre-chorus",
+            Self::Chorus => "Chorus",
+            Self::Bridge => "Bridge",
+            Self::Solo => "Solo",
+            Self::Outro => "Outro",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SongSection {
+    /// Start of the section in seconds
+    pub start: f32,
+    pub kind: SectionKind,
+}
+
+/// Add a marker at `position`, replacing one already there. Keeps the list
+/// sorted by start time.
+pub fn insert_section(sections: &mut Vec<SongSection>, position: Duration, kind: SectionKind) {
+    let start = position.as_secs_f32();
+    remove_section_near(sections, position);
+    let index = sections.partition_point(|section| section.start < start);
+    sections.insert(index, SongSection { start, kind });
+}
+
+/// Remove the marker within half a second of `position`. Returns whether
+/// one was removed.
+pub fn remove_section_near(sections: &mut Vec<SongSection>, position: Duration) -> bool {
+    let start = position.as_secs_f32();
+    let before = sections.len();
+    sections.retain(|section| (section.start - start).abs() >= MERGE_DISTANCE_SECS);
+    sections.len() != before
+}
+
+/// The section playing at `position`, if a marker precedes it.
+pub fn section_at(sections: &[SongSection], position: Duration) -> Option<&SongSection> {
+    let secs = position.as_secs_f32();
+    sections.iter().rev().find(|section| section.start <= secs)
+}
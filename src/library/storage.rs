@@ -0,0 +1,251 @@
+//! Library persistence: the known songs, their per-song settings and the
+//! queue.
+//!
+//! Stored as `library.json` in the data directory, keyed by song path.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::cues::CuePoint;
+use super::difficulty::Difficulty;
+use super::results::SongResult;
+use super::sections::SongSection;
+use crate::audio::effects::VoiceEffect;
+use crate::audio::key::Key;
+use crate::audio::separation;
+use crate::config;
+use crate::lrc::bidi::TextDirection;
+use crate::lrc::{self, chords};
+use crate::lyrics::LyricsProvider;
+use crate::video::VIDEO_EXTENSIONS;
+
+const LIBRARY_FILE_NAME: &str = "library.json";
+/// Results kept per song; the oldest are dropped beyond this.
+const MAX_RESULTS: usize = 100;
+
+/// Per-song data kept alongside the library.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SongEntry {
+    /// Vocal removal strength overriding the global setting (0.0 = off)
+    pub vocal_removal: Option<f32>,
+    /// Voice effects preset replacing the default rack
+    pub voice_effects: Option<Vec<VoiceEffect>>,
+    /// Play the separated instrumental stem instead of the original
+    pub instrumental: bool,
+    /// Detected musical key
+    pub key: Option<Key>,
+    /// Rehearsal playback rate (`None` = normal speed)
+    pub playback_rate: Option<f32>,
+    /// Key shift in semitones (`None` = the singer's preferred key)
+    pub key_shift: Option<i8>,
+    /// Lyric timing nudge in milliseconds; positive shows lines earlier
+    pub lyric_offset_ms: i64,
+    /// Gain applied to this song's track, in dB
+    pub volume_offset_db: f32,
+    /// Tempo for the count-in, in beats per minute
+    pub bpm: Option<f32>,
+    /// Section markers, sorted by start time
+    pub sections: Vec<SongSection>,
+    /// Named jump targets, sorted by position
+    pub cues: Vec<CuePoint>,
+    /// Start and end of the audible part in seconds, once analysed
+    pub audible: Option<(f32, f32)>,
+    /// Difficulty rated from the lyrics and melody, once analysed
+    pub difficulty: Option<Difficulty>,
+    /// Base direction of the title and lyrics
+    pub text_direction: TextDirection,
+    /// Language set by hand, overriding the detected one
+    pub language: Option<String>,
+    /// Language guessed from the lyrics or title, once detected
+    pub detected_language: Option<String>,
+    /// Modification time of the lyrics no language was found in, so they
+    /// are not looked at again
+    pub language_checked: Option<SystemTime>,
+    /// Where the lyrics came from, when added by the app
+    pub lyrics_provider: Option<LyricsProvider>,
+    /// Scored karaoke runs, oldest first
+    pub results: Vec<SongResult>,
+    /// Content id of a song imported into the content store
+    pub content: Option<String>,
+}
+
+impl SongEntry {
+    /// Language of the song: the one set by hand, else the detected one.
+    pub fn language(&self) -> Option<&str> {
+        self.language
+            .as_deref()
+            .or(self.detected_language.as_deref())
+    }
+
+    /// Keep `result`, dropping the oldest beyond [`MAX_RESULTS`].
+    pub fn add_result(&mut self, result: SongResult) {
+        self.results.push(result);
+        let excess = self.results.len().saturating_sub(MAX_RESULTS);
+        self.results.drain(..excess);
+    }
+
+    /// The highest scoring run of the song.
+    pub fn best_result(&self) -> Option<&SongResult> {
+        self.results.iter().max_by_key(|result| result.score)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LibraryStorage {
+    songs: BTreeMap<PathBuf, SongEntry>,
+    /// Songs queued when the library was last saved
+    queue: Vec<PathBuf>,
+}
+
+impl LibraryStorage {
+    /// Load the library file, starting empty when it is missing or invalid.
+    pub fn load() -> Self {
+        let path = library_path();
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!("Invalid library file {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tracing::warn!("Failed to read library file {}: {e}", path.display());
+                Self::default()
+            },
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = library_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add songs to the library, keeping existing entries untouched.
+    pub fn add_songs(&mut self, paths: impl IntoIterator<Item = PathBuf>) {
+        for path in paths {
+            self.songs.entry(path).or_default();
+        }
+    }
+
+    /// Song paths in sorted order.
+    pub fn songs(&self) -> impl Iterator<Item = &Path> {
+        self.songs.keys().map(PathBuf::as_path)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.songs.is_empty()
+    }
+
+    pub fn queue(&self) -> &[PathBuf] {
+        &self.queue
+    }
+
+    pub fn set_queue(&mut self, queue: impl IntoIterator<Item = PathBuf>) {
+        self.queue = queue.into_iter().collect();
+    }
+
+    pub fn entry(&self, path: &Path) -> Option<&SongEntry> {
+        self.songs.get(path)
+    }
+
+    /// Songs imported into the content store, by content id.
+    pub fn stored_contents(&self) -> Vec<(String, PathBuf)> {
+        self.songs
+            .iter()
+            .filter_map(|(path, entry)| Some((entry.content.clone()?, path.clone())))
+            .collect()
+    }
+
+    /// Rename or move the song `from` to `to`, with its lyrics, chords,
+    /// instrumental and video, keeping its entry. Within one drive this
+    /// only changes names; nothing is copied. Fails without changing
+    /// anything when `to` is taken.
+    pub fn rename_song(&mut self, from: &Path, to: &Path) -> Result<()> {
+        if to.exists() || to.is_symlink() || self.songs.contains_key(to) {
+            bail!("{} already exists", to.display());
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::rename(from, to)
+            .with_context(|| format!("Failed to rename {} to {}", from.display(), to.display()))?;
+        for (old, new) in sidecars(from).into_iter().zip(sidecars(to)) {
+            if !old.exists() {
+                continue;
+            }
+            if let Err(e) = fs::rename(&old, &new) {
+                tracing::warn!("Failed to rename {}: {e}", old.display());
+            }
+        }
+        if let Some(entry) = self.songs.remove(from) {
+            self.songs.insert(to.to_path_buf(), entry);
+        }
+        for queued in &mut self.queue {
+            if queued == from {
+                *queued = to.to_path_buf();
+            }
+        }
+        Ok(())
+    }
+
+    /// Re-key the songs kept under `from` after that folder moved to `to`,
+    /// e.g. the content store when the data directory moves.
+    pub fn relocate(&mut self, from: &Path, to: &Path) {
+        let moved: Vec<PathBuf> = self
+            .songs
+            .keys()
+            .filter(|path| path.starts_with(from))
+            .cloned()
+            .collect();
+        for path in moved {
+            let Ok(relative) = path.strip_prefix(from) else {
+                continue;
+            };
+            let new = to.join(relative);
+            if let Some(entry) = self.songs.remove(&path) {
+                self.songs.insert(new, entry);
+            }
+        }
+        for queued in &mut self.queue {
+            if let Ok(relative) = queued.strip_prefix(from) {
+                *queued = to.join(relative);
+            }
+        }
+    }
+
+    /// Mutable entry for `path`, created when the song is not in the library yet.
+    pub fn entry_mut(&mut self, path: &Path) -> &mut SongEntry {
+        self.songs.entry(path.to_path_buf()).or_default()
+    }
+}
+
+/// Files kept next to `song` under its name.
+fn sidecars(song: &Path) -> Vec<PathBuf> {
+    let mut files = vec![
+        lrc::lrc_path(song),
+        chords::chords_path(song),
+        separation::instrumental_path(song),
+    ];
+    files.extend(
+        VIDEO_EXTENSIONS
+            .iter()
+            .map(|extension| song.with_extension(extension)),
+    );
+    files
+}
+
+fn library_path() -> PathBuf {
+    config::data_dir().join(LIBRARY_FILE_NAME)
+}
@@ -0,0 +1,232 @@
+//! Right-to-left lyrics: Arabic shaping and bidirectional reordering.
+//!
+//! The text renderer draws characters left to right as they are stored and
+//! does no shaping, so Arabic and Hebrew lines are prepared here: Arabic
+//! letters are replaced by their contextual presentation forms (joined
+//! the way they are written), then the line is reordered from logical to
+//! visual order with the Unicode bidirectional algorithm, which keeps
+//! embedded Latin words and numbers readable in mixed-direction lines.
+
+use serde::{Deserialize, Serialize};
+use unicode_bidi::{BidiInfo, Level};
+
+/// Base direction of a song's lyrics and title.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextDirection {
+    /// From the first strong character of each line
+    #[default]
+    Auto,
+    LeftToRight,
+    RightToLeft,
+}
+
+impl TextDirection {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::LeftToRight, Self::RightToLeft];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Auto => "Automatic",
+            Self::LeftToRight => "Left to right",
+            Self::RightToLeft => "Right to left",
+        }
+    }
+
+    fn level(self) -> Option<Level> {
+        match self {
+            Self::Auto => None,
+            Self::LeftToRight => Some(Level::ltr()),
+            Self::RightToLeft => Some(Level::rtl()),
+        }
+    }
+}
+
+/// Whether `text` needs [`visual_line`] at all: it has right-to-left
+/// characters or an explicit right-to-left direction.
+pub fn needs_reordering(text: &str, direction: TextDirection) -> bool {
+    direction == TextDirection::RightToLeft || text.chars().any(is_rtl_char)
+}
+
+/// `text` shaped and in visual order, ready to be drawn left to right.
+/// Each line of a multi-line text is reordered on its own.
+pub fn visual_line(text: &str, direction: TextDirection) -> String {
+    if !needs_reordering(text, direction) {
+        return text.to_string();
+    }
+    text.split('\n')
+        .map(|line| {
+            let shaped = shape_arabic(line);
+            let info = BidiInfo::new(&shaped, direction.level());
+            match info.paragraphs.first() {
+                Some(paragraph) => info
+                    .reorder_line(paragraph, paragraph.range.clone())
+                    .into_owned(),
+                None => shaped,
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_rtl_char(c: char) -> bool {
+    matches!(u32::from(c),
+        0x0590..=0x08FF | 0xFB1D..=0xFDFF | 0xFE70..=0xFEFF)
+}
+
+/// How an Arabic letter connects to its neighbours.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Joining {
+    /// Connects on both sides
+    Dual,
+    /// Connects to the previous letter only
+    Right,
+}
+
+/// Presentation forms of a letter: isolated, final, initial, medial. The
+/// last two are unused for right-joining letters.
+struct Forms {
+    letter: char,
+    joining: Joining,
+    forms: [u32; 4],
+}
+
+const fn dual(letter: char, isolated: u32) -> Forms {
+    Forms {
+        letter,
+        joining: Joining::Dual,
+        forms: [isolated, isolated + 1, isolated + 2, isolated + 3],
+    }
+}
+
+const fn right(letter: char, isolated: u32) -> Forms {
+    Forms {
+        letter,
+        joining: Joining::Right,
+        forms: [isolated, isolated + 1, isolated, isolated + 1],
+    }
+}
+
+/// Arabic letters with their Presentation Forms-A/B code points, including
+/// the common Persian and Urdu additions.
+const FORMS: [Forms; 42] = [
+    right('\u{0622}', 0xFE81),
+    right('\u{0623}', 0xFE83),
+    right('\u{0624}', 0xFE85),
+    right('\u{0625}', 0xFE87),
+    dual('\u{0626}', 0xFE89),
+    right('\u{0627}', 0xFE8D),
+    dual('\u{0628}', 0xFE8F),
+    right('\u{0629}', 0xFE93),
+    dual('\u{062A}', 0xFE95),
+    dual('\u{062B}', 0xFE99),
+    dual('\u{062C}', 0xFE9D),
+    dual('\u{062D}', 0xFEA1),
+    dual('\u{062E}', 0xFEA5),
+    right('\u{062F}', 0xFEA9),
+    right('\u{0630}', 0xFEAB),
+    right('\u{0631}', 0xFEAD),
+    right('\u{0632}', 0xFEAF),
+    dual('\u{0633}', 0xFEB1),
+    dual('\u{0634}', 0xFEB5),
+    dual('\u{0635}', 0xFEB9),
+    dual('\u{0636}', 0xFEBD),
+    dual('\u{0637}', 0xFEC1),
+    dual('\u{0638}', 0xFEC5),
+    dual('\u{0639}', 0xFEC9),
+    dual('\u{063A}', 0xFECD),
+    dual('\u{0641}', 0xFED1),
+    dual('\u{0642}', 0xFED5),
+    dual('\u{0643}', 0xFED9),
+    dual('\u{0644}', 0xFEDD),
+    dual('\u{0645}', 0xFEE1),
+    dual('\u{0646}', 0xFEE5),
+    dual('\u{0647}', 0xFEE9),
+    right('\u{0648}', 0xFEED),
+    right('\u{0649}', 0xFEEF),
+    dual('\u{064A}', 0xFEF1),
+    dual('\u{067E}', 0xFB56),
+    dual('\u{0686}', 0xFB7A),
+    right('\u{0698}', 0xFB8A),
+    dual('\u{06A9}', 0xFB8E),
+    dual('\u{06AF}', 0xFB92),
+    dual('\u{06CC}', 0xFBFC),
+    right('\u{0688}', 0xFB88),
+];
+
+/// Lam followed by these alefs is written as one ligature (isolated form;
+/// the final form follows it).
+const LAM_ALEF: [(char, u32); 4] = [
+    ('\u{0622}', 0xFEF5),
+    ('\u{0623}', 0xFEF7),
+    ('\u{0625}', 0xFEF9),
+    ('\u{0627}', 0xFEFB),
+];
+const LAM: char = '\u{0644}';
+const TATWEEL: char = '\u{0640}';
+
+fn forms(c: char) -> Option<&'static Forms> {
+    FORMS.iter().find(|forms| forms.letter == c)
+}
+
+/// Marks (harakat) are skipped when looking for the neighbouring letter.
+fn is_transparent(c: char) -> bool {
+    matches!(u32::from(c), 0x0610..=0x061A | 0x064B..=0x065F | 0x0670 | 0x06D6..=0x06ED)
+}
+
+/// Whether `c` connects to the letter after it.
+fn joins_forward(c: char) -> bool {
+    c == TATWEEL || forms(c).is_some_and(|forms| forms.joining == Joining::Dual)
+}
+
+/// Whether `c` connects to the letter before it.
+fn joins_backward(c: char) -> bool {
+    c == TATWEEL || forms(c).is_some()
+}
+
+/// Replace Arabic letters with the presentation form matching their
+/// position in the word, in logical order.
+fn shape_arabic(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let neighbour = |index: usize, step: isize| {
+        let mut i = index as isize + step;
+        while let Some(&c) = usize::try_from(i).ok().and_then(|i| chars.get(i)) {
+            if !is_transparent(c) {
+                return Some(c);
+            }
+            i += step;
+        }
+        None
+    };
+
+    let mut shaped = String::with_capacity(text.len());
+    let mut index = 0;
+    while let Some(&c) = chars.get(index) {
+        let Some(letter) = forms(c) else {
+            shaped.push(c);
+            index += 1;
+            continue;
+        };
+        let joined_before = neighbour(index, -1).is_some_and(joins_forward);
+
+        if c == LAM {
+            let next = chars.get(index + 1).copied();
+            if let Some(&(_, ligature)) = LAM_ALEF.iter().find(|(alef, _)| Some(*alef) == next) {
+                let form = ligature + u32::from(joined_before);
+                shaped.extend(char::from_u32(form));
+                index += 2;
+                continue;
+            }
+        }
+
+        let joined_after =
+            letter.joining == Joining::Dual && neighbour(index, 1).is_some_and(joins_backward);
+        let form = match (joined_before, joined_after) {
+            (false, false) => letter.forms[0],
+            (true, false) => letter.forms[1],
+            (false, true) => letter.forms[2],
+            (true, true) => letter.forms[3],
+        };
+        shaped.push(char::from_u32(form).unwrap_or(c));
+        index += 1;
+    }
+    shaped
+}
@@ -0,0 +1,219 @@
+//! Chord annotations, for playing along on guitar or piano.
+//!
+//! Chords are written inline in a lyric line, in brackets in front of the
+//! syllable they fall on: `[00:12.00][Am]Hello [F]darkness`. Songs whose
+//! `.lrc` has none can take them from a sidecar `.chords` file next to the
+//! audio, one timed chord change per line: `[00:12.00]Am`. Sidecar chords
+//! are placed on the line sung at their time, as far into its text as they
+//! are into the line's duration.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::{lrc_path, offset_ms, parse_lrc, read_lrc_text, shift, LrcError, LrcEvent};
+
+/// Chord suffixes, longest first so `maj7` is not read as `m` + `aj7`;
+/// `b` and `#` alter extensions, as in `C7b9`.
+const SUFFIXES: &[&str] = &[
+    "maj", "min", "dim", "aug", "sus", "add", "m", "M", "°", "ø", "+", "-", "(", ")", "b", "#",
+];
+/// Longest a line is assumed to last when it is the last one.
+const LAST_LINE_SECS: f32 = 5.0;
+/// Chords shown on one line when there are no lyrics to put them on.
+const CHORDS_PER_LINE: usize = 8;
+
+/// A chord over a lyric line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChordMark {
+    /// Position in the line text, in characters
+    pub offset: usize,
+    pub chord: String,
+}
+
+/// A timed lyric line with its chords.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChordLine {
+    pub start: Duration,
+    pub text: String,
+    pub chords: Vec<ChordMark>,
+}
+
+/// Whether `text` is a chord name such as `C`, `F#m7`, `Bbmaj7` or `G/B`.
+pub fn is_chord(text: &str) -> bool {
+    let (chord, bass) = match text.split_once('/') {
+        Some((chord, bass)) => (chord, Some(bass)),
+        None => (text, None),
+    };
+    let Some(suffix) = strip_root(chord) else {
+        return false;
+    };
+    let mut rest = suffix;
+    while !rest.is_empty() {
+        let digits = rest.trim_start_matches(|c: char| c.is_ascii_digit());
+        if digits.len() < rest.len() {
+            rest = digits;
+            continue;
+        }
+        match SUFFIXES.iter().find_map(|suffix| rest.strip_prefix(suffix)) {
+            Some(after) => rest = after,
+            None => return false,
+        }
+    }
+    bass.is_none_or(|bass| strip_root(bass).is_some_and(str::is_empty))
+}
+
+/// What follows the root note and its accidental, if `text` starts with one.
+fn strip_root(text: &str) -> Option<&str> {
+    let rest = text.strip_prefix(|c: char| matches!(c, 'A'..='G'))?;
+    Some(rest.strip_prefix(['#', 'b', '♯', '♭']).unwrap_or(rest))
+}
+
+/// Take the inline `[chord]` marks out of `text`, leaving single spaces
+/// between words. Brackets holding anything else are kept as text.
+pub(super) fn extract_chords(text: &str) -> (String, Vec<ChordMark>) {
+    let mut out = String::with_capacity(text.len());
+    let mut chords = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(inner) = rest.strip_prefix('[') {
+            if let Some(end) = inner.find(']').filter(|&end| is_chord(&inner[..end])) {
+                chords.push(ChordMark {
+                    offset: out.chars().count(),
+                    chord: inner[..end].to_string(),
+                });
+                rest = &inner[end + 1..];
+                continue;
+            }
+        }
+        let Some(c) = rest.chars().next() else {
+            break;
+        };
+        if !c.is_whitespace() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with(' ') {
+            out.push(' ');
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    let text = out.trim_end().to_string();
+    let len = text.chars().count();
+    for mark in &mut chords {
+        mark.offset = mark.offset.min(len);
+    }
+    (text, chords)
+}
+
+/// Where the sidecar chords of `song` are stored.
+pub fn chords_path(song: &Path) -> PathBuf {
+    song.with_extension("chords")
+}
+
+/// The timed lines of `song` in singing order, with their inline chords,
+/// or with the sidecar chords when the lyrics have none. Empty when the
+/// song has no lyrics.
+pub fn chord_sheet(song: &Path) -> Result<Vec<ChordLine>, LrcError> {
+    let lrc = lrc_path(song);
+    let mut lines = if lrc.exists() {
+        let (text, _) = read_lrc_text(&lrc)?;
+        timed_lines(&parse_lrc(&text))
+    } else {
+        Vec::new()
+    };
+    let sidecar = chords_path(song);
+    if sidecar.exists() && lines.iter().all(|line| line.chords.is_empty()) {
+        let (text, _) = read_lrc_text(&sidecar)?;
+        place_changes(&mut lines, chord_changes(&parse_lrc(&text)));
+    }
+    Ok(lines)
+}
+
+/// Every timed line with its chords, moved by the `[offset:]` tag.
+fn timed_lines(events: &[LrcEvent]) -> Vec<ChordLine> {
+    let offset = offset_ms(events);
+    let mut lines: Vec<ChordLine> = events
+        .iter()
+        .filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps,
+                text,
+                chords,
+                ..
+            } => Some(timestamps.iter().map(move |&start| ChordLine {
+                start: shift(start, offset),
+                text: text.clone(),
+                chords: chords.clone(),
+            })),
+            LrcEvent::Metadata { .. } => None,
+        })
+        .flatten()
+        .collect();
+    lines.sort_by_key(|line| line.start);
+    lines
+}
+
+/// Chord changes of a sidecar file, in time order, moved by its own
+/// `[offset:]` tag.
+fn chord_changes(events: &[LrcEvent]) -> Vec<(Duration, String)> {
+    let offset = offset_ms(events);
+    let mut changes: Vec<(Duration, String)> = events
+        .iter()
+        .filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps,
+                text,
+                chords,
+                ..
+            } => {
+                // `[00:12.00]Am` and `[00:12.00][Am]` both name one chord
+                let chord = match chords.first() {
+                    Some(mark) => mark.chord.clone(),
+                    None if is_chord(text) => text.clone(),
+                    None => return None,
+                };
+                Some(
+                    timestamps
+                        .iter()
+                        .map(move |&time| (shift(time, offset), chord.clone())),
+                )
+            },
+            LrcEvent::Metadata { .. } => None,
+        })
+        .flatten()
+        .collect();
+    changes.sort_by_key(|(time, _)| *time);
+    changes
+}
+
+/// Put each change on the line sung at its time. Changes before the first
+/// line, or in a song without lyrics, go on lines of their own.
+fn place_changes(lines: &mut Vec<ChordLine>, changes: Vec<(Duration, String)>) {
+    let starts: Vec<Duration> = lines.iter().map(|line| line.start).collect();
+    let mut unplaced = Vec::new();
+    for (time, chord) in changes {
+        let index = starts.partition_point(|&start| start <= time);
+        let Some(line) = index.checked_sub(1).and_then(|index| lines.get_mut(index)) else {
+            unplaced.push((time, chord));
+            continue;
+        };
+        let end = starts
+            .get(index)
+            .copied()
+            .unwrap_or(line.start + Duration::from_secs_f32(LAST_LINE_SECS));
+        let into = (time - line.start).as_secs_f32() / (end - line.start).as_secs_f32().max(0.001);
+        let len = line.text.chars().count();
+        let offset = (into.clamp(0.0, 1.0) * len as f32).round() as usize;
+        line.chords.push(ChordMark { offset, chord });
+    }
+    let own_lines = unplaced.chunks(CHORDS_PER_LINE).map(|chunk| ChordLine {
+        start: chunk[0].0,
+        text: String::new(),
+        chords: chunk
+            .iter()
+            .map(|(_, chord)| ChordMark {
+                offset: 0,
+                chord: chord.clone(),
+            })
+            .collect(),
+    });
+    lines.splice(0..0, own_lines.collect::<Vec<_>>());
+}
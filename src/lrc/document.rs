@@ -0,0 +1,169 @@
+//! Lyrics ready to be looked up by time.
+//!
+//! The lines are put in singing order once, a line with several timestamps
+//! once per timestamp, so finding the line or word sung at a position is a
+//! binary search rather than a walk through the events every frame.
+
+use std::time::Duration;
+
+use super::{timed_lines, LrcEvent, LyricSegment, TimedLine};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LrcDocument {
+    /// Sorted by start
+    lines: Vec<TimedLine>,
+}
+
+impl LrcDocument {
+    /// The lines of parsed LRC, moved by its `[offset:]` tag.
+    pub fn from_events(events: &[LrcEvent]) -> Self {
+        Self {
+            lines: timed_lines(events),
+        }
+    }
+
+    /// Lyrics timed some other way, e.g. by a MIDI file.
+    pub fn from_lines(mut lines: Vec<TimedLine>) -> Self {
+        lines.sort_by_key(|line| line.start);
+        Self { lines }
+    }
+
+    /// Every line in singing order.
+    pub fn lines(&self) -> &[TimedLine] {
+        &self.lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Index in [`lines`](Self::lines) of the line sung at `position`: the
+    /// last one started by then. `None` before the first line.
+    pub fn index_at(&self, position: Duration) -> Option<usize> {
+        self.lines
+            .partition_point(|line| line.start <= position)
+            .checked_sub(1)
+    }
+
+    /// The line sung at `position`, see [`index_at`](Self::index_at).
+    pub fn line_at(&self, position: Duration) -> Option<&TimedLine> {
+        self.lines.get(self.index_at(position)?)
+    }
+
+    /// The first line starting after `position`.
+    pub fn next_line_after(&self, position: Duration) -> Option<&TimedLine> {
+        let next = self.lines.partition_point(|line| line.start <= position);
+        self.lines.get(next)
+    }
+
+    /// The word or syllable sung at `position`, when its line has word
+    /// timestamps: the last one of the line started by then.
+    pub fn segment_at(&self, position: Duration) -> Option<&LyricSegment> {
+        let segments = &self.line_at(position)?.segments;
+        let index = segments
+            .partition_point(|segment| segment.start <= position)
+            .checked_sub(1)?;
+        segments.get(index)
+    }
+
+    /// How many characters of the line sung at `position` have been sung,
+    /// when it has word timestamps. A segment fills in steadily until the
+    /// next one starts; the last one until the next line starts, or at
+    /// once when there is none.
+    pub fn sung_chars(&self, position: Duration) -> Option<usize> {
+        let line = self.line_at(position)?;
+        let first = line.segments.first()?;
+        let Some(segment) = self.segment_at(position) else {
+            return Some(first.offset);
+        };
+        let next = line.segments.iter().find(|next| next.start > position);
+        let (until, to) = match next {
+            Some(next) => (Some(next.start), next.offset),
+            None => (
+                self.next_line_after(position).map(|line| line.start),
+                line.text.chars().count(),
+            ),
+        };
+        let Some(until) = until.filter(|until| *until > segment.start) else {
+            return Some(to);
+        };
+        let progress =
+            (position - segment.start).as_secs_f32() / (until - segment.start).as_secs_f32();
+        let length = to.saturating_sub(segment.offset) as f32;
+        Some(segment.offset + (length * progress.min(1.0)).ceil() as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrc::parse_lrc;
+
+    fn secs(secs: f32) -> Duration {
+        Duration::from_secs_f32(secs)
+    }
+
+    /// Lines at 10 s, 20 s and 30 s once the offset is applied; the first
+    /// and last are one line sung twice.
+    fn document() -> LrcDocument {
+        LrcDocument::from_events(&parse_lrc(
+            "[offset:500]\n\
+             [00:10.50][00:30.50]<00:10.50>Hel<00:11.50>lo\n\
+             [00:20.50]World\n",
+        ))
+    }
+
+    #[test]
+    fn repeated_lines_come_once_per_time_in_order() {
+        let starts: Vec<Duration> = document().lines().iter().map(|line| line.start).collect();
+        assert_eq!(starts, [secs(10.0), secs(20.0), secs(30.0)]);
+    }
+
+    #[test]
+    fn lines_are_found_by_time() {
+        let document = document();
+        assert_eq!(document.index_at(secs(5.0)), None);
+        assert_eq!(document.index_at(secs(10.0)), Some(0));
+        assert_eq!(
+            document.line_at(secs(25.0)).map(|line| line.text.as_str()),
+            Some("World")
+        );
+        assert_eq!(
+            document.next_line_after(secs(20.0)).map(|line| line.start),
+            Some(secs(30.0))
+        );
+        assert!(document.next_line_after(secs(30.0)).is_none());
+    }
+
+    #[test]
+    fn word_timestamps_move_with_the_repeat() {
+        let document = document();
+        let offset = |at| document.segment_at(secs(at)).map(|segment| segment.offset);
+        assert_eq!(offset(10.5), Some(0));
+        assert_eq!(offset(11.2), Some(3));
+        assert_eq!(offset(30.5), Some(0));
+        assert_eq!(offset(31.0), Some(3));
+        assert_eq!(offset(20.5), None);
+    }
+
+    #[test]
+    fn sung_characters_fill_in_between_word_timestamps() {
+        let document = document();
+        assert_eq!(document.sung_chars(secs(9.0)), None);
+        assert_eq!(document.sung_chars(secs(10.0)), Some(0));
+        assert_eq!(document.sung_chars(secs(10.5)), Some(2));
+        // The last word fills in until the next line
+        assert_eq!(document.sung_chars(secs(11.5)), Some(4));
+        assert_eq!(document.sung_chars(secs(19.9)), Some(5));
+        // Lines without word timestamps are not filled in
+        assert_eq!(document.sung_chars(secs(20.5)), None);
+        // The last word of the last line is sung at once
+        assert_eq!(document.sung_chars(secs(31.1)), Some(5));
+    }
+
+    #[test]
+    fn words_before_the_first_timestamp_count_as_sung() {
+        let document = LrcDocument::from_events(&parse_lrc("[00:01.00]la <00:02.00>la\n"));
+        assert_eq!(document.sung_chars(secs(1.5)), Some(3));
+    }
+}
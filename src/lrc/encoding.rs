@@ -0,0 +1,27 @@
+//! Character encoding of LRC files.
+//!
+//! Many lyrics files predate UTF-8 and are stored in a regional code page
+//! (Shift-JIS, GBK, Windows-1252, …) without saying so. A byte order mark
+//! wins when present and valid UTF-8 is taken as is; anything else is
+//! guessed with chardetng, the detector Firefox uses for unlabeled pages.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+
+/// Decode the bytes of an LRC file, returning the text and the encoding
+/// it was read with.
+pub fn decode(bytes: &[u8]) -> (String, &'static Encoding) {
+    if let Some((encoding, bom_length)) = Encoding::for_bom(bytes) {
+        let (text, _) = encoding.decode_without_bom_handling(&bytes[bom_length..]);
+        return (text.into_owned(), encoding);
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return (text.to_string(), UTF_8);
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    let encoding = detector.guess(None, true);
+    let (text, _) = encoding.decode_without_bom_handling(bytes);
+    (text.into_owned(), encoding)
+}
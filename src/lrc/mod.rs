@@ -0,0 +1,273 @@
+//! LRC lyrics files.
+//!
+//! Lyrics for a song live next to its audio file with the `.lrc` extension.
+//! The parser turns a file into a flat list of [`LrcEvent`]s: `[key:value]`
+//! tags and timed lines, in file order, and gathers the standard tags into
+//! [`LrcMetadata`]. Inline chords and enhanced-LRC word
+//! timestamps are taken out of the line text and kept alongside it; see
+//! [`chords`] and [`LyricSegment`]. [`LrcDocument`] looks lines up by
+//! time; [`write_lrc`] turns events back into text. Lines sung more than
+//! once can be split up or merged, see [`expand_repeats`], and lines
+//! without word timestamps can have them estimated, see [`word_timing`].
+
+pub mod bidi;
+pub mod chords;
+mod document;
+pub mod encoding;
+mod parser;
+pub mod romaji;
+pub mod subtitles;
+mod transform;
+pub mod word_timing;
+mod writer;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use encoding_rs::{Encoding, UTF_8};
+
+pub use document::LrcDocument;
+pub use parser::parse_lrc;
+pub use transform::{expand_repeats, merge_repeats};
+pub use writer::{write_lrc, write_lrc_compact};
+
+use self::chords::ChordMark;
+
+#[derive(thiserror::Error, Debug)]
+pub enum LrcError {
+    #[error("Failed to read lyrics file {0}: {1}")]
+    ReadError(PathBuf, String),
+
+    #[error("Failed to write lyrics file {0}: {1}")]
+    WriteError(PathBuf, String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LrcEvent {
+    /// A `[key:value]` tag such as `[ar:Artist]`; the key is lowercased
+    Metadata { key: String, value: String },
+    /// A lyric line with every timestamp it is sung at
+    Line {
+        timestamps: Vec<Duration>,
+        text: String,
+        /// Inline chord annotations, positioned in `text`
+        chords: Vec<ChordMark>,
+        /// Word timestamps, when the line has them
+        segments: Vec<LyricSegment>,
+    },
+}
+
+/// The standard ID tags of an LRC file, from its first tag of each kind.
+/// Tags left empty count as missing.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LrcMetadata {
+    /// `[ar:]`
+    pub artist: Option<String>,
+    /// `[ti:]`
+    pub title: Option<String>,
+    /// `[al:]`
+    pub album: Option<String>,
+    /// `[au:]`, who wrote the song
+    pub author: Option<String>,
+    /// `[length:]`, how long the song is
+    pub length: Option<Duration>,
+    /// `[offset:]` in milliseconds, 0 without one. Positive values make
+    /// every line come earlier.
+    pub offset_ms: i64,
+    /// `[by:]`, who made the LRC file
+    pub by: Option<String>,
+    /// `[re:]`, the program the file was made with
+    pub re: Option<String>,
+    /// `[ve:]`, the version of that program
+    pub ve: Option<String>,
+}
+
+/// Where a word or syllable of a line starts being sung, from an
+/// enhanced-LRC `<mm:ss.xx>` timestamp.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LyricSegment {
+    /// When it is sung, for the line's first timestamp
+    pub start: Duration,
+    /// Position in the line text, in characters
+    pub offset: usize,
+}
+
+/// A lyric line at one of the times it is sung.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimedLine {
+    pub start: Duration,
+    pub text: String,
+    /// Word timestamps, moved to this time of the line
+    pub segments: Vec<LyricSegment>,
+}
+
+/// Every timed line in singing order, moved by the file's `[offset:]`
+/// tag. A line with several timestamps appears once per timestamp, its
+/// word timestamps moved along.
+pub fn timed_lines(events: &[LrcEvent]) -> Vec<TimedLine> {
+    let offset = offset_ms(events);
+    let mut lines: Vec<TimedLine> = events
+        .iter()
+        .filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps,
+                text,
+                segments,
+                ..
+            } => {
+                let first = timestamps.first().copied().unwrap_or_default();
+                Some(timestamps.iter().map(move |&start| {
+                    let start = shift(start, offset);
+                    TimedLine {
+                        start,
+                        text: text.clone(),
+                        segments: segments
+                            .iter()
+                            .map(|segment| LyricSegment {
+                                start: start + segment.start.saturating_sub(first),
+                                offset: segment.offset,
+                            })
+                            .collect(),
+                    }
+                }))
+            },
+            LrcEvent::Metadata { .. } => None,
+        })
+        .flatten()
+        .collect();
+    lines.sort_by_key(|line| line.start);
+    lines
+}
+
+/// The `[offset:]` tag in milliseconds, 0 without one. Positive values
+/// make every line come earlier.
+pub fn offset_ms(events: &[LrcEvent]) -> i64 {
+    LrcMetadata::from_events(events).offset_ms
+}
+
+/// `time` made earlier by `offset_ms` milliseconds, later when negative.
+pub fn shift(time: Duration, offset_ms: i64) -> Duration {
+    let offset = Duration::from_millis(offset_ms.unsigned_abs());
+    if offset_ms >= 0 {
+        time.saturating_sub(offset)
+    } else {
+        time + offset
+    }
+}
+
+/// Write `offset_ms` into the `[offset:]` tag of an LRC file, replacing
+/// the tag it has or adding one at the top; 0 removes it. The file is
+/// saved as UTF-8.
+pub fn set_offset_tag(path: &Path, offset_ms: i64) -> Result<(), LrcError> {
+    let (text, _) = read_lrc_text(path)?;
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let is_offset_tag = |line: &str| {
+        let line = line.trim_start();
+        line.get(..8)
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("[offset:"))
+    };
+    let mut lines: Vec<&str> = text.lines().filter(|line| !is_offset_tag(line)).collect();
+    let tag = format!("[offset:{offset_ms:+}]");
+    if offset_ms != 0 {
+        lines.insert(0, &tag);
+    }
+    let mut rewritten = lines.join(newline);
+    rewritten.push_str(newline);
+    fs::write(path, rewritten).map_err(|e| LrcError::WriteError(path.to_path_buf(), e.to_string()))
+}
+
+/// Where the lyrics of `song` are stored.
+pub fn lrc_path(song: &Path) -> PathBuf {
+    song.with_extension("lrc")
+}
+
+/// Where the `language` translation of the lyrics of `song` is stored,
+/// e.g. `song.en.lrc`.
+pub fn translation_path(song: &Path, language: &str) -> PathBuf {
+    song.with_extension(format!("{language}.lrc"))
+}
+
+/// Languages `song` has translated lyrics in, as named in their files.
+pub fn translation_languages(song: &Path) -> Vec<String> {
+    let (Some(folder), Some(stem)) = (
+        song.parent(),
+        song.file_stem().and_then(|stem| stem.to_str()),
+    ) else {
+        return Vec::new();
+    };
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut languages: Vec<String> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if !is_lrc_file(&path) {
+                return None;
+            }
+            let language = path
+                .file_stem()?
+                .to_str()?
+                .strip_prefix(stem)?
+                .strip_prefix('.')?;
+            let valid = !language.is_empty() && !language.contains('.') && language != "romaji";
+            valid.then(|| language.to_string())
+        })
+        .collect();
+    languages.sort();
+    languages
+}
+
+/// Whether `path` looks like an LRC file.
+pub fn is_lrc_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("lrc"))
+}
+
+/// Read and parse an LRC file, whatever its encoding.
+pub fn parse_lrc_file(path: &Path) -> Result<Vec<LrcEvent>, LrcError> {
+    let (text, _) = read_lrc_text(path)?;
+    Ok(parse_lrc(&text))
+}
+
+/// Write `events` to `path` as canonical LRC in UTF-8, replacing what is
+/// there. `compact` writes repeated lines once, see [`write_lrc_compact`].
+pub fn write_lrc_file(path: &Path, events: &[LrcEvent], compact: bool) -> Result<(), LrcError> {
+    let text = if compact {
+        write_lrc_compact(events)
+    } else {
+        write_lrc(events)
+    };
+    fs::write(path, text).map_err(|e| LrcError::WriteError(path.to_path_buf(), e.to_string()))
+}
+
+/// Read an LRC file as text, with the encoding it was detected in.
+pub fn read_lrc_text(path: &Path) -> Result<(String, &'static Encoding), LrcError> {
+    let bytes =
+        fs::read(path).map_err(|e| LrcError::ReadError(path.to_path_buf(), e.to_string()))?;
+    Ok(encoding::decode(&bytes))
+}
+
+/// Rewrite an LRC file as UTF-8 without a byte order mark. Returns the
+/// encoding it was stored in; files already in UTF-8 are left untouched.
+pub fn resave_as_utf8(path: &Path) -> Result<&'static Encoding, LrcError> {
+    let bytes =
+        fs::read(path).map_err(|e| LrcError::ReadError(path.to_path_buf(), e.to_string()))?;
+    let (text, encoding) = encoding::decode(&bytes);
+    if encoding != UTF_8 || Encoding::for_bom(&bytes).is_some() {
+        fs::write(path, text)
+            .map_err(|e| LrcError::WriteError(path.to_path_buf(), e.to_string()))?;
+    }
+    Ok(encoding)
+}
+
+/// Value of the first `key` tag, compared case-insensitively.
+pub fn metadata<'a>(events: &'a [LrcEvent], key: &str) -> Option<&'a str> {
+    events.iter().find_map(|event| match event {
+        LrcEvent::Metadata { key: tag, value } if tag.eq_ignore_ascii_case(key) => {
+            Some(value.as_str())
+        },
+        _ => None,
+    })
+}
@@ -0,0 +1,216 @@
+//! Line-based LRC parser.
+//!
+//! Accepts the common dialects: `[mm:ss]`, `[mm:ss.xx]`, `[mm:ss.xxx]` and
+//! `[mm:ss:xx]` timestamps, several timestamps in front of one line, and
+//! tags with or without spaces around the colon. Enhanced-LRC word
+//! timestamps (`<mm:ss.xx>`) and inline chords (`[Am]`) are taken out of
+//! the line text and kept alongside it. Anything that is neither a tag nor
+//! a timed line is ignored.
+
+use std::time::Duration;
+
+use super::chords::{extract_chords, ChordMark};
+use super::{metadata, LrcEvent, LrcMetadata, LyricSegment};
+
+pub fn parse_lrc(text: &str) -> Vec<LrcEvent> {
+    text.lines().filter_map(parse_line).collect()
+}
+
+impl LrcMetadata {
+    /// The standard tags among `events`.
+    pub fn from_events(events: &[LrcEvent]) -> Self {
+        let tag = |key| {
+            metadata(events, key)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+        Self {
+            artist: tag("ar"),
+            title: tag("ti"),
+            album: tag("al"),
+            author: tag("au"),
+            length: metadata(events, "length").and_then(parse_timestamp),
+            offset_ms: metadata(events, "offset")
+                .and_then(|value| value.trim().parse().ok())
+                .unwrap_or(0),
+            by: tag("by"),
+            re: tag("re"),
+            ve: tag("ve"),
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Option<LrcEvent> {
+    let mut rest = line.trim().trim_start_matches('\u{feff}');
+    let mut timestamps = Vec::new();
+
+    while let Some(inner) = rest.strip_prefix('[') {
+        let end = inner.find(']')?;
+        let (content, after) = (&inner[..end], &inner[end + 1..]);
+        match parse_timestamp(content) {
+            Some(time) => timestamps.push(time),
+            None if timestamps.is_empty() => return parse_tag(content),
+            None => break,
+        }
+        rest = after;
+    }
+
+    if timestamps.is_empty() {
+        return None;
+    }
+    let (text, chords) = extract_chords(rest.trim());
+    let (text, segments, moved) = extract_segments(&text);
+    let chords = chords
+        .into_iter()
+        .map(|mark| ChordMark {
+            offset: moved[mark.offset.min(moved.len() - 1)],
+            ..mark
+        })
+        .collect();
+    Some(LrcEvent::Line {
+        timestamps,
+        text,
+        chords,
+        segments,
+    })
+}
+
+/// `mm:ss`, `mm:ss.f…` or `mm:ss:ff`.
+fn parse_timestamp(content: &str) -> Option<Duration> {
+    let (minutes, seconds) = content.trim().split_once(':')?;
+    let minutes: u64 = minutes.parse().ok()?;
+    let (whole, fraction) = match seconds.split_once(['.', ':']) {
+        Some((whole, fraction)) => (whole, fraction),
+        None => (seconds, ""),
+    };
+    let whole: u64 = whole.parse().ok()?;
+    if whole >= 60 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Scale "5" to 500 ms, "05" to 50 ms, "005" to 5 ms
+    let millis = match fraction.len() {
+        0 => 0,
+        len => {
+            let digits = &fraction[..len.min(3)];
+            digits.parse::<u64>().ok()? * 10_u64.pow(3 - digits.len() as u32)
+        },
+    };
+    let total = minutes
+        .checked_mul(60)?
+        .checked_add(whole)?
+        .checked_mul(1000)?
+        .checked_add(millis)?;
+    Some(Duration::from_millis(total))
+}
+
+/// `[key:value]`, keys being letters only.
+fn parse_tag(content: &str) -> Option<LrcEvent> {
+    let (key, value) = content.split_once(':')?;
+    let key = key.trim();
+    if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    Some(LrcEvent::Metadata {
+        key: key.to_ascii_lowercase(),
+        value: value.trim().to_string(),
+    })
+}
+
+/// Take the `<mm:ss.xx>` markers out of `text` as segments starting where
+/// each marker was, leaving single spaces between words. Also returns the
+/// new position of every character position of `text`, one past the end
+/// included, so marks placed in `text` can follow.
+fn extract_segments(text: &str) -> (String, Vec<LyricSegment>, Vec<usize>) {
+    let mut out = String::with_capacity(text.len());
+    let mut segments = Vec::new();
+    let mut moved = Vec::with_capacity(text.len() + 1);
+    let mut len = 0;
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        let marker = rest
+            .strip_prefix('<')
+            .and_then(|inner| Some((inner, inner.find('>')?)))
+            .and_then(|(inner, end)| Some((parse_timestamp(&inner[..end])?, &inner[end + 1..])));
+        if let Some((start, after)) = marker {
+            // Positions inside the marker move to where it was
+            let skipped = rest.len() - after.len();
+            moved.extend(std::iter::repeat_n(len, rest[..skipped].chars().count()));
+            // A later marker at the same place replaces an earlier one
+            segments.retain(|segment: &LyricSegment| segment.offset != len);
+            segments.push(LyricSegment { start, offset: len });
+            rest = after;
+            continue;
+        }
+        moved.push(len);
+        if !c.is_whitespace() || (len > 0 && !out.ends_with(' ')) {
+            out.push(if c.is_whitespace() { ' ' } else { c });
+            len += 1;
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    if out.ends_with(' ') {
+        out.pop();
+        len -= 1;
+    }
+    moved.push(len);
+    for position in &mut moved {
+        *position = (*position).min(len);
+    }
+    for segment in &mut segments {
+        segment.offset = segment.offset.min(len);
+    }
+    (out, segments, moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn starts(text: &str) -> Vec<Duration> {
+        parse_lrc(text)
+            .into_iter()
+            .flat_map(|event| match event {
+                LrcEvent::Line { timestamps, .. } => timestamps,
+                LrcEvent::Metadata { .. } => Vec::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn timestamps_are_read_in_every_dialect() {
+        assert_eq!(
+            starts("[01:02]a\n[01:02.5]b\n[01:02.05]c\n[01:02.005]d\n[01:02:50]e\n"),
+            [
+                Duration::from_millis(62_000),
+                Duration::from_millis(62_500),
+                Duration::from_millis(62_050),
+                Duration::from_millis(62_005),
+                Duration::from_millis(62_500),
+            ]
+        );
+    }
+
+    #[test]
+    fn tags_may_have_spaces_around_the_colon() {
+        let events =
+            parse_lrc("[ar: Queen]\n[ti :Bohemian Rhapsody ]\n[AL:A Night at the Opera]\n");
+        let metadata = LrcMetadata::from_events(&events);
+        assert_eq!(metadata.artist.as_deref(), Some("Queen"));
+        assert_eq!(metadata.title.as_deref(), Some("Bohemian Rhapsody"));
+        assert_eq!(metadata.album.as_deref(), Some("A Night at the Opera"));
+    }
+
+    #[test]
+    fn offsets_may_have_a_sign() {
+        let offset = |text| LrcMetadata::from_events(&parse_lrc(text)).offset_ms;
+        assert_eq!(offset("[offset:+250]\n"), 250);
+        assert_eq!(offset("[offset: -120]\n"), -120);
+        assert_eq!(offset("[offset:soon]\n"), 0);
+    }
+
+    #[test]
+    fn out_of_range_timestamps_are_not_lines() {
+        assert_eq!(starts("[00:60.00]a\n[99999999999999999:00.00]b\n"), []);
+        assert_eq!(parse_lrc("[18446744073709551615:59.999]c\n"), []);
+    }
+}
@@ -0,0 +1,215 @@
+//! Romaji for Japanese lyrics.
+//!
+//! The reading of a line comes from a companion `song.romaji.lrc` when the
+//! song has one. Otherwise lines written in kana alone are transliterated
+//! here (Hepburn); lines with kanji are left out, as their reading needs a
+//! dictionary.
+
+use std::path::{Path, PathBuf};
+
+/// Companion file with the romaji of the lyrics of `song`.
+pub fn romaji_path(song: &Path) -> PathBuf {
+    song.with_extension("romaji.lrc")
+}
+
+/// Whether `text` has any kana, so it would be read in romaji.
+pub fn has_kana(text: &str) -> bool {
+    text.chars().any(|c| hiragana(c).is_some())
+}
+
+/// `text` in Hepburn romaji, or `None` when it has no kana or has kanji.
+pub fn transliterate(text: &str) -> Option<String> {
+    if !has_kana(text) || text.chars().any(is_kanji) {
+        return None;
+    }
+
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len() * 2);
+    let mut index = 0;
+    // A small tsu doubles the consonant that follows
+    let mut double_next = false;
+    while let Some(&c) = chars.get(index) {
+        index += 1;
+        let Some(kana) = hiragana(c) else {
+            out.push_str(&punctuation(c));
+            double_next = false;
+            continue;
+        };
+        match kana {
+            'っ' => {
+                double_next = true;
+                continue;
+            },
+            'ー' => {
+                if let Some(vowel) = out.chars().last().filter(|c| "aeiou".contains(*c)) {
+                    out.push(vowel);
+                }
+                continue;
+            },
+            _ => {},
+        }
+        let Some(base) = syllable(kana) else {
+            out.push(c);
+            continue;
+        };
+        let mut romaji = base.to_string();
+        // Combined with a following small kana: きゃ kya, ファ fa
+        if let Some(small) = chars.get(index).and_then(|&next| hiragana(next)) {
+            if let Some(combined) = combine(base, small) {
+                romaji = combined;
+                index += 1;
+            }
+        }
+        if romaji == "n" {
+            let next = chars
+                .get(index)
+                .and_then(|&next| hiragana(next))
+                .and_then(syllable);
+            if next.is_some_and(|next| next.starts_with(['a', 'i', 'u', 'e', 'o', 'y'])) {
+                romaji.push('\'');
+            }
+        }
+        if std::mem::take(&mut double_next) {
+            match romaji.chars().next() {
+                Some('c') => out.push('t'),
+                Some(first) if !"aiueon".contains(first) => out.push(first),
+                _ => {},
+            }
+        }
+        out.push_str(&romaji);
+    }
+    Some(out)
+}
+
+/// `c` as hiragana, katakana folded onto it; `ー` is kept.
+fn hiragana(c: char) -> Option<char> {
+    match u32::from(c) {
+        0x3041..=0x3096 | 0x30FC => Some(c),
+        0x30A1..=0x30F6 => char::from_u32(u32::from(c) - 0x60),
+        _ => None,
+    }
+}
+
+fn is_kanji(c: char) -> bool {
+    matches!(u32::from(c), 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF)
+}
+
+/// Japanese punctuation and spaces in their Latin form.
+fn punctuation(c: char) -> String {
+    match c {
+        '\u{3000}' | '・' => " ".to_string(),
+        '、' => ",".to_string(),
+        '。' => ".".to_string(),
+        '「' | '」' | '『' | '』' => "\"".to_string(),
+        '！' => "!".to_string(),
+        '？' => "?".to_string(),
+        '～' | '〜' => "~".to_string(),
+        c => c.to_string(),
+    }
+}
+
+/// A kana with what comes after it merged in, when `small` is one of the
+/// small kana that combine.
+fn combine(base: &str, small: char) -> Option<String> {
+    let vowel = match small {
+        'ゃ' => "ya",
+        'ゅ' => "yu",
+        'ょ' => "yo",
+        'ぁ' => "a",
+        'ぃ' => "i",
+        'ぅ' => "u",
+        'ぇ' => "e",
+        'ぉ' => "o",
+        _ => return None,
+    };
+    let stem = &base[..base.len() - 1];
+    Some(match (stem, vowel) {
+        // しゃ sha, ちゅ chu, じょ jo
+        ("sh" | "ch" | "j", _) => format!("{stem}{}", vowel.trim_start_matches('y')),
+        (_, "ya" | "yu" | "yo") if base.ends_with('i') && !stem.is_empty() => {
+            format!("{stem}{vowel}")
+        },
+        (_, "ya" | "yu" | "yo") => return None,
+        // うぃ wi; other vowels keep the small one as its own sound
+        ("", _) if base == "u" => format!("w{vowel}"),
+        ("", _) => return None,
+        // ファ fa, ティ ti
+        _ => format!("{stem}{vowel}"),
+    })
+}
+
+/// Hepburn reading of a single hiragana.
+fn syllable(kana: char) -> Option<&'static str> {
+    Some(match kana {
+        'あ' | 'ぁ' => "a",
+        'い' | 'ぃ' | 'ゐ' => "i",
+        'う' | 'ぅ' => "u",
+        'え' | 'ぇ' | 'ゑ' => "e",
+        'お' | 'ぉ' | 'を' => "o",
+        'か' | 'ゕ' => "ka",
+        'き' => "ki",
+        'く' => "ku",
+        'け' | 'ゖ' => "ke",
+        'こ' => "ko",
+        'が' => "ga",
+        'ぎ' => "gi",
+        'ぐ' => "gu",
+        'げ' => "ge",
+        'ご' => "go",
+        'さ' => "sa",
+        'し' => "shi",
+        'す' => "su",
+        'せ' => "se",
+        'そ' => "so",
+        'ざ' => "za",
+        'じ' | 'ぢ' => "ji",
+        'ず' | 'づ' => "zu",
+        'ぜ' => "ze",
+        'ぞ' => "zo",
+        'た' => "ta",
+        'ち' => "chi",
+        'つ' => "tsu",
+        'て' => "te",
+        'と' => "to",
+        'だ' => "da",
+        'で' => "de",
+        'ど' => "do",
+        'な' => "na",
+        'に' => "ni",
+        'ぬ' => "nu",
+        'ね' => "ne",
+        'の' => "no",
+        'は' => "ha",
+        'ひ' => "hi",
+        'ふ' => "fu",
+        'へ' => "he",
+        'ほ' => "ho",
+        'ば' => "ba",
+        'び' => "bi",
+        'ぶ' => "bu",
+        'べ' => "be",
+        'ぼ' => "bo",
+        'ぱ' => "pa",
+        'ぴ' => "pi",
+        'ぷ' => "pu",
+        'ぺ' => "pe",
+        'ぽ' => "po",
+        'ま' => "ma",
+        'み' => "mi",
+        'む' => "mu",
+        'め' => "me",
+        'も' => "mo",
+        'や' | 'ゃ' => "ya",
+        'ゆ' | 'ゅ' => "yu",
+        'よ' | 'ょ' => "yo",
+        'ら' => "ra",
+        'り' => "ri",
+        'る' => "ru",
+        'れ' => "re",
+        'ろ' => "ro",
+        'わ' | 'ゎ' => "wa",
+        'ん' => "n",
+        'ゔ' => "vu",
+        _ => return None,
+    })
+}
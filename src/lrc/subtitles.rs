@@ -0,0 +1,448 @@
+//! Subtitles converted to LRC lyrics.
+//!
+//! Parses WebVTT and SRT, the formats ffmpeg and yt-dlp hand out subtitles
+//! in, into timed cues. Styling tags and the rolling duplicates of
+//! auto-generated captions are removed, so the cues read as lyric lines.
+//!
+//! The other way round, timed lyrics are exported as SRT or ASS for video
+//! editors and OBS; ASS keeps word timestamps as karaoke tags.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::writer::format_timestamp;
+use super::{read_lrc_text, LrcDocument, LrcError, LrcEvent, LrcMetadata, TimedLine};
+
+/// Extensions of the subtitle files that can be converted.
+const EXTENSIONS: [&str; 2] = ["vtt", "srt"];
+
+/// Gaps between cues longer than this get an empty line, so the previous
+/// lyric does not stay on screen through an instrumental break.
+const BLANK_GAP: Duration = Duration::from_secs(2);
+
+/// Longest an exported line stays up when the next one is far off.
+const MAX_CUE: Duration = Duration::from_secs(8);
+
+/// Subtitle formats lyrics can be exported to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubtitleFormat {
+    Srt,
+    /// Advanced SubStation Alpha, with karaoke tags for word timestamps
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub const ALL: [Self; 2] = [Self::Srt, Self::Ass];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Srt => "SRT",
+            Self::Ass => "ASS",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Srt => "srt",
+            Self::Ass => "ass",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubtitleCue {
+    pub start: Duration,
+    pub end: Duration,
+    pub text: String,
+}
+
+/// Parse WebVTT or SRT: the cue numbers of SRT are skipped like VTT cue
+/// identifiers. Cues without text are dropped, as are cues repeating the
+/// previous one. Leading lines carried over from the previous cue, as
+/// auto-generated captions roll up, are left out.
+pub fn parse_subtitles(text: &str) -> Vec<SubtitleCue> {
+    let mut cues: Vec<SubtitleCue> = Vec::new();
+    let mut previous: Vec<String> = Vec::new();
+    let mut lines = text.lines().map(|line| line.trim_start_matches('\u{feff}'));
+
+    while let Some(line) = lines.next() {
+        let Some((start, end)) = parse_timing(line) else {
+            continue;
+        };
+        let cue_lines: Vec<String> = lines
+            .by_ref()
+            .take_while(|line| !line.trim().is_empty())
+            .map(strip_markup)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let text = cue_lines
+            .iter()
+            .skip_while(|line| previous.contains(line))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        previous = cue_lines;
+        if text.is_empty() || cues.last().is_some_and(|last| last.text == text) {
+            continue;
+        }
+        cues.push(SubtitleCue { start, end, text });
+    }
+    cues
+}
+
+/// LRC text with one line per cue.
+pub fn cues_to_lrc(cues: &[SubtitleCue]) -> String {
+    let mut lrc = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        lrc += &format!("[{}]{}\n", format_timestamp(cue.start), cue.text);
+        let next = cues.get(index + 1).map(|next| next.start);
+        if next.is_none_or(|next| next.saturating_sub(cue.end) > BLANK_GAP) {
+            lrc += &format!("[{}]\n", format_timestamp(cue.end));
+        }
+    }
+    lrc
+}
+
+/// Cues of the subtitle file at `path`, in whatever encoding it is stored.
+pub fn read_subtitle_file(path: &Path) -> Result<Vec<SubtitleCue>, LrcError> {
+    let (text, _) = read_lrc_text(path)?;
+    Ok(parse_subtitles(&text))
+}
+
+/// Subtitle files saved next to `song` under its name, as yt-dlp writes
+/// them: `Song.vtt`, or `Song.en.vtt` with the language in between. Each
+/// comes with that language, empty when there is none.
+pub fn sidecar_files(song: &Path) -> Vec<(PathBuf, String)> {
+    let (Some(folder), Some(stem)) = (song.parent(), song.file_stem()) else {
+        return Vec::new();
+    };
+    let stem = stem.to_string_lossy();
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(PathBuf, String)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            let extension = path.extension()?.to_str()?.to_lowercase();
+            if !EXTENSIONS.contains(&extension.as_str()) {
+                return None;
+            }
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            let language = match name.strip_prefix(stem.as_ref())? {
+                "" => String::new(),
+                rest => rest.strip_prefix('.')?.to_string(),
+            };
+            Some((path, language))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Subtitles of the lyrics in `events`, moved by their `[offset:]` tag.
+/// Each line is shown until the next one starts, for at most [`MAX_CUE`];
+/// empty lines only end the one before.
+pub fn export_subtitles(events: &[LrcEvent], format: SubtitleFormat) -> String {
+    let metadata = LrcMetadata::from_events(events);
+    let document = LrcDocument::from_events(events);
+    let lines = document.lines();
+    let cues: Vec<(&TimedLine, Duration)> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.text.trim().is_empty())
+        .map(|(index, line)| {
+            let next = lines
+                .get(index + 1)
+                .map(|next| next.start)
+                .or(metadata.length)
+                .filter(|next| *next > line.start);
+            let end = next.map_or(line.start + MAX_CUE, |next| next.min(line.start + MAX_CUE));
+            (line, end)
+        })
+        .collect();
+    match format {
+        SubtitleFormat::Srt => to_srt(&cues),
+        SubtitleFormat::Ass => to_ass(&cues, metadata.title.as_deref()),
+    }
+}
+
+/// Export the lyrics in `events` to the subtitle file at `path`.
+pub fn write_subtitle_file(
+    path: &Path,
+    events: &[LrcEvent],
+    format: SubtitleFormat,
+) -> Result<(), LrcError> {
+    fs::write(path, export_subtitles(events, format))
+        .map_err(|e| LrcError::WriteError(path.to_path_buf(), e.to_string()))
+}
+
+fn to_srt(cues: &[(&TimedLine, Duration)]) -> String {
+    let mut srt = String::new();
+    for (number, (line, end)) in cues.iter().enumerate() {
+        srt += &format!(
+            "{}\n{} --> {}\n{}\n\n",
+            number + 1,
+            srt_time(line.start),
+            srt_time(*end),
+            line.text.trim()
+        );
+    }
+    srt
+}
+
+fn to_ass(cues: &[(&TimedLine, Duration)], title: Option<&str>) -> String {
+    let mut ass = format!(
+        "[Script Info]\n\
+         Title: {}\n\
+         ScriptType: v4.00+\n\
+         PlayResX: 1920\n\
+         PlayResY: 1080\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, \
+         BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, \
+         BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Default,Arial,72,&H0000FFFF,&H00FFFFFF,&H00000000,&H80000000,-1,0,0,0,\
+         100,100,0,0,1,3,0,2,60,60,80,1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        title.unwrap_or("Lyrics")
+    );
+    for (line, end) in cues {
+        ass += &format!(
+            "Dialogue: 0,{},{},Default,,0,0,0,,{}\n",
+            ass_time(line.start),
+            ass_time(*end),
+            karaoke_text(line, *end)
+        );
+    }
+    ass
+}
+
+/// Line text for ASS, each word timestamp turned into a `{\k}` tag lasting
+/// until the next one, the last until `end`.
+fn karaoke_text(line: &TimedLine, end: Duration) -> String {
+    let chars: Vec<char> = line
+        .text
+        .chars()
+        .map(|c| match c {
+            '{' => '(',
+            '}' => ')',
+            c => c,
+        })
+        .collect();
+    let Some(first) = line.segments.first() else {
+        return chars.iter().collect();
+    };
+    let mut text: String = chars[..first.offset.min(chars.len())].iter().collect();
+    for (index, segment) in line.segments.iter().enumerate() {
+        let next = line.segments.get(index + 1);
+        let until = next.map_or(end, |next| next.start);
+        let to = next
+            .map_or(chars.len(), |next| next.offset)
+            .min(chars.len());
+        let centis = until.saturating_sub(segment.start).as_millis() / 10;
+        text += &format!("{{\\k{centis}}}");
+        text.extend(&chars[segment.offset.min(to)..to]);
+    }
+    text
+}
+
+/// `hh:mm:ss,mmm`
+fn srt_time(time: Duration) -> String {
+    let millis = time.as_millis();
+    format!(
+        "{:02}:{:02}:{:02},{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// `h:mm:ss.cc`
+fn ass_time(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+/// `00:01:02.500 --> 00:01:04.000 align:start` → start and end.
+fn parse_timing(line: &str) -> Option<(Duration, Duration)> {
+    let (start, rest) = line.split_once("-->")?;
+    let end = rest.split_whitespace().next()?;
+    Some((parse_time(start.trim())?, parse_time(end)?))
+}
+
+/// `hh:mm:ss.ttt` or `mm:ss.ttt`; SRT's comma separator is accepted too.
+fn parse_time(text: &str) -> Option<Duration> {
+    let (clock, millis) = text.split_once(['.', ','])?;
+    let mut seconds = 0_u64;
+    for part in clock.split(':') {
+        seconds = seconds * 60 + part.parse::<u64>().ok()?;
+    }
+    let millis: u64 = millis.get(..3)?.parse().ok()?;
+    Some(Duration::from_millis(seconds * 1000 + millis))
+}
+
+/// Drop `<…>` tags (voices, colours, word timings) and the `{\an8}`
+/// overrides of SRT, and decode entities.
+fn strip_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut in_tag = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '<' => in_tag = true,
+            '{' if chars.peek() == Some(&'\\') => in_tag = true,
+            '>' | '}' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {},
+        }
+    }
+    text.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrc::parse_lrc;
+
+    const LYRICS: &str = "[ti:Song]\n\
+                          [length:00:20.00]\n\
+                          [00:01.00]<00:01.00>Hello <00:01.50>{you}\n\
+                          [00:03.00]\n\
+                          [00:04.00]Last\n";
+
+    fn texts(cues: &[SubtitleCue]) -> Vec<&str> {
+        cues.iter().map(|cue| cue.text.as_str()).collect()
+    }
+
+    #[test]
+    fn srt_cues_are_parsed_without_overrides() {
+        let srt = "\u{feff}1\n\
+                   00:00:01,500 --> 00:00:03,000\n\
+                   {\\an8}Hello <i>darkness</i>\n\
+                   my old friend\n\
+                   \n\
+                   2\n\
+                   00:00:04,000 --> 00:00:05,250\n\
+                   Tom &amp; Jerry\n";
+        let cues = parse_subtitles(srt);
+        assert_eq!(
+            cues,
+            [
+                SubtitleCue {
+                    start: Duration::from_millis(1500),
+                    end: Duration::from_millis(3000),
+                    text: "Hello darkness my old friend".to_string(),
+                },
+                SubtitleCue {
+                    start: Duration::from_millis(4000),
+                    end: Duration::from_millis(5250),
+                    text: "Tom & Jerry".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rolling_captions_keep_only_what_is_new() {
+        let vtt = "WEBVTT\n\
+                   Kind: captions\n\
+                   \n\
+                   00:00:01.000 --> 00:00:03.000 align:start position:0%\n\
+                   hello<00:00:01.500><c> world</c>\n\
+                   \n\
+                   00:00:03.000 --> 00:00:03.010 align:start position:0%\n\
+                   hello world\n\
+                   \u{20}\n\
+                   \n\
+                   00:00:03.010 --> 00:00:05.000 align:start position:0%\n\
+                   hello world\n\
+                   how<00:00:03.500><c> are</c><00:00:04.000><c> you</c>\n\
+                   \n\
+                   00:00:05.000 --> 00:00:05.010 align:start position:0%\n\
+                   how are you\n";
+        assert_eq!(texts(&parse_subtitles(vtt)), ["hello world", "how are you"]);
+    }
+
+    #[test]
+    fn repeated_and_empty_cues_are_dropped() {
+        let vtt = "WEBVTT\n\n\
+                   00:01.000 --> 00:02.000\nLa la\n\n\
+                   00:02.000 --> 00:03.000\nLa la\n\n\
+                   00:03.000 --> 00:04.000\n<c></c>\n\n\
+                   00:04.000 --> 00:05.000\nLa di da\n";
+        let cues = parse_subtitles(vtt);
+        assert_eq!(texts(&cues), ["La la", "La di da"]);
+        assert_eq!(cues[1].start, Duration::from_secs(4));
+    }
+
+    #[test]
+    fn long_gaps_get_an_empty_line() {
+        let cues = parse_subtitles(
+            "00:00:01.000 --> 00:00:02.000\nOne\n\n\
+             00:00:02.500 --> 00:00:03.000\nTwo\n\n\
+             00:00:10.000 --> 00:00:11.000\nThree\n",
+        );
+        assert_eq!(
+            cues_to_lrc(&cues),
+            "[00:01.00]One\n[00:02.50]Two\n[00:03.00]\n[00:10.00]Three\n[00:11.00]\n"
+        );
+    }
+
+    #[test]
+    fn srt_export_ends_lines_at_the_next_or_the_song_length() {
+        assert_eq!(
+            export_subtitles(&parse_lrc(LYRICS), SubtitleFormat::Srt),
+            "1\n00:00:01,000 --> 00:00:03,000\nHello {you}\n\n\
+             2\n00:00:04,000 --> 00:00:12,000\nLast\n\n"
+        );
+    }
+
+    #[test]
+    fn ass_export_turns_word_timestamps_into_karaoke_tags() {
+        let ass = export_subtitles(&parse_lrc(LYRICS), SubtitleFormat::Ass);
+        assert!(ass.contains("Title: Song\n"));
+        let dialogue: Vec<&str> = ass
+            .lines()
+            .filter(|line| line.starts_with("Dialogue:"))
+            .collect();
+        assert_eq!(
+            dialogue,
+            [
+                "Dialogue: 0,0:00:01.00,0:00:03.00,Default,,0,0,0,,{\\k50}Hello {\\k150}(you)",
+                "Dialogue: 0,0:00:04.00,0:00:12.00,Default,,0,0,0,,Last",
+            ]
+        );
+    }
+
+    #[test]
+    fn export_follows_the_offset_tag() {
+        let srt = export_subtitles(
+            &parse_lrc("[offset:-1500]\n[00:01.00]Late\n"),
+            SubtitleFormat::Srt,
+        );
+        assert_eq!(srt, "1\n00:00:02,500 --> 00:00:10,500\nLate\n\n");
+    }
+
+    #[test]
+    fn times_are_formatted_past_an_hour() {
+        let time = Duration::from_millis(3_723_004);
+        assert_eq!(srt_time(time), "01:02:03,004");
+        assert_eq!(ass_time(time), "1:02:03.00");
+    }
+}
@@ -0,0 +1,168 @@
+//! Rewriting lines sung more than once.
+//!
+//! LRC can give a repeated line, typically a chorus, several timestamps
+//! (`[01:00.00][02:00.00]line`) or one line per time. [`expand_repeats`]
+//! turns the first form into the second, [`merge_repeats`] the second into
+//! the first. Tags stay ahead of the lines, in their order; lines come in
+//! singing order.
+
+use std::time::Duration;
+
+use super::{LrcEvent, LyricSegment};
+
+/// One line event per timestamp, word timestamps moved along with it.
+pub fn expand_repeats(events: &[LrcEvent]) -> Vec<LrcEvent> {
+    let mut tags = Vec::new();
+    let mut lines = Vec::new();
+    for event in events {
+        let LrcEvent::Line {
+            timestamps,
+            text,
+            chords,
+            segments,
+        } = event
+        else {
+            tags.push(event.clone());
+            continue;
+        };
+        let first = timestamps.first().copied().unwrap_or_default();
+        for &start in timestamps {
+            lines.push(LrcEvent::Line {
+                timestamps: vec![start],
+                text: text.clone(),
+                chords: chords.clone(),
+                segments: segments
+                    .iter()
+                    .map(|segment| LyricSegment {
+                        start: start + segment.start.saturating_sub(first),
+                        offset: segment.offset,
+                    })
+                    .collect(),
+            });
+        }
+    }
+    lines.sort_by_key(first_timestamp);
+    tags.extend(lines);
+    tags
+}
+
+/// One line event per distinct line, with every timestamp it is sung at.
+/// Lines are the same when their text, chords and word timing within the
+/// line match; a merged line keeps the word timestamps of its first time.
+pub fn merge_repeats(events: &[LrcEvent]) -> Vec<LrcEvent> {
+    let mut tags = Vec::new();
+    let mut lines: Vec<LrcEvent> = Vec::new();
+    for event in expand_repeats(events) {
+        let LrcEvent::Line {
+            timestamps,
+            text,
+            chords,
+            segments,
+        } = event
+        else {
+            tags.push(event);
+            continue;
+        };
+        let Some(&start) = timestamps.first() else {
+            continue;
+        };
+        let timing = word_timing(start, &segments);
+        let repeated = lines.iter_mut().find_map(|line| match line {
+            LrcEvent::Line {
+                timestamps: merged,
+                text: merged_text,
+                chords: merged_chords,
+                segments: merged_segments,
+            } if *merged_text == text
+                && *merged_chords == chords
+                && word_timing(merged.first().copied().unwrap_or_default(), merged_segments)
+                    == timing =>
+            {
+                Some(merged)
+            },
+            _ => None,
+        });
+        match repeated {
+            Some(merged) => merged.push(start),
+            None => lines.push(LrcEvent::Line {
+                timestamps,
+                text,
+                chords,
+                segments,
+            }),
+        }
+    }
+    tags.extend(lines);
+    tags
+}
+
+/// When a line event is first sung; tags sort first.
+fn first_timestamp(event: &LrcEvent) -> Option<Duration> {
+    match event {
+        LrcEvent::Line { timestamps, .. } => timestamps.first().copied(),
+        LrcEvent::Metadata { .. } => None,
+    }
+}
+
+/// Word timestamps of a line sung at `start`, from the start of the line.
+fn word_timing(start: Duration, segments: &[LyricSegment]) -> Vec<(Duration, usize)> {
+    segments
+        .iter()
+        .map(|segment| (segment.start.saturating_sub(start), segment.offset))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrc::{parse_lrc, write_lrc};
+
+    /// A chorus with word timestamps and an empty line, each sung twice.
+    const COMPACT: &str = "[ti:Song]\n\
+                           [00:10.00][00:30.00]<00:10.00>Cho<00:10.50>rus\n\
+                           [00:20.00]Verse\n\
+                           [00:25.00][00:40.00]\n";
+
+    const EXPANDED: &str = "[ti:Song]\n\
+                            [00:10.00]<00:10.00>Cho<00:10.50>rus\n\
+                            [00:20.00]Verse\n\
+                            [00:25.00]\n\
+                            [00:30.00]<00:30.00>Cho<00:30.50>rus\n\
+                            [00:40.00]\n";
+
+    #[test]
+    fn expanding_gives_one_line_per_time_with_its_word_timing() {
+        assert_eq!(write_lrc(&expand_repeats(&parse_lrc(COMPACT))), EXPANDED);
+    }
+
+    #[test]
+    fn merging_gives_every_time_to_one_line() {
+        assert_eq!(write_lrc(&merge_repeats(&parse_lrc(EXPANDED))), COMPACT);
+    }
+
+    #[test]
+    fn expanding_and_merging_undo_each_other() {
+        let compact = parse_lrc(COMPACT);
+        let expanded = parse_lrc(EXPANDED);
+        assert_eq!(merge_repeats(&expand_repeats(&compact)), compact);
+        assert_eq!(expand_repeats(&merge_repeats(&expanded)), expanded);
+    }
+
+    #[test]
+    fn lines_sung_differently_are_not_merged() {
+        let lrc = "[00:10.00]<00:10.00>La <00:10.50>la\n\
+                   [00:20.00]<00:20.00>La <00:21.00>la\n\
+                   [00:30.00][C]La la\n\
+                   [00:40.00][G]La la\n";
+        assert_eq!(write_lrc(&merge_repeats(&parse_lrc(lrc))), lrc);
+    }
+
+    #[test]
+    fn tags_stay_ahead_of_the_lines() {
+        let lrc = "[00:20.00]Two\n[ar:Artist]\n[00:10.00]One\n[ti:Song]\n";
+        assert_eq!(
+            write_lrc(&expand_repeats(&parse_lrc(lrc))),
+            "[ar:Artist]\n[ti:Song]\n[00:10.00]One\n[00:20.00]Two\n"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Word timestamps estimated for lines timed as a whole.
+//!
+//! Without enhanced `<mm:ss.xx>` timestamps there is nothing to highlight
+//! word by word. The time from a line to the next is shared out between
+//! its words by how long they are, counted in syllables or characters,
+//! which is close enough for most songs and easy to correct by hand.
+
+use std::time::Duration;
+
+use super::{expand_repeats, LrcEvent, LrcMetadata, LyricSegment};
+
+/// Longest a line is taken to be sung for, so words do not stretch over
+/// an instrumental break before the next line.
+const MAX_LINE: Duration = Duration::from_secs(6);
+
+/// How the length of a word is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WordWeighting {
+    Syllables,
+    Characters,
+}
+
+impl WordWeighting {
+    pub const ALL: [Self; 2] = [Self::Syllables, Self::Characters];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Syllables => "By syllables",
+            Self::Characters => "By characters",
+        }
+    }
+
+    fn weight(self, word: &str) -> u32 {
+        match self {
+            Self::Syllables => syllables(word),
+            Self::Characters => word.chars().filter(|c| c.is_alphanumeric()).count() as u32,
+        }
+        .max(1)
+    }
+}
+
+/// Word timestamps for `text` sung from `start` until `end`, each word
+/// given a share of the time by its weight.
+pub fn estimate_segments(
+    text: &str,
+    start: Duration,
+    end: Duration,
+    weighting: WordWeighting,
+) -> Vec<LyricSegment> {
+    let mut words: Vec<(usize, String)> = Vec::new();
+    let mut previous = ' ';
+    for (offset, c) in text.chars().enumerate() {
+        if !c.is_whitespace() {
+            if previous.is_whitespace() {
+                words.push((offset, String::new()));
+            }
+            if let Some((_, word)) = words.last_mut() {
+                word.push(c);
+            }
+        }
+        previous = c;
+    }
+    let weights: Vec<u32> = words
+        .iter()
+        .map(|(_, word)| weighting.weight(word))
+        .collect();
+
+    let total: u32 = weights.iter().sum();
+    let length = end.saturating_sub(start).min(MAX_LINE);
+    let mut sung = 0;
+    words
+        .iter()
+        .zip(&weights)
+        .map(|(&(offset, _), weight)| {
+            let segment = LyricSegment {
+                start: start + length * sung / total.max(1),
+                offset,
+            };
+            sung += weight;
+            segment
+        })
+        .collect()
+}
+
+/// `events` with word timestamps estimated for every line that has none.
+/// Lines sung more than once come out once per time, each timed by the
+/// line after it.
+pub fn estimate_word_timing(events: &[LrcEvent], weighting: WordWeighting) -> Vec<LrcEvent> {
+    let length = LrcMetadata::from_events(events).length;
+    let mut events = expand_repeats(events);
+    let starts: Vec<Option<Duration>> = events
+        .iter()
+        .map(|event| match event {
+            LrcEvent::Line { timestamps, .. } => timestamps.first().copied(),
+            LrcEvent::Metadata { .. } => None,
+        })
+        .collect();
+    for (index, event) in events.iter_mut().enumerate() {
+        let LrcEvent::Line {
+            timestamps,
+            text,
+            segments,
+            ..
+        } = event
+        else {
+            continue;
+        };
+        let Some(&start) = timestamps.first() else {
+            continue;
+        };
+        if !segments.is_empty() || text.trim().is_empty() {
+            continue;
+        }
+        let end = starts[index + 1..]
+            .iter()
+            .flatten()
+            .copied()
+            .find(|next| *next > start)
+            .or(length.filter(|length| *length > start))
+            .unwrap_or(start + MAX_LINE);
+        *segments = estimate_segments(text, start, end, weighting);
+    }
+    events
+}
+
+/// Rough syllable count: groups of vowels, a final silent `e` left out.
+/// Scripts without these vowels, such as kana or hanzi, count one per
+/// character.
+fn syllables(word: &str) -> u32 {
+    let letters: Vec<char> = word
+        .chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if !letters.iter().copied().any(is_vowel) {
+        return letters.len() as u32;
+    }
+    let mut count = 0;
+    let mut previous = false;
+    for &c in &letters {
+        let vowel = is_vowel(c);
+        if vowel && !previous {
+            count += 1;
+        }
+        previous = vowel;
+    }
+    if count > 1 && letters.ends_with(&['e']) && !letters.ends_with(&['l', 'e']) {
+        count -= 1;
+    }
+    count
+}
+
+fn is_vowel(c: char) -> bool {
+    "aeiouyàáâãäåæèéêëìíîïòóôõöøœùúûüý".contains(c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrc::{parse_lrc, write_lrc};
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    fn starts(segments: &[LyricSegment]) -> Vec<Duration> {
+        segments.iter().map(|segment| segment.start).collect()
+    }
+
+    #[test]
+    fn syllables_are_counted_from_vowel_groups() {
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("the"), 1);
+        assert_eq!(syllables("friend"), 1);
+        assert_eq!(syllables("rhythm"), 1);
+        assert_eq!(syllables("Hello,"), 2);
+    }
+
+    #[test]
+    fn kana_and_hanzi_count_one_syllable_per_character() {
+        assert_eq!(syllables("こんにちは"), 5);
+        assert_eq!(syllables("你好"), 2);
+        assert_eq!(syllables("42"), 0);
+        assert_eq!(WordWeighting::Syllables.weight("42"), 1);
+    }
+
+    #[test]
+    fn words_share_the_line_by_weight() {
+        let segments = estimate_segments(
+            "Hello  there my friend",
+            ms(10_000),
+            ms(14_000),
+            WordWeighting::Syllables,
+        );
+        let offsets: Vec<usize> = segments.iter().map(|segment| segment.offset).collect();
+        assert_eq!(offsets, [0, 7, 13, 16]);
+        assert_eq!(
+            starts(&segments),
+            [ms(10_000), ms(11_600), ms(12_400), ms(13_200)]
+        );
+
+        let segments = estimate_segments("a bcd", ms(0), ms(4_000), WordWeighting::Characters);
+        assert_eq!(starts(&segments), [ms(0), ms(1_000)]);
+    }
+
+    #[test]
+    fn starts_rise_and_stay_within_the_longest_line() {
+        let text = "one two three four five six seven eight nine ten";
+        let start = ms(60_000);
+        for weighting in WordWeighting::ALL {
+            let segments = estimate_segments(text, start, ms(200_000), weighting);
+            let starts = starts(&segments);
+            assert_eq!(starts.first(), Some(&start));
+            assert!(starts.windows(2).all(|pair| pair[0] < pair[1]));
+            assert!(starts.iter().all(|&time| time < start + MAX_LINE));
+        }
+        // An end before the start gives every word the line's start
+        let segments = estimate_segments("a b", start, ms(1_000), WordWeighting::Syllables);
+        assert_eq!(starts(&segments), [start, start]);
+    }
+
+    #[test]
+    fn lines_are_timed_until_the_next_one() {
+        let events = parse_lrc(
+            "[length:00:13.00]\n\
+             [00:01.00][00:09.00]la la\n\
+             [00:05.00]<00:05.00>kept <00:06.00>as is\n\
+             [00:07.00]\n",
+        );
+        assert_eq!(
+            write_lrc(&estimate_word_timing(&events, WordWeighting::Syllables)),
+            "[length:00:13.00]\n\
+             [00:01.00]<00:01.00>la <00:03.00>la\n\
+             [00:05.00]<00:05.00>kept <00:06.00>as is\n\
+             [00:07.00]\n\
+             [00:09.00]<00:09.00>la <00:11.00>la\n"
+        );
+    }
+
+    #[test]
+    fn lines_past_the_length_are_timed_for_the_longest_line() {
+        let events = parse_lrc("[length:00:13.00]\n[00:20.00]after the end\n");
+        assert_eq!(
+            write_lrc(&estimate_word_timing(&events, WordWeighting::Syllables)),
+            "[length:00:13.00]\n[00:20.00]<00:20.00>after <00:23.00>the <00:24.50>end\n"
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! LRC writer, the reverse of the parser.
+//!
+//! Events are written one per line in their order: tags as `[key:value]`,
+//! lines with each of their `[mm:ss.xx]` timestamps in front, and word
+//! timestamps and chords put back into the text where they were taken out.
+//! Parsing the result gives the same events, timestamps cut to hundredths
+//! of a second.
+
+use std::time::Duration;
+
+use super::chords::ChordMark;
+use super::{merge_repeats, LrcEvent, LyricSegment};
+
+/// Canonical LRC text of `events`, lines ending in `\n`.
+pub fn write_lrc(events: &[LrcEvent]) -> String {
+    let mut lrc = String::new();
+    for event in events {
+        match event {
+            LrcEvent::Metadata { key, value } => lrc += &format!("[{key}:{value}]"),
+            LrcEvent::Line {
+                timestamps,
+                text,
+                chords,
+                segments,
+            } => {
+                for timestamp in timestamps {
+                    lrc += &format!("[{}]", format_timestamp(*timestamp));
+                }
+                lrc += &marked_text(text, chords, segments);
+            },
+        }
+        lrc.push('\n');
+    }
+    lrc
+}
+
+/// Compact LRC text of `events`: a line sung more than once is written
+/// once with all of its timestamps, lines in singing order.
+pub fn write_lrc_compact(events: &[LrcEvent]) -> String {
+    write_lrc(&merge_repeats(events))
+}
+
+/// `text` with `<mm:ss.xx>` in front of each segment and `[chord]` in front
+/// of each chord, a segment first where both start at one character.
+fn marked_text(text: &str, chords: &[ChordMark], segments: &[LyricSegment]) -> String {
+    let length = text.chars().count();
+    // Marks past the end of the text go at its end, as parsing puts them
+    let marks_at = |offset: usize| {
+        let at = move |mark: usize| mark.min(length) == offset;
+        let segments = segments
+            .iter()
+            .filter(move |segment| at(segment.offset))
+            .map(|segment| format!("<{}>", format_timestamp(segment.start)));
+        let chords = chords
+            .iter()
+            .filter(move |mark| at(mark.offset))
+            .map(|mark| format!("[{}]", mark.chord));
+        segments.chain(chords)
+    };
+    let mut marked = String::with_capacity(text.len());
+    for (offset, c) in text.chars().enumerate() {
+        marked.extend(marks_at(offset));
+        marked.push(c);
+    }
+    marked.extend(marks_at(length));
+    marked
+}
+
+/// `mm:ss.xx` timestamp text, minutes going past 99 as needed.
+pub(super) fn format_timestamp(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    format!(
+        "{:02}:{:02}.{:02}",
+        centis / 6000,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lrc::parse_lrc;
+
+    const LRC: &str = "\
+[ti:Sound of Silence]
+[ar:Simon & Garfunkel]
+[00:12.00][01:30.50][Am]Hello <00:12.50>[F]darkness my <00:13.20>old friend<00:14.00>[G]
+[00:15.00]<00:15.00>I've come to talk
+";
+
+    fn line(text: &str, segments: Vec<LyricSegment>, chords: Vec<ChordMark>) -> LrcEvent {
+        LrcEvent::Line {
+            timestamps: vec![Duration::from_secs(1)],
+            text: text.to_string(),
+            chords,
+            segments,
+        }
+    }
+
+    #[test]
+    fn canonical_text_round_trips() {
+        assert_eq!(write_lrc(&parse_lrc(LRC)), LRC);
+    }
+
+    #[test]
+    fn parsing_written_events_gives_them_back() {
+        let events = parse_lrc(LRC);
+        assert_eq!(parse_lrc(&write_lrc(&events)), events);
+    }
+
+    #[test]
+    fn chords_and_segments_at_the_end_of_the_text_are_kept() {
+        let events = parse_lrc(LRC);
+        let Some(LrcEvent::Line {
+            text,
+            chords,
+            segments,
+            ..
+        }) = events.get(2)
+        else {
+            unreachable!("the third event is a line");
+        };
+        let length = text.chars().count();
+        assert_eq!(text, "Hello darkness my old friend");
+        assert_eq!(segments.last().map(|segment| segment.offset), Some(length));
+        assert_eq!(chords.last().map(|mark| mark.offset), Some(length));
+        assert_eq!(chords.last().map(|mark| mark.chord.as_str()), Some("G"));
+    }
+
+    #[test]
+    fn segment_comes_before_chord_at_one_offset() {
+        let events = [line(
+            "ab",
+            vec![LyricSegment {
+                start: Duration::from_millis(1500),
+                offset: 1,
+            }],
+            vec![ChordMark {
+                offset: 1,
+                chord: "C".to_string(),
+            }],
+        )];
+        assert_eq!(write_lrc(&events), "[00:01.00]a<00:01.50>[C]b\n");
+    }
+
+    #[test]
+    fn marks_past_the_end_are_written_at_the_end() {
+        let events = [line(
+            "la",
+            vec![LyricSegment {
+                start: Duration::from_secs(2),
+                offset: 10,
+            }],
+            vec![ChordMark {
+                offset: 7,
+                chord: "D".to_string(),
+            }],
+        )];
+        let written = write_lrc(&events);
+        assert_eq!(written, "[00:01.00]la<00:02.00>[D]\n");
+        let Some(LrcEvent::Line {
+            chords, segments, ..
+        }) = parse_lrc(&written).pop()
+        else {
+            unreachable!("one line was written");
+        };
+        assert_eq!(segments[0].offset, 2);
+        assert_eq!(chords[0].offset, 2);
+    }
+
+    #[test]
+    fn timestamps_are_cut_to_hundredths() {
+        assert_eq!(format_timestamp(Duration::from_millis(1_239)), "00:01.23");
+        assert_eq!(format_timestamp(Duration::from_secs(100 * 60)), "100:00.00");
+    }
+
+    #[test]
+    fn compact_writes_repeated_lines_once() {
+        let expanded = "[00:10.00]Chorus\n[00:20.00]Verse\n[00:30.00]Chorus\n";
+        assert_eq!(
+            write_lrc_compact(&parse_lrc(expanded)),
+            "[00:10.00][00:30.00]Chorus\n[00:20.00]Verse\n"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+//! On-disk cache of lyrics lookups.
+//!
+//! Found lyrics are kept until the cache is cleared. "Not found" answers
+//! expire after a configurable time, so songs added to the provider later
+//! are picked up by a later run.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::Lyrics;
+use crate::config;
+
+const CACHE_FILE_NAME: &str = "lyrics_cache.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    fetched: SystemTime,
+    /// `None` when the provider had nothing
+    lyrics: Option<Lyrics>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsCache {
+    entries: HashMap<String, CacheEntry>,
+}
+
+impl LyricsCache {
+    /// Load the cache file, starting empty when it is missing or invalid.
+    pub fn load() -> Self {
+        let path = cache_path();
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!("Invalid lyrics cache {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tracing::warn!("Failed to read lyrics cache {}: {e}", path.display());
+                Self::default()
+            },
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = cache_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The cached answer for `key`: `Some(None)` is a "not found" younger
+    /// than `negative_ttl`, `None` means the provider has to be asked.
+    pub fn get(&self, key: &str, negative_ttl: Duration) -> Option<Option<Lyrics>> {
+        let entry = self.entries.get(key)?;
+        match &entry.lyrics {
+            Some(lyrics) => Some(Some(lyrics.clone())),
+            None => {
+                let age = entry.fetched.elapsed().unwrap_or(Duration::MAX);
+                (age < negative_ttl).then_some(None)
+            },
+        }
+    }
+
+    pub fn insert(&mut self, key: String, lyrics: Option<Lyrics>) {
+        self.entries.insert(
+            key,
+            CacheEntry {
+                fetched: SystemTime::now(),
+                lyrics,
+            },
+        );
+    }
+
+    /// Delete the cache file.
+    pub fn clear() -> Result<()> {
+        let path = cache_path();
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(e).with_context(|| format!("Failed to delete {}", path.display()))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+fn cache_path() -> PathBuf {
+    config::data_dir().join(CACHE_FILE_NAME)
+}
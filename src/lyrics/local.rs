@@ -0,0 +1,111 @@
+//! Lyrics found on disk: tags embedded in the audio file, or `.lrc` files
+//! in a folder of the user's.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey};
+use symphonia::core::probe::Hint;
+use walkdir::WalkDir;
+
+use super::Lyrics;
+use crate::library;
+use crate::lrc::{self, LrcEvent};
+
+/// Lyrics tag of `song` (ID3 `USLT`, Vorbis `LYRICS`, ...), `None` when it
+/// has none or the file cannot be read.
+pub fn embedded(song: &Path) -> Option<Lyrics> {
+    let file = File::open(song).ok()?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = song.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+    let mut probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .ok()?;
+
+    // ID3 tags ahead of the stream are read by the probe, others by the
+    // format reader
+    let text = probed
+        .metadata
+        .get()
+        .as_ref()
+        .and_then(|metadata| metadata.current().and_then(lyrics_tag))
+        .or_else(|| probed.format.metadata().current().and_then(lyrics_tag))?;
+    Some(as_lyrics(text))
+}
+
+fn lyrics_tag(revision: &MetadataRevision) -> Option<String> {
+    revision
+        .tags()
+        .iter()
+        .find(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+        .map(|tag| tag.value.to_string())
+        .filter(|text| !text.trim().is_empty())
+}
+
+/// The `.lrc` file for `song` in `folder` or its subfolders: same file
+/// name, or same artist and title.
+pub fn in_folder(song: &Path, folder: &Path) -> Option<Lyrics> {
+    let path = find_in_folder(song, folder)?;
+    match lrc::read_lrc_text(&path) {
+        Ok((text, _)) => Some(as_lyrics(text)),
+        Err(e) => {
+            tracing::warn!("{e}");
+            None
+        },
+    }
+}
+
+fn find_in_folder(song: &Path, folder: &Path) -> Option<PathBuf> {
+    let stem = song.file_stem()?.to_string_lossy().to_lowercase();
+    let (artist, title) = library::artist_and_title(song);
+    let same_song = |path: &Path| {
+        let (lrc_artist, lrc_title) = library::artist_and_title(path);
+        lrc_title.eq_ignore_ascii_case(&title)
+            && match (&lrc_artist, &artist) {
+                (Some(a), Some(b)) => a.eq_ignore_ascii_case(b),
+                _ => true,
+            }
+    };
+
+    let lrc_files: Vec<PathBuf> = WalkDir::new(folder)
+        .into_iter()
+        .filter_map(Result::ok)
+        .map(walkdir::DirEntry::into_path)
+        .filter(|path| lrc::is_lrc_file(path))
+        .collect();
+    let same_name = lrc_files.iter().find(|path| {
+        path.file_stem()
+            .is_some_and(|lrc_stem| lrc_stem.to_string_lossy().to_lowercase() == stem)
+    });
+    same_name
+        .or_else(|| lrc_files.iter().find(|path| same_song(path)))
+        .cloned()
+}
+
+/// Lyrics text as synced when it has timed lines, plain otherwise.
+fn as_lyrics(text: String) -> Lyrics {
+    let synced = lrc::parse_lrc(&text)
+        .iter()
+        .any(|event| matches!(event, LrcEvent::Line { .. }));
+    if synced {
+        Lyrics {
+            synced: Some(text),
+            plain: None,
+        }
+    } else {
+        Lyrics {
+            synced: None,
+            plain: Some(text),
+        }
+    }
+}
@@ -0,0 +1,52 @@
+//! LRCLIB (lrclib.net), a free database of synced lyrics.
+
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::{Lyrics, LyricsQuery};
+use crate::net::{HttpClient, NetError};
+
+pub const NAME: &str = "LRCLIB";
+const SEARCH_URL: &str = "https://lrclib.net/api/search";
+/// Results whose length differs more than this are another recording.
+const DURATION_TOLERANCE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    duration: Option<f32>,
+    synced_lyrics: Option<String>,
+    plain_lyrics: Option<String>,
+}
+
+/// Search for `query`. `Ok(None)` means LRCLIB has no matching lyrics.
+pub fn fetch(client: &HttpClient, query: &LyricsQuery) -> Result<Option<Lyrics>, NetError> {
+    let mut params = vec![("track_name", query.title.as_str())];
+    if let Some(artist) = &query.artist {
+        params.push(("artist_name", artist));
+    }
+    let records: Vec<Record> = client.get_json(SEARCH_URL, &params)?;
+    Ok(best_match(records, query.duration))
+}
+
+/// The closest-length record, preferring synced lyrics.
+fn best_match(records: Vec<Record>, duration: Option<Duration>) -> Option<Lyrics> {
+    let distance = |record: &Record| match (record.duration, duration) {
+        (Some(found), Some(wanted)) => (found - wanted.as_secs_f32()).abs(),
+        _ => 0.0,
+    };
+    records
+        .into_iter()
+        .filter(|record| distance(record) <= DURATION_TOLERANCE.as_secs_f32())
+        .filter(|record| record.synced_lyrics.is_some() || record.plain_lyrics.is_some())
+        .min_by(|a, b| {
+            (a.synced_lyrics.is_none(), distance(a))
+                .partial_cmp(&(b.synced_lyrics.is_none(), distance(b)))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+        .map(|record| Lyrics {
+            synced: record.synced_lyrics,
+            plain: record.plain_lyrics,
+        })
+}
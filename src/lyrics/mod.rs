@@ -0,0 +1,312 @@
+//! Lyrics lookup.
+//!
+//! "Fetch lyrics" looks up every library song without an `.lrc` file and
+//! saves synced results next to the audio. The providers are asked in the
+//! order set in the settings ([`LyricsConfig`]) until one has synced
+//! lyrics, and the one that did is recorded with the song. Online answers,
+//! including "not found", are kept in an on-disk [`cache`] so repeated runs
+//! skip songs already looked up and work offline for everything seen
+//! before.
+
+pub mod cache;
+pub mod local;
+pub mod lrclib;
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use rodio::Source;
+use serde::{Deserialize, Serialize};
+
+use self::cache::LyricsCache;
+use crate::audio::player;
+use crate::config::{LyricsConfig, NetworkConfig};
+use crate::library;
+use crate::lrc::{self, LrcError};
+use crate::net::{HttpClient, NetError};
+
+/// What is searched for.
+#[derive(Debug, Clone)]
+pub struct LyricsQuery {
+    pub title: String,
+    pub artist: Option<String>,
+    pub duration: Option<Duration>,
+}
+
+impl LyricsQuery {
+    /// Query for a library song, from its file name and length.
+    pub fn for_song(song: &Path) -> Self {
+        let (artist, title) = library::artist_and_title(song);
+        Self {
+            title,
+            artist,
+            duration: player::open_decoder(song)
+                .ok()
+                .and_then(|decoder| decoder.total_duration()),
+        }
+    }
+
+    /// Cache key: provider plus the normalized query.
+    fn cache_key(&self, provider: &str) -> String {
+        format!(
+            "{provider}|{}|{}|{}",
+            self.artist.as_deref().unwrap_or_default().to_lowercase(),
+            self.title.to_lowercase(),
+            self.duration.map_or(0, |duration| duration.as_secs())
+        )
+    }
+}
+
+/// Lyrics found for a song.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lyrics {
+    /// LRC text with line timestamps
+    pub synced: Option<String>,
+    pub plain: Option<String>,
+}
+
+/// A source of lyrics in the fetch chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LyricsProvider {
+    /// Lyrics tag of the audio file
+    Embedded,
+    /// `.lrc` files in a folder of the user's
+    LocalFolder,
+    Lrclib,
+    /// Lyrics the user adds by hand; ends the chain
+    Manual,
+}
+
+impl LyricsProvider {
+    pub const ALL: [Self; 4] = [
+        Self::Embedded,
+        Self::LocalFolder,
+        Self::Lrclib,
+        Self::Manual,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Embedded => "Embedded tags",
+            Self::LocalFolder => "Local folder",
+            Self::Lrclib => lrclib::NAME,
+            Self::Manual => "Manual",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Self::Embedded => "Lyrics stored in the audio file's tags",
+            Self::LocalFolder => "Matching .lrc files in the lyrics folder",
+            Self::Lrclib => "Online database of synced lyrics",
+            Self::Manual => {
+                "Stop here: songs still without lyrics are left for you to import, and \
+                 providers below are not asked"
+            },
+        }
+    }
+}
+
+/// Synced lyrics supplied by a provider of the chain.
+#[derive(Debug, Clone)]
+pub struct Found {
+    pub lyrics: Lyrics,
+    pub provider: LyricsProvider,
+    /// Answered from the cache instead of the provider
+    pub cached: bool,
+}
+
+/// Counters of a running fetch.
+#[derive(Debug, Clone, Default)]
+pub struct FetchProgress {
+    pub total: usize,
+    pub done: usize,
+    /// Songs that got an `.lrc` file
+    pub found: usize,
+    /// Answers taken from the cache
+    pub cached: usize,
+    pub failed: usize,
+    /// Songs no provider had lyrics for, when the chain ends with manual
+    /// entry
+    pub manual: usize,
+    /// Provider of each song that got an `.lrc` file
+    pub supplied: Vec<(PathBuf, LyricsProvider)>,
+}
+
+/// Fetches lyrics for a list of songs on a background thread.
+pub struct LyricsFetchJob {
+    progress: Arc<Mutex<FetchProgress>>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl LyricsFetchJob {
+    /// Look up `songs` that have no `.lrc` file yet.
+    pub fn start(songs: Vec<PathBuf>, config: &LyricsConfig, network: &NetworkConfig) -> Self {
+        let songs: Vec<PathBuf> = songs
+            .into_iter()
+            .filter(|song| !lrc::lrc_path(song).exists())
+            .collect();
+        let progress = Arc::new(Mutex::new(FetchProgress {
+            total: songs.len(),
+            ..FetchProgress::default()
+        }));
+        let cancel = Arc::new(AtomicBool::new(false));
+        let client = HttpClient::new(network);
+        let negative_ttl = network.negative_cache_ttl();
+
+        let handle = {
+            let (config, progress, cancel) = (config.clone(), progress.clone(), cancel.clone());
+            thread::spawn(move || {
+                fetch_all(&songs, &config, &client, negative_ttl, &progress, &cancel);
+            })
+        };
+        Self {
+            progress,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn progress(&self) -> FetchProgress {
+        lock(&self.progress).clone()
+    }
+
+    /// Stop after the song being looked up.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The final counters once the job has ended; `None` while it runs.
+    pub fn try_finish(&mut self) -> Option<FetchProgress> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        if handle.join().is_err() {
+            tracing::error!("Lyrics fetch crashed");
+        }
+        Some(self.progress())
+    }
+}
+
+fn fetch_all(
+    songs: &[PathBuf],
+    config: &LyricsConfig,
+    client: &HttpClient,
+    negative_ttl: Duration,
+    progress: &Mutex<FetchProgress>,
+    cancel: &AtomicBool,
+) {
+    let mut cache = LyricsCache::load();
+    let ends_manual = config.chain().contains(&LyricsProvider::Manual);
+    for song in songs {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let result = look_up(song, config, client, &mut cache, negative_ttl);
+
+        let mut progress = lock(progress);
+        progress.done += 1;
+        match result {
+            Ok(Some(found)) => {
+                progress.cached += usize::from(found.cached);
+                match save(song, &found.lyrics) {
+                    Ok(()) => {
+                        progress.found += 1;
+                        progress.supplied.push((song.clone(), found.provider));
+                    },
+                    Err(e) => {
+                        tracing::error!("Failed to save lyrics for {}: {e}", song.display());
+                        progress.failed += 1;
+                    },
+                }
+            },
+            Ok(None) if ends_manual => progress.manual += 1,
+            // Not found, or offline without a cached answer
+            Ok(None) | Err(NetError::Offline) => {},
+            Err(e) => {
+                tracing::warn!("{}: {e}", song.display());
+                progress.failed += 1;
+            },
+        }
+    }
+
+    if let Err(e) = cache.save() {
+        tracing::error!("{e:#}");
+    }
+}
+
+/// Write the synced `lyrics` as the `.lrc` file of `song`, in canonical
+/// LRC whatever dialect the provider uses.
+pub fn save(song: &Path, lyrics: &Lyrics) -> Result<(), LrcError> {
+    let events = lrc::parse_lrc(lyrics.synced.as_deref().unwrap_or_default());
+    lrc::write_lrc_file(&lrc::lrc_path(song), &events, false)
+}
+
+/// Ask the enabled providers in the order of `config` for synced lyrics of
+/// `song`, up to a [`LyricsProvider::Manual`] step. A failed provider does
+/// not stop the chain; its error is returned only when no later one found
+/// lyrics.
+pub fn look_up(
+    song: &Path,
+    config: &LyricsConfig,
+    client: &HttpClient,
+    cache: &mut LyricsCache,
+    negative_ttl: Duration,
+) -> Result<Option<Found>, NetError> {
+    let mut error = None;
+    for provider in config.chain() {
+        let (lyrics, cached) = match provider {
+            LyricsProvider::Embedded => (local::embedded(song), false),
+            LyricsProvider::LocalFolder => match &config.local_folder {
+                Some(folder) => (local::in_folder(song, folder), false),
+                None => continue,
+            },
+            LyricsProvider::Lrclib => match look_up_lrclib(song, client, cache, negative_ttl) {
+                Ok(answer) => answer,
+                Err(e) => {
+                    error = Some(e);
+                    continue;
+                },
+            },
+            LyricsProvider::Manual => break,
+        };
+        if let Some(lyrics) = lyrics.filter(|lyrics| lyrics.synced.is_some()) {
+            return Ok(Some(Found {
+                lyrics,
+                provider,
+                cached,
+            }));
+        }
+    }
+    error.map_or(Ok(None), Err)
+}
+
+/// Look up `song` on LRCLIB, asking it only without a usable cached
+/// answer. Returns the lyrics, if any, and whether they came from the cache.
+fn look_up_lrclib(
+    song: &Path,
+    client: &HttpClient,
+    cache: &mut LyricsCache,
+    negative_ttl: Duration,
+) -> Result<(Option<Lyrics>, bool), NetError> {
+    let query = LyricsQuery::for_song(song);
+    let key = query.cache_key(lrclib::NAME);
+    if let Some(cached) = cache.get(&key, negative_ttl) {
+        return Ok((cached, true));
+    }
+    let lyrics = lrclib::fetch(client, &query)?;
+    cache.insert(key, lyrics.clone());
+    Ok((lyrics, false))
+}
+
+/// Lock ignoring poisoning: the counters stay usable after a panic.
+fn lock(progress: &Mutex<FetchProgress>) -> MutexGuard<'_, FetchProgress> {
+    progress
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
@@ -1,8 +1,46 @@
 // PWE Karaoke - Main entry point
-// This is just a placeholder to ensure the project structure is set up correctly
+// Initializes logging and launches the eframe window
 
-fn main() {
-    println!("PWE Karaoke - Setup complete!");
-    println!("Environment is ready for development.");
-    println!("Please refer to README.md and SETUP.md for next steps.");
+mod adjustments;
+mod announcer;
+mod app;
+mod audio;
+mod cdg;
+mod config;
+mod cover_art;
+mod download;
+mod library;
+mod lrc;
+mod lyrics;
+mod migration;
+mod net;
+mod profiles;
+mod remote;
+mod self_test;
+mod session;
+mod ui;
+mod video;
+
+use app::KaraokeApp;
+
+fn main() -> eframe::Result {
+    tracing_subscriber::fmt::init();
+
+    if std::env::args().any(|arg| arg == self_test::FLAG) {
+        std::process::exit(if self_test::run() { 0 } else { 1 });
+    }
+
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_title("PWE Karaoke")
+            .with_inner_size([1200.0, 800.0])
+            .with_min_inner_size([800.0, 500.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "PWE Karaoke",
+        native_options,
+        Box::new(|cc| Ok(Box::new(KaraokeApp::new(cc)))),
+    )
 }
@@ -0,0 +1,275 @@
+//! Moving the data directory to another folder.
+//!
+//! Every file is copied on a background thread and then read back, its
+//! checksum compared with what was written. Only after that does the app
+//! switch over to the new folder; the old copy is deleted when the user
+//! confirms. A failed or cancelled move removes what it copied and leaves
+//! the old folder in use, untouched.
+//!
+//! Files hard linked together, as in the content store, are linked again
+//! in the new folder so their data is copied once.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use anyhow::{bail, Context, Result};
+use walkdir::WalkDir;
+
+use crate::config::{self, LOCATION_FILE_NAME};
+
+const CHUNK_SIZE: usize = 1 << 16;
+
+/// Why `to` cannot take the data from `from`, if it cannot.
+pub fn check_target(from: &Path, to: &Path) -> Result<()> {
+    if to == from {
+        bail!("The data is already in {}", to.display());
+    }
+    if to.starts_with(from) {
+        bail!("{} is inside the current data folder", to.display());
+    }
+    let empty = match fs::read_dir(to) {
+        Ok(mut entries) => entries.next().is_none(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => true,
+        Err(e) => return Err(e).with_context(|| format!("Cannot open {}", to.display())),
+    };
+    if !empty {
+        bail!("{} is not empty", to.display());
+    }
+    Ok(())
+}
+
+/// Steps of a move, for the progress display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Copying,
+    Validating,
+}
+
+/// Files copied and checked so far.
+#[derive(Debug, Default)]
+struct Progress {
+    total: AtomicUsize,
+    copied: AtomicUsize,
+    validated: AtomicUsize,
+}
+
+/// A data directory move running on a background thread.
+pub struct DataMigration {
+    from: PathBuf,
+    to: PathBuf,
+    progress: Arc<Progress>,
+    cancel: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Result<Vec<PathBuf>>>>,
+}
+
+impl DataMigration {
+    /// Copy the data directory in use to `to`, which must pass
+    /// [`check_target`].
+    pub fn start(to: PathBuf) -> Self {
+        let from = config::data_dir();
+        let progress = Arc::new(Progress::default());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (from, to) = (from.clone(), to.clone());
+            let (progress, cancel) = (progress.clone(), cancel.clone());
+            thread::spawn(move || {
+                let result = copy_and_validate(&from, &to, &progress, &cancel);
+                if result.is_err() {
+                    if let Err(e) = fs::remove_dir_all(&to) {
+                        tracing::warn!("Failed to remove {}: {e}", to.display());
+                    }
+                }
+                result
+            })
+        };
+        Self {
+            from,
+            to,
+            progress,
+            cancel,
+            handle: Some(handle),
+        }
+    }
+
+    pub fn from(&self) -> &Path {
+        &self.from
+    }
+
+    pub fn to(&self) -> &Path {
+        &self.to
+    }
+
+    /// The current step, and files done and in total in that step.
+    pub fn progress(&self) -> (Stage, usize, usize) {
+        let total = self.progress.total.load(Ordering::Relaxed);
+        let copied = self.progress.copied.load(Ordering::Relaxed);
+        if copied < total || total == 0 {
+            (Stage::Copying, copied, total)
+        } else {
+            let validated = self.progress.validated.load(Ordering::Relaxed);
+            (Stage::Validating, validated, total)
+        }
+    }
+
+    /// Stop and remove what was copied.
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+
+    /// The files copied, relative to the data directory, once every one is
+    /// validated; `None` while the move runs.
+    pub fn try_finish(&mut self) -> Option<Result<Vec<PathBuf>>> {
+        if !self.handle.as_ref()?.is_finished() {
+            return None;
+        }
+        let handle = self.handle.take()?;
+        Some(
+            handle
+                .join()
+                .unwrap_or_else(|_| Err(anyhow::anyhow!("Moving the data crashed"))),
+        )
+    }
+}
+
+fn copy_and_validate(
+    from: &Path,
+    to: &Path,
+    progress: &Progress,
+    cancel: &AtomicBool,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in WalkDir::new(from) {
+        let entry = entry.with_context(|| format!("Cannot read {}", from.display()))?;
+        // Symlinked files are copied as files
+        if !entry.file_type().is_file() && !entry.path_is_symlink() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(from)?.to_path_buf();
+        // The location file stays with the default data directory
+        if relative == Path::new(LOCATION_FILE_NAME) {
+            continue;
+        }
+        files.push(relative);
+    }
+    progress.total.store(files.len(), Ordering::Relaxed);
+
+    let mut checksums = Vec::with_capacity(files.len());
+    let mut linked = HashMap::new();
+    for relative in &files {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Moving the data was cancelled");
+        }
+        let (source, target) = (from.join(relative), to.join(relative));
+        let checksum = match link_id(&source).and_then(|id| linked.get(&id)) {
+            Some((first, checksum)) => {
+                let first: &PathBuf = first;
+                fs::hard_link(to.join(first), &target)
+                    .with_context(|| format!("Failed to link {}", target.display()))?;
+                *checksum
+            },
+            None => {
+                let checksum = copy_file(&source, &target)?;
+                if let Some(id) = link_id(&source) {
+                    linked.insert(id, (relative.clone(), checksum));
+                }
+                checksum
+            },
+        };
+        checksums.push(checksum);
+        progress.copied.fetch_add(1, Ordering::Relaxed);
+    }
+    for (relative, checksum) in files.iter().zip(checksums) {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Moving the data was cancelled");
+        }
+        let target = to.join(relative);
+        let file =
+            File::open(&target).with_context(|| format!("Cannot read {}", target.display()))?;
+        if hash(file, None)? != checksum {
+            bail!("{} does not match the original", target.display());
+        }
+        progress.validated.fetch_add(1, Ordering::Relaxed);
+    }
+    Ok(files)
+}
+
+/// Device and inode of a file with more than one hard link.
+#[cfg(unix)]
+fn link_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::symlink_metadata(path).ok()?;
+    (metadata.is_file() && metadata.nlink() > 1).then(|| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn link_id(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
+
+/// Copy `from` to `to`, returning the checksum of what was written. The
+/// checksum is of the data copied rather than of the source afterwards,
+/// as the app may still append to the source, e.g. the session log.
+fn copy_file(from: &Path, to: &Path) -> Result<u64> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let source = File::open(from).with_context(|| format!("Cannot read {}", from.display()))?;
+    let mut target =
+        File::create(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    let checksum = hash(source, Some(&mut target))
+        .with_context(|| format!("Failed to copy {}", from.display()))?;
+    target
+        .sync_all()
+        .with_context(|| format!("Failed to write {}", to.display()))?;
+    Ok(checksum)
+}
+
+/// Checksum of everything read from `reader`, which is also written to
+/// `copy` when given.
+fn hash(mut reader: impl Read, mut copy: Option<&mut File>) -> Result<u64> {
+    let mut hasher = DefaultHasher::new();
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = reader.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(hasher.finish());
+        }
+        hasher.write(&buffer[..read]);
+        if let Some(copy) = copy.as_mut() {
+            copy.write_all(&buffer[..read])?;
+        }
+    }
+}
+
+/// Delete the moved `files` from the old data directory `from`, then the
+/// folders left empty. Files the app did not put there are kept.
+pub fn remove_old(from: &Path, files: &[PathBuf]) -> Result<()> {
+    for relative in files {
+        let path = from.join(relative);
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Failed to delete {}", path.display()));
+            },
+            _ => {},
+        }
+    }
+    let folders = WalkDir::new(from)
+        .contents_first(true)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_dir());
+    for folder in folders {
+        // Fails for folders that still hold something, which is intended
+        let _ = fs::remove_dir(folder.path());
+    }
+    Ok(())
+}
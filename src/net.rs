@@ -0,0 +1,132 @@
+//! Outbound HTTP.
+//!
+//! Every online feature goes through [`HttpClient`], which runs the system
+//! `curl` (HTTPS without bundling a TLS stack) with the timeout, retries and
+//! user agent from the Network settings, and refuses to connect at all in
+//! offline mode.
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+
+use crate::config::NetworkConfig;
+
+const USER_AGENT: &str = concat!("PWE Karaoke/", env!("CARGO_PKG_VERSION"));
+/// Wait before the first retry; doubled for every further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(thiserror::Error, Debug)]
+pub enum NetError {
+    #[error("Offline mode is on")]
+    Offline,
+
+    #[error("Cannot run curl: {0}")]
+    CurlMissing(String),
+
+    #[error("Connection failed: {0}")]
+    TransportError(String),
+
+    #[error("HTTP {0} from {1}")]
+    StatusError(u16, String),
+
+    #[error("Invalid response from {0}: {1}")]
+    InvalidResponse(String, String),
+}
+
+impl NetError {
+    /// Whether trying again later may succeed.
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::TransportError(_) => true,
+            Self::StatusError(status, _) => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+/// HTTP client configured from the Network settings. Cheap to clone, so
+/// background jobs take their own copy.
+#[derive(Debug, Clone)]
+pub struct HttpClient {
+    offline: bool,
+    timeout: Duration,
+    retries: u32,
+}
+
+impl HttpClient {
+    pub fn new(config: &NetworkConfig) -> Self {
+        Self {
+            offline: config.offline,
+            timeout: Duration::from_secs(config.timeout_secs.max(1).into()),
+            retries: config.retries,
+        }
+    }
+
+    /// GET `url` with URL-encoded `query` parameters and return the body.
+    /// Connection failures, rate limiting and server errors are retried
+    /// with exponential backoff.
+    pub fn get(&self, url: &str, query: &[(&str, &str)]) -> Result<Vec<u8>, NetError> {
+        if self.offline {
+            return Err(NetError::Offline);
+        }
+        let mut attempt = 0;
+        loop {
+            match self.get_once(url, query) {
+                Err(e) if e.is_transient() && attempt < self.retries => {
+                    let delay = RETRY_BASE_DELAY * 2u32.pow(attempt);
+                    tracing::debug!("{e}; retrying {url} in {delay:?}");
+                    thread::sleep(delay);
+                    attempt += 1;
+                },
+                result => return result,
+            }
+        }
+    }
+
+    /// GET `url` and parse the body as JSON.
+    pub fn get_json<T: DeserializeOwned>(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<T, NetError> {
+        let body = self.get(url, query)?;
+        serde_json::from_slice(&body)
+            .map_err(|e| NetError::InvalidResponse(url.to_string(), e.to_string()))
+    }
+
+    fn get_once(&self, url: &str, query: &[(&str, &str)]) -> Result<Vec<u8>, NetError> {
+        let mut command = Command::new("curl");
+        command
+            .args(["--silent", "--show-error", "--location", "--get"])
+            .args(["--max-time", &self.timeout.as_secs().to_string()])
+            .args(["--user-agent", USER_AGENT])
+            // Status code on a line of its own after the body
+            .args(["--write-out", "\n%{http_code}"]);
+        for (name, value) in query {
+            command.args(["--data-urlencode", &format!("{name}={value}")]);
+        }
+        let output = command
+            .arg(url)
+            .output()
+            .map_err(|e| NetError::CurlMissing(e.to_string()))?;
+        if !output.status.success() {
+            return Err(NetError::TransportError(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+
+        let mut body = output.stdout;
+        let split = body.iter().rposition(|&byte| byte == b'\n').unwrap_or(0);
+        let status = String::from_utf8_lossy(&body[split..])
+            .trim()
+            .parse()
+            .unwrap_or(0);
+        body.truncate(split);
+        match status {
+            200..=299 => Ok(body),
+            _ => Err(NetError::StatusError(status, url.to_string())),
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Singer profiles: who is singing, with their color and preferred key.
+//!
+//! Songs queued while a profile is active are attributed to it, and its
+//! runs are scored under its name, which is how a profile's score history
+//! is found in the library. Names are therefore fixed once created.
+//!
+//! Stored as `profiles.json` in the data directory.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::adjustments::SongAdjustments;
+use crate::audio::key;
+use crate::config;
+use crate::library::results::SongResult;
+use crate::library::storage::LibraryStorage;
+
+const PROFILES_FILE_NAME: &str = "profiles.json";
+/// Colors given to new profiles in turn.
+const PALETTE: [[u8; 3]; 8] = [
+    [230, 80, 80],
+    [80, 160, 230],
+    [90, 190, 100],
+    [230, 170, 50],
+    [170, 100, 220],
+    [60, 200, 190],
+    [230, 110, 180],
+    [150, 150, 150],
+];
+/// Furthest a preferred key may be from the original, in semitones: as
+/// far as the backing track can be shifted.
+pub const MAX_KEY_OFFSET: i8 = key::MAX_SHIFT;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SingerProfile {
+    pub name: String,
+    /// sRGB
+    pub color: [u8; 3],
+    /// Semitones from the original key the singer prefers; songs they
+    /// sing start shifted by this much
+    pub key_offset: i8,
+    /// Key, tempo and lyric timing the singer chose per song
+    #[serde(default)]
+    pub adjustments: BTreeMap<PathBuf, SongAdjustments>,
+}
+
+impl SingerProfile {
+    pub fn color32(&self) -> egui::Color32 {
+        let [r, g, b] = self.color;
+        egui::Color32::from_rgb(r, g, b)
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profiles {
+    profiles: Vec<SingerProfile>,
+    /// Name of the profile singing next
+    active: Option<String>,
+}
+
+impl Profiles {
+    /// Load the profiles file, starting empty when it is missing or invalid.
+    pub fn load() -> Self {
+        let path = profiles_path();
+        match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!("Invalid profiles file {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tracing::warn!("Failed to read profiles file {}: {e}", path.display());
+                Self::default()
+            },
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = profiles_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SingerProfile> {
+        self.profiles.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut SingerProfile> {
+        self.profiles.iter_mut()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.profiles.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SingerProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut SingerProfile> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.name == name)
+    }
+
+    pub fn active(&self) -> Option<&SingerProfile> {
+        self.get(self.active.as_deref()?)
+    }
+
+    pub fn set_active(&mut self, name: Option<&str>) {
+        self.active = name.map(str::to_string);
+    }
+
+    /// Create a profile named `name` and make it the active one.
+    pub fn add(&mut self, name: &str) -> Result<()> {
+        let name = name.trim();
+        if name.is_empty() {
+            bail!("A singer needs a name");
+        }
+        if self.get(name).is_some() {
+            bail!("There is already a singer named {name}");
+        }
+        self.profiles.push(SingerProfile {
+            name: name.to_string(),
+            color: PALETTE[self.profiles.len() % PALETTE.len()],
+            key_offset: 0,
+            adjustments: BTreeMap::new(),
+        });
+        self.active = Some(name.to_string());
+        Ok(())
+    }
+
+    /// Remove the profile; its results stay with the songs.
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|profile| profile.name != name);
+        if self.active.as_deref() == Some(name) {
+            self.active = None;
+        }
+    }
+}
+
+/// Results sung under `name`, newest first.
+pub fn history<'a>(storage: &'a LibraryStorage, name: &str) -> Vec<(&'a Path, &'a SongResult)> {
+    let mut results: Vec<(&Path, &SongResult)> = storage
+        .songs()
+        .filter_map(|song| Some((song, storage.entry(song)?)))
+        .flat_map(|(song, entry)| entry.results.iter().map(move |result| (song, result)))
+        .filter(|(_, result)| result.singer == name)
+        .collect();
+    results.sort_by_key(|(_, result)| std::cmp::Reverse(result.sung_at));
+    results
+}
+
+fn profiles_path() -> PathBuf {
+    config::data_dir().join(PROFILES_FILE_NAME)
+}
@@ -0,0 +1,343 @@
+//! Web remote: a small HTTP server guests open on their phones to browse the
+//! library, request songs and vote for queued ones.
+//!
+//! The server runs on its own thread and only talks to the app through
+//! [`RemoteState`]: the app publishes the song list and queue there, and
+//! collects the guests' requests and votes from it every frame.
+
+pub mod tokens;
+pub mod votes;
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use tokens::{GuestTokens, TokenError};
+
+const INDEX_HTML: &str = include_str!("index.html");
+/// How often the server thread checks whether it should stop.
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(thiserror::Error, Debug)]
+pub enum RemoteError {
+    #[error("Failed to start the web remote on port {0}: {1}")]
+    BindError(u16, String),
+}
+
+/// A song as guests see it.
+#[derive(Debug, Clone)]
+pub struct RemoteSong {
+    pub id: String,
+    pub path: PathBuf,
+    pub title: String,
+    /// Section the song is listed under
+    pub language: Option<String>,
+}
+
+impl RemoteSong {
+    pub fn new(path: &Path, title: String, language: Option<String>) -> Self {
+        Self {
+            id: song_id(path),
+            path: path.to_path_buf(),
+            title,
+            language,
+        }
+    }
+}
+
+/// Id of the song at `path` in the remote's API.
+pub fn song_id(path: &Path) -> String {
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// A queued song as guests see it.
+#[derive(Debug, Clone)]
+pub struct QueuedSong {
+    pub id: String,
+    pub path: PathBuf,
+    pub title: String,
+    /// Tokens of the guests who voted for it
+    pub voters: Vec<String>,
+}
+
+/// A guest's vote for a queued song, waiting for the app to count it.
+#[derive(Debug, Clone)]
+pub struct Vote {
+    pub path: PathBuf,
+    /// Token of the guest, which keeps their votes apart
+    pub voter: String,
+    pub guest: String,
+}
+
+/// A guest's song request, waiting for the app to queue it.
+#[derive(Debug, Clone)]
+pub struct SongRequest {
+    pub path: PathBuf,
+    pub guest: String,
+}
+
+/// State shared between the app and the server thread.
+#[derive(Debug, Default)]
+pub struct RemoteState {
+    pub tokens: GuestTokens,
+    pub songs: Vec<RemoteSong>,
+    /// The queued songs, in order
+    pub queue: Vec<QueuedSong>,
+    pub now_playing: Option<String>,
+    pub requests: Vec<SongRequest>,
+    pub votes: Vec<Vote>,
+}
+
+/// The running HTTP server. Dropping it stops the server.
+pub struct RemoteServer {
+    state: Arc<Mutex<RemoteState>>,
+    port: u16,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteServer {
+    /// Listen on all interfaces on `port`.
+    pub fn start(port: u16) -> Result<Self, RemoteError> {
+        let server = Server::http((Ipv4Addr::UNSPECIFIED, port))
+            .map_err(|e| RemoteError::BindError(port, e.to_string()))?;
+        let state = Arc::new(Mutex::new(RemoteState {
+            tokens: GuestTokens::load(),
+            ..RemoteState::default()
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || serve(&server, &state, &stop))
+        };
+        tracing::info!("Web remote listening on port {port}");
+
+        Ok(Self {
+            state,
+            port,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Lock the shared state. A poisoned lock is recovered: the state is
+    /// plain data and stays usable.
+    pub fn state(&self) -> MutexGuard<'_, RemoteState> {
+        lock(&self.state)
+    }
+
+    /// Write the guest tokens after a change. Call without holding
+    /// [`Self::state`].
+    pub fn save_tokens(&self) -> anyhow::Result<()> {
+        save_tokens(&self.state)
+    }
+
+    /// Link a guest opens to use the remote with `token`.
+    pub fn guest_url(&self, token: &str) -> String {
+        format!("http://{}:{}/?token={token}", local_ip(), self.port)
+    }
+}
+
+impl Drop for RemoteServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                tracing::error!("Web remote thread panicked");
+            }
+        }
+    }
+}
+
+/// Address other devices on the network can reach us at.
+///
+/// Connecting a UDP socket sends nothing but picks the outgoing interface.
+fn local_ip() -> IpAddr {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| {
+            socket.connect((Ipv4Addr::new(192, 0, 2, 1), 9))?;
+            socket.local_addr()
+        })
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST))
+}
+
+fn serve(server: &Server, state: &Mutex<RemoteState>, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+        match server.recv_timeout(POLL_INTERVAL) {
+            Ok(Some(request)) => handle(request, state),
+            Ok(None) => {},
+            Err(e) => {
+                tracing::error!("Web remote stopped: {e}");
+                break;
+            },
+        }
+    }
+}
+
+fn lock(state: &Mutex<RemoteState>) -> MutexGuard<'_, RemoteState> {
+    state
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Write a copy of the guest tokens, so the lock, which the UI thread
+/// takes every frame, is not held while writing.
+fn save_tokens(state: &Mutex<RemoteState>) -> anyhow::Result<()> {
+    let tokens = lock(state).tokens.clone();
+    tokens.save()
+}
+
+fn handle(request: Request, state: &Mutex<RemoteState>) {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let param = |name: &str| {
+        query
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
+            .unwrap_or_default()
+    };
+    let token = param("token");
+
+    let (status, body, content_type) = match (request.method(), path) {
+        (Method::Get, "/") => (200, INDEX_HTML.to_string(), "text/html; charset=utf-8"),
+        (Method::Get, "/api/state") => api_state(&lock(state), &token),
+        (Method::Post, "/api/request") => api_request(state, &token, &param("song")),
+        (Method::Post, "/api/vote") => api_vote(&mut lock(state), &token, &param("song")),
+        _ => (404, error_body("Not found"), "application/json"),
+    };
+
+    let header = Header::from_bytes("Content-Type", content_type);
+    let mut response = Response::from_string(body).with_status_code(status);
+    if let Ok(header) = header {
+        response.add_header(header);
+    }
+    if let Err(e) = request.respond(response) {
+        tracing::debug!("Web remote response failed: {e}");
+    }
+}
+
+type ApiResponse = (u16, String, &'static str);
+
+fn api_state(state: &RemoteState, token: &str) -> ApiResponse {
+    let guest = match state.tokens.check(token) {
+        Ok(guest) => guest,
+        Err(e) => return token_error(e),
+    };
+    let songs: Vec<_> = state
+        .songs
+        .iter()
+        .map(|song| json!({ "id": song.id, "title": song.title, "language": song.language }))
+        .collect();
+    let queue: Vec<_> = state
+        .queue
+        .iter()
+        .map(|song| {
+            json!({
+                "id": song.id,
+                "title": song.title,
+                "votes": song.voters.len(),
+                "voted": song.voters.iter().any(|voter| voter == token),
+            })
+        })
+        .collect();
+    let body = json!({
+        "guest": guest.label,
+        "remaining": guest.remaining(),
+        "songs": songs,
+        "queue": queue,
+        "now_playing": state.now_playing,
+    });
+    (200, body.to_string(), "application/json")
+}
+
+fn api_request(state_lock: &Mutex<RemoteState>, token: &str, song_id: &str) -> ApiResponse {
+    let mut state = lock(state_lock);
+    if let Err(e) = state.tokens.check(token) {
+        return token_error(e);
+    }
+    let Some(song) = state.songs.iter().find(|song| song.id == song_id) else {
+        return (404, error_body("Unknown song"), "application/json");
+    };
+    let (path, title) = (song.path.clone(), song.title.clone());
+
+    let guest = match state.tokens.record_request(token) {
+        Ok(guest) => guest,
+        Err(e) => return token_error(e),
+    };
+    let (label, remaining) = (guest.label.clone(), guest.remaining());
+    tracing::info!("{label} requested {title}");
+    state.requests.push(SongRequest { path, guest: label });
+
+    drop(state);
+    if let Err(e) = save_tokens(state_lock) {
+        tracing::warn!("{e:#}");
+    }
+
+    let body = json!({ "queued": title, "remaining": remaining });
+    (200, body.to_string(), "application/json")
+}
+
+/// Votes do not count against the guest's requests.
+fn api_vote(state: &mut RemoteState, token: &str, song_id: &str) -> ApiResponse {
+    let guest = match state.tokens.check(token) {
+        Ok(guest) => guest.label.clone(),
+        Err(e) => return token_error(e),
+    };
+    let Some(song) = state.queue.iter_mut().find(|song| song.id == song_id) else {
+        return (
+            404,
+            error_body("This song is not queued"),
+            "application/json",
+        );
+    };
+    if song.voters.iter().any(|voter| voter == token) {
+        return (
+            409,
+            error_body("You already voted for this song"),
+            "application/json",
+        );
+    }
+    // Counted here too, so a second tap before the app catches up is refused
+    song.voters.push(token.to_string());
+    let (path, title) = (song.path.clone(), song.title.clone());
+
+    tracing::info!("{guest} voted for {title}");
+    state.votes.push(Vote {
+        path,
+        voter: token.to_string(),
+        guest,
+    });
+    let body = json!({ "voted": title });
+    (200, body.to_string(), "application/json")
+}
+
+fn token_error(error: TokenError) -> ApiResponse {
+    let status = match error {
+        TokenError::Unknown => 401,
+        TokenError::Expired => 403,
+        TokenError::LimitReached => 429,
+    };
+    (status, error_body(&error.to_string()), "application/json")
+}
+
+fn error_body(message: &str) -> String {
+    json!({ "error": message }).to_string()
+}
@@ -0,0 +1,192 @@
+//! Expiring guest tokens for the web remote.
+//!
+//! Each guest gets their own link carrying a random token. A token is valid
+//! until its expiry time and allows a limited number of song requests, so one
+//! guest cannot flood the queue. Tokens are stored in the data directory and
+//! survive an app restart during the evening.
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config;
+
+const TOKENS_FILE_NAME: &str = "guest_tokens.json";
+
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("This guest link is not valid")]
+    Unknown,
+
+    #[error("This guest link has expired")]
+    Expired,
+
+    #[error("This guest link has no requests left")]
+    LimitReached,
+}
+
+/// One guest's access to the remote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestToken {
+    pub token: String,
+    /// Name the host gave the guest
+    pub label: String,
+    pub expires: SystemTime,
+    pub request_limit: u32,
+    pub requests: u32,
+}
+
+impl GuestToken {
+    pub fn is_expired(&self) -> bool {
+        SystemTime::now() >= self.expires
+    }
+
+    pub fn remaining(&self) -> u32 {
+        self.request_limit.saturating_sub(self.requests)
+    }
+
+    /// Time left before expiry, zero once expired.
+    pub fn time_left(&self) -> Duration {
+        self.expires
+            .duration_since(SystemTime::now())
+            .unwrap_or_default()
+    }
+}
+
+/// The tokens are edited under the remote's state lock but written after
+/// it is released, from a copy. Copies share the revision last written, so
+/// a copy older than what is on disk is not written over it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuestTokens {
+    tokens: Vec<GuestToken>,
+    /// Bumped on every change
+    #[serde(skip)]
+    revision: u64,
+    #[serde(skip)]
+    saved_revision: Arc<Mutex<u64>>,
+}
+
+impl GuestTokens {
+    /// Load the stored tokens, dropping expired ones.
+    pub fn load() -> Self {
+        let path = tokens_path();
+        let mut tokens: Self = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_else(|e| {
+                tracing::warn!("Invalid guest tokens file {}: {e}", path.display());
+                Self::default()
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(e) => {
+                tracing::warn!("Failed to read guest tokens {}: {e}", path.display());
+                Self::default()
+            },
+        };
+        tokens.prune_expired();
+        tokens
+    }
+
+    /// Write the tokens, unless a newer copy was written already.
+    pub fn save(&self) -> Result<()> {
+        let mut saved = self
+            .saved_revision
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if *saved >= self.revision {
+            return Ok(());
+        }
+        self.write()?;
+        *saved = self.revision;
+        Ok(())
+    }
+
+    fn write(&self) -> Result<()> {
+        let path = tokens_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let contents = serde_json::to_string_pretty(self)?;
+        fs::write(&path, contents).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Issue a new token for `label`.
+    pub fn create(
+        &mut self,
+        label: &str,
+        valid_for: Duration,
+        request_limit: u32,
+    ) -> Result<&GuestToken> {
+        self.tokens.push(GuestToken {
+            token: new_token()?,
+            label: label.trim().to_string(),
+            expires: SystemTime::now() + valid_for,
+            request_limit,
+            requests: 0,
+        });
+        self.revision += 1;
+        let index = self.tokens.len() - 1;
+        Ok(&self.tokens[index])
+    }
+
+    pub fn revoke(&mut self, token: &str) {
+        self.tokens.retain(|guest| guest.token != token);
+        self.revision += 1;
+    }
+
+    pub fn prune_expired(&mut self) {
+        let before = self.tokens.len();
+        self.tokens.retain(|guest| !guest.is_expired());
+        if self.tokens.len() != before {
+            self.revision += 1;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GuestToken> {
+        self.tokens.iter()
+    }
+
+    /// The guest behind `token`, if it is still valid.
+    pub fn check(&self, token: &str) -> Result<&GuestToken, TokenError> {
+        let guest = self
+            .tokens
+            .iter()
+            .find(|guest| guest.token == token)
+            .ok_or(TokenError::Unknown)?;
+        if guest.is_expired() {
+            return Err(TokenError::Expired);
+        }
+        Ok(guest)
+    }
+
+    /// Count one song request against `token`.
+    pub fn record_request(&mut self, token: &str) -> Result<&GuestToken, TokenError> {
+        self.check(token)?;
+        let guest = self
+            .tokens
+            .iter_mut()
+            .find(|guest| guest.token == token)
+            .ok_or(TokenError::Unknown)?;
+        if guest.remaining() == 0 {
+            return Err(TokenError::LimitReached);
+        }
+        guest.requests += 1;
+        self.revision += 1;
+        Ok(guest)
+    }
+}
+
+/// 128 bits from the OS random number generator, as hex.
+fn new_token() -> Result<String> {
+    let mut bytes = [0_u8; 16];
+    getrandom::getrandom(&mut bytes).context("Failed to generate a guest token")?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+fn tokens_path() -> PathBuf {
+    config::data_dir().join(TOKENS_FILE_NAME)
+}
@@ -0,0 +1,82 @@
+//! Guests' votes on queued songs, and the fair order they can sort the
+//! queue into.
+//!
+//! A guest votes for a queued song once. Sorting by votes goes in rounds
+//! so every singer still gets a turn: each round holds at most one song per
+//! singer, a singer's most voted song first, and the songs within a round
+//! play most voted first. Songs without a singer count as one singer.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+/// Voters of each queued song, by guest token.
+#[derive(Debug, Default)]
+pub struct QueueVotes {
+    votes: HashMap<PathBuf, BTreeSet<String>>,
+}
+
+impl QueueVotes {
+    /// Count a vote of `voter` for `song`. Returns false when they had voted
+    /// for it already.
+    pub fn add(&mut self, song: &Path, voter: &str) -> bool {
+        self.votes
+            .entry(song.to_path_buf())
+            .or_default()
+            .insert(voter.to_string())
+    }
+
+    pub fn count(&self, song: &Path) -> usize {
+        self.votes.get(song).map_or(0, BTreeSet::len)
+    }
+
+    pub fn voters(&self, song: &Path) -> impl Iterator<Item = &str> {
+        self.votes
+            .get(song)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Forget the votes of songs no longer in `queue`.
+    pub fn retain_queued(&mut self, queue: &VecDeque<PathBuf>) {
+        self.votes.retain(|song, _| queue.contains(song));
+    }
+}
+
+/// `queue` reordered by votes in rounds of one song per singer, as
+/// described in the module docs. Ties keep their order in `queue`.
+pub fn fair_order<'a>(
+    queue: &VecDeque<PathBuf>,
+    votes: &QueueVotes,
+    singer: impl Fn(&Path) -> Option<&'a str>,
+) -> VecDeque<PathBuf> {
+    // Each singer's songs, most voted first, singers in order of their
+    // first song
+    let mut singers: Vec<(Option<&str>, Vec<&PathBuf>)> = Vec::new();
+    for song in queue {
+        let name = singer(song);
+        match singers.iter_mut().find(|(other, _)| *other == name) {
+            Some((_, songs)) => songs.push(song),
+            None => singers.push((name, vec![song])),
+        }
+    }
+    for (_, songs) in &mut singers {
+        songs.sort_by_key(|song| std::cmp::Reverse(votes.count(song)));
+    }
+
+    let rounds = singers
+        .iter()
+        .map(|(_, songs)| songs.len())
+        .max()
+        .unwrap_or(0);
+    let mut ordered = VecDeque::with_capacity(queue.len());
+    for round in 0..rounds {
+        let mut songs: Vec<&PathBuf> = singers
+            .iter()
+            .filter_map(|(_, songs)| songs.get(round).copied())
+            .collect();
+        songs.sort_by_key(|song| std::cmp::Reverse(votes.count(song)));
+        ordered.extend(songs.into_iter().cloned());
+    }
+    ordered
+}
@@ -0,0 +1,278 @@
+//! `pwe --self-test`: checks the parts of the app that differ between
+//! machines and prints a pass/fail report, so a support thread can start
+//! from what works and what does not.
+//!
+//! Every decoder is run on a tiny fixture bundled into the binary, a corpus
+//! of LRC dialects and encodings is parsed, and the configured audio
+//! devices are opened and closed. Each check is timed, which doubles as a
+//! rough startup benchmark: loading the settings and the library are
+//! checks too.
+
+use std::io::Cursor;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use rodio::{Decoder, Source};
+
+use crate::audio::input::MonitorOutput;
+use crate::audio::{AudioPlayer, MicInput, ProcessorParams};
+use crate::config::{self, AppConfig};
+use crate::library::storage::LibraryStorage;
+use crate::lrc::{self, encoding, LrcEvent};
+
+/// Command-line flag running the self-test instead of the app.
+pub const FLAG: &str = "--self-test";
+
+/// One fixture per format the player decodes.
+const FIXTURES: [(&str, &[u8]); 4] = [
+    ("WAV", include_bytes!("fixtures/fixture.wav")),
+    ("FLAC", include_bytes!("fixtures/fixture.flac")),
+    ("MP3", include_bytes!("fixtures/fixture.mp3")),
+    ("Ogg Vorbis", include_bytes!("fixtures/fixture.ogg")),
+];
+
+/// An LRC file and what parsing it must give.
+struct LrcCase {
+    name: &'static str,
+    bytes: &'static [u8],
+    /// Timed lines
+    lines: usize,
+    /// When the first line is sung, in milliseconds, and its text
+    first: (u64, &'static str),
+}
+
+const LRC_CORPUS: [LrcCase; 8] = [
+    LrcCase {
+        name: "tags and lines",
+        bytes: b"[ti:Song]\n[ar:Artist]\n[00:01.00]Hello\n[00:02.50]World\n",
+        lines: 2,
+        first: (1000, "Hello"),
+    },
+    LrcCase {
+        name: "timestamp dialects",
+        bytes: b"[00:01.250]One\n[00:02:50]Two\n[00:03]Three\n[00:04.5]Four\n",
+        lines: 4,
+        first: (1250, "One"),
+    },
+    LrcCase {
+        name: "repeated lines",
+        bytes: b"[00:10.00][00:40.00]Chorus\n",
+        lines: 1,
+        first: (10_000, "Chorus"),
+    },
+    LrcCase {
+        name: "enhanced word timestamps",
+        bytes: b"[00:05.00]<00:05.00>Hel<00:05.40>lo <00:05.80>there\n",
+        lines: 1,
+        first: (5000, "Hello there"),
+    },
+    LrcCase {
+        name: "CRLF line endings",
+        bytes: b"[ar:Artist]\r\n[00:01.00]Hello\r\n[00:02.00]World\r\n",
+        lines: 2,
+        first: (1000, "Hello"),
+    },
+    LrcCase {
+        name: "UTF-8 with a byte order mark",
+        bytes: b"\xef\xbb\xbf[00:01.00]Caf\xc3\xa9\n",
+        lines: 1,
+        first: (1000, "Caf\u{e9}"),
+    },
+    LrcCase {
+        name: "Windows-1252",
+        bytes: b"[00:01.00]Caf\xe9 cr\xe8me br\xfbl\xe9e\n[00:02.00]D\xe9j\xe0 vu\n",
+        lines: 2,
+        first: (1000, "Caf\u{e9} cr\u{e8}me br\u{fb}l\u{e9}e"),
+    },
+    LrcCase {
+        name: "Shift-JIS",
+        bytes: b"[00:01.00]\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x81\x41\x90\xa2\x8a\x45\n",
+        lines: 1,
+        first: (
+            1000,
+            "\u{3053}\u{3093}\u{306b}\u{3061}\u{306f}\u{3001}\u{4e16}\u{754c}",
+        ),
+    },
+];
+
+enum Outcome {
+    Pass(String),
+    Fail(String),
+    /// Not run, because a check it depends on failed
+    Skip(String),
+}
+
+/// Run every check, print the report, and return whether all passed.
+pub fn run() -> bool {
+    println!("PWE Karaoke {} self-test", env!("CARGO_PKG_VERSION"));
+    let started = Instant::now();
+    let mut report = Report::default();
+
+    let config = report.check("Load settings", || {
+        let config = AppConfig::load();
+        let dir = config::data_dir();
+        (Outcome::Pass(dir.display().to_string()), Some(config))
+    });
+    let config = config.unwrap_or_default();
+    report.check("Load library", || {
+        let storage = LibraryStorage::load();
+        (
+            Outcome::Pass(format!("{} songs", storage.songs().count())),
+            Some(()),
+        )
+    });
+
+    for (format, bytes) in FIXTURES {
+        report.check(&format!("Decode {format}"), || (decode(bytes), Some(())));
+    }
+    for case in &LRC_CORPUS {
+        report.check(&format!("Parse LRC: {}", case.name), || {
+            (parse(case), Some(()))
+        });
+    }
+
+    let params = Arc::new(ProcessorParams::new(&config.audio));
+    let player = report.check("Open audio output", || {
+        match AudioPlayer::new(params.clone()) {
+            Ok(player) => (
+                Outcome::Pass(player.device_name().to_string()),
+                Some(player),
+            ),
+            Err(e) => (Outcome::Fail(e.to_string()), None),
+        }
+    });
+    let mut mics = vec![(
+        "Open microphone",
+        0,
+        config.audio.input_device.as_deref(),
+        config.audio.input_channel,
+    )];
+    if config.audio.duet.enabled {
+        mics.push((
+            "Open duet microphone",
+            1,
+            config.audio.duet.input_device.as_deref(),
+            config.audio.duet.input_channel,
+        ));
+    }
+    for (name, slot, device, channel) in mics {
+        report.check(name, || {
+            let Some(player) = &player else {
+                return (Outcome::Skip("needs the audio output".to_string()), None);
+            };
+            let mic = MicInput::start(
+                MonitorOutput::Mix(player.mixer()),
+                params.clone(),
+                slot,
+                device,
+                channel,
+                config.audio.mic_latency_ms,
+            );
+            match mic {
+                // Dropped right away, which closes it
+                Ok(mic) => (Outcome::Pass(mic.device_name().to_string()), Some(())),
+                Err(e) => (Outcome::Fail(e.to_string()), None),
+            }
+        });
+    }
+    report.check("Close audio output", || match player {
+        Some(player) => {
+            drop(player);
+            (Outcome::Pass(String::new()), Some(()))
+        },
+        None => (Outcome::Skip("it did not open".to_string()), None),
+    });
+
+    println!(
+        "{} passed, {} failed, {} skipped in {} ms",
+        report.passed,
+        report.failed,
+        report.skipped,
+        started.elapsed().as_millis()
+    );
+    report.failed == 0
+}
+
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Report {
+    /// Time `check`, print its line and keep its value.
+    fn check<T>(&mut self, name: &str, check: impl FnOnce() -> (Outcome, Option<T>)) -> Option<T> {
+        let started = Instant::now();
+        let (outcome, value) = check();
+        let elapsed = started.elapsed();
+        let (label, detail) = match outcome {
+            Outcome::Pass(detail) => {
+                self.passed += 1;
+                ("PASS", detail)
+            },
+            Outcome::Fail(detail) => {
+                self.failed += 1;
+                ("FAIL", detail)
+            },
+            Outcome::Skip(detail) => {
+                self.skipped += 1;
+                ("SKIP", detail)
+            },
+        };
+        println!(
+            "{label}  {name:<40} {:>9}  {detail}",
+            format_elapsed(elapsed)
+        );
+        value
+    }
+}
+
+fn format_elapsed(elapsed: Duration) -> String {
+    format!("{:.1} ms", elapsed.as_secs_f64() * 1000.0)
+}
+
+/// Decode all of `bytes` with the player's decoder.
+fn decode(bytes: &'static [u8]) -> Outcome {
+    let decoder = match Decoder::new(Cursor::new(bytes)) {
+        Ok(decoder) => decoder,
+        Err(e) => return Outcome::Fail(e.to_string()),
+    };
+    let (channels, sample_rate) = (decoder.channels(), decoder.sample_rate());
+    let samples = decoder.count();
+    if samples == 0 {
+        return Outcome::Fail("no samples decoded".to_string());
+    }
+    let duration = samples as f64 / f64::from(channels.max(1)) / f64::from(sample_rate.max(1));
+    Outcome::Pass(format!(
+        "{sample_rate} Hz, {channels} ch, {:.2} s",
+        duration
+    ))
+}
+
+fn parse(case: &LrcCase) -> Outcome {
+    let (text, detected) = encoding::decode(case.bytes);
+    let events = lrc::parse_lrc(&text);
+    let lines: Vec<(&[Duration], &str)> = events
+        .iter()
+        .filter_map(|event| match event {
+            LrcEvent::Line {
+                timestamps, text, ..
+            } => Some((timestamps.as_slice(), text.as_str())),
+            LrcEvent::Metadata { .. } => None,
+        })
+        .collect();
+    if lines.len() != case.lines {
+        return Outcome::Fail(format!("{} lines, expected {}", lines.len(), case.lines));
+    }
+    let (millis, text) = case.first;
+    let first = lines
+        .first()
+        .map(|(timestamps, text)| (timestamps.first().map(Duration::as_millis), *text));
+    if first != Some((Some(u128::from(millis)), text)) {
+        return Outcome::Fail(format!(
+            "first line {first:?}, expected {text:?} at {millis} ms"
+        ));
+    }
+    Outcome::Pass(detected.name().to_string())
+}
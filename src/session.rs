@@ -0,0 +1,458 @@
+//! Session log: a timeline of what happened while the app was running.
+//!
+//! Each run of the app is one session. Events are appended as JSON lines to
+//! `Sessions/<start time>.jsonl` in the data directory as they happen, so the
+//! log survives a crash. Past sessions can be loaded back for the timeline
+//! view and exported as CSV, e.g. for a venue's records or to look into a
+//! complaint about lyrics running out of sync.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::audio::recorder;
+use crate::config::data_dir;
+
+/// A forward jump of the song position this far beyond the time passed is
+/// a seek rather than playback.
+const SEEK_TOLERANCE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum SessionEvent {
+    SongStarted {
+        song: String,
+        /// The guest who requested it through the web remote, or the
+        /// singer profile it was queued for
+        singer: Option<String>,
+        key: Option<String>,
+    },
+    SongEnded {
+        song: String,
+        position_secs: f32,
+        /// Played to the end, rather than stopped or skipped
+        finished: bool,
+    },
+    Paused {
+        position_secs: f32,
+    },
+    Resumed {
+        position_secs: f32,
+    },
+    Seeked {
+        from_secs: f32,
+        to_secs: f32,
+    },
+    KeyDetected {
+        song: String,
+        key: String,
+    },
+    Requested {
+        guest: String,
+        song: String,
+    },
+    WarmupScored {
+        exercise: String,
+        /// Accuracy of each singer, 0.0 - 1.0
+        accuracy: Vec<f32>,
+    },
+    SongScored {
+        song: String,
+        singer: String,
+        /// Out of [`crate::audio::scoring::MAX_SCORE`]
+        score: u32,
+    },
+    Applause,
+    RecordingSaved {
+        path: PathBuf,
+    },
+    OutputRecovered {
+        device: String,
+    },
+    /// The song stopped moving while playing, and the output was reopened
+    PlaybackStalled {
+        device: String,
+        position_secs: f32,
+    },
+}
+
+impl SessionEvent {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Self::SongStarted { .. } => "▶",
+            Self::SongEnded { .. } => "⏹",
+            Self::Paused { .. } => "⏸",
+            Self::Resumed { .. } => "⏵",
+            Self::Seeked { .. } => "⏩",
+            Self::KeyDetected { .. } => "🎼",
+            Self::Requested { .. } => "📱",
+            Self::WarmupScored { .. } => "🎵",
+            Self::SongScored { .. } => "🏆",
+            Self::Applause => "👏",
+            Self::RecordingSaved { .. } => "⏺",
+            Self::OutputRecovered { .. } => "🔊",
+            Self::PlaybackStalled { .. } => "⚠",
+        }
+    }
+
+    /// Short name of the kind of event, used as the CSV event column.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::SongStarted { .. } => "Song started",
+            Self::SongEnded { .. } => "Song ended",
+            Self::Paused { .. } => "Paused",
+            Self::Resumed { .. } => "Resumed",
+            Self::Seeked { .. } => "Seeked",
+            Self::KeyDetected { .. } => "Key detected",
+            Self::Requested { .. } => "Requested",
+            Self::WarmupScored { .. } => "Warm-up scored",
+            Self::SongScored { .. } => "Song scored",
+            Self::Applause => "Applause",
+            Self::RecordingSaved { .. } => "Recording saved",
+            Self::OutputRecovered { .. } => "Output recovered",
+            Self::PlaybackStalled { .. } => "Playback stalled",
+        }
+    }
+
+    pub fn details(&self) -> String {
+        let at = |secs: &f32| format_secs(*secs);
+        match self {
+            Self::SongStarted { song, singer, key } => {
+                let mut text = song.clone();
+                if let Some(singer) = singer {
+                    text += &format!(", sung by {singer}");
+                }
+                if let Some(key) = key {
+                    text += &format!(", in {key}");
+                }
+                text
+            },
+            Self::SongEnded {
+                song,
+                position_secs,
+                finished,
+            } => {
+                if *finished {
+                    format!("{song}, played to the end")
+                } else {
+                    format!("{song}, stopped at {}", at(position_secs))
+                }
+            },
+            Self::Paused { position_secs } | Self::Resumed { position_secs } => {
+                format!("at {}", at(position_secs))
+            },
+            Self::Seeked { from_secs, to_secs } => {
+                format!("from {} to {}", at(from_secs), at(to_secs))
+            },
+            Self::KeyDetected { song, key } => format!("{song} is in {key}"),
+            Self::Requested { guest, song } => format!("{guest} requested {song}"),
+            Self::WarmupScored { exercise, accuracy } => {
+                let scores: Vec<String> = accuracy
+                    .iter()
+                    .map(|accuracy| format!("{:.0}%", accuracy * 100.0))
+                    .collect();
+                format!("{exercise}: {}", scores.join(" / "))
+            },
+            Self::SongScored {
+                song,
+                singer,
+                score,
+            } => format!("{singer} scored {score} on {song}"),
+            Self::Applause => String::new(),
+            Self::RecordingSaved { path } => path.display().to_string(),
+            Self::OutputRecovered { device } => format!("now playing on {device}"),
+            Self::PlaybackStalled {
+                device,
+                position_secs,
+            } => format!("stuck at {} on {device}", at(position_secs)),
+        }
+    }
+}
+
+/// An event and when it happened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    /// Time since the session started
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub event: SessionEvent,
+}
+
+impl LogEntry {
+    pub fn at(&self) -> Duration {
+        Duration::from_millis(self.at_ms)
+    }
+}
+
+/// What the player is doing, as seen by [`SessionLog::observe`].
+#[derive(Debug, Clone, Copy)]
+pub struct PlaybackState<'a> {
+    pub song: &'a Path,
+    pub playing: bool,
+    pub finished: bool,
+    pub position: Duration,
+}
+
+/// The song being followed for pauses, seeks and its end.
+#[derive(Debug)]
+struct Followed {
+    song: PathBuf,
+    playing: bool,
+    position: Duration,
+    seen: Instant,
+}
+
+/// The running session's log.
+pub struct SessionLog {
+    path: PathBuf,
+    started: Instant,
+    entries: Vec<LogEntry>,
+    file: Option<File>,
+    /// Set after a failed write, so the warning is logged once
+    failed: bool,
+    followed: Option<Followed>,
+    /// Guests, or singer profiles, who requested the queued songs
+    requesters: HashMap<PathBuf, String>,
+}
+
+impl SessionLog {
+    pub fn new() -> Self {
+        let name = format!("{}.jsonl", recorder::timestamp(SystemTime::now()));
+        Self {
+            path: sessions_dir().join(name),
+            started: Instant::now(),
+            entries: Vec::new(),
+            file: None,
+            failed: false,
+            followed: None,
+            requesters: HashMap::new(),
+        }
+    }
+
+    /// The file this session is written to; it exists from the first event.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn entries(&self) -> &[LogEntry] {
+        &self.entries
+    }
+
+    pub fn record(&mut self, event: SessionEvent) {
+        let entry = LogEntry {
+            at_ms: self.started.elapsed().as_millis() as u64,
+            event,
+        };
+        if let Err(e) = self.append(&entry) {
+            if !self.failed {
+                tracing::warn!("Session log not saved: {e:#}");
+                self.failed = true;
+            }
+        }
+        self.entries.push(entry);
+    }
+
+    /// Continue in the sessions folder of the data directory after it was
+    /// moved. The file is written anew from the entries so far, as events
+    /// logged while the data was being copied are missing from the copy.
+    pub fn relocate(&mut self) {
+        let Some(name) = self.path.file_name() else {
+            return;
+        };
+        self.path = sessions_dir().join(name);
+        self.file = None;
+        if let Err(e) = fs::remove_file(&self.path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                tracing::warn!("Failed to delete {}: {e}", self.path.display());
+            }
+        }
+        let entries = std::mem::take(&mut self.entries);
+        for entry in &entries {
+            if let Err(e) = self.append(entry) {
+                tracing::warn!("Session log not saved: {e:#}");
+                break;
+            }
+        }
+        self.entries = entries;
+    }
+
+    fn append(&mut self, entry: &LogEntry) -> Result<()> {
+        let file = match &mut self.file {
+            Some(file) => file,
+            None => {
+                let dir = sessions_dir();
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create {}", dir.display()))?;
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)
+                    .with_context(|| format!("Failed to open {}", self.path.display()))?;
+                self.file.insert(file)
+            },
+        };
+        let line = serde_json::to_string(entry)?;
+        writeln!(file, "{line}").context("Failed to write the session log")?;
+        Ok(())
+    }
+
+    /// Log a guest's request, remembering them as the song's singer.
+    pub fn record_request(&mut self, song: &Path, guest: &str, title: String) {
+        self.requesters
+            .insert(song.to_path_buf(), guest.to_string());
+        self.record(SessionEvent::Requested {
+            guest: guest.to_string(),
+            song: title,
+        });
+    }
+
+    /// The guest who requested `song`, while it is queued.
+    pub fn requester(&self, song: &Path) -> Option<&str> {
+        self.requesters.get(song).map(String::as_str)
+    }
+
+    /// The guest who requested `song`, once: the next time it is sung it
+    /// is someone else's turn.
+    pub fn take_requester(&mut self, song: &Path) -> Option<String> {
+        self.requesters.remove(song)
+    }
+
+    /// Compare the player with what was seen last, logging pauses, resumes,
+    /// seeks and the end of the song. `title` names a song for the log.
+    pub fn observe(&mut self, state: Option<PlaybackState<'_>>, title: impl Fn(&Path) -> String) {
+        let now = Instant::now();
+        let Some(followed) = &mut self.followed else {
+            if let Some(state) = state.filter(|state| !state.finished) {
+                self.followed = Some(Followed {
+                    song: state.song.to_path_buf(),
+                    playing: state.playing,
+                    position: state.position,
+                    seen: now,
+                });
+            }
+            return;
+        };
+
+        let same_song = state.is_some_and(|state| state.song == followed.song);
+        let Some(state) = state.filter(|_| same_song) else {
+            let event = SessionEvent::SongEnded {
+                song: title(&followed.song),
+                position_secs: followed.position.as_secs_f32(),
+                finished: false,
+            };
+            self.followed = None;
+            self.record(event);
+            // Follow the new song from the next frame
+            return;
+        };
+        if state.finished {
+            let event = SessionEvent::SongEnded {
+                song: title(&followed.song),
+                position_secs: state.position.as_secs_f32(),
+                finished: true,
+            };
+            self.followed = None;
+            self.record(event);
+            return;
+        }
+
+        let mut events = Vec::new();
+        let passed = if followed.playing {
+            now - followed.seen
+        } else {
+            Duration::ZERO
+        };
+        let backwards = state.position + Duration::from_millis(500) < followed.position;
+        let forwards = state.position > followed.position + passed + SEEK_TOLERANCE;
+        if backwards || forwards {
+            events.push(SessionEvent::Seeked {
+                from_secs: followed.position.as_secs_f32(),
+                to_secs: state.position.as_secs_f32(),
+            });
+        }
+        if state.playing != followed.playing {
+            let position_secs = state.position.as_secs_f32();
+            events.push(if state.playing {
+                SessionEvent::Resumed { position_secs }
+            } else {
+                SessionEvent::Paused { position_secs }
+            });
+        }
+        followed.playing = state.playing;
+        followed.position = state.position;
+        followed.seen = now;
+        for event in events {
+            self.record(event);
+        }
+    }
+}
+
+pub fn sessions_dir() -> PathBuf {
+    data_dir().join("Sessions")
+}
+
+/// Saved session logs, newest first.
+pub fn list_sessions() -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(sessions_dir()) else {
+        return Vec::new();
+    };
+    let mut sessions: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    // The file names are start times, which sort by date
+    sessions.sort();
+    sessions.reverse();
+    sessions
+}
+
+/// Read a saved session, skipping lines that cannot be parsed.
+pub fn load_session(path: &Path) -> Result<Vec<LogEntry>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+        match serde_json::from_str(&line) {
+            Ok(entry) => entries.push(entry),
+            Err(e) => tracing::warn!("{}: skipping an entry: {e}", path.display()),
+        }
+    }
+    Ok(entries)
+}
+
+/// Write `entries` as CSV: time since the start, event, details.
+pub fn export_csv(entries: &[LogEntry], path: &Path) -> Result<()> {
+    let mut csv = String::from("time,event,details\n");
+    for entry in entries {
+        csv += &format!(
+            "{},{},{}\n",
+            format_secs(entry.at().as_secs_f32()),
+            csv_field(entry.event.kind()),
+            csv_field(&entry.event.details())
+        );
+    }
+    fs::write(path, csv).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// `secs` as `h:mm:ss`, or `m:ss` under an hour.
+pub fn format_secs(secs: f32) -> String {
+    let secs = secs.max(0.0) as u64;
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
@@ -0,0 +1,64 @@
+//! Settings → Announcer: spoken "Next up" between queued songs.
+
+use crate::announcer::{self, Announcement, VOICE_HINT};
+use crate::app::{display_title, KaraokeApp};
+
+impl KaraokeApp {
+    /// Returns true when the config changed.
+    pub(crate) fn announcer_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        egui::Grid::new("announcer_settings")
+            .num_columns(2)
+            .spacing([24.0, 8.0])
+            .show(ui, |ui| {
+                let announcer = &mut self.config.announcer;
+
+                ui.label("Announce songs");
+                changed |= ui
+                    .checkbox(&mut announcer.enabled, "Enabled")
+                    .on_hover_text("Say who sings which song next before each queued song")
+                    .changed();
+                ui.end_row();
+
+                ui.label("Voice");
+                ui.horizontal(|ui| {
+                    let mut voice = announcer.voice.clone().unwrap_or_default();
+                    if ui
+                        .add(
+                            egui::TextEdit::singleline(&mut voice)
+                                .hint_text(format!("System default ({VOICE_HINT})"))
+                                .desired_width(200.0),
+                        )
+                        .changed()
+                    {
+                        announcer.voice = Some(voice).filter(|voice| !voice.trim().is_empty());
+                        changed = true;
+                    }
+                });
+                ui.end_row();
+            });
+
+        let speaking = self.announcement.is_some();
+        if ui
+            .add_enabled(!speaking, egui::Button::new("🔊 Test"))
+            .on_hover_text("Announce the first queued song")
+            .clicked()
+        {
+            let singer = self.profiles.active().map(|profile| profile.name.as_str());
+            let title = self.queue.front().map_or_else(
+                || "the next song".to_string(),
+                |song| display_title(&self.storage, song),
+            );
+            let text = announcer::next_up(singer, &title);
+            match Announcement::speak(&text, self.config.announcer.voice.as_deref()) {
+                Ok(announcement) => self.announcement = Some((None, announcement)),
+                Err(e) => self.status = Some(format!("{e:#}")),
+            }
+        }
+        ui.weak(
+            "Speech uses the system's text-to-speech: say on macOS, System.Speech on \
+             Windows, espeak-ng on Linux.",
+        );
+        changed
+    }
+}
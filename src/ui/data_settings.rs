@@ -0,0 +1,114 @@
+//! Settings → Data: where the library, settings and caches are kept, and
+//! moving them to another folder.
+
+use std::fs;
+
+use crate::app::KaraokeApp;
+use crate::config::{self, APP_DIR_NAME};
+use crate::migration::{self, Stage};
+
+impl KaraokeApp {
+    pub(crate) fn data_settings(&mut self, ui: &mut egui::Ui) {
+        let data_dir = config::data_dir();
+        let default = config::default_data_dir();
+        let mut target = None;
+        // Delete (true) or keep (false) the previous folder after a move
+        let mut delete_old = None;
+
+        egui::Grid::new("data_settings")
+            .num_columns(2)
+            .spacing([24.0, 8.0])
+            .show(ui, |ui| {
+                ui.label("Data folder");
+                ui.vertical(|ui| {
+                    ui.label(data_dir.display().to_string());
+                    if let Some(migration) = &self.data_migration {
+                        let (stage, done, total) = migration.progress();
+                        ui.horizontal(|ui| {
+                            ui.spinner();
+                            ui.weak(match stage {
+                                Stage::Copying => format!("Copying files… {done}/{total}"),
+                                Stage::Validating => format!("Checking the copies… {done}/{total}"),
+                            });
+                            if ui.small_button("Cancel").clicked() {
+                                migration.cancel();
+                            }
+                        });
+                        return;
+                    }
+
+                    let in_use = self.data_in_use();
+                    ui.horizontal(|ui| {
+                        let hint = if in_use {
+                            "Wait for recordings and downloads to finish"
+                        } else {
+                            "Copy everything to another folder, check the copies and use them \
+                             from then on"
+                        };
+                        if ui
+                            .add_enabled(!in_use, egui::Button::new("Move…"))
+                            .on_hover_text(hint)
+                            .clicked()
+                        {
+                            target = rfd::FileDialog::new().pick_folder().map(|folder| {
+                                // Keep the data in its own folder unless an empty one was picked
+                                let empty = fs::read_dir(&folder)
+                                    .is_ok_and(|mut entries| entries.next().is_none());
+                                if empty {
+                                    folder
+                                } else {
+                                    folder.join(APP_DIR_NAME)
+                                }
+                            });
+                        }
+                        if data_dir != default
+                            && ui
+                                .add_enabled(!in_use, egui::Button::new("Move back"))
+                                .on_hover_text(format!("Move the data to {}", default.display()))
+                                .clicked()
+                        {
+                            target = Some(default.clone());
+                        }
+                    });
+                });
+                ui.end_row();
+
+                let Some((old, _)) = &self.moved_data else {
+                    return;
+                };
+                ui.label("Previous folder");
+                ui.vertical(|ui| {
+                    ui.label(old.display().to_string());
+                    ui.weak("Everything was copied and checked. The old copy is no longer used.");
+                    ui.horizontal(|ui| {
+                        if ui
+                            .button("Delete old copy")
+                            .on_hover_text("Delete the moved files; anything else there is kept")
+                            .clicked()
+                        {
+                            delete_old = Some(true);
+                        }
+                        if ui.button("Keep it").clicked() {
+                            delete_old = Some(false);
+                        }
+                    });
+                });
+                ui.end_row();
+            });
+
+        if let Some(target) = target {
+            self.start_data_migration(target);
+        }
+        if let Some(delete) = delete_old {
+            let Some((old, files)) = self.moved_data.take() else {
+                return;
+            };
+            if delete {
+                self.status = Some(match migration::remove_old(&old, &files) {
+                    Ok(()) => format!("Deleted the old copy in {}", old.display()),
+                    Err(e) => format!("{e:#}"),
+                });
+            }
+        }
+    }
+}
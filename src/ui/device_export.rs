@@ -0,0 +1,106 @@
+//! "Export to device" dialog: copy the selected songs and their lyrics to a
+//! folder or USB drive.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::app::KaraokeApp;
+use crate::library::device_export::{DeviceExport, DeviceFormat};
+
+/// Dialog steps.
+pub(crate) enum DeviceExportDialog {
+    /// Choosing where to and in which format
+    Setup {
+        folder: Option<PathBuf>,
+        format: DeviceFormat,
+    },
+    Running(DeviceExport),
+}
+
+impl KaraokeApp {
+    pub(crate) fn device_export_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut start = None;
+        if let Some(DeviceExportDialog::Running(export)) = &mut self.device_export {
+            if let Some(result) = export.try_finish() {
+                let folder = export.folder().display().to_string();
+                self.status = Some(match result {
+                    Ok(summary) if summary.failed.is_empty() => format!(
+                        "Exported {} songs ({} with lyrics) to {folder}",
+                        summary.songs, summary.lyrics
+                    ),
+                    Ok(summary) => format!(
+                        "Exported {} songs to {folder}; {} failed, see the log",
+                        summary.songs,
+                        summary.failed.len()
+                    ),
+                    Err(e) => format!("{e:#}"),
+                });
+                self.device_export = None;
+            }
+        }
+        let selected = self.selected_songs.len();
+        let Some(dialog) = &mut self.device_export else {
+            return;
+        };
+
+        egui::Window::new("Export to device")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| match dialog {
+                DeviceExportDialog::Setup { folder, format } => {
+                    ui.label(format!(
+                        "Copy {selected} songs with their lyrics, e.g. to a USB drive."
+                    ));
+                    ui.horizontal(|ui| {
+                        if ui.button("Choose folder…").clicked() {
+                            if let Some(picked) = rfd::FileDialog::new().pick_folder() {
+                                *folder = Some(picked);
+                            }
+                        }
+                        match folder {
+                            Some(folder) => ui.monospace(folder.display().to_string()),
+                            None => ui.weak("No folder chosen"),
+                        };
+                    });
+                    for option in DeviceFormat::ALL {
+                        ui.radio_value(format, option, option.label());
+                    }
+                    if *format == DeviceFormat::Mp3 {
+                        ui.weak("Converting needs ffmpeg and takes a while per song.");
+                    }
+                    ui.add_space(8.0);
+                    let ready = folder.is_some() && selected > 0;
+                    if ui.add_enabled(ready, egui::Button::new("Export")).clicked() {
+                        start = folder.clone().map(|folder| (folder, *format));
+                    }
+                },
+                DeviceExportDialog::Running(export) => {
+                    let (done, total) = export.progress();
+                    ui.label(format!("Copying to {}", export.folder().display()));
+                    ui.add(
+                        egui::ProgressBar::new(done as f32 / total.max(1) as f32)
+                            .text(format!("{done}/{total} songs")),
+                    );
+                    if ui.button("Cancel").clicked() {
+                        export.cancel();
+                    }
+                    ctx.request_repaint_after(Duration::from_millis(250));
+                },
+            });
+
+        if let Some((folder, format)) = start {
+            let songs = self.selected_songs.iter().cloned().collect();
+            self.device_export = Some(DeviceExportDialog::Running(DeviceExport::start(
+                songs, folder, format,
+            )));
+        } else if !open {
+            match &self.device_export {
+                // The dialog stays until the song being copied is done
+                Some(DeviceExportDialog::Running(export)) => export.cancel(),
+                _ => self.device_export = None,
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+//! Diagnostics HUD with live engine readings (toggled with F3), and the clip
+//! indicator shown in the top panel.
+
+use std::time::{Duration, Instant};
+
+use crate::app::KaraokeApp;
+use crate::audio::spectrum::SpectrumFrame;
+
+/// Gain reduction shown as a full meter.
+const MAX_GAIN_REDUCTION_DB: f32 = 12.0;
+/// Spectrum display: log-spaced bands over this range, levels from the floor up.
+const SPECTRUM_BANDS: usize = 48;
+const SPECTRUM_MIN_HZ: f32 = 40.0;
+const SPECTRUM_MAX_HZ: f32 = 16_000.0;
+const SPECTRUM_FLOOR_DB: f32 = -90.0;
+/// How long the clip indicator stays lit after the last clip.
+const CLIP_HOLD: Duration = Duration::from_secs(2);
+/// How long the feedback warning stays up after feedback was acted on.
+const FEEDBACK_TOAST_HOLD: Duration = Duration::from_secs(6);
+
+impl KaraokeApp {
+    /// Light shown while the mix recently went over full scale. Red when
+    /// the clipping reached the output, amber when the limiter caught it.
+    pub(crate) fn clip_indicator(&mut self, ui: &mut egui::Ui) {
+        let clips = self.params.clip_count();
+        if clips != self.clips_seen {
+            self.clips_seen = clips;
+            self.last_clip = Some(Instant::now());
+        }
+        let Some(since) = self.last_clip.map(|at| at.elapsed()) else {
+            return;
+        };
+        if since >= CLIP_HOLD {
+            self.last_clip = None;
+            return;
+        }
+        ui.ctx().request_repaint_after(CLIP_HOLD - since);
+
+        let limited = self.config.audio.limiter_enabled;
+        let (color, hint) = if limited {
+            (
+                ui.visuals().warn_fg_color,
+                "The mix went over 0 dBFS; the limiter kept it from clipping",
+            )
+        } else {
+            (
+                ui.visuals().error_fg_color,
+                "The output is clipping; lower the volume or enable the limiter",
+            )
+        };
+        let text = egui::RichText::new("● CLIP").strong().color(color);
+        let label = ui
+            .add(egui::Label::new(text).sense(egui::Sense::click()))
+            .on_hover_text(format!("{hint}\nClick to reset"));
+        if label.clicked() {
+            self.last_clip = None;
+        }
+    }
+
+    /// Warning shown over the views when feedback was notched or the
+    /// output ducked, telling the host what to do about it.
+    pub(crate) fn feedback_toast(&mut self, ctx: &egui::Context) {
+        let events = self.params.feedback_events();
+        // The count restarts when a mic is reopened
+        if events > self.feedback_seen {
+            self.last_feedback = Some(Instant::now());
+        }
+        self.feedback_seen = events;
+        let Some(since) = self.last_feedback.map(|at| at.elapsed()) else {
+            return;
+        };
+        let ducking = self.params.feedback_duck_db() > 0.5;
+        if since >= FEEDBACK_TOAST_HOLD && !ducking {
+            self.last_feedback = None;
+            return;
+        }
+        ctx.request_repaint_after(Duration::from_millis(250));
+
+        let action = if ducking {
+            "The output is turned down until it stops."
+        } else {
+            "A filter was added to stop it."
+        };
+        let mut dismissed = false;
+        egui::Area::new(egui::Id::new("feedback_toast"))
+            .order(egui::Order::Foreground)
+            .anchor(egui::Align2::CENTER_BOTTOM, [0.0, -64.0])
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.colored_label(ui.visuals().warn_fg_color, "🔔 Feedback detected.");
+                        ui.label(action);
+                        if ui.small_button("✖").clicked() {
+                            dismissed = true;
+                        }
+                    });
+                    ui.weak(
+                        "Point the mic away from the speakers, keep it closer to the singer \
+                         or lower the mic gain.",
+                    );
+                });
+            });
+        if dismissed {
+            self.last_feedback = None;
+        }
+    }
+
+    pub(crate) fn diagnostics_hud(&mut self, ctx: &egui::Context) {
+        let receiver = self
+            .spectrum
+            .get_or_insert_with(|| self.params.subscribe_spectrum());
+        if let Some(frame) = receiver.try_iter().last() {
+            self.last_spectrum = Some(frame);
+        }
+
+        egui::Window::new("Diagnostics")
+            .open(&mut self.show_diagnostics)
+            .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+            .resizable(false)
+            .collapsible(true)
+            .show(ctx, |ui| {
+                let reduction = self.params.gain_reduction_db().max(0.0);
+                ui.label("Limiter gain reduction");
+                ui.add(
+                    egui::ProgressBar::new(reduction / MAX_GAIN_REDUCTION_DB)
+                        .desired_width(180.0)
+                        .text(format!("{reduction:.1} dB")),
+                );
+                if !self.config.audio.limiter_enabled {
+                    ui.weak("Limiter disabled");
+                }
+
+                let ducking = self.params.ducking_db().max(0.0);
+                ui.label("Music ducking");
+                ui.add(
+                    egui::ProgressBar::new(ducking / self.config.audio.ducking_depth_db.max(1.0))
+                        .desired_width(180.0)
+                        .text(format!("{ducking:.1} dB")),
+                );
+
+                let ducking = self.params.feedback_duck_db().max(0.0);
+                if ducking > 0.5 {
+                    ui.colored_label(
+                        ui.visuals().warn_fg_color,
+                        format!("Feedback ducking the output by {ducking:.0} dB"),
+                    );
+                }
+
+                ui.separator();
+                ui.label("Feedback notches");
+                let notches = self.params.active_notches();
+                if notches.is_empty() {
+                    ui.weak("None");
+                }
+                for (mic, slot, frequency) in notches {
+                    ui.horizontal(|ui| {
+                        ui.label(format!("Mic {}: {frequency:.0} Hz", mic + 1));
+                        if ui.small_button("Release").clicked() {
+                            self.params.mic(mic).release_notch(slot);
+                        }
+                    });
+                }
+
+                ui.separator();
+                ui.label("Output spectrum");
+                spectrum_bars(ui, self.last_spectrum.as_deref());
+            });
+    }
+}
+
+/// Draw the spectrum as log-spaced bars, each showing the loudest bin in its band.
+fn spectrum_bars(ui: &mut egui::Ui, frame: Option<&SpectrumFrame>) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(180.0, 60.0), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let Some(frame) = frame else {
+        return;
+    };
+
+    let bin_of = |frequency: f32| {
+        ((frequency / frame.frequency(1)) as usize).min(frame.magnitudes.len() - 1)
+    };
+    let ratio = SPECTRUM_MAX_HZ / SPECTRUM_MIN_HZ;
+    let width = rect.width() / SPECTRUM_BANDS as f32;
+    let color = ui.visuals().selection.bg_fill;
+
+    for band in 0..SPECTRUM_BANDS {
+        let low = SPECTRUM_MIN_HZ * ratio.powf(band as f32 / SPECTRUM_BANDS as f32);
+        let high = SPECTRUM_MIN_HZ * ratio.powf((band + 1) as f32 / SPECTRUM_BANDS as f32);
+        let (first, last) = (bin_of(low), bin_of(high).max(bin_of(low) + 1));
+        let level = frame.magnitudes[first..last.min(frame.magnitudes.len())]
+            .iter()
+            .fold(SPECTRUM_FLOOR_DB, |max, &db| max.max(db));
+        let height = (1.0 - level / SPECTRUM_FLOOR_DB).clamp(0.0, 1.0) * rect.height();
+
+        let x = rect.left() + band as f32 * width;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x + 1.0, rect.bottom() - height),
+            egui::pos2(x + width - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 0.0, color);
+    }
+}
@@ -0,0 +1,80 @@
+//! Difficulty badge for the library and per-line heat map for the karaoke
+//! view.
+
+use std::time::Duration;
+
+use crate::app::format_time;
+use crate::library::difficulty::{Difficulty, LineDifficulty};
+
+const HEAT_MAP_HEIGHT: f32 = 10.0;
+
+/// Colour of a difficulty score, green (easy) through amber to red (hard).
+pub(crate) fn difficulty_color(score: f32) -> egui::Color32 {
+    let easy = egui::Color32::from_rgb(0x00, 0x9e, 0x73);
+    let medium = egui::Color32::from_rgb(0xe6, 0x9f, 0x00);
+    let hard = egui::Color32::from_rgb(0xd5, 0x5e, 0x00);
+    let score = score.clamp(0.0, 1.0);
+    if score < 0.5 {
+        easy.lerp_to_gamma(medium, score * 2.0)
+    } else {
+        medium.lerp_to_gamma(hard, score * 2.0 - 1.0)
+    }
+}
+
+/// Small coloured label with the song's difficulty level.
+pub(crate) fn difficulty_badge(ui: &mut egui::Ui, difficulty: &Difficulty) {
+    let text = egui::RichText::new(difficulty.level().label())
+        .small()
+        .color(difficulty_color(difficulty.overall));
+    ui.label(text).on_hover_text(format!(
+        "Difficulty {:.0}/100, from word speed and pitch range",
+        difficulty.overall * 100.0
+    ));
+}
+
+/// One cell per lyric line, coloured by its score, with the line being sung
+/// outlined. Returns the start of the line clicked, to rehearse it.
+pub(crate) fn difficulty_heat_map(
+    ui: &mut egui::Ui,
+    lines: &[LineDifficulty],
+    position: Duration,
+    width: f32,
+) -> Option<Duration> {
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(width, HEAT_MAP_HEIGHT), egui::Sense::click());
+    let (Some(first), Some(last)) = (lines.first(), lines.last()) else {
+        return None;
+    };
+    let span = (last.end - first.start).max(f32::EPSILON);
+    let x = |time: f32| rect.left() + (time - first.start) / span * rect.width();
+    let cell = |line: &LineDifficulty| {
+        egui::Rect::from_x_y_ranges(x(line.start)..=x(line.end), rect.y_range())
+    };
+
+    let painter = ui.painter_at(rect);
+    let now = position.as_secs_f32();
+    for line in lines {
+        painter.rect_filled(cell(line), 0.0, difficulty_color(line.score));
+    }
+    if let Some(current) = lines
+        .iter()
+        .find(|line| (line.start..line.end).contains(&now))
+    {
+        painter.rect_stroke(cell(current), 0.0, ui.visuals().widgets.active.fg_stroke);
+    }
+
+    let hovered = response
+        .hover_pos()
+        .and_then(|pointer| lines.iter().find(|line| cell(line).contains(pointer)));
+    let clicked = response.clicked();
+    if let Some(line) = hovered {
+        response.on_hover_text(format!(
+            "Line at {}: difficulty {:.0}/100 (click to jump there)",
+            format_time(Duration::from_secs_f32(line.start)),
+            line.score * 100.0
+        ));
+    }
+    hovered
+        .filter(|_| clicked)
+        .map(|line| Duration::from_secs_f32(line.start))
+}
@@ -0,0 +1,136 @@
+//! Downloads: URL entry, the download list with per-step pipeline status,
+//! and Settings → Downloads.
+
+use crate::app::KaraokeApp;
+use crate::download::pipeline::{PipelineStep, StepStatus};
+use crate::download::{DownloadJob, Stage};
+
+impl KaraokeApp {
+    /// URL field and the list of downloads, each expandable to show its
+    /// pipeline steps.
+    pub(crate) fn downloads_panel(&mut self, ui: &mut egui::Ui) {
+        let offline = self.config.network.offline;
+        ui.horizontal(|ui| {
+            let field = ui.add(
+                egui::TextEdit::singleline(&mut self.download_url)
+                    .hint_text("Video or song URL")
+                    .desired_width(320.0),
+            );
+            let url = self.download_url.trim();
+            let submitted = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            let button = ui
+                .add_enabled(!url.is_empty() && !offline, egui::Button::new("⬇ Download"))
+                .on_hover_text(if offline {
+                    "Offline mode is on"
+                } else {
+                    "Download the audio with yt-dlp"
+                });
+            if (button.clicked() || submitted) && !url.is_empty() && !offline {
+                let job = DownloadJob::start(
+                    url,
+                    &self.config.downloads,
+                    &self.config.lyrics,
+                    &self.config.network,
+                );
+                self.downloads.push(job);
+                self.download_url.clear();
+            }
+            if self.downloads.iter().any(|job| !job.is_running())
+                && ui.small_button("Clear finished").clicked()
+            {
+                self.downloads.retain(DownloadJob::is_running);
+            }
+        });
+
+        for (index, job) in self.downloads.iter().enumerate() {
+            let state = job.state();
+            let title = state
+                .file
+                .as_deref()
+                .and_then(|file| file.file_stem())
+                .map_or_else(
+                    || job.url().to_string(),
+                    |stem| stem.to_string_lossy().into_owned(),
+                );
+            let summary = match &state.stage {
+                Stage::Downloading => "downloading…".to_string(),
+                Stage::Processing => "processing…".to_string(),
+                Stage::Finished => "done".to_string(),
+                Stage::Failed(e) => format!("failed: {e}"),
+            };
+
+            egui::CollapsingHeader::new(format!("{title} — {summary}"))
+                .id_salt(("download", index))
+                .show(ui, |ui| {
+                    ui.weak(job.url());
+                    egui::Grid::new(("download_steps", index))
+                        .num_columns(2)
+                        .spacing([16.0, 4.0])
+                        .show(ui, |ui| {
+                            for (step, status) in &state.steps {
+                                ui.label(step.label());
+                                step_status(ui, status);
+                                ui.end_row();
+                            }
+                        });
+                });
+        }
+    }
+
+    /// Settings → Downloads. Returns true when the config changed.
+    pub(crate) fn download_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        egui::Grid::new("download_settings")
+            .num_columns(2)
+            .spacing([24.0, 8.0])
+            .show(ui, |ui| {
+                let downloads = &mut self.config.downloads;
+
+                ui.label("Download folder");
+                ui.horizontal(|ui| {
+                    ui.label(downloads.folder().display().to_string());
+                    if ui.small_button("Change…").clicked() {
+                        if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                            downloads.folder = Some(folder);
+                            changed = true;
+                        }
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Music videos");
+                changed |= ui
+                    .checkbox(&mut downloads.keep_video, "Keep the video")
+                    .on_hover_text("Download the video too and play it behind the lyrics")
+                    .changed();
+                ui.end_row();
+
+                ui.label("After downloading");
+                ui.vertical(|ui| {
+                    for step in PipelineStep::ALL {
+                        changed |= ui
+                            .checkbox(step.enabled_mut(downloads), step.label())
+                            .changed();
+                    }
+                });
+                ui.end_row();
+            });
+        changed
+    }
+}
+
+fn step_status(ui: &mut egui::Ui, status: &StepStatus) {
+    match status {
+        StepStatus::Pending => ui.weak("waiting"),
+        StepStatus::Running => {
+            ui.horizontal(|ui| {
+                ui.spinner();
+                ui.label("running…");
+            })
+            .response
+        },
+        StepStatus::Done(summary) => ui.label(format!("✔ {summary}")),
+        StepStatus::Skipped => ui.weak("off"),
+        StepStatus::Failed(e) => ui.colored_label(ui.visuals().error_fg_color, format!("✖ {e}")),
+    };
+}
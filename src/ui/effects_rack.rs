@@ -0,0 +1,86 @@
+//! Editor for a voice effects rack, shared by the settings and karaoke views.
+
+use crate::audio::effects::{VoiceEffect, VoiceEffectKind};
+
+/// Show the rack as an ordered list of effects. Returns true when it changed.
+pub(crate) fn effects_rack_editor(
+    ui: &mut egui::Ui,
+    id_salt: &str,
+    effects: &mut Vec<VoiceEffect>,
+) -> bool {
+    let mut changed = false;
+    let mut move_up = None;
+    let mut remove = None;
+
+    if effects.is_empty() {
+        ui.weak("No effects");
+    }
+
+    egui::Grid::new(id_salt)
+        .num_columns(3)
+        .spacing([12.0, 4.0])
+        .show(ui, |ui| {
+            let count = effects.len();
+            for (index, effect) in effects.iter_mut().enumerate() {
+                changed |= ui
+                    .checkbox(&mut effect.enabled, effect.kind.label())
+                    .changed();
+                changed |= ui
+                    .add_enabled(
+                        effect.enabled,
+                        egui::Slider::new(&mut effect.amount, 0.0..=1.0)
+                            .text(amount_label(effect.kind)),
+                    )
+                    .changed();
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(index > 0, egui::Button::new("⏶").small())
+                        .on_hover_text("Move earlier in the chain")
+                        .clicked()
+                    {
+                        move_up = Some(index);
+                    }
+                    if ui
+                        .add_enabled(index + 1 < count, egui::Button::new("⏷").small())
+                        .on_hover_text("Move later in the chain")
+                        .clicked()
+                    {
+                        move_up = Some(index + 1);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        remove = Some(index);
+                    }
+                });
+                ui.end_row();
+            }
+        });
+
+    if let Some(index) = move_up {
+        effects.swap(index - 1, index);
+        changed = true;
+    }
+    if let Some(index) = remove {
+        effects.remove(index);
+        changed = true;
+    }
+
+    ui.menu_button("➕ Add effect", |ui| {
+        for kind in VoiceEffectKind::ALL {
+            if ui.button(kind.label()).clicked() {
+                effects.push(VoiceEffect::new(kind));
+                changed = true;
+                ui.close_menu();
+            }
+        }
+    });
+
+    changed
+}
+
+fn amount_label(kind: VoiceEffectKind) -> &'static str {
+    match kind {
+        VoiceEffectKind::PitchCorrection => "strength",
+        kind if kind.is_ambience() => "level",
+        _ => "mix",
+    }
+}
@@ -0,0 +1,86 @@
+//! Fallback fonts for scripts the bundled egui fonts lack.
+//!
+//! egui ships Latin, Greek and Cyrillic glyphs only, so Arabic and Hebrew
+//! lyrics would draw as boxes. Common system fonts covering them are added
+//! behind the default fonts when present.
+//!
+//! Lyrics are drawn in their own font family, so a font file chosen in the
+//! settings changes the lyrics and leaves the rest of the interface alone.
+
+use std::fs;
+use std::path::Path;
+
+/// System fonts with Arabic and/or Hebrew glyphs, in order of preference.
+const FALLBACK_FONTS: [&str; 9] = [
+    "/usr/share/fonts/truetype/noto/NotoSansArabic-Regular.ttf",
+    "/usr/share/fonts/truetype/noto/NotoSansHebrew-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansArabic-Regular.ttf",
+    "/usr/share/fonts/noto/NotoSansHebrew-Regular.ttf",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "C:\\Windows\\Fonts\\segoeui.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+];
+
+/// Name of the font family lyrics are drawn in.
+const LYRICS_FAMILY: &str = "lyrics";
+
+/// Lyrics font at `size` points.
+pub fn lyrics_font(size: f32) -> egui::FontId {
+    egui::FontId::new(size, egui::FontFamily::Name(LYRICS_FAMILY.into()))
+}
+
+/// Add every fallback font found on this system to the font families,
+/// and `lyric_font` in front of the lyrics family when it is a readable
+/// font file.
+pub fn install_fonts(ctx: &egui::Context, lyric_font: Option<&Path>) {
+    let mut fonts = egui::FontDefinitions::default();
+    let mut installed = 0;
+    for path in FALLBACK_FONTS.map(Path::new) {
+        let Ok(data) = fs::read(path) else {
+            continue;
+        };
+        let name = path.display().to_string();
+        fonts
+            .font_data
+            .insert(name.clone(), egui::FontData::from_owned(data));
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts.families.entry(family).or_default().push(name.clone());
+        }
+        installed += 1;
+    }
+    tracing::debug!("Installed {installed} fallback fonts");
+
+    let mut lyrics = fonts
+        .families
+        .get(&egui::FontFamily::Proportional)
+        .cloned()
+        .unwrap_or_default();
+    if let Some(path) = lyric_font {
+        match fs::read(path) {
+            // egui panics on data that is not a font
+            Ok(data) if is_font(&data) => {
+                let name = format!("{LYRICS_FAMILY}: {}", path.display());
+                fonts
+                    .font_data
+                    .insert(name.clone(), egui::FontData::from_owned(data));
+                lyrics.insert(0, name);
+            },
+            Ok(_) => tracing::warn!("{} is not a TrueType or OpenType font", path.display()),
+            Err(e) => tracing::warn!("Cannot read lyrics font {}: {e}", path.display()),
+        }
+    }
+    fonts
+        .families
+        .insert(egui::FontFamily::Name(LYRICS_FAMILY.into()), lyrics);
+    ctx.set_fonts(fonts);
+}
+
+/// Whether `data` starts like a TrueType or OpenType font or collection.
+fn is_font(data: &[u8]) -> bool {
+    matches!(
+        data.get(..4),
+        Some([0, 1, 0, 0] | b"OTTO" | b"true" | b"ttcf")
+    )
+}
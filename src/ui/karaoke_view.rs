@@ -0,0 +1,1392 @@
+//! Karaoke view shown while a song plays.
+
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::time::Duration;
+
+use super::difficulty::difficulty_heat_map;
+use super::effects_rack::effects_rack_editor;
+use super::lyrics_layout::{
+    fit_line, fit_sung_line, lyric_label, side_lines, LineTransition, LyricColors,
+};
+use super::pitch_guide::pitch_guide;
+use super::reference_keyboard::reference_keyboard;
+use crate::app::{format_time, song_title, KaraokeApp, DEFAULT_BPM, LYRIC_NUDGE_MS};
+use crate::audio::effects::{VoiceEffect, VoiceEffectKind};
+use crate::audio::export::MixPreset;
+use crate::audio::key;
+use crate::audio::midi;
+use crate::audio::separation;
+use crate::audio::AudioPlayer;
+use crate::cdg::{self, CdgPlayback};
+use crate::cover_art::CoverArt;
+use crate::library::cues;
+use crate::library::language::LANGUAGES;
+use crate::library::sections::{self, SectionKind};
+use crate::lrc;
+use crate::lrc::bidi::TextDirection;
+use crate::lrc::romaji;
+use crate::lrc::{LrcDocument, TimedLine};
+use crate::lyrics::local;
+use crate::video::{self, VideoPlayback};
+
+/// A translation line belongs to the lyric line sung at most this long
+/// before it starts, as the two files are timed separately.
+const TRANSLATION_TOLERANCE: Duration = Duration::from_millis(500);
+/// Seconds before the next line that the break countdown shows.
+const COUNTDOWN_SECS: f32 = 5.0;
+/// Size of the break countdown, one dot per second over a shrinking bar.
+const COUNTDOWN_SIZE: egui::Vec2 = egui::vec2(120.0, 16.0);
+/// Height of the bar under the sung line.
+const LINE_PROGRESS_HEIGHT: f32 = 3.0;
+/// Change of the teleprompter speed per key press.
+const TELEPROMPTER_NUDGE: f32 = 0.1;
+/// The teleprompter speed is kept within this range.
+const TELEPROMPTER_SPEEDS: RangeInclusive<f32> = 0.2..=3.0;
+/// A larger move of the song position is a seek, after which the
+/// teleprompter starts again from where the song is.
+const TELEPROMPTER_MAX_STEP: Duration = Duration::from_secs(1);
+
+/// Auto-scrolling through lyrics without timestamps.
+pub(crate) struct Teleprompter {
+    /// How far down the text the view is, 0.0 to 1.0
+    progress: f32,
+    /// Song position `progress` was last moved on at
+    position: Duration,
+    /// Pace relative to going through the text once over the song
+    speed: f32,
+    /// How far the text can scroll, as of the last frame
+    scrollable: f32,
+}
+
+impl Default for Teleprompter {
+    fn default() -> Self {
+        Self {
+            progress: 0.0,
+            position: Duration::ZERO,
+            speed: 1.0,
+            scrollable: 0.0,
+        }
+    }
+}
+
+impl Teleprompter {
+    /// Speed up by `steps` nudges, or slow down when negative.
+    pub(crate) fn nudge(&mut self, steps: i64) {
+        self.speed = (self.speed + steps as f32 * TELEPROMPTER_NUDGE)
+            .clamp(*TELEPROMPTER_SPEEDS.start(), *TELEPROMPTER_SPEEDS.end());
+    }
+
+    /// Move on as the song went on to `position` of `duration`.
+    fn follow(&mut self, position: Duration, duration: Duration) {
+        let duration = duration.as_secs_f32().max(0.001);
+        self.progress = match position.checked_sub(self.position) {
+            Some(step) if step <= TELEPROMPTER_MAX_STEP => {
+                self.progress + step.as_secs_f32() / duration * self.speed
+            },
+            _ => position.as_secs_f32() / duration,
+        }
+        .clamp(0.0, 1.0);
+        self.position = position;
+    }
+}
+/// Brightness of the cover art background at full intensity, darkened so
+/// the lyrics stand out.
+const COVER_BRIGHTNESS: u8 = 96;
+/// Brightness of the music video behind the lyrics.
+const VIDEO_BRIGHTNESS: u8 = 160;
+
+impl KaraokeApp {
+    pub(crate) fn karaoke_view(&mut self, ui: &mut egui::Ui) {
+        let current = self
+            .player
+            .as_ref()
+            .and_then(|player| player.current_path())
+            .map(Path::to_path_buf);
+        if let Some(path) = &current {
+            self.karaoke_background(ui, path);
+        }
+        self.ticker(ui);
+
+        let Some(path) = current else {
+            self.video = None;
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.label("Pick a song in the Library to start singing.");
+                self.export_button(ui);
+            });
+            return;
+        };
+        if self.song_summary.is_some() {
+            self.song_summary_view(ui);
+            return;
+        }
+
+        self.track_choice(ui, &path);
+        self.song_controls(ui, &path);
+        self.rate_controls(ui, &path);
+        self.key_controls(ui, &path);
+        self.recording_controls(ui, &path);
+        self.section_controls(ui, &path);
+        self.cue_controls(ui, &path);
+        self.lyric_offset_controls(ui, &path);
+        self.translation_controls(ui, &path);
+        self.romaji_controls(ui, &path);
+        self.autotune_controls(ui, &path);
+        self.ambience_controls(ui, &path);
+        self.reference_controls(ui, &path);
+        self.difficulty_row(ui, &path);
+        if self.config.display.show_mic_meter && self.mic.is_some() {
+            ui.horizontal(|ui| {
+                ui.label("Mic:");
+                self.mic_meter(ui, 0, 160.0, None);
+                if self.duet_mic.is_some() {
+                    ui.label("Mic 2:");
+                    self.mic_meter(ui, 1, 160.0, None);
+                }
+            });
+        }
+        if self.config.display.show_pitch_guide {
+            self.pitch_guide_lane(ui, &path);
+        }
+        if let Some(e) = self.video.as_ref().and_then(VideoPlayback::error) {
+            ui.weak(format!("Video unavailable: {e}"));
+        }
+        ui.separator();
+
+        if self.cdg_graphics(ui, &path) {
+            return;
+        }
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 3.0);
+            let direction = self
+                .storage
+                .entry(&path)
+                .map(|entry| entry.text_direction)
+                .unwrap_or_default();
+            let title = fit_line(
+                ui,
+                &song_title(&path),
+                direction,
+                &self.config.display,
+                ui.visuals().text_color(),
+                ui.available_width(),
+            );
+            ui.label(title);
+            self.preferred_key_hint(ui, &path);
+            ui.add_space(self.config.display.font_size);
+            self.synced_lyrics(ui, &path, direction);
+        });
+    }
+
+    /// The lyric line being sung at the configured font size, between the
+    /// previous line and the configured number of upcoming lines, in
+    /// smaller, dimmer text, with its translation under it when one is
+    /// chosen. The `.lrc` is read again whenever it changes on disk, e.g.
+    /// after fetching lyrics. Without one, the lyrics tag of the song is
+    /// shown instead.
+    pub(crate) fn synced_lyrics(
+        &mut self,
+        ui: &mut egui::Ui,
+        path: &Path,
+        direction: TextDirection,
+    ) {
+        let lrc = lrc::lrc_path(path);
+        let modified = fs::metadata(&lrc)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if self
+            .lyrics
+            .as_ref()
+            .is_none_or(|(song, read, _)| song != path || *read != modified)
+        {
+            // MIDI karaoke files carry their own lyrics
+            self.plain_lyrics = None;
+            self.teleprompter = None;
+            let document = match modified {
+                Some(_) => read_document(&lrc),
+                None if midi::is_midi(path) => LrcDocument::from_lines(midi::lyrics(path)),
+                None => match local::embedded(path) {
+                    Some(embedded) => {
+                        self.plain_lyrics = embedded.plain;
+                        embedded
+                            .synced
+                            .map(|text| LrcDocument::from_events(&lrc::parse_lrc(&text)))
+                            .unwrap_or_default()
+                    },
+                    None => LrcDocument::default(),
+                },
+            };
+            self.lyrics = Some((path.to_path_buf(), modified, document));
+        }
+        self.load_translation(path);
+        self.load_romaji(path);
+        let Some((_, _, document)) = &self.lyrics else {
+            return;
+        };
+        if document.is_empty() {
+            match self.plain_lyrics.clone() {
+                Some(text) => self.unsynced_lyrics(ui, &text, direction),
+                None => {
+                    ui.weak("This song has no synced lyrics. Fetch or import them in the Library.");
+                },
+            }
+            return;
+        }
+
+        // Lines shown earlier are the same as the song being further on
+        let position = self
+            .player
+            .as_ref()
+            .map(|player| lrc::shift(player.get_position(), -self.adjustments.lyric_offset_ms))
+            .unwrap_or_default();
+        let lines = document.lines();
+        let current = document.index_at(position);
+        let text = |index: Option<usize>| {
+            index
+                .and_then(|index| lines.get(index))
+                .map_or("", |line| line.text.as_str())
+        };
+        let previous = text(current.and_then(|index| index.checked_sub(1)));
+        let first_upcoming = current.map_or(0, |index| index + 1);
+        let sung = match text(current) {
+            // Before the first line and in instrumental breaks
+            "" => "♪",
+            sung => sung,
+        };
+        // As far as the word timestamps say it has been sung
+        let sung_chars = document.sung_chars(position);
+
+        // How far the move onto the current line has got
+        let now = ui.input(|input| input.time);
+        if self.lyric_line_shown.0 != current {
+            self.lyric_line_shown = (current, now);
+        }
+        let display = &self.config.display;
+        let elapsed = (now - self.lyric_line_shown.1) as f32;
+        let progress = display
+            .transition_easing
+            .apply(elapsed / display.transition_secs.max(0.001));
+        if progress < 1.0 {
+            // Also when paused, e.g. after seeking
+            ui.ctx().request_repaint();
+        }
+        let (fade, scroll) = match display.line_transition {
+            LineTransition::Instant => (1.0, 0.0),
+            LineTransition::Fade => (progress, 0.0),
+            LineTransition::Scroll => (1.0, 1.0 - progress),
+        };
+
+        let side = side_lines(display);
+        let width = ui.available_width();
+        let colors = LyricColors::new(ui.visuals(), display);
+        let upcoming = colors
+            .active
+            .gamma_multiply(1.0 - display.upcoming_dim.clamp(0.0, 1.0));
+        // Each line fades from the colour it had as the line below
+        let previous_color = colors.active.lerp_to_gamma(colors.inactive, fade);
+        let active = upcoming.lerp_to_gamma(colors.active, fade);
+        // Starts a line lower and slides up
+        ui.add_space(scroll * display.font_size);
+        let galley = fit_line(ui, previous, direction, &side, previous_color, width);
+        lyric_label(ui, galley, &side);
+        if display.break_countdown_secs > 0.0 {
+            let left = countdown_left(lines, current, position, display.break_countdown_secs);
+            break_countdown(ui, left);
+        }
+        if self.config.display.show_romaji {
+            let line = current.and_then(|index| lines.get(index));
+            let reading = match (&self.romaji, line) {
+                (Some((_, _, romaji)), Some(line)) if !romaji.is_empty() => {
+                    Some(translated_line(romaji, line.start).to_string())
+                },
+                (_, Some(line)) => romaji::transliterate(&line.text),
+                (_, None) => None,
+            };
+            let reading = reading.unwrap_or_default();
+            let galley = fit_line(
+                ui,
+                &reading,
+                TextDirection::Auto,
+                &side,
+                colors.inactive,
+                width,
+            );
+            lyric_label(ui, galley, &side);
+        }
+        let galley = match sung_chars {
+            Some(chars) => fit_sung_line(
+                ui,
+                sung,
+                chars,
+                direction,
+                display,
+                (colors.highlight, active),
+                width,
+            ),
+            None => fit_line(ui, sung, direction, display, active, width),
+        };
+        let sung_width = galley.size().x;
+        lyric_label(ui, galley, display);
+        if display.line_progress {
+            let fraction = line_progress(lines, current, position);
+            line_progress_bar(ui, sung_width, fraction, colors.highlight);
+        }
+        if let Some((_, _, translation)) = &self.translation {
+            let start = current
+                .and_then(|index| lines.get(index))
+                .map(|line| line.start);
+            let translated = start.map_or("", |start| translated_line(translation, start));
+            let galley = fit_line(
+                ui,
+                translated,
+                TextDirection::Auto,
+                &side,
+                colors.inactive,
+                width,
+            );
+            lyric_label(ui, galley, &side);
+        }
+        for (offset, index) in (first_upcoming..first_upcoming + display.upcoming_lines).enumerate()
+        {
+            let line = text(Some(index));
+            // The last line is new, so it fades in
+            let color = if offset + 1 == display.upcoming_lines {
+                upcoming.gamma_multiply(fade)
+            } else {
+                upcoming
+            };
+            let galley = fit_line(ui, line, direction, &side, color, width);
+            lyric_label(ui, galley, &side);
+        }
+    }
+
+    /// Lyrics without timestamps, gone through at an even pace over the
+    /// song: as a teleprompter scrolling the whole text, or else with the
+    /// line that far into it highlighted. Only a guess at what is being
+    /// sung, but better than nothing to read along.
+    fn unsynced_lyrics(&mut self, ui: &mut egui::Ui, text: &str, direction: TextDirection) {
+        let (position, duration) = self
+            .player
+            .as_ref()
+            .and_then(|player| Some((player.get_position(), player.duration()?)))
+            .unwrap_or_default();
+        let lines: Vec<&str> = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+
+        let display = &self.config.display;
+        if !display.teleprompter {
+            self.teleprompter = None;
+            let fraction = (position.as_secs_f32() / duration.as_secs_f32().max(0.001)).min(1.0);
+            let current =
+                ((fraction * lines.len() as f32) as usize).min(lines.len().saturating_sub(1));
+            let side = side_lines(display);
+            let colors = LyricColors::new(ui.visuals(), display);
+            ui.weak("Lyrics from the song's tags, not synced");
+            egui::ScrollArea::vertical()
+                .auto_shrink(false)
+                .show(ui, |ui| {
+                    let width = ui.available_width();
+                    for (index, line) in lines.iter().enumerate() {
+                        let color = if index == current {
+                            colors.active
+                        } else {
+                            colors.inactive
+                        };
+                        let galley = fit_line(ui, line, direction, &side, color, width);
+                        let response = lyric_label(ui, galley, &side);
+                        if index == current {
+                            response.scroll_to_me(Some(egui::Align::Center));
+                        }
+                    }
+                });
+            return;
+        }
+
+        let teleprompter = self.teleprompter.get_or_insert_with(Teleprompter::default);
+        teleprompter.follow(position, duration);
+        ui.weak(format!(
+            "Lyrics from the song's tags, not synced. Teleprompter at {:.0}% (+/− to change)",
+            teleprompter.speed * 100.0
+        ));
+        let color = LyricColors::new(ui.visuals(), display).active;
+        let output = egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .enable_scrolling(false)
+            .vertical_scroll_offset(teleprompter.progress * teleprompter.scrollable)
+            .show(ui, |ui| {
+                let width = ui.available_width();
+                for line in &lines {
+                    let galley = fit_line(ui, line, direction, display, color, width);
+                    lyric_label(ui, galley, display);
+                }
+            });
+        teleprompter.scrollable = (output.content_size.y - output.inner_rect.height()).max(0.0);
+    }
+
+    /// Languages `song` has lyrics translations in, looked up once per song.
+    fn translation_languages(&mut self, song: &Path) -> &[String] {
+        if self
+            .translation_languages
+            .as_ref()
+            .is_none_or(|(read, _)| read != song)
+        {
+            let languages = lrc::translation_languages(song);
+            self.translation_languages = Some((song.to_path_buf(), languages));
+        }
+        self.translation_languages
+            .as_ref()
+            .map_or(&[], |(_, languages)| languages.as_slice())
+    }
+
+    /// Read the chosen translation of `song`, again whenever it changes on
+    /// disk; none when the song is not translated into that language.
+    fn load_translation(&mut self, song: &Path) {
+        let Some(language) = self.config.display.translation_language.clone() else {
+            self.translation = None;
+            return;
+        };
+        if !self.translation_languages(song).contains(&language) {
+            self.translation = None;
+            return;
+        }
+        let lrc = lrc::translation_path(song, &language);
+        let modified = fs::metadata(&lrc)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if self
+            .translation
+            .as_ref()
+            .is_none_or(|(read, at, _)| *read != lrc || *at != modified)
+        {
+            let document = read_document(&lrc);
+            self.translation = Some((lrc, modified, document));
+        }
+    }
+
+    /// Read the romaji companion of `song` while romaji are shown, again
+    /// whenever it changes on disk.
+    fn load_romaji(&mut self, song: &Path) {
+        if !self.config.display.show_romaji {
+            self.romaji = None;
+            return;
+        }
+        let lrc = romaji::romaji_path(song);
+        let modified = fs::metadata(&lrc)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if self
+            .romaji
+            .as_ref()
+            .is_none_or(|(read, at, _)| *read != lrc || *at != modified)
+        {
+            let document = modified.map_or_else(LrcDocument::default, |_| read_document(&lrc));
+            self.romaji = Some((lrc, modified, document));
+        }
+    }
+
+    /// Turn the romaji reading above the lyrics on or off. Only shown for
+    /// songs with a romaji companion or lyrics in kana.
+    fn romaji_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let japanese = self.lyrics.as_ref().is_some_and(|(song, _, document)| {
+            song == path
+                && document
+                    .lines()
+                    .iter()
+                    .any(|line| romaji::has_kana(&line.text))
+        });
+        if !japanese && !romaji::romaji_path(path).exists() {
+            return;
+        }
+        if ui
+            .checkbox(&mut self.config.display.show_romaji, "Romaji")
+            .on_hover_text(
+                "Show the reading above the lyrics, from song.romaji.lrc or generated from kana",
+            )
+            .changed()
+        {
+            self.save_config();
+        }
+    }
+
+    /// Pick the translation shown under the lyrics, among the languages the
+    /// song is translated into. Only shown when there are some.
+    fn translation_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let languages = self.translation_languages(path).to_vec();
+        if languages.is_empty() {
+            return;
+        }
+        let chosen = &mut self.config.display.translation_language;
+        let before = chosen.clone();
+        ui.horizontal(|ui| {
+            ui.label("Translation:");
+            ui.selectable_value(chosen, None, "Off");
+            for language in languages {
+                let label = language.clone();
+                ui.selectable_value(chosen, Some(language), label);
+            }
+        });
+        if self.config.display.translation_language != before {
+            self.save_config();
+        }
+    }
+
+    /// Nudge the lyric timing while the song plays, and write the nudge
+    /// into the `.lrc`'s `[offset:]` tag. Only shown for synced lyrics.
+    fn lyric_offset_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let has_lyrics = self
+            .lyrics
+            .as_ref()
+            .is_some_and(|(song, _, document)| song == path && !document.is_empty());
+        if !has_lyrics {
+            return;
+        }
+        let destination = self.adjustments_destination();
+        let before = self.adjustments.lyric_offset_ms;
+        let offset = &mut self.adjustments.lyric_offset_ms;
+        let mut save = false;
+        ui.horizontal(|ui| {
+            ui.label("Lyrics timing:");
+            if ui
+                .small_button("−")
+                .on_hover_text("Show the lyrics later (− key)")
+                .clicked()
+            {
+                *offset -= LYRIC_NUDGE_MS;
+            }
+            ui.monospace(format!("{offset:+} ms"));
+            if ui
+                .small_button("+")
+                .on_hover_text("Show the lyrics earlier (+ key)")
+                .clicked()
+            {
+                *offset += LYRIC_NUDGE_MS;
+            }
+            let nudged = *offset != 0;
+            if ui.add_enabled(nudged, egui::Button::new("Reset")).clicked() {
+                *offset = 0;
+            }
+            save = ui
+                .add_enabled(nudged, egui::Button::new("💾 Save to .lrc"))
+                .on_hover_text("Keep this timing in the lyrics file's [offset:] tag")
+                .clicked();
+            adjustments_indicator(ui, &destination);
+        });
+        if save {
+            self.save_lyric_offset(path);
+        } else if self.adjustments.lyric_offset_ms != before {
+            self.adjustments_changed();
+        }
+    }
+
+    /// Add the live nudge to the `.lrc`'s offset tag, then reload it.
+    fn save_lyric_offset(&mut self, path: &Path) {
+        let lrc = lrc::lrc_path(path);
+        let saved = lrc::parse_lrc_file(&lrc).and_then(|events| {
+            lrc::set_offset_tag(
+                &lrc,
+                lrc::offset_ms(&events) + self.adjustments.lyric_offset_ms,
+            )
+        });
+        match saved {
+            Ok(()) => {
+                self.adjustments.lyric_offset_ms = 0;
+                self.adjustments_changed();
+                // The file may change within its timestamp's resolution
+                self.lyrics = None;
+            },
+            Err(e) => self.status = Some(e.to_string()),
+        }
+    }
+
+    /// The pitch guide once the melody is tracked, a note while it is.
+    fn pitch_guide_lane(&mut self, ui: &mut egui::Ui, path: &Path) {
+        // Turned on in the middle of a song
+        self.request_melody(path);
+        match &self.melody {
+            Some((song, melody)) if song == path && !melody.notes.is_empty() => {
+                let position = self
+                    .player
+                    .as_ref()
+                    .map(AudioPlayer::get_position)
+                    .unwrap_or_default();
+                for (index, trail) in self.pitch_trails.iter_mut().enumerate() {
+                    trail.push(position.as_secs_f32(), self.params.mic(index).pitch());
+                }
+                let key_shift = self.key_shift();
+                pitch_guide(ui, melody, key_shift, position, &self.pitch_trails);
+            },
+            _ if self
+                .melody_job
+                .as_ref()
+                .is_some_and(|job| job.song() == path) =>
+            {
+                ui.weak("Pitch guide: tracking the melody…");
+            },
+            _ => {},
+        }
+    }
+
+    /// The song's music video, or else its blurred cover, filling `ui`
+    /// behind what is drawn next. Call before laying out anything else.
+    pub(crate) fn karaoke_background(&mut self, ui: &egui::Ui, path: &Path) {
+        if !(self.config.display.show_video && self.video_background(ui, path)) {
+            self.cover_background(ui, path);
+        }
+    }
+
+    /// The song's cover, blurred and darkened, filling `ui`.
+    fn cover_background(&mut self, ui: &egui::Ui, path: &Path) {
+        let intensity = self.config.display.cover_background.clamp(0.0, 1.0);
+        if intensity <= 0.0 {
+            return;
+        }
+        let cover = match &mut self.cover_art {
+            Some(cover) if cover.song() == path => cover,
+            slot => slot.insert(CoverArt::load(path)),
+        };
+        let Some(texture) = cover.texture(ui.ctx()) else {
+            return;
+        };
+        let rect = ui.clip_rect();
+        let uv = cover_uv(rect.aspect_ratio(), texture.aspect_ratio());
+        let tint = egui::Color32::from_gray(COVER_BRIGHTNESS).gamma_multiply(intensity);
+        ui.painter().image(texture.id(), rect, uv, tint);
+    }
+
+    /// The song's CD+G graphics at the playback position, as large as they
+    /// fit. Returns false when the song has none to show.
+    pub(crate) fn cdg_graphics(&mut self, ui: &mut egui::Ui, path: &Path) -> bool {
+        let Some(position) = self.player.as_ref().map(|player| player.get_position()) else {
+            return false;
+        };
+        let Some(cdg_path) = cdg::cdg_path(path) else {
+            self.cdg = None;
+            return false;
+        };
+        let cdg = match &mut self.cdg {
+            Some(cdg) if cdg.path() == cdg_path => cdg,
+            slot => slot.insert(CdgPlayback::open(&cdg_path)),
+        };
+
+        if let Some(e) = cdg.error() {
+            ui.weak(format!("CD+G graphics unavailable: {e}"));
+            return false;
+        }
+        let Some(texture) = cdg.update(ui.ctx(), position) else {
+            return false;
+        };
+        let available = ui.available_size();
+        let size = texture.size_vec2();
+        let scale = (available.x / size.x).min(available.y / size.y);
+        ui.vertical_centered(|ui| ui.image((texture.id(), size * scale)));
+        true
+    }
+
+    /// The song's music video at the playback position, darkened and
+    /// filling `ui`. Returns false when the song has no video to show.
+    fn video_background(&mut self, ui: &egui::Ui, path: &Path) -> bool {
+        let Some(position) = self.player.as_ref().map(|player| player.get_position()) else {
+            return false;
+        };
+        let Some(video_path) = video::video_path(path) else {
+            self.video = None;
+            return false;
+        };
+        let video = match &mut self.video {
+            Some(video) if video.path() == video_path => video,
+            slot => slot.insert(VideoPlayback::open(&video_path, position)),
+        };
+
+        if video.error().is_some() {
+            return false;
+        }
+        // Nothing yet while the first frame decodes
+        let Some(texture) = video.update(ui.ctx(), position) else {
+            return true;
+        };
+        let rect = ui.clip_rect();
+        let uv = cover_uv(rect.aspect_ratio(), texture.aspect_ratio());
+        let tint = egui::Color32::from_gray(VIDEO_BRIGHTNESS);
+        ui.painter().image(texture.id(), rect, uv, tint);
+        true
+    }
+
+    /// Original / Instrumental switch. Choosing the instrumental of a song
+    /// that has not been separated yet starts the separation.
+    fn track_choice(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let entry = self.storage.entry_mut(path);
+        let before = entry.instrumental;
+        let cached = separation::instrumental_path(path).exists();
+
+        ui.horizontal(|ui| {
+            ui.label("Track:");
+            ui.selectable_value(&mut entry.instrumental, false, "Original");
+            ui.selectable_value(&mut entry.instrumental, true, "Instrumental")
+                .on_hover_text(if cached {
+                    "Play the separated instrumental stem; switching keeps your place"
+                } else {
+                    "Separate the vocals first (this takes a while)"
+                });
+            if self.separator.is_pending(path) {
+                ui.spinner();
+                ui.weak(format!(
+                    "Separating with {}…",
+                    self.config.audio.separation_backend.label()
+                ));
+            }
+        });
+
+        if entry.instrumental == before {
+            return;
+        }
+        let instrumental = entry.instrumental;
+        self.save_library();
+        if instrumental && !cached {
+            self.separator
+                .request(path, self.config.audio.separation_backend);
+            return;
+        }
+        // Instant when both renditions are loaded, otherwise reload in place
+        let switched = self
+            .player
+            .as_ref()
+            .is_some_and(|player| player.set_instrumental(instrumental));
+        if !switched {
+            self.reload_song();
+        }
+    }
+
+    /// Quick playback rate presets for rehearsing fast passages, kept as
+    /// the adjustment policy says, and the count-in tempo, saved per song.
+    fn rate_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        const RATES: [(f32, &str); 3] = [(0.5, "0.5×"), (0.75, "0.75×"), (1.0, "1×")];
+
+        let count_in = self.config.audio.count_in;
+        let current = self.adjustments.playback_rate.unwrap_or(1.0);
+        let destination = self.adjustments_destination();
+        let mut rate_changed = false;
+        let entry = self.storage.entry_mut(path);
+        let before = entry.bpm;
+
+        ui.horizontal(|ui| {
+            ui.label("Speed:");
+            for (rate, label) in RATES {
+                if ui
+                    .selectable_label(current == rate, label)
+                    .on_hover_text("Slower playback also lowers the pitch")
+                    .clicked()
+                    && rate != current
+                {
+                    self.adjustments.playback_rate = (rate != 1.0).then_some(rate);
+                    rate_changed = true;
+                }
+            }
+            adjustments_indicator(ui, &destination);
+
+            if count_in {
+                ui.separator();
+                let mut bpm = entry.bpm.unwrap_or(DEFAULT_BPM);
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut bpm)
+                            .range(30.0..=300.0)
+                            .speed(0.5)
+                            .suffix(" BPM"),
+                    )
+                    .on_hover_text("Tempo of the count-in for this song")
+                    .changed()
+                {
+                    entry.bpm = Some(bpm);
+                }
+            }
+        });
+
+        if entry.bpm != before {
+            self.save_library();
+        }
+        if rate_changed {
+            self.adjustments_changed();
+        }
+    }
+
+    /// Shift the backing track up or down a semitone at a time, from the
+    /// key detected for the song.
+    fn key_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let detected = self.storage.entry(path).and_then(|entry| entry.key);
+        let shift = self.key_shift();
+        let adjusted = self.adjustments.key_shift.is_some();
+        let destination = self.adjustments_destination();
+        let mut changed = None;
+
+        ui.horizontal(|ui| {
+            ui.label("Key:");
+            if ui
+                .add_enabled(shift > -key::MAX_SHIFT, egui::Button::new("−"))
+                .on_hover_text("Lower a semitone")
+                .clicked()
+            {
+                changed = Some(Some(shift - 1));
+            }
+            let shifted = match detected {
+                Some(key) if shift == 0 => key.to_string(),
+                Some(key) => format!("{} ({shift:+})", key.transposed(shift)),
+                None if shift == 0 => "Original".to_owned(),
+                None => format!("{shift:+} semitones"),
+            };
+            let label = ui.label(shifted);
+            match detected {
+                Some(key) => label.on_hover_text(format!("Detected key: {key}")),
+                None => label.on_hover_text("Key not detected yet"),
+            };
+            if ui
+                .add_enabled(shift < key::MAX_SHIFT, egui::Button::new("+"))
+                .on_hover_text("Raise a semitone")
+                .clicked()
+            {
+                changed = Some(Some(shift + 1));
+            }
+            if ui
+                .add_enabled(adjusted, egui::Button::new("Reset"))
+                .on_hover_text("Back to the singer's preferred key, or the original")
+                .clicked()
+            {
+                changed = Some(None);
+            }
+            adjustments_indicator(ui, &destination);
+        });
+
+        if let Some(key_shift) = changed {
+            self.adjustments.key_shift = key_shift;
+            self.adjustments_changed();
+        }
+    }
+
+    /// Start or stop recording the performance of this song.
+    fn recording_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        ui.horizontal(|ui| match &self.recording {
+            Some((_, _, recorder)) => {
+                let elapsed = recorder.elapsed();
+                if ui.button("⏹ Stop recording").clicked() {
+                    self.stop_recording();
+                }
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("● REC {}", format_time(elapsed)),
+                );
+            },
+            None => {
+                if ui
+                    .button("⏺ Record")
+                    .on_hover_text(format!(
+                        "Record your voice into {}",
+                        self.config.recording.folder().display()
+                    ))
+                    .clicked()
+                {
+                    self.start_recording(path);
+                }
+                self.export_button(ui);
+            },
+        });
+    }
+
+    /// Export the last recording mixed with its backing track, once there
+    /// is one.
+    fn export_button(&mut self, ui: &mut egui::Ui) {
+        if self.last_performance.is_none() {
+            return;
+        }
+        if self.export_job.is_some() {
+            ui.spinner();
+            ui.label("Exporting…");
+        } else {
+            ui.menu_button("Export performance…", |ui| {
+                for preset in MixPreset::ALL {
+                    if ui
+                        .button(preset.label())
+                        .on_hover_text(preset.description())
+                        .clicked()
+                    {
+                        ui.close_menu();
+                        self.export_performance(preset);
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Save your last recording mixed with the backing track");
+        }
+    }
+
+    /// Mark song sections at the playback position; they colour the seek
+    /// bar.
+    fn section_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let Some(position) = self.player.as_ref().map(|player| player.get_position()) else {
+            return;
+        };
+        let entry = self.storage.entry_mut(path);
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Sections:");
+            egui::ComboBox::from_id_salt("section_kind")
+                .selected_text(self.section_kind.label())
+                .show_ui(ui, |ui| {
+                    for kind in SectionKind::ALL {
+                        ui.selectable_value(&mut self.section_kind, kind, kind.label());
+                    }
+                });
+            if ui
+                .button("📍 Mark here")
+                .on_hover_text("Start a section at the current position")
+                .clicked()
+            {
+                sections::insert_section(&mut entry.sections, position, self.section_kind);
+                changed = true;
+            }
+            if let Some(current) = sections::section_at(&entry.sections, position) {
+                let start = Duration::from_secs_f32(current.start);
+                if ui
+                    .small_button(format!("🗑 {}", current.kind.label()))
+                    .on_hover_text("Remove the marker of the current section")
+                    .clicked()
+                {
+                    changed |= sections::remove_section_near(&mut entry.sections, start);
+                }
+            }
+            if !entry.sections.is_empty() && ui.small_button("Clear all").clicked() {
+                entry.sections.clear();
+                changed = true;
+            }
+        });
+
+        if changed {
+            self.save_library();
+        }
+    }
+
+    /// One button per cue point jumping to it; right-click removes a cue.
+    fn cue_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        let position = player.get_position();
+        let entry = self.storage.entry_mut(path);
+        let mut target = None;
+        let mut changed = false;
+
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Cues:");
+            let mut removed = None;
+            for (index, cue) in entry.cues.iter().enumerate() {
+                let response = ui.button(format!("⏩ {}", cue.name)).on_hover_text(format!(
+                    "Jump to {} (right-click to remove)",
+                    format_time(cue.position())
+                ));
+                if response.clicked() {
+                    target = Some(cue.position());
+                }
+                if response.secondary_clicked() {
+                    removed = Some(index);
+                }
+            }
+            if let Some(index) = removed {
+                entry.cues.remove(index);
+                changed = true;
+            }
+
+            ui.add(
+                egui::TextEdit::singleline(&mut self.cue_name)
+                    .hint_text("Skip intro")
+                    .desired_width(100.0),
+            );
+            let name = self.cue_name.trim();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("➕ Cue here"))
+                .on_hover_text("Name the current position; an existing cue of that name moves here")
+                .clicked()
+            {
+                cues::insert_cue(&mut entry.cues, name, position);
+                self.cue_name.clear();
+                changed = true;
+            }
+        });
+
+        if changed {
+            self.save_library();
+        }
+        if let (Some(target), Some(player)) = (target, &self.player) {
+            if let Err(e) = player.seek(target) {
+                self.status = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Autotune toggle and strength (global), with the key it snaps to.
+    fn autotune_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let audio = &mut self.config.audio;
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            changed |= ui
+                .checkbox(&mut audio.autotune, "Autotune")
+                .on_hover_text("Pull the sung pitch toward the notes of the song's key")
+                .changed();
+            changed |= ui
+                .add_enabled(
+                    audio.autotune,
+                    egui::Slider::new(&mut audio.autotune_strength, 0.0..=1.0).text("strength"),
+                )
+                .changed();
+
+            let key = self.storage.entry(path).and_then(|entry| entry.key);
+            let detecting = self
+                .key_detection
+                .as_ref()
+                .is_some_and(|detection| detection.is_pending(path));
+            match key {
+                Some(key) => ui.label(format!("Key: {key}")),
+                None if detecting => ui.weak("Key: detecting…"),
+                None => ui
+                    .weak("Key: unknown")
+                    .on_hover_text("Autotune snaps to the nearest semitone"),
+            };
+        });
+
+        if changed {
+            self.params.apply_config(&self.config.audio);
+            self.save_config();
+        }
+    }
+
+    /// Live choice of the mic reverb or echo. Edits the song's own effects
+    /// when it has them, the default rack otherwise.
+    fn ambience_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let entry = self.storage.entry_mut(path);
+        let per_song = entry.voice_effects.is_some();
+        let effects = entry
+            .voice_effects
+            .as_mut()
+            .unwrap_or(&mut self.config.audio.voice_effects);
+        let current = effects
+            .iter()
+            .position(|effect| effect.enabled && effect.kind.is_ambience());
+        let mut choice = current.map(|index| effects[index].kind);
+        let mut changed = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Mic ambience:");
+            changed |= ui.selectable_value(&mut choice, None, "Dry").changed();
+            for kind in VoiceEffectKind::AMBIENCE {
+                changed |= ui
+                    .selectable_value(&mut choice, Some(kind), kind.label())
+                    .changed();
+            }
+            if let Some(index) = current.filter(|_| !changed) {
+                changed |= ui
+                    .add(egui::Slider::new(&mut effects[index].amount, 0.0..=1.0).text("level"))
+                    .changed();
+            }
+        });
+        if !changed {
+            return;
+        }
+
+        if choice != current.map(|index| effects[index].kind) {
+            effects.retain(|effect| !effect.kind.is_ambience());
+            effects.extend(choice.map(VoiceEffect::new));
+        }
+        self.apply_song_settings();
+        if per_song {
+            self.save_library();
+        } else {
+            self.save_config();
+        }
+    }
+
+    /// Keyboard playing a reference note, so the singer can find their
+    /// starting note. The tonic of the song's key is highlighted.
+    fn reference_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        const NOTE_LENGTH: Duration = Duration::from_millis(1500);
+
+        let tonic = self
+            .storage
+            .entry(path)
+            .and_then(|entry| entry.key)
+            .map(|key| key.tonic);
+        ui.horizontal(|ui| {
+            ui.label("Starting note:");
+            let clicked = reference_keyboard(ui, self.reference_octave, tonic);
+            ui.add(
+                egui::DragValue::new(&mut self.reference_octave)
+                    .range(1..=6)
+                    .prefix("octave "),
+            );
+            if let (Some(note), Some(player)) = (clicked, &mut self.player) {
+                player.play_reference_note(note, self.reference_octave, NOTE_LENGTH);
+            }
+        });
+    }
+
+    /// Heat map of how hard each lyric line is; clicking a line jumps to it.
+    fn difficulty_row(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let (Some(player), Some(difficulty)) = (
+            &self.player,
+            self.storage
+                .entry(path)
+                .and_then(|entry| entry.difficulty.as_ref()),
+        ) else {
+            return;
+        };
+        let mut target = None;
+        ui.horizontal(|ui| {
+            ui.label(format!("Difficulty: {}", difficulty.level().label()));
+            let width = ui.available_width().min(480.0);
+            target = difficulty_heat_map(ui, &difficulty.lines, player.get_position(), width);
+        });
+        if let Some(target) = target {
+            if let Err(e) = player.seek(target) {
+                self.status = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Per-song overrides, saved in the library.
+    fn song_controls(&mut self, ui: &mut egui::Ui, path: &Path) {
+        let entry = self.storage.entry_mut(path);
+        let before = entry.vocal_removal;
+        // A slider being dragged is applied as it moves but only saved
+        // once let go
+        let mut dragging = false;
+        let mut released = false;
+
+        ui.horizontal(|ui| {
+            ui.label("Vocal removal for this song:");
+            let selected = match entry.vocal_removal {
+                None => "Default",
+                Some(strength) if strength <= 0.0 => "Off",
+                Some(_) => "Custom",
+            };
+            egui::ComboBox::from_id_salt("vocal_removal_override")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut entry.vocal_removal, None, "Default");
+                    ui.selectable_value(&mut entry.vocal_removal, Some(0.0), "Off");
+                    if ui
+                        .selectable_label(selected == "Custom", "Custom")
+                        .clicked()
+                    {
+                        entry.vocal_removal = Some(self.config.audio.vocal_removal_strength);
+                    }
+                });
+
+            if let Some(strength) = &mut entry.vocal_removal {
+                if *strength > 0.0 {
+                    let slider = ui.add(egui::Slider::new(strength, 0.01..=1.0).text("strength"));
+                    dragging |= slider.dragged();
+                    released |= slider.drag_stopped();
+                }
+            }
+        });
+
+        let mut changed = entry.vocal_removal != before;
+
+        ui.horizontal(|ui| {
+            ui.label("Volume offset:");
+            let slider = ui
+                .add(
+                    egui::Slider::new(&mut entry.volume_offset_db, -12.0..=12.0)
+                        .suffix(" dB")
+                        .step_by(0.5),
+                )
+                .on_hover_text("Make this song louder or quieter than the others");
+            changed |= slider.changed();
+            dragging |= slider.dragged();
+            released |= slider.drag_stopped();
+            if entry.volume_offset_db != 0.0 && ui.small_button("Reset").clicked() {
+                entry.volume_offset_db = 0.0;
+                changed = true;
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.label("Text direction:");
+            egui::ComboBox::from_id_salt("text_direction")
+                .selected_text(entry.text_direction.label())
+                .show_ui(ui, |ui| {
+                    for direction in TextDirection::ALL {
+                        changed |= ui
+                            .selectable_value(
+                                &mut entry.text_direction,
+                                direction,
+                                direction.label(),
+                            )
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Force right-to-left for Arabic or Hebrew lyrics that start with Latin text",
+                );
+
+            ui.label("Language:");
+            let automatic = match &entry.detected_language {
+                Some(detected) => format!("Automatic ({detected})"),
+                None => "Automatic".to_string(),
+            };
+            egui::ComboBox::from_id_salt("song_language")
+                .selected_text(entry.language.clone().unwrap_or_else(|| automatic.clone()))
+                .show_ui(ui, |ui| {
+                    changed |= ui
+                        .selectable_value(&mut entry.language, None, automatic)
+                        .changed();
+                    for language in LANGUAGES {
+                        changed |= ui
+                            .selectable_value(
+                                &mut entry.language,
+                                Some(language.to_string()),
+                                language,
+                            )
+                            .changed();
+                    }
+                })
+                .response
+                .on_hover_text("Groups the song in the library and on the web remote");
+        });
+
+        ui.horizontal(|ui| {
+            let mut custom = entry.voice_effects.is_some();
+            if ui
+                .checkbox(&mut custom, "Voice effects for this song")
+                .on_hover_text("Use a saved effects preset instead of the default rack")
+                .changed()
+            {
+                entry.voice_effects = custom.then(|| self.config.audio.voice_effects.clone());
+                changed = true;
+            }
+        });
+        if let Some(effects) = &mut entry.voice_effects {
+            changed |= effects_rack_editor(ui, "song_voice_effects", effects);
+        }
+
+        if changed {
+            self.apply_song_settings();
+        }
+        if (changed && !dragging) || released {
+            self.save_library();
+        }
+    }
+}
+
+/// The middle part of an image or video with aspect ratio `image` that fills a view
+/// with aspect ratio `view`, in texture coordinates.
+fn cover_uv(view: f32, image: f32) -> egui::Rect {
+    let (width, height) = if view > image {
+        (1.0, image / view)
+    } else {
+        (view / image, 1.0)
+    };
+    egui::Rect::from_center_size(egui::pos2(0.5, 0.5), egui::vec2(width, height))
+}
+
+/// Timed lines of `lrc`, none when it cannot be read.
+fn read_document(lrc: &Path) -> LrcDocument {
+    match lrc::parse_lrc_file(lrc) {
+        Ok(events) => LrcDocument::from_events(&events),
+        Err(e) => {
+            tracing::warn!("{e}");
+            LrcDocument::default()
+        },
+    }
+}
+
+/// Text of the `translation` line for the lyric line starting at `start`:
+/// the last one starting by then, give or take the tolerance.
+fn translated_line(translation: &LrcDocument, start: Duration) -> &str {
+    translation
+        .line_at(start + TRANSLATION_TOLERANCE)
+        .map_or("", |line| line.text.as_str())
+}
+
+/// Seconds left before the next line when it ends a break longer than
+/// `min_break` seconds and is at most [`COUNTDOWN_SECS`] away. A break runs
+/// from the start of the current line, or of the song, to the next line.
+fn countdown_left(
+    lines: &[TimedLine],
+    current: Option<usize>,
+    position: Duration,
+    min_break: f32,
+) -> Option<f32> {
+    let next = lines.get(current.map_or(0, |index| index + 1))?;
+    let since = current
+        .and_then(|index| lines.get(index))
+        .map_or(Duration::ZERO, |line| line.start);
+    let gap = next.start.saturating_sub(since).as_secs_f32();
+    let left = next.start.saturating_sub(position).as_secs_f32();
+    (gap > min_break && left <= COUNTDOWN_SECS).then_some(left)
+}
+
+/// How far `position` is through the current line, up to the start of the
+/// next one; `None` in breaks and after the last line.
+fn line_progress(lines: &[TimedLine], current: Option<usize>, position: Duration) -> Option<f32> {
+    let index = current?;
+    let line = &lines[index];
+    let next = lines.get(index + 1)?;
+    let length = next.start.saturating_sub(line.start).as_secs_f32();
+    if line.text.trim().is_empty() || length <= 0.0 {
+        return None;
+    }
+    Some((position.saturating_sub(line.start).as_secs_f32() / length).min(1.0))
+}
+
+/// A thin bar `width` wide filled to `fraction` in `color`, or the empty
+/// space it takes so the lyrics do not move when it appears.
+fn line_progress_bar(ui: &mut egui::Ui, width: f32, fraction: Option<f32>, color: egui::Color32) {
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(width, LINE_PROGRESS_HEIGHT),
+        egui::Sense::hover(),
+    );
+    let Some(fraction) = fraction else {
+        return;
+    };
+    let painter = ui.painter();
+    let rounding = LINE_PROGRESS_HEIGHT / 2.0;
+    painter.rect_filled(rect, rounding, color.gamma_multiply(0.25));
+    let filled =
+        egui::Rect::from_min_size(rect.min, egui::vec2(rect.width() * fraction, rect.height()));
+    painter.rect_filled(filled, rounding, color);
+}
+
+/// The break countdown for `left` seconds, or the empty space it takes so
+/// the lyrics do not move when it appears.
+fn break_countdown(ui: &mut egui::Ui, left: Option<f32>) {
+    let (rect, _) = ui.allocate_exact_size(COUNTDOWN_SIZE, egui::Sense::hover());
+    let Some(left) = left else {
+        return;
+    };
+    let painter = ui.painter();
+    let color = ui.visuals().selection.stroke.color;
+    let radius = 4.0;
+    let spacing = rect.width() / COUNTDOWN_SECS;
+    let dots = left.ceil().min(COUNTDOWN_SECS);
+    let first = rect.center().x - (dots - 1.0) * spacing / 2.0;
+    for dot in 0..dots as usize {
+        let x = first + dot as f32 * spacing;
+        painter.circle_filled(egui::pos2(x, rect.top() + radius), radius, color);
+    }
+    let bar = egui::Rect::from_center_size(
+        egui::pos2(rect.center().x, rect.bottom() - 2.0),
+        egui::vec2(rect.width() * (left / COUNTDOWN_SECS).min(1.0), 3.0),
+    );
+    painter.rect_filled(bar, 1.5, color);
+}
+
+/// Where the adjustments made by the controls next to it are kept.
+fn adjustments_indicator(ui: &mut egui::Ui, destination: &str) {
+    ui.weak(format!("({destination})"))
+        .on_hover_text("Change where in Settings → Audio System → Song adjustments");
+}
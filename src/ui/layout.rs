@@ -0,0 +1,93 @@
+//! Workspace layout presets.
+//!
+//! A preset sets which panels are shown and how wide the queue sidebar is,
+//! and opens the view the role works in. Resizing or toggling a panel by
+//! hand afterwards turns the layout into a custom one.
+
+use egui::containers::panel::PanelState;
+use serde::{Deserialize, Serialize};
+
+use crate::app::{KaraokeApp, View, QUEUE_PANEL_ID};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LayoutPreset {
+    /// Running the night: wide queue and the diagnostics HUD
+    Host,
+    /// On stage: just the lyrics and transport
+    Performer,
+    /// Preparing songs: queue beside the library, no HUD
+    Editor,
+}
+
+impl LayoutPreset {
+    pub const ALL: [Self; 3] = [Self::Host, Self::Performer, Self::Editor];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Host => "Host",
+            Self::Performer => "Performer",
+            Self::Editor => "Editor",
+        }
+    }
+
+    fn show_queue_panel(self) -> bool {
+        !matches!(self, Self::Performer)
+    }
+
+    fn queue_panel_width(self) -> f32 {
+        match self {
+            Self::Host => 280.0,
+            Self::Performer | Self::Editor => 200.0,
+        }
+    }
+
+    fn show_diagnostics(self) -> bool {
+        matches!(self, Self::Host)
+    }
+
+    fn view(self) -> View {
+        match self {
+            Self::Host | Self::Editor => View::Library,
+            Self::Performer => View::Karaoke,
+        }
+    }
+}
+
+impl KaraokeApp {
+    /// Layout picker for the top panel.
+    pub(crate) fn layout_menu(&mut self, ui: &mut egui::Ui) {
+        let current = self.config.display.layout;
+        let mut chosen = None;
+        egui::ComboBox::from_id_salt("layout_preset")
+            .selected_text(current.map_or("Custom", LayoutPreset::label))
+            .width(100.0)
+            .show_ui(ui, |ui| {
+                for preset in LayoutPreset::ALL {
+                    if ui
+                        .selectable_label(current == Some(preset), preset.label())
+                        .clicked()
+                    {
+                        chosen = Some(preset);
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Workspace layout");
+
+        if let Some(preset) = chosen {
+            self.apply_layout(ui.ctx(), preset);
+        }
+    }
+
+    fn apply_layout(&mut self, ctx: &egui::Context, preset: LayoutPreset) {
+        let display = &mut self.config.display;
+        display.layout = Some(preset);
+        display.show_queue_panel = preset.show_queue_panel();
+        display.queue_panel_width = preset.queue_panel_width();
+        self.show_diagnostics = preset.show_diagnostics();
+        self.view = preset.view();
+        // Forget the sidebar's remembered width so the preset's one applies
+        ctx.data_mut(|data| data.remove::<PanelState>(egui::Id::new(QUEUE_PANEL_ID)));
+        self.save_config();
+    }
+}
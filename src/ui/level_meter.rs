@@ -0,0 +1,98 @@
+//! Microphone input meter, for setting the input gain.
+
+use std::time::{Duration, Instant};
+
+use crate::app::KaraokeApp;
+use crate::audio::dsp;
+use crate::audio::processor::MIC_COUNT;
+
+/// Range of the meter scale.
+const FLOOR_DB: f32 = -60.0;
+/// Levels above these are drawn amber, then red.
+const WARN_DB: f32 = -12.0;
+const HOT_DB: f32 = -3.0;
+/// How long the clip light stays on after the mic clipped.
+const CLIP_HOLD: Duration = Duration::from_secs(2);
+
+impl KaraokeApp {
+    /// Live level of microphone `slot` after the input gain: RMS as the
+    /// bar, peak as a tick, and a clip light. `marker` adds a tick at a level
+    /// in dB, e.g. the noise gate threshold.
+    pub(crate) fn mic_meter(
+        &mut self,
+        ui: &mut egui::Ui,
+        slot: usize,
+        width: f32,
+        marker: Option<f32>,
+    ) {
+        let mic = self.params.mic(slot);
+        let slot = slot.min(MIC_COUNT - 1);
+        let clips = mic.clip_count();
+        if clips != self.mic_clips_seen[slot] {
+            self.mic_clips_seen[slot] = clips;
+            self.last_mic_clip[slot] = Some(Instant::now());
+        }
+        let clipping = self.last_mic_clip[slot].is_some_and(|at| at.elapsed() < CLIP_HOLD);
+        if !clipping {
+            self.last_mic_clip[slot] = None;
+        }
+
+        let (peak, rms) = mic.meter();
+        ui.horizontal(|ui| {
+            level_meter(ui, width, peak, rms, marker);
+            let visuals = ui.visuals();
+            let color = if clipping {
+                visuals.error_fg_color
+            } else {
+                visuals.weak_text_color()
+            };
+            ui.label(egui::RichText::new("● CLIP").small().strong().color(color))
+                .on_hover_text("Lit when the mic reaches full scale; lower the input gain");
+        });
+        // The meter moves continuously while the mic is open
+        ui.ctx().request_repaint();
+    }
+}
+
+/// Horizontal meter on a dB scale.
+fn level_meter(ui: &mut egui::Ui, width: f32, peak: f32, rms: f32, marker: Option<f32>) {
+    let height = ui.spacing().interact_size.y * 0.6;
+    let (rect, response) = ui.allocate_exact_size(egui::vec2(width, height), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    let x = |db: f32| {
+        let fraction = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+        rect.left() + fraction * rect.width()
+    };
+    let color = |db: f32| {
+        if db >= HOT_DB {
+            visuals.error_fg_color
+        } else if db >= WARN_DB {
+            visuals.warn_fg_color
+        } else {
+            visuals.selection.bg_fill
+        }
+    };
+
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+    let rms_db = dsp::linear_to_db(rms);
+    let bar = egui::Rect::from_min_max(rect.min, egui::pos2(x(rms_db), rect.bottom()));
+    painter.rect_filled(bar, 2.0, color(rms_db));
+
+    let peak_db = dsp::linear_to_db(peak);
+    if peak_db > FLOOR_DB {
+        painter.vline(x(peak_db), rect.y_range(), (2.0, color(peak_db)));
+    }
+    if let Some(marker) = marker {
+        painter.vline(
+            x(marker),
+            rect.y_range(),
+            (1.0, visuals.widgets.active.fg_stroke.color),
+        );
+    }
+    response.on_hover_text(format!(
+        "Peak {:.0} dB · RMS {:.0} dB",
+        peak_db.max(FLOOR_DB),
+        rms_db.max(FLOOR_DB)
+    ));
+}
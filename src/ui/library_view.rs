@@ -0,0 +1,445 @@
+//! Library browser.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::device_export::DeviceExportDialog;
+use super::difficulty::difficulty_badge;
+use crate::app::{display_title, song_title, KaraokeApp};
+use crate::library::device_export::DeviceFormat;
+use crate::library::difficulty::{DifficultyJob, DifficultyLevel};
+use crate::library::scanner;
+use crate::library::storage::SongEntry;
+use crate::lrc::subtitles::{self, SubtitleFormat};
+use crate::lrc::word_timing::{self, WordWeighting};
+use crate::lrc::{self, LrcEvent};
+use crate::lyrics::LyricsFetchJob;
+
+impl KaraokeApp {
+    pub(crate) fn library_view(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.heading("Library");
+            if ui.button("📂 Add folder…").clicked() {
+                if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                    let songs = scanner::scan_folder(&folder);
+                    tracing::info!("Found {} songs in {}", songs.len(), folder.display());
+                    self.storage.add_songs(songs.clone());
+                    self.detect_languages();
+                    self.detect_keys(songs, false);
+                    self.save_library();
+                }
+            }
+            if ui
+                .add_enabled(
+                    self.import_job.is_none(),
+                    egui::Button::new("📥 Import songs…"),
+                )
+                .on_hover_text(
+                    "Copy songs into the data folder. A song imported twice is stored once.",
+                )
+                .clicked()
+            {
+                if let Some(files) = rfd::FileDialog::new()
+                    .add_filter("Audio", scanner::AUDIO_EXTENSIONS)
+                    .pick_files()
+                {
+                    self.import_songs(files);
+                }
+            }
+            if ui
+                .button("📝 Import lyrics…")
+                .on_hover_text("Pair a folder of .lrc files with songs in the library")
+                .clicked()
+            {
+                self.start_lrc_import();
+            }
+            let selected = self.selected_songs.len();
+            if ui
+                .add_enabled(
+                    selected > 0 && self.device_export.is_none(),
+                    egui::Button::new(format!("💾 Export to device… ({selected})")),
+                )
+                .on_hover_text("Copy the selected songs and their lyrics to a folder or USB drive")
+                .on_disabled_hover_text("Click songs to select them, Ctrl+click to select several")
+                .clicked()
+            {
+                self.device_export = Some(DeviceExportDialog::Setup {
+                    folder: None,
+                    format: DeviceFormat::default(),
+                });
+            }
+            self.lyrics_fetch_controls(ui);
+            self.difficulty_controls(ui);
+            self.key_detection_progress(ui);
+            self.import_progress(ui);
+            ui.checkbox(&mut self.group_by_language, "Group by language")
+                .on_hover_text("One section per language, e.g. for mixed-language nights");
+        });
+        egui::CollapsingHeader::new("⬇ Downloads")
+            .default_open(!self.downloads.is_empty())
+            .show(ui, |ui| self.downloads_panel(ui));
+        ui.separator();
+
+        if self.storage.is_empty() {
+            ui.label("No songs yet. Add a folder containing audio files.");
+            return;
+        }
+
+        let songs: Vec<PathBuf> = self
+            .storage
+            .songs()
+            .filter(|path| {
+                let easy = self
+                    .storage
+                    .entry(path)
+                    .and_then(|entry| entry.difficulty.as_ref())
+                    .is_some_and(|rating| rating.level() == DifficultyLevel::Easy);
+                !self.easy_only || easy
+            })
+            .map(Path::to_path_buf)
+            .collect();
+
+        let mut selected = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            if !self.group_by_language {
+                for path in &songs {
+                    if self.song_row(ui, path) {
+                        selected = Some(path.clone());
+                    }
+                }
+                return;
+            }
+            for (language, songs) in self.by_language(songs) {
+                let heading = language.as_deref().unwrap_or("Unknown language");
+                egui::CollapsingHeader::new(format!("{heading} ({})", songs.len()))
+                    .id_salt(("language", &language))
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        for path in &songs {
+                            if self.song_row(ui, path) {
+                                selected = Some(path.clone());
+                            }
+                        }
+                    });
+            }
+        });
+
+        if let Some(path) = selected {
+            self.play_song(&path);
+        }
+    }
+
+    /// One library entry; returns whether it was double-clicked to play.
+    fn song_row(&mut self, ui: &mut egui::Ui, path: &Path) -> bool {
+        ui.horizontal(|ui| {
+            if ui
+                .small_button("➕")
+                .on_hover_text("Add to queue")
+                .clicked()
+            {
+                self.enqueue(path);
+            }
+            let entry = self.storage.entry(path);
+            let mut title = ui.selectable_label(
+                self.selected_songs.contains(path),
+                display_title(&self.storage, path),
+            );
+            let mut edit_lyrics = false;
+            let mut export_lyrics = None;
+            let mut estimate_words = None;
+            title.context_menu(|ui| {
+                if ui.button("Rename…").clicked() {
+                    let name = path
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned())
+                        .unwrap_or_default();
+                    self.renaming = Some((path.to_path_buf(), name));
+                    ui.close_menu();
+                }
+                if ui.button("Edit lyrics…").clicked() {
+                    edit_lyrics = true;
+                    ui.close_menu();
+                }
+                let has_lyrics = lrc::lrc_path(path).exists();
+                ui.add_enabled_ui(has_lyrics, |ui| {
+                    ui.menu_button("Export lyrics as", |ui| {
+                        for format in SubtitleFormat::ALL {
+                            if ui.button(format!("{}…", format.label())).clicked() {
+                                export_lyrics = Some(format);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    ui.menu_button("Estimate word timing", |ui| {
+                        for weighting in WordWeighting::ALL {
+                            if ui.button(weighting.label()).clicked() {
+                                estimate_words = Some(weighting);
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            });
+            if title.clicked() {
+                let selected = self.selected_songs.contains(path);
+                if !ui.input(|input| input.modifiers.command) {
+                    self.selected_songs.clear();
+                }
+                if selected {
+                    self.selected_songs.remove(path);
+                } else {
+                    self.selected_songs.insert(path.to_path_buf());
+                }
+            }
+            if let Some(provider) = entry.and_then(|entry| entry.lyrics_provider) {
+                title = title.on_hover_text(format!("Lyrics from {}", provider.label()));
+            }
+            let play = title.double_clicked();
+            if let Some(difficulty) = entry.and_then(|entry| entry.difficulty.as_ref()) {
+                difficulty_badge(ui, difficulty);
+            }
+            if let Some(key) = entry.and_then(|entry| entry.key) {
+                ui.weak(key.to_string());
+            }
+            if let Some(language) = entry.and_then(SongEntry::language) {
+                ui.weak(language);
+            }
+            if let Some(best) = entry.and_then(SongEntry::best_result) {
+                ui.weak(format!("🏆 {}", best.score))
+                    .on_hover_text(format!("Best score, by {}", best.singer));
+            }
+            if edit_lyrics {
+                self.open_lyrics_editor(path);
+            }
+            if let Some(format) = export_lyrics {
+                self.export_lyrics(path, format);
+            }
+            if let Some(weighting) = estimate_words {
+                self.estimate_word_timing(path, weighting);
+            }
+            play
+        })
+        .inner
+    }
+
+    /// `songs` grouped by language, alphabetically, songs of unknown
+    /// language last.
+    fn by_language(&self, songs: Vec<PathBuf>) -> Vec<(Option<String>, Vec<PathBuf>)> {
+        let mut groups: BTreeMap<(bool, String), Vec<PathBuf>> = BTreeMap::new();
+        for song in songs {
+            let language = self
+                .storage
+                .entry(&song)
+                .and_then(SongEntry::language)
+                .map(str::to_string);
+            groups
+                .entry((language.is_none(), language.unwrap_or_default()))
+                .or_default()
+                .push(song);
+        }
+        groups
+            .into_iter()
+            .map(|((unknown, language), songs)| ((!unknown).then_some(language), songs))
+            .collect()
+    }
+
+    /// "Rate difficulty" button, or the progress of the running rating, and
+    /// the easy songs filter.
+    fn difficulty_controls(&mut self, ui: &mut egui::Ui) {
+        match &self.difficulty_job {
+            Some(job) => {
+                let (done, total) = job.progress();
+                ui.spinner();
+                ui.weak(format!("Rating difficulty… {done}/{total}"));
+                if ui.small_button("Cancel").clicked() {
+                    job.cancel();
+                }
+            },
+            None => {
+                if ui
+                    .add_enabled(
+                        !self.storage.is_empty(),
+                        egui::Button::new("📊 Rate difficulty"),
+                    )
+                    .on_hover_text("Rate songs with lyrics from their word speed and pitch range")
+                    .clicked()
+                {
+                    let songs = self
+                        .storage
+                        .songs()
+                        .filter(|path| {
+                            self.storage
+                                .entry(path)
+                                .is_some_and(|entry| entry.difficulty.is_none())
+                        })
+                        .map(|path| path.to_path_buf())
+                        .collect();
+                    self.difficulty_job = Some(DifficultyJob::start(songs));
+                }
+            },
+        }
+        ui.checkbox(&mut self.easy_only, "Easy songs only")
+            .on_hover_text("Hide songs that are not rated easy");
+    }
+
+    /// Progress of the key detection of a scanned folder.
+    fn import_progress(&mut self, ui: &mut egui::Ui) {
+        let Some(job) = &self.import_job else {
+            return;
+        };
+        let (done, total) = job.progress();
+        ui.spinner();
+        ui.weak(format!("Importing… {done}/{total}"));
+        if ui.small_button("Cancel").clicked() {
+            job.cancel();
+        }
+    }
+
+    /// Save the lyrics of `song` as subtitles where the user picks.
+    fn export_lyrics(&mut self, song: &Path, format: SubtitleFormat) {
+        let events = match lrc::parse_lrc_file(&lrc::lrc_path(song)) {
+            Ok(events) => events,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            },
+        };
+        let mut output = rfd::FileDialog::new().add_filter(format.label(), &[format.extension()]);
+        if let Some(stem) = song.file_stem() {
+            output =
+                output.set_file_name(format!("{}.{}", stem.to_string_lossy(), format.extension()));
+        }
+        let Some(output) = output.save_file() else {
+            return;
+        };
+        self.status = Some(
+            match subtitles::write_subtitle_file(&output, &events, format) {
+                Ok(()) => format!("Lyrics exported to {}", output.display()),
+                Err(e) => e.to_string(),
+            },
+        );
+    }
+
+    /// Give the lines of the lyrics of `song` that are timed as a whole
+    /// estimated word timestamps, rewriting its `.lrc`.
+    fn estimate_word_timing(&mut self, song: &Path, weighting: WordWeighting) {
+        let path = lrc::lrc_path(song);
+        let events = match lrc::parse_lrc_file(&path) {
+            Ok(events) => events,
+            Err(e) => {
+                self.status = Some(e.to_string());
+                return;
+            },
+        };
+        let untimed = lrc::timed_lines(&events)
+            .iter()
+            .filter(|line| line.segments.is_empty() && !line.text.trim().is_empty())
+            .count();
+        if untimed == 0 {
+            self.status = Some(format!("{} already has word timing", song_title(song)));
+            return;
+        }
+        // Keep repeated lines merged when the file had them that way
+        let compact = events.iter().any(
+            |event| matches!(event, LrcEvent::Line { timestamps, .. } if timestamps.len() > 1),
+        );
+        let estimated = word_timing::estimate_word_timing(&events, weighting);
+        self.status = Some(match lrc::write_lrc_file(&path, &estimated, compact) {
+            Ok(()) => format!(
+                "Estimated word timing for {untimed} lines of {}",
+                song_title(song)
+            ),
+            Err(e) => e.to_string(),
+        });
+    }
+
+    /// Window renaming a song's file, with its lyrics and other files.
+    pub(crate) fn rename_window(&mut self, ctx: &egui::Context) {
+        let Some((song, name)) = &mut self.renaming else {
+            return;
+        };
+        let mut open = true;
+        let mut rename = false;
+        let mut cancel = false;
+        egui::Window::new("Rename song")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.weak(song.display().to_string());
+                let input = ui.text_edit_singleline(name);
+                rename =
+                    input.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                ui.horizontal(|ui| {
+                    rename |= ui.button("Rename").clicked();
+                    cancel = ui.button("Cancel").clicked();
+                });
+            });
+        if rename {
+            let (song, name) = (song.clone(), name.clone());
+            match self.rename_song(&song, &name) {
+                Ok(()) => self.renaming = None,
+                Err(e) => self.status = Some(format!("{e:#}")),
+            }
+        } else if !open || cancel {
+            self.renaming = None;
+        }
+    }
+
+    fn key_detection_progress(&mut self, ui: &mut egui::Ui) {
+        let Some(detection) = &self.key_detection else {
+            return;
+        };
+        let (done, total) = detection.progress();
+        if total <= 1 {
+            // Just the song being played
+            return;
+        }
+        ui.spinner();
+        ui.weak(format!("Detecting keys… {done}/{total}"));
+        if ui.small_button("Cancel").clicked() {
+            detection.cancel();
+        }
+    }
+
+    /// "Fetch lyrics" button, or the progress of the running lookup.
+    fn lyrics_fetch_controls(&mut self, ui: &mut egui::Ui) {
+        if let Some(job) = &self.lyrics_fetch {
+            let progress = job.progress();
+            ui.spinner();
+            ui.weak(format!(
+                "Looking up lyrics… {}/{}",
+                progress.done, progress.total
+            ));
+            if ui.small_button("Cancel").clicked() {
+                job.cancel();
+            }
+            return;
+        }
+
+        let offline = self.config.network.offline;
+        if ui
+            .add_enabled(
+                !self.storage.is_empty(),
+                egui::Button::new("🌐 Fetch lyrics"),
+            )
+            .on_hover_text(if offline {
+                "Offline mode: only cached lyrics are used"
+            } else {
+                "Look up synced lyrics online for songs without an .lrc file"
+            })
+            .clicked()
+        {
+            let songs = self
+                .storage
+                .songs()
+                .map(|path| path.to_path_buf())
+                .collect();
+            self.lyrics_fetch = Some(LyricsFetchJob::start(
+                songs,
+                &self.config.lyrics,
+                &self.config.network,
+            ));
+        }
+    }
+}
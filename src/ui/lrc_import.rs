@@ -0,0 +1,248 @@
+//! LRC import wizard: pick a folder of lyrics, review the proposed pairs,
+//! then copy the confirmed ones next to their songs.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use encoding_rs::UTF_8;
+
+use crate::app::{song_title, KaraokeApp};
+use crate::library::lrc_import::{self, LrcImportJob, Pairing};
+use crate::lrc;
+use crate::lyrics::LyricsProvider;
+
+/// Wizard steps.
+pub(crate) enum LrcImportWizard {
+    /// Matching runs in the background
+    Pairing(LrcImportJob),
+    Review(Vec<ReviewRow>),
+}
+
+/// One lyrics file in the review step.
+pub(crate) struct ReviewRow {
+    pairing: Pairing,
+    /// Song the file will be copied to
+    chosen: Option<PathBuf>,
+    /// Import this row; preset for confident pairs only
+    confirmed: bool,
+    /// Convert the copy to UTF-8; preset for files in another encoding
+    to_utf8: bool,
+}
+
+impl ReviewRow {
+    fn new(pairing: Pairing) -> Self {
+        let confident = !pairing.is_ambiguous();
+        Self {
+            chosen: pairing.best().map(|best| best.song.clone()),
+            confirmed: confident,
+            to_utf8: pairing.lrc.encoding != UTF_8,
+            pairing,
+        }
+    }
+}
+
+impl KaraokeApp {
+    /// Ask for a folder of `.lrc` files and start pairing them.
+    pub(crate) fn start_lrc_import(&mut self) {
+        let Some(folder) = rfd::FileDialog::new().pick_folder() else {
+            return;
+        };
+        let songs = self.storage.songs().map(PathBuf::from).collect();
+        self.lrc_import = Some(LrcImportWizard::Pairing(LrcImportJob::start(
+            &folder, songs,
+        )));
+    }
+
+    pub(crate) fn lrc_import_window(&mut self, ctx: &egui::Context) {
+        let mut open = true;
+        let mut finished = false;
+        if let Some(LrcImportWizard::Pairing(job)) = &mut self.lrc_import {
+            if let Some(pairings) = job.try_finish() {
+                let rows = pairings.into_iter().map(ReviewRow::new).collect();
+                self.lrc_import = Some(LrcImportWizard::Review(rows));
+            }
+        }
+        let Some(wizard) = &mut self.lrc_import else {
+            return;
+        };
+
+        egui::Window::new("Import lyrics")
+            .open(&mut open)
+            .default_width(640.0)
+            .show(ctx, |ui| match wizard {
+                LrcImportWizard::Pairing(_) => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Matching lyrics files to songs…");
+                    });
+                    ctx.request_repaint_after(Duration::from_millis(250));
+                },
+                LrcImportWizard::Review(rows) if rows.is_empty() => {
+                    ui.label("No .lrc files found in that folder.");
+                },
+                LrcImportWizard::Review(rows) => {
+                    ui.label(
+                        "Check the pairs to import. Uncertain matches are left unchecked; \
+                         pick the right song and tick them to include them.",
+                    );
+                    ui.separator();
+                    egui::ScrollArea::vertical()
+                        .max_height(400.0)
+                        .show(ui, |ui| review_grid(ui, rows));
+                    ui.separator();
+
+                    let selected = rows
+                        .iter()
+                        .filter(|row| row.confirmed && row.chosen.is_some())
+                        .count();
+                    ui.horizontal(|ui| {
+                        if ui
+                            .add_enabled(
+                                selected > 0,
+                                egui::Button::new(format!("Import {selected}")),
+                            )
+                            .clicked()
+                        {
+                            finished = true;
+                        }
+                        ui.weak("Lyrics are copied next to the audio files.");
+                    });
+                },
+            });
+
+        if finished {
+            if let Some(LrcImportWizard::Review(rows)) = self.lrc_import.take() {
+                self.import_lyrics(&rows);
+            }
+        } else if !open {
+            self.lrc_import = None;
+        }
+    }
+
+    fn import_lyrics(&mut self, rows: &[ReviewRow]) {
+        let mut imported = 0;
+        let mut failed = 0;
+        for row in rows.iter().filter(|row| row.confirmed) {
+            let Some(song) = &row.chosen else {
+                continue;
+            };
+            match lrc_import::import(&row.pairing.lrc.path, song, row.to_utf8) {
+                Ok(()) => {
+                    let entry = self.storage.entry_mut(song);
+                    entry.lyrics_provider = Some(LyricsProvider::Manual);
+                    // Detected again from the new lyrics
+                    entry.detected_language = None;
+                    imported += 1;
+                },
+                Err(e) => {
+                    tracing::error!("{e:#}");
+                    failed += 1;
+                },
+            }
+        }
+        if imported > 0 {
+            self.detect_languages();
+            self.save_library();
+        }
+        self.status = Some(if failed > 0 {
+            format!("Imported lyrics for {imported} songs, {failed} failed")
+        } else {
+            format!("Imported lyrics for {imported} songs")
+        });
+    }
+}
+
+fn review_grid(ui: &mut egui::Ui, rows: &mut [ReviewRow]) {
+    egui::Grid::new("lrc_import_review")
+        .num_columns(5)
+        .striped(true)
+        .spacing([12.0, 6.0])
+        .show(ui, |ui| {
+            ui.strong("Import");
+            ui.strong("Lyrics file");
+            ui.strong("Song");
+            ui.strong("Confidence");
+            ui.strong("Encoding");
+            ui.end_row();
+
+            for (index, row) in rows.iter_mut().enumerate() {
+                ui.add_enabled(
+                    row.chosen.is_some(),
+                    egui::Checkbox::without_text(&mut row.confirmed),
+                );
+
+                let lrc = &row.pairing.lrc;
+                ui.label(song_title(&lrc.path)).on_hover_text(
+                    lrc.first_line
+                        .as_deref()
+                        .unwrap_or("(no timed lyrics in this file)"),
+                );
+
+                let before = row.chosen.clone();
+                egui::ComboBox::from_id_salt(("lrc_import_song", index))
+                    .width(220.0)
+                    .selected_text(row.chosen.as_deref().map_or("None".to_string(), song_title))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut row.chosen, None, "None");
+                        for candidate in &row.pairing.matches {
+                            ui.selectable_value(
+                                &mut row.chosen,
+                                Some(candidate.song.clone()),
+                                format!(
+                                    "{} ({:.0}%)",
+                                    song_title(&candidate.song),
+                                    candidate.confidence * 100.0
+                                ),
+                            );
+                        }
+                    });
+                // Picking a song by hand is the confirmation
+                if row.chosen != before {
+                    row.confirmed = row.chosen.is_some();
+                }
+
+                let confidence = row
+                    .pairing
+                    .matches
+                    .iter()
+                    .find(|candidate| Some(&candidate.song) == row.chosen.as_ref())
+                    .map(|candidate| candidate.confidence);
+                ui.horizontal(|ui| {
+                    match confidence {
+                        Some(confidence) if row.pairing.is_ambiguous() => {
+                            ui.colored_label(
+                                ui.visuals().warn_fg_color,
+                                format!("{:.0}% ?", confidence * 100.0),
+                            )
+                            .on_hover_text("Uncertain match, please check");
+                        },
+                        Some(confidence) => {
+                            ui.label(format!("{:.0}%", confidence * 100.0));
+                        },
+                        None => {
+                            ui.weak("–");
+                        },
+                    }
+                    let replaces = row
+                        .chosen
+                        .as_deref()
+                        .is_some_and(|song| lrc::lrc_path(song).exists());
+                    if replaces {
+                        ui.weak("⚠")
+                            .on_hover_text("Replaces the song's current lyrics");
+                    }
+                });
+
+                let encoding = row.pairing.lrc.encoding;
+                if encoding == UTF_8 {
+                    ui.weak(encoding.name());
+                } else {
+                    ui.checkbox(&mut row.to_utf8, format!("{} → UTF-8", encoding.name()))
+                        .on_hover_text(
+                            "Detected encoding; tick to save the imported copy as UTF-8",
+                        );
+                }
+                ui.end_row();
+            }
+        });
+}
@@ -0,0 +1,577 @@
+//! Lyrics editor: type or paste the words of a song, then tap along while
+//! it plays to time each line, or each word, and save the result as the
+//! song's `.lrc`.
+//!
+//! Space stamps the line or word up next with the playback position and
+//! Backspace takes the last stamp back. Stamps can be nudged or set to the
+//! position afterwards, one line at a time.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::app::{format_time, song_title, KaraokeApp, View};
+use crate::lrc::chords::ChordMark;
+use crate::lrc::word_timing::{self, WordWeighting};
+use crate::lrc::{self, LrcEvent, LyricSegment};
+use crate::lyrics::local;
+
+/// How far the nudge buttons move a timestamp, in milliseconds.
+const NUDGE_MS: i64 = 100;
+
+/// What a tap times.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TapMode {
+    Lines,
+    /// Every word, for enhanced LRC; the first word also times its line
+    Words,
+}
+
+impl TapMode {
+    pub const ALL: [Self; 2] = [Self::Lines, Self::Words];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Lines => "Lines",
+            Self::Words => "Words",
+        }
+    }
+}
+
+/// A lyric line being timed.
+struct EditorLine {
+    text: String,
+    start: Option<Duration>,
+    /// When each word of `text` starts
+    words: Vec<Option<Duration>>,
+    /// Chords of the file the line came from, kept as they are
+    chords: Vec<ChordMark>,
+}
+
+impl EditorLine {
+    fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            start: None,
+            words: vec![None; text.split_whitespace().count()],
+            chords: Vec::new(),
+        }
+    }
+
+    /// Position in `text` of each word, in characters.
+    fn word_offsets(&self) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut previous = ' ';
+        for (offset, c) in self.text.chars().enumerate() {
+            if !c.is_whitespace() && previous.is_whitespace() {
+                offsets.push(offset);
+            }
+            previous = c;
+        }
+        offsets
+    }
+
+    fn event(&self, start: Duration) -> LrcEvent {
+        let segments = self
+            .word_offsets()
+            .into_iter()
+            .zip(&self.words)
+            .filter_map(|(offset, word)| {
+                Some(LyricSegment {
+                    start: (*word)?,
+                    offset,
+                })
+            })
+            .collect();
+        LrcEvent::Line {
+            timestamps: vec![start],
+            text: self.text.clone(),
+            chords: self.chords.clone(),
+            segments,
+        }
+    }
+}
+
+/// The lyrics of one song in the editor.
+pub(crate) struct LyricsEditor {
+    song: PathBuf,
+    /// Tags of the file being edited, kept when saving. Its `[offset:]` is
+    /// applied to the stamps instead.
+    tags: Vec<LrcEvent>,
+    /// The lyrics as text, one line per row
+    text: String,
+    lines: Vec<EditorLine>,
+    mode: TapMode,
+    /// Line the next tap times
+    line: usize,
+    /// Word of that line the next tap times, in word mode
+    word: usize,
+    /// Line and word of each tap, latest last, for taking them back
+    taps: Vec<(usize, Option<usize>)>,
+    /// Not saved since the last change
+    changed: bool,
+    /// Save repeated lines once with all of their timestamps
+    compact: bool,
+}
+
+impl LyricsEditor {
+    /// Open the lyrics of `song`: its `.lrc` when it has one, else the
+    /// lyrics in its tags, else nothing to start from.
+    pub(crate) fn open(song: &Path) -> Self {
+        let mut editor = Self {
+            song: song.to_path_buf(),
+            tags: Vec::new(),
+            text: String::new(),
+            lines: Vec::new(),
+            mode: TapMode::Lines,
+            line: 0,
+            word: 0,
+            taps: Vec::new(),
+            changed: false,
+            compact: false,
+        };
+        let lrc = lrc::lrc_path(song);
+        if lrc.exists() {
+            match lrc::parse_lrc_file(&lrc) {
+                Ok(events) => editor.load_events(&events),
+                Err(e) => tracing::warn!("{e}"),
+            }
+        } else if let Some(lyrics) = local::embedded(song) {
+            match lyrics.synced {
+                Some(synced) => editor.load_events(&lrc::parse_lrc(&synced)),
+                None => editor.set_text(lyrics.plain.unwrap_or_default()),
+            }
+        }
+        editor
+    }
+
+    /// Take the lines of parsed LRC, once per timestamp, in singing order.
+    /// Files giving lines several timestamps are saved that way again.
+    fn load_events(&mut self, events: &[LrcEvent]) {
+        let offset = lrc::offset_ms(events);
+        self.compact = events.iter().any(
+            |event| matches!(event, LrcEvent::Line { timestamps, .. } if timestamps.len() > 1),
+        );
+        for event in lrc::expand_repeats(events) {
+            let LrcEvent::Line {
+                timestamps,
+                text,
+                chords,
+                segments,
+            } = event
+            else {
+                if !matches!(&event, LrcEvent::Metadata { key, .. } if key == "offset") {
+                    self.tags.push(event);
+                }
+                continue;
+            };
+            let Some(&timestamp) = timestamps.first() else {
+                continue;
+            };
+            let mut line = EditorLine::new(&text);
+            line.start = Some(lrc::shift(timestamp, offset));
+            line.chords = chords;
+            for (word, start) in line.word_offsets().into_iter().enumerate() {
+                line.words[word] = segments
+                    .iter()
+                    .find(|segment| segment.offset == start)
+                    .map(|segment| lrc::shift(segment.start, offset));
+            }
+            self.lines.push(line);
+        }
+        self.text = self
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.line = self
+            .lines
+            .iter()
+            .position(|line| line.start.is_none())
+            .unwrap_or(self.lines.len());
+    }
+
+    /// Replace the lines with the rows of `text`. Lines whose text is
+    /// unchanged keep their stamps, as long as they stay in order.
+    fn set_text(&mut self, text: String) {
+        let mut old = std::mem::take(&mut self.lines);
+        old.reverse();
+        for row in text.lines().map(str::trim).filter(|row| !row.is_empty()) {
+            // Old lines are popped from the back, the next one in order last
+            let kept = old.iter().rposition(|line| line.text == row);
+            if let Some(kept) = kept {
+                old.truncate(kept + 1);
+            }
+            let line = kept
+                .and_then(|_| old.pop())
+                .unwrap_or_else(|| EditorLine::new(row));
+            self.lines.push(line);
+        }
+        self.text = text;
+        self.taps.clear();
+        self.line = self
+            .lines
+            .iter()
+            .position(|line| line.start.is_none())
+            .unwrap_or(self.lines.len());
+        self.word = 0;
+        self.changed = true;
+    }
+
+    /// Stamp the line or word up next with `position` and move on.
+    fn tap(&mut self, position: Duration) {
+        let Some(line) = self.lines.get_mut(self.line) else {
+            return;
+        };
+        match self.mode {
+            TapMode::Lines => {
+                line.start = Some(position);
+                self.taps.push((self.line, None));
+                self.line += 1;
+            },
+            TapMode::Words => {
+                if self.word == 0 {
+                    line.start = Some(position);
+                }
+                if let Some(word) = line.words.get_mut(self.word) {
+                    *word = Some(position);
+                }
+                self.taps.push((self.line, Some(self.word)));
+                self.word += 1;
+                if self.word >= line.words.len() {
+                    self.line += 1;
+                    self.word = 0;
+                }
+            },
+        }
+        self.changed = true;
+    }
+
+    /// Take the last tap back, to tap it again.
+    fn undo(&mut self) {
+        let Some((index, word)) = self.taps.pop() else {
+            return;
+        };
+        let line = &mut self.lines[index];
+        match word {
+            Some(word) => {
+                line.words[word] = None;
+                if word == 0 {
+                    line.start = None;
+                }
+            },
+            None => line.start = None,
+        }
+        self.line = index;
+        self.word = word.unwrap_or(0);
+        self.changed = true;
+    }
+
+    /// Estimate the word stamps of the timed lines that have none, from
+    /// their start to the start of the next line.
+    fn estimate_words(&mut self, weighting: WordWeighting) {
+        let starts: Vec<Option<Duration>> = self.lines.iter().map(|line| line.start).collect();
+        for (index, line) in self.lines.iter_mut().enumerate() {
+            let Some(start) = line.start else {
+                continue;
+            };
+            if line.words.iter().any(Option::is_some) {
+                continue;
+            }
+            let end = starts[index + 1..]
+                .iter()
+                .flatten()
+                .copied()
+                .find(|next| *next > start)
+                .unwrap_or(Duration::MAX);
+            let segments = word_timing::estimate_segments(&line.text, start, end, weighting);
+            for (word, segment) in line.words.iter_mut().zip(segments) {
+                *word = Some(segment.start);
+            }
+        }
+        self.taps.clear();
+        self.changed = true;
+    }
+
+    /// Move the stamps of line `index` by `ms` milliseconds, later when
+    /// positive.
+    fn nudge(&mut self, index: usize, ms: i64) {
+        let line = &mut self.lines[index];
+        for stamp in line.words.iter_mut().chain([&mut line.start]).flatten() {
+            *stamp = lrc::shift(*stamp, -ms);
+        }
+        self.changed = true;
+    }
+
+    /// Move the stamps of line `index` so it starts at `position`.
+    fn retime(&mut self, index: usize, position: Duration) {
+        let line = &mut self.lines[index];
+        let Some(start) = line.start else {
+            line.start = Some(position);
+            self.changed = true;
+            return;
+        };
+        let ms = position.as_millis() as i64 - start.as_millis() as i64;
+        self.nudge(index, ms);
+    }
+
+    /// Write the timed lines as the song's `.lrc`. Returns how many lines
+    /// were left out for having no stamp.
+    fn save(&mut self) -> Result<usize, lrc::LrcError> {
+        let mut events = self.tags.clone();
+        let mut untimed = 0;
+        for line in &self.lines {
+            match line.start {
+                Some(start) => events.push(line.event(start)),
+                None => untimed += 1,
+            }
+        }
+        lrc::write_lrc_file(&lrc::lrc_path(&self.song), &events, self.compact)?;
+        self.changed = false;
+        Ok(untimed)
+    }
+}
+
+impl KaraokeApp {
+    /// Open the lyrics of `song` in the editor.
+    pub(crate) fn open_lyrics_editor(&mut self, song: &Path) {
+        self.lyrics_editor = Some(LyricsEditor::open(song));
+        self.view = View::Editor;
+    }
+
+    pub(crate) fn lyrics_editor_view(&mut self, ui: &mut egui::Ui) {
+        let current = self
+            .player
+            .as_ref()
+            .and_then(|player| player.current_path())
+            .map(Path::to_path_buf);
+        let Some(editor) = &mut self.lyrics_editor else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.label(
+                    "Right-click a song in the Library and pick Edit lyrics to time its lyrics.",
+                );
+                if let Some(current) = &current {
+                    if ui.button("Edit the lyrics of the song playing").clicked() {
+                        self.open_lyrics_editor(current);
+                    }
+                }
+            });
+            return;
+        };
+
+        let song = editor.song.clone();
+        let playing = current.as_deref() == Some(song.as_path());
+        let position = self
+            .player
+            .as_ref()
+            .filter(|_| playing)
+            .map(|player| player.get_position());
+
+        let mut play = false;
+        let mut save = false;
+        let mut close = false;
+        ui.horizontal(|ui| {
+            ui.heading(song_title(&song));
+            if editor.changed {
+                ui.weak("(not saved)");
+            }
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                close = ui.button("Close").clicked();
+                save = ui
+                    .add_enabled(editor.changed, egui::Button::new("💾 Save"))
+                    .clicked();
+                editor.changed |= ui
+                    .checkbox(&mut editor.compact, "Merge repeated lines")
+                    .on_hover_text("Save a line sung more than once with all of its times")
+                    .changed();
+            });
+        });
+        ui.separator();
+
+        ui.collapsing("Lyrics text", |ui| {
+            ui.weak("One lyric line per row. Lines kept as they are keep their timing.");
+            ui.add(
+                egui::TextEdit::multiline(&mut editor.text)
+                    .desired_width(f32::INFINITY)
+                    .desired_rows(8),
+            );
+            if ui.button("Use this text").clicked() {
+                let text = editor.text.clone();
+                editor.set_text(text);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            match position {
+                Some(position) => {
+                    ui.label(format!("⏱ {}", format_time(position)));
+                },
+                None => play = ui.button("▶ Play the song to time it").clicked(),
+            }
+            ui.separator();
+            ui.label("Tap:");
+            for mode in TapMode::ALL {
+                if ui
+                    .selectable_value(&mut editor.mode, mode, mode.label())
+                    .changed()
+                {
+                    editor.word = 0;
+                }
+            }
+            ui.separator();
+            let tap = ui
+                .add_enabled(position.is_some(), egui::Button::new("Stamp (Space)"))
+                .clicked();
+            let undo = ui
+                .add_enabled(
+                    !editor.taps.is_empty(),
+                    egui::Button::new("Undo (Backspace)"),
+                )
+                .clicked();
+            let keys = !ui.ctx().wants_keyboard_input();
+            let (space, backspace) = ui.input(|i| {
+                (
+                    keys && i.key_pressed(egui::Key::Space),
+                    keys && i.key_pressed(egui::Key::Backspace),
+                )
+            });
+            if let Some(position) = position.filter(|_| tap || space) {
+                editor.tap(position);
+            }
+            if undo || backspace {
+                editor.undo();
+            }
+            ui.separator();
+            ui.menu_button("Estimate words", |ui| {
+                for weighting in WordWeighting::ALL {
+                    if ui.button(weighting.label()).clicked() {
+                        editor.estimate_words(weighting);
+                        ui.close_menu();
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Time the words of lines timed as a whole");
+        });
+        ui.separator();
+
+        if editor.lines.is_empty() {
+            ui.weak("Type or paste the lyrics above to start.");
+        }
+        egui::ScrollArea::vertical()
+            .auto_shrink(false)
+            .show(ui, |ui| {
+                egui::Grid::new("lyrics_editor_lines")
+                    .num_columns(3)
+                    .spacing([8.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for index in 0..editor.lines.len() {
+                            editor_line_row(ui, editor, index, position);
+                            ui.end_row();
+                        }
+                    });
+            });
+
+        if play {
+            self.play_song(&song);
+            self.view = View::Editor;
+        }
+        if save {
+            self.save_lyrics_editor();
+        }
+        if close {
+            self.lyrics_editor = None;
+        }
+    }
+
+    fn save_lyrics_editor(&mut self) {
+        let Some(editor) = &mut self.lyrics_editor else {
+            return;
+        };
+        self.status = Some(match editor.save() {
+            Ok(0) => format!("Saved the lyrics of {}", song_title(&editor.song)),
+            Ok(untimed) => format!(
+                "Saved the lyrics of {}, leaving out {untimed} lines without a time",
+                song_title(&editor.song)
+            ),
+            Err(e) => e.to_string(),
+        });
+    }
+}
+
+/// Line `index`: its time with the controls to change it, then its text
+/// with the words up next and timed marked.
+fn editor_line_row(
+    ui: &mut egui::Ui,
+    editor: &mut LyricsEditor,
+    index: usize,
+    position: Option<Duration>,
+) {
+    let line = &editor.lines[index];
+    let start = line.start;
+    ui.horizontal(|ui| {
+        let time = start.map_or("--:--.--".to_string(), |start| {
+            format!("{}.{:02}", format_time(start), start.subsec_millis() / 10)
+        });
+        ui.monospace(time);
+        ui.add_enabled_ui(start.is_some(), |ui| {
+            if ui
+                .small_button("−")
+                .on_hover_text(format!("{NUDGE_MS} ms earlier"))
+                .clicked()
+            {
+                editor.nudge(index, -NUDGE_MS);
+            }
+            if ui
+                .small_button("+")
+                .on_hover_text(format!("{NUDGE_MS} ms later"))
+                .clicked()
+            {
+                editor.nudge(index, NUDGE_MS);
+            }
+        });
+        if let Some(position) = position {
+            if ui
+                .small_button("⏱")
+                .on_hover_text("Start the line here")
+                .clicked()
+            {
+                editor.retime(index, position);
+            }
+        }
+    });
+
+    let up_next = index == editor.line;
+    let line = &editor.lines[index];
+    let visuals = ui.visuals();
+    let mut job = egui::text::LayoutJob::default();
+    let font = egui::TextStyle::Body.resolve(ui.style());
+    let offsets = line.word_offsets();
+    for (offset, c) in line.text.chars().enumerate() {
+        let word = offsets
+            .partition_point(|&start| start <= offset)
+            .checked_sub(1);
+        let timed = word.is_some_and(|word| line.words[word].is_some());
+        let next = up_next && editor.mode == TapMode::Words && word == Some(editor.word);
+        let color = if next {
+            visuals.selection.stroke.color
+        } else if timed || (editor.mode == TapMode::Lines && start.is_some()) {
+            visuals.strong_text_color()
+        } else {
+            visuals.text_color()
+        };
+        job.append(
+            &c.to_string(),
+            0.0,
+            egui::TextFormat::simple(font.clone(), color),
+        );
+    }
+    let marker = if up_next { "▶" } else { "" };
+    ui.label(marker);
+    let response = ui.label(job);
+    if up_next {
+        response.scroll_to_me(Some(egui::Align::Center));
+    }
+}
@@ -0,0 +1,390 @@
+//! Sizing of lyric lines so they fit the view.
+//!
+//! A line is drawn at the configured font size when it fits. Longer lines
+//! shrink down to the smallest size allowed, and wrap onto several rows of
+//! about the same length only when even that is too wide. Right-to-left
+//! lines are wrapped here in reading order before each row is reordered
+//! for display, since egui's own wrapping would break them in visual order.
+//!
+//! The line being sung can be coloured as far as it has been sung. Lines
+//! that are reordered are coloured a whole row at a time, once the row is
+//! sung, because reordering and shaping move characters around.
+//!
+//! Colours, the font and an outline or shadow behind the text come from the
+//! display settings, with the theme's colours where none are chosen.
+
+use std::f32::consts::TAU;
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use super::fonts::lyrics_font;
+use crate::config::DisplayConfig;
+use crate::lrc::bidi::{self, TextDirection};
+
+/// Size of the previous and upcoming lyric lines against the current one.
+const SIDE_LINE_SCALE: f32 = 0.6;
+/// Outline width and shadow offset against the font size.
+const EFFECT_SCALE: f32 = 0.05;
+/// Times the size of a line is measured again and brought down before it
+/// is settled on.
+const FIT_ATTEMPTS: usize = 4;
+/// Steps of the search for the narrowest width a wrapped line keeps its
+/// number of rows at.
+const BALANCE_STEPS: usize = 8;
+
+/// Drawn behind lyrics to keep them readable over busy video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextEffect {
+    #[default]
+    None,
+    Outline,
+    Shadow,
+}
+
+impl TextEffect {
+    pub const ALL: [Self; 3] = [Self::None, Self::Outline, Self::Shadow];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Outline => "Outline",
+            Self::Shadow => "Drop shadow",
+        }
+    }
+}
+
+/// How the lyrics move on when the next line starts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineTransition {
+    /// Cut straight to the next line
+    Instant,
+    /// Lines fade into their new colours
+    #[default]
+    Fade,
+    /// Lines slide up into place
+    Scroll,
+}
+
+impl LineTransition {
+    pub const ALL: [Self; 3] = [Self::Instant, Self::Fade, Self::Scroll];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Instant => "Instant",
+            Self::Fade => "Fade",
+            Self::Scroll => "Smooth scroll",
+        }
+    }
+}
+
+/// Pace of a line transition over its duration.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    Linear,
+    /// Fast at first, settling gently
+    #[default]
+    EaseOut,
+    /// Slow at both ends
+    EaseInOut,
+}
+
+impl Easing {
+    pub const ALL: [Self; 3] = [Self::Linear, Self::EaseOut, Self::EaseInOut];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Linear => "Linear",
+            Self::EaseOut => "Ease out",
+            Self::EaseInOut => "Ease in and out",
+        }
+    }
+
+    /// How far along the transition is after `t` of its duration, both
+    /// from 0.0 to 1.0.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Self::Linear => t,
+            Self::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Self::EaseInOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+/// Colours lyric lines are drawn in.
+pub(crate) struct LyricColors {
+    /// The line being sung
+    pub active: egui::Color32,
+    /// The lines around it
+    pub inactive: egui::Color32,
+    /// The part of the line already sung
+    pub highlight: egui::Color32,
+}
+
+impl LyricColors {
+    /// The colours chosen in `display`, the theme's where none is.
+    pub(crate) fn new(visuals: &egui::Visuals, display: &DisplayConfig) -> Self {
+        let color = |chosen: Option<[u8; 3]>, theme| {
+            chosen.map_or(theme, |[r, g, b]| egui::Color32::from_rgb(r, g, b))
+        };
+        Self {
+            active: color(display.active_color, visuals.strong_text_color()),
+            inactive: color(display.inactive_color, visuals.weak_text_color()),
+            highlight: color(display.highlight_color, visuals.selection.stroke.color),
+        }
+    }
+}
+
+/// `display` for the smaller lines around the one being sung.
+pub(crate) fn side_lines(display: &DisplayConfig) -> DisplayConfig {
+    DisplayConfig {
+        font_size: display.font_size * SIDE_LINE_SCALE,
+        min_font_size: display.min_font_size * SIDE_LINE_SCALE,
+        ..display.clone()
+    }
+}
+
+/// Lay out `text` centred in `width` points, shrinking or wrapping it
+/// within the bounds of `display`.
+pub(crate) fn fit_line(
+    ui: &egui::Ui,
+    text: &str,
+    direction: TextDirection,
+    display: &DisplayConfig,
+    color: egui::Color32,
+    width: f32,
+) -> Arc<egui::Galley> {
+    fit_sung_line(ui, text, 0, direction, display, (color, color), width)
+}
+
+/// Like [`fit_line`], with the first `sung` characters of `text` in the
+/// first of `colors` and the rest in the second. The sung part is also
+/// underlined, so it stands out without telling the colours apart.
+pub(crate) fn fit_sung_line(
+    ui: &egui::Ui,
+    text: &str,
+    sung: usize,
+    direction: TextDirection,
+    display: &DisplayConfig,
+    colors: (egui::Color32, egui::Color32),
+    width: f32,
+) -> Arc<egui::Galley> {
+    let (sung_color, color) = colors;
+    let largest = display.font_size;
+    let smallest = display.min_font_size.min(largest);
+    // The outline or shadow reaches past the text
+    let width = (width - effect_margin(display)).max(1.0);
+    let size = fitting_size(
+        ui,
+        &bidi::visual_line(text, direction),
+        smallest,
+        largest,
+        width,
+    );
+    let width = balanced_width(ui, text, direction, size, width);
+    let font = lyrics_font(size);
+    let format = |sung: bool| {
+        let mut format =
+            egui::TextFormat::simple(font.clone(), if sung { sung_color } else { color });
+        if sung {
+            format.underline = egui::Stroke::new((size / 16.0).max(1.5), sung_color);
+        }
+        format
+    };
+
+    let mut job = egui::text::LayoutJob::default();
+    if bidi::needs_reordering(text, direction) {
+        // Rows are separated by one space in `text`
+        let mut read = 0;
+        for (index, row) in wrap_words(ui, text, direction, size, width)
+            .iter()
+            .enumerate()
+        {
+            read += row.chars().count() + usize::from(index > 0);
+            let separator = if index > 0 { "\n" } else { "" };
+            job.append(
+                &format!("{separator}{}", bidi::visual_line(row, direction)),
+                0.0,
+                format(read <= sung),
+            );
+        }
+    } else {
+        let split = text
+            .char_indices()
+            .nth(sung)
+            .map_or(text.len(), |(index, _)| index);
+        let (sung, rest) = text.split_at(split);
+        job.append(sung, 0.0, format(true));
+        job.append(rest, 0.0, format(false));
+        job.wrap.max_width = width;
+    }
+    job.halign = egui::Align::Center;
+    ui.fonts(|fonts| fonts.layout_job(job))
+}
+
+/// Show a line laid out by [`fit_line`] or [`fit_sung_line`] with the
+/// outline or shadow of `display` behind it.
+pub(crate) fn lyric_label(
+    ui: &mut egui::Ui,
+    galley: Arc<egui::Galley>,
+    display: &DisplayConfig,
+) -> egui::Response {
+    let (rect, response) = ui.allocate_exact_size(galley.size(), egui::Sense::hover());
+    if !ui.is_rect_visible(rect) {
+        return response;
+    }
+    // Lines are centred on their top
+    let position = rect.center_top();
+    let [r, g, b] = display.effect_color;
+    let effect_color = egui::Color32::from_rgb(r, g, b);
+    let width = effect_width(display);
+    let offsets = match display.text_effect {
+        TextEffect::None => Vec::new(),
+        TextEffect::Outline => (0..8)
+            .map(|step| egui::Vec2::angled(step as f32 * TAU / 8.0) * width)
+            .collect(),
+        TextEffect::Shadow => vec![egui::vec2(width, width)],
+    };
+    let painter = ui.painter();
+    for offset in offsets {
+        painter.galley_with_override_text_color(position + offset, galley.clone(), effect_color);
+    }
+    painter.galley(position, galley, effect_color);
+    response
+}
+
+/// Sample lines drawn the way `display` draws lyrics: a previous line, a
+/// line half sung and an upcoming one.
+pub(crate) fn lyric_preview(ui: &mut egui::Ui, display: &DisplayConfig) {
+    let colors = LyricColors::new(ui.visuals(), display);
+    let side = side_lines(display);
+    let upcoming = colors
+        .active
+        .gamma_multiply(1.0 - display.upcoming_dim.clamp(0.0, 1.0));
+    let sung = "Every voice a shining light";
+    egui::Frame::none()
+        .fill(ui.visuals().extreme_bg_color)
+        .inner_margin(16.0)
+        .rounding(4.0)
+        .show(ui, |ui| {
+            ui.vertical_centered(|ui| {
+                let width = ui.available_width().min(560.0);
+                let direction = TextDirection::Auto;
+                let previous = "Sing it loud, the night is young";
+                let galley = fit_line(ui, previous, direction, &side, colors.inactive, width);
+                lyric_label(ui, galley, &side);
+                let galley = fit_sung_line(
+                    ui,
+                    sung,
+                    sung.chars().count() / 2,
+                    direction,
+                    display,
+                    (colors.highlight, colors.active),
+                    width,
+                );
+                lyric_label(ui, galley, display);
+                let next = "Hold the note and let it go";
+                let galley = fit_line(ui, next, direction, &side, upcoming, width);
+                lyric_label(ui, galley, &side);
+            });
+        });
+}
+
+fn effect_width(display: &DisplayConfig) -> f32 {
+    (display.font_size * EFFECT_SCALE).max(1.0)
+}
+
+/// How much wider than the text its outline or shadow is.
+fn effect_margin(display: &DisplayConfig) -> f32 {
+    match display.text_effect {
+        TextEffect::None => 0.0,
+        TextEffect::Outline => effect_width(display) * 2.0,
+        TextEffect::Shadow => effect_width(display),
+    }
+}
+
+/// The largest size from `smallest` to `largest` that `text` fits in
+/// `width` at. Text does not widen quite in proportion to its size, so the
+/// estimate is measured again until it fits.
+fn fitting_size(ui: &egui::Ui, text: &str, smallest: f32, largest: f32, width: f32) -> f32 {
+    let mut size = largest;
+    for _ in 0..FIT_ATTEMPTS {
+        let natural = text_width(ui, text, size);
+        if natural <= width || size <= smallest {
+            break;
+        }
+        size = (size * width / natural).min(size - 0.5).max(smallest);
+    }
+    size
+}
+
+/// Width to wrap `text` at so its rows come out about the same length,
+/// rather than full rows and a last word on its own: the narrowest width
+/// that still needs no more rows than `width` does.
+fn balanced_width(
+    ui: &egui::Ui,
+    text: &str,
+    direction: TextDirection,
+    size: f32,
+    width: f32,
+) -> f32 {
+    if text_width(ui, &bidi::visual_line(text, direction), size) <= width {
+        return width;
+    }
+    let rows = wrap_words(ui, text, direction, size, width).len();
+    if rows <= 1 {
+        return width;
+    }
+    let (mut narrow, mut wide) = (width / rows as f32, width);
+    for _ in 0..BALANCE_STEPS {
+        let middle = (narrow + wide) / 2.0;
+        if wrap_words(ui, text, direction, size, middle).len() > rows {
+            narrow = middle;
+        } else {
+            wide = middle;
+        }
+    }
+    wide
+}
+
+fn text_width(ui: &egui::Ui, text: &str, size: f32) -> f32 {
+    ui.fonts(|fonts| {
+        fonts
+            .layout_no_wrap(
+                text.to_string(),
+                lyrics_font(size),
+                egui::Color32::PLACEHOLDER,
+            )
+            .size()
+            .x
+    })
+}
+
+/// Split `text` into rows of whole words no wider than `width`, in
+/// reading order.
+fn wrap_words(
+    ui: &egui::Ui,
+    text: &str,
+    direction: TextDirection,
+    size: f32,
+    width: f32,
+) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if row.is_empty() {
+            word.to_string()
+        } else {
+            format!("{row} {word}")
+        };
+        let fits = text_width(ui, &bidi::visual_line(&candidate, direction), size) <= width;
+        if fits || row.is_empty() {
+            row = candidate;
+        } else {
+            rows.push(std::mem::replace(&mut row, word.to_string()));
+        }
+    }
+    rows.push(row);
+    rows
+}
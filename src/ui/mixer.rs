@@ -0,0 +1,92 @@
+//! Mixer popover opened from the bottom panel: the live balance of music,
+//! microphones, effects and master without going to Settings.
+
+use crate::app::KaraokeApp;
+
+/// Height of the faders.
+const FADER_HEIGHT: f32 = 120.0;
+
+impl KaraokeApp {
+    /// "Mixer" button with a popover of faders. They change the same
+    /// settings as Settings → Audio System and are saved with them.
+    pub(crate) fn mixer_popover(&mut self, ui: &mut egui::Ui) {
+        let popup_id = ui.make_persistent_id("mixer_popover");
+        let button = ui
+            .button("🎚 Mixer")
+            .on_hover_text("Balance the music and microphones");
+        if button.clicked() {
+            ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+        }
+
+        let mut changed = false;
+        egui::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &button,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.spacing_mut().slider_width = FADER_HEIGHT;
+                let audio = &mut self.config.audio;
+                ui.horizontal(|ui| {
+                    changed |= fader(
+                        ui,
+                        "Music",
+                        egui::Slider::new(&mut audio.music_level_db, -24.0..=6.0)
+                            .suffix(" dB")
+                            .step_by(0.5),
+                    );
+                    changed |= fader(
+                        ui,
+                        "Mic 1",
+                        egui::Slider::new(&mut audio.input_gain_db, -12.0..=24.0)
+                            .suffix(" dB")
+                            .step_by(0.5),
+                    );
+                    if audio.duet.enabled {
+                        changed |= fader(
+                            ui,
+                            "Mic 2",
+                            egui::Slider::new(&mut audio.duet.input_gain_db, -12.0..=24.0)
+                                .suffix(" dB")
+                                .step_by(0.5),
+                        );
+                    }
+                    changed |= fader(
+                        ui,
+                        "FX",
+                        egui::Slider::new(&mut audio.effects_send, 0.0..=1.0)
+                            .custom_formatter(|send, _| format!("{:.0}%", send * 100.0)),
+                    );
+                    ui.separator();
+                    changed |= fader(
+                        ui,
+                        "Master",
+                        egui::Slider::new(&mut audio.master_volume, 0.0..=1.0)
+                            .custom_formatter(|volume, _| format!("{:.0}%", volume * 100.0)),
+                    );
+                });
+            },
+        );
+
+        if changed {
+            self.params.apply_config(&self.config.audio);
+            self.apply_song_settings();
+            if let Some(player) = &self.player {
+                player.set_volume(self.config.audio.master_volume);
+            }
+            self.config_unsaved = true;
+        }
+    }
+}
+
+/// A vertical `slider` over its channel name. Returns whether it moved.
+fn fader(ui: &mut egui::Ui, name: &str, slider: egui::Slider) -> bool {
+    ui.vertical_centered(|ui| {
+        ui.set_width(56.0);
+        let changed = ui.add(slider.vertical()).changed();
+        ui.small(name);
+        changed
+    })
+    .inner
+}
@@ -0,0 +1,33 @@
+//! UI components. Each view is an `impl KaraokeApp` block in its own file.
+
+pub mod announcer_settings;
+pub mod data_settings;
+pub mod device_export;
+pub mod diagnostics;
+pub mod difficulty;
+pub mod downloads;
+pub mod effects_rack;
+pub mod fonts;
+pub mod karaoke_view;
+pub mod layout;
+pub mod level_meter;
+pub mod library_view;
+pub mod lrc_import;
+pub mod lyrics_editor;
+pub mod lyrics_layout;
+pub mod mixer;
+pub mod pitch_guide;
+pub mod practice_view;
+pub mod queue_popover;
+pub mod reference_keyboard;
+pub mod remote_settings;
+pub mod results_view;
+pub mod session_view;
+pub mod settings_view;
+pub mod singer_profiles;
+pub mod singer_screen;
+pub mod song_summary;
+pub mod theme;
+pub mod ticker;
+pub mod warmup_view;
+pub mod waveform_bar;
@@ -0,0 +1,145 @@
+//! Pitch guide lane: the song's melody scrolling past the playhead, with
+//! the pitch being sung trailing behind it.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::audio::melody::Melody;
+use crate::audio::pitch;
+use crate::audio::processor::MIC_COUNT;
+
+const HEIGHT: f32 = 90.0;
+/// Seconds of the melody shown before and after the playhead.
+const BEHIND_SECS: f32 = 2.0;
+const AHEAD_SECS: f32 = 6.0;
+/// Headroom above and below the song's range, in semitones.
+const MARGIN_NOTES: f32 = 3.0;
+/// Smallest range shown, so a monotone song does not fill the lane.
+const MIN_RANGE_NOTES: f32 = 12.0;
+
+/// The pitch sung over the last seconds, as `(seconds, fractional MIDI
+/// note)`, `None` where nothing was sung.
+#[derive(Debug, Default)]
+pub(crate) struct PitchTrail {
+    points: VecDeque<(f32, Option<f32>)>,
+}
+
+impl PitchTrail {
+    /// Add the pitch sung at `time` seconds, in Hz, and forget what has
+    /// scrolled out of the lane. Going back in the song, e.g. by seeking,
+    /// starts the trail again.
+    pub(crate) fn push(&mut self, time: f32, frequency: Option<f32>) {
+        if self.points.back().is_some_and(|&(last, _)| last > time) {
+            self.points.clear();
+        }
+        self.points
+            .push_back((time, frequency.map(pitch::frequency_to_midi)));
+        while self
+            .points
+            .front()
+            .is_some_and(|&(first, _)| first < time - BEHIND_SECS)
+        {
+            self.points.pop_front();
+        }
+    }
+}
+
+/// Draw the lane at `position` with the trail of what each singer sang,
+/// the melody moved `key_shift` semitones along with the backing track.
+pub(crate) fn pitch_guide(
+    ui: &mut egui::Ui,
+    melody: &Melody,
+    key_shift: i8,
+    position: Duration,
+    trails: &[PitchTrail; MIC_COUNT],
+) {
+    let size = egui::vec2(ui.available_width(), HEIGHT);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 4.0, visuals.extreme_bg_color);
+
+    let Some((low, high)) = melody.range() else {
+        return;
+    };
+    let shift = f32::from(key_shift);
+    let (low, high) = (low + shift, high + shift);
+    let middle = (low + high) / 2.0;
+    let half = ((high - low) / 2.0 + MARGIN_NOTES).max(MIN_RANGE_NOTES / 2.0);
+    let (bottom, top) = (middle - half, middle + half);
+
+    let now = position.as_secs_f32();
+    let (start, end) = (now - BEHIND_SECS, now + AHEAD_SECS);
+    let x_of = |secs: f32| rect.left() + (secs - start) / (end - start) * rect.width();
+    let y_of = |note: f32| rect.bottom() - (note - bottom) / (top - bottom) * rect.height();
+    // Sung pitch folded to the octave nearest the melody, so a singer an
+    // octave off still lands on the lane
+    let fold = |note: f32| note + ((middle - note) / 12.0).round() * 12.0;
+
+    let notes = melody.notes_between(start, end);
+    for note in notes {
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x_of(note.start), y_of(note.note + shift + 0.5)),
+            egui::pos2(x_of(note.end), y_of(note.note + shift - 0.5)),
+        );
+        let current = note.start <= now && now < note.end;
+        let color = if current {
+            visuals.selection.bg_fill
+        } else {
+            visuals.widgets.inactive.bg_fill
+        };
+        painter.rect_filled(bar, 2.0, color);
+    }
+    if let Some(note) = notes.iter().find(|note| note.end > now) {
+        painter.text(
+            egui::pos2(x_of(note.start.max(now)), y_of(note.note + shift + 0.5))
+                + egui::vec2(2.0, -2.0),
+            egui::Align2::LEFT_BOTTOM,
+            pitch::note_name((note.note + shift).round() as u8),
+            egui::FontId::proportional(11.0),
+            visuals.weak_text_color(),
+        );
+    }
+
+    let playhead = x_of(now);
+    painter.line_segment(
+        [
+            egui::pos2(playhead, rect.top()),
+            egui::pos2(playhead, rect.bottom()),
+        ],
+        egui::Stroke::new(1.0, visuals.weak_text_color()),
+    );
+
+    // The second singer is drawn hollow as well as in another colour
+    for (singer, trail) in trails.iter().enumerate() {
+        let color = if singer == 0 {
+            visuals.strong_text_color()
+        } else {
+            visuals.hyperlink_color
+        };
+        let points: Vec<(f32, Option<f32>)> = trail
+            .points
+            .iter()
+            .map(|&(time, note)| (time, note.map(|note| fold(note).clamp(bottom, top))))
+            .collect();
+        for pair in points.windows(2) {
+            if let [(from_time, Some(from)), (to_time, Some(to))] = [pair[0], pair[1]] {
+                painter.line_segment(
+                    [
+                        egui::pos2(x_of(from_time), y_of(from)),
+                        egui::pos2(x_of(to_time), y_of(to)),
+                    ],
+                    (2.0, color),
+                );
+            }
+        }
+        if let Some(&(_, Some(note))) = points.last() {
+            let center = egui::pos2(playhead, y_of(note));
+            if singer == 0 {
+                painter.circle_filled(center, 4.0, color);
+            } else {
+                painter.circle_stroke(center, 4.5, (1.5, color));
+            }
+        }
+    }
+}
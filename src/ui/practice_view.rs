@@ -0,0 +1,123 @@
+//! Practice view: the current song's lyrics with chords above the words,
+//! following playback, for playing along on guitar or piano.
+
+use std::path::Path;
+
+use crate::app::{song_title, KaraokeApp};
+use crate::audio::AudioPlayer;
+use crate::lrc::chords::{self, ChordLine, ChordMark};
+
+impl KaraokeApp {
+    pub(crate) fn practice_view(&mut self, ui: &mut egui::Ui) {
+        let current = self
+            .player
+            .as_ref()
+            .and_then(AudioPlayer::current_path)
+            .map(Path::to_path_buf);
+        let Some(path) = current else {
+            ui.vertical_centered(|ui| {
+                ui.add_space(ui.available_height() / 3.0);
+                ui.label("Pick a song in the Library to practise with its chords.");
+            });
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.heading(song_title(&path));
+            if ui
+                .small_button("⟳")
+                .on_hover_text("Read the lyrics and chords again")
+                .clicked()
+            {
+                self.chord_sheet = None;
+            }
+        });
+        ui.separator();
+
+        if self
+            .chord_sheet
+            .as_ref()
+            .is_none_or(|(song, _)| *song != path)
+        {
+            let lines = chords::chord_sheet(&path).unwrap_or_else(|e| {
+                tracing::warn!("{e}");
+                Vec::new()
+            });
+            self.chord_sheet = Some((path.clone(), lines));
+        }
+        let Some((_, lines)) = &self.chord_sheet else {
+            return;
+        };
+        if lines.iter().all(|line| line.chords.is_empty()) {
+            ui.label("This song has no chords yet.");
+            ui.weak(
+                "Write them in front of the words in the .lrc file, e.g. \
+                 [00:12.00][Am]Hello [F]darkness, or as timed changes such as \
+                 [00:12.00]Am in a .chords file next to the song.",
+            );
+            return;
+        }
+
+        let position = self
+            .player
+            .as_ref()
+            .map(AudioPlayer::get_position)
+            .unwrap_or_default();
+        let current = lines
+            .partition_point(|line| line.start <= position)
+            .checked_sub(1);
+        let size = self.config.display.min_font_size;
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for (index, line) in lines.iter().enumerate() {
+                    let active = current == Some(index);
+                    let response = chord_line(ui, line, size, active);
+                    if active {
+                        response.scroll_to_me(Some(egui::Align::Center));
+                    }
+                    ui.add_space(size / 2.0);
+                }
+            });
+    }
+}
+
+/// The chords of `line` in a row above its text, both monospaced so each
+/// chord sits over the character it falls on.
+fn chord_line(ui: &mut egui::Ui, line: &ChordLine, size: f32, active: bool) -> egui::Response {
+    let visuals = ui.visuals();
+    let (text_color, chord_color) = if active {
+        (visuals.strong_text_color(), visuals.warn_fg_color)
+    } else {
+        (visuals.weak_text_color(), visuals.text_color())
+    };
+    let font = egui::FontId::monospace(size);
+    ui.vertical(|ui| {
+        if !line.chords.is_empty() {
+            ui.label(
+                egui::RichText::new(chord_row(&line.chords))
+                    .font(font.clone())
+                    .color(chord_color)
+                    .strong(),
+            );
+        }
+        if !line.text.is_empty() {
+            ui.label(egui::RichText::new(&line.text).font(font).color(text_color));
+        }
+    })
+    .response
+}
+
+/// `marks` spaced out to their offsets, at least one space apart.
+fn chord_row(marks: &[ChordMark]) -> String {
+    let mut row = String::new();
+    let mut width = 0;
+    for mark in marks {
+        let gap = if width == 0 { 0 } else { 1 };
+        let column = mark.offset.max(width + gap);
+        row.extend(std::iter::repeat_n(' ', column - width));
+        row += &mark.chord;
+        width = column + mark.chord.chars().count();
+    }
+    row
+}
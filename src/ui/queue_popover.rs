@@ -0,0 +1,80 @@
+//! Queue popover opened from the bottom panel, for fixing the next songs
+//! without leaving the current view.
+
+use std::sync::Arc;
+
+use crate::app::{display_title, KaraokeApp};
+
+/// Songs shown in the popover.
+const UPCOMING: usize = 3;
+
+impl KaraokeApp {
+    /// "Up next" button with a popover listing the next queued songs. Rows
+    /// can be dragged onto each other to reorder them, or removed.
+    pub(crate) fn queue_popover(&mut self, ui: &mut egui::Ui) {
+        let popup_id = ui.make_persistent_id("queue_popover");
+        let button = ui
+            .button(format!("☰ Up next ({})", self.queue.len()))
+            .on_hover_text("Edit the upcoming songs");
+        if button.clicked() {
+            ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+        }
+
+        let mut moved = None;
+        let mut remove = None;
+        egui::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &button,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(240.0);
+                if self.queue.is_empty() {
+                    ui.weak("Queue is empty");
+                    return;
+                }
+                for (index, path) in self.queue.iter().take(UPCOMING).enumerate() {
+                    let row = ui.horizontal(|ui| {
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                        ui.dnd_drag_source(popup_id.with(index), index, |ui| {
+                            ui.label(format!(
+                                "☰ {}. {}",
+                                index + 1,
+                                display_title(&self.storage, path)
+                            ));
+                        });
+                        self.singer_label(ui, self.session_log.requester(path));
+                    });
+                    let response = row.response;
+                    if response.dnd_hover_payload::<usize>().is_some() {
+                        ui.painter().hline(
+                            response.rect.x_range(),
+                            response.rect.top(),
+                            ui.visuals().selection.stroke,
+                        );
+                    }
+                    if let Some(from) = response.dnd_release_payload::<usize>() {
+                        moved = Some((Arc::unwrap_or_clone(from), index));
+                    }
+                }
+                let more = self.queue.len().saturating_sub(UPCOMING);
+                if more > 0 {
+                    ui.weak(format!("…and {more} more"));
+                }
+            },
+        );
+
+        if let Some((from, to)) = moved.filter(|(from, to)| from != to) {
+            if let Some(path) = self.queue.remove(from) {
+                self.queue.insert(to, path);
+            }
+            self.queue_changed();
+        } else if let Some(index) = remove {
+            self.queue.remove(index);
+            self.queue_changed();
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! One-octave keyboard for playing a starting note.
+
+use crate::audio::pitch;
+
+const WHITE_WIDTH: f32 = 20.0;
+const WHITE_HEIGHT: f32 = 52.0;
+const BLACK_WIDTH: f32 = 12.0;
+const BLACK_HEIGHT: f32 = 32.0;
+
+/// Pitch classes of the white keys, left to right.
+const WHITE_KEYS: [u8; 7] = [0, 2, 4, 5, 7, 9, 11];
+/// Pitch classes of the black keys with the white key they sit after.
+const BLACK_KEYS: [(u8, usize); 5] = [(1, 0), (3, 1), (6, 3), (8, 4), (10, 5)];
+
+/// Draw a keyboard of one octave, marking the `tonic` pitch class if known.
+/// Returns the pitch class of the key clicked.
+pub(crate) fn reference_keyboard(ui: &mut egui::Ui, octave: i8, tonic: Option<u8>) -> Option<u8> {
+    let size = egui::vec2(WHITE_WIDTH * WHITE_KEYS.len() as f32, WHITE_HEIGHT);
+    let (rect, response) = ui.allocate_exact_size(size, egui::Sense::click());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+
+    let white_rect = |index: usize| {
+        egui::Rect::from_min_size(
+            rect.min + egui::vec2(index as f32 * WHITE_WIDTH, 0.0),
+            egui::vec2(WHITE_WIDTH, WHITE_HEIGHT),
+        )
+    };
+    let black_rect = |after: usize| {
+        egui::Rect::from_min_size(
+            rect.min + egui::vec2((after + 1) as f32 * WHITE_WIDTH - BLACK_WIDTH / 2.0, 0.0),
+            egui::vec2(BLACK_WIDTH, BLACK_HEIGHT),
+        )
+    };
+
+    // Black keys cover the white ones, so they win hit tests
+    let hovered = response.hover_pos().and_then(|pointer| {
+        BLACK_KEYS
+            .iter()
+            .find(|(_, after)| black_rect(*after).contains(pointer))
+            .map(|(note, _)| *note)
+            .or_else(|| {
+                (0..WHITE_KEYS.len())
+                    .find(|index| white_rect(*index).contains(pointer))
+                    .map(|index| WHITE_KEYS[index])
+            })
+    });
+    let fill = |note: u8, base: egui::Color32| {
+        if hovered == Some(note) {
+            visuals.widgets.hovered.bg_fill
+        } else if tonic == Some(note) {
+            visuals.selection.bg_fill
+        } else {
+            base
+        }
+    };
+
+    let stroke = egui::Stroke::new(1.0, visuals.widgets.noninteractive.bg_stroke.color);
+    for (index, note) in WHITE_KEYS.into_iter().enumerate() {
+        let key = white_rect(index);
+        painter.rect(key, 2.0, fill(note, egui::Color32::WHITE), stroke);
+        painter.text(
+            key.center_bottom() - egui::vec2(0.0, 3.0),
+            egui::Align2::CENTER_BOTTOM,
+            pitch::NOTE_NAMES[usize::from(note)],
+            egui::FontId::proportional(10.0),
+            egui::Color32::DARK_GRAY,
+        );
+    }
+    for (note, after) in BLACK_KEYS {
+        painter.rect(
+            black_rect(after),
+            2.0,
+            fill(note, egui::Color32::BLACK),
+            stroke,
+        );
+    }
+
+    let response = match hovered {
+        Some(note) => {
+            let midi = (i32::from(octave) + 1) * 12 + i32::from(note);
+            response.on_hover_text(pitch::note_name(midi.clamp(0, 127) as u8))
+        },
+        None => response,
+    };
+    hovered.filter(|_| response.clicked())
+}
@@ -0,0 +1,134 @@
+//! Settings → Web Remote: server toggle and guest links.
+
+use std::time::Duration;
+
+use crate::app::KaraokeApp;
+use crate::remote::votes;
+
+impl KaraokeApp {
+    /// Returns true when the config changed.
+    pub(crate) fn remote_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        let mut restart = false;
+        let mut sort = false;
+
+        egui::Grid::new("remote_settings")
+            .num_columns(2)
+            .spacing([24.0, 8.0])
+            .show(ui, |ui| {
+                let remote = &mut self.config.remote;
+
+                ui.label("Web remote");
+                restart |= ui
+                    .checkbox(&mut remote.enabled, "Enabled")
+                    .on_hover_text(
+                        "Let guests browse the library and request songs from their phones",
+                    )
+                    .changed();
+                ui.end_row();
+
+                ui.label("Port");
+                // Restart once editing is done, not on every dragged value
+                let port = ui.add(egui::DragValue::new(&mut remote.port).range(1024..=65535));
+                restart |= port.drag_stopped() || port.lost_focus();
+                ui.end_row();
+
+                ui.label("Guest link lifetime");
+                changed |= ui
+                    .add(egui::Slider::new(&mut remote.guest_link_hours, 1.0..=24.0).suffix(" h"))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Requests per guest");
+                changed |= ui
+                    .add(egui::Slider::new(&mut remote.guest_request_limit, 1..=50))
+                    .changed();
+                ui.end_row();
+
+                ui.label("Queue order");
+                sort |= ui
+                    .checkbox(&mut remote.sort_queue_by_votes, "Sort by guest votes")
+                    .on_hover_text(
+                        "Most voted songs first, in rounds so every singer still gets a turn",
+                    )
+                    .changed();
+                ui.end_row();
+            });
+
+        if restart {
+            self.update_remote_server();
+        }
+        if sort && self.config.remote.sort_queue_by_votes {
+            self.queue = votes::fair_order(&self.queue, &self.queue_votes, |song| {
+                self.session_log.requester(song)
+            });
+        }
+        changed |= sort;
+
+        let Some(remote) = &self.remote else {
+            return changed || restart;
+        };
+
+        ui.add_space(8.0);
+        ui.label("Guest links");
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.guest_label).hint_text("Guest name"));
+            if ui.button("➕ Create link").clicked() {
+                let config = &self.config.remote;
+                let valid_for = Duration::from_secs_f32(config.guest_link_hours * 3600.0);
+                let created = remote
+                    .state()
+                    .tokens
+                    .create(&self.guest_label, valid_for, config.guest_request_limit)
+                    .map(drop);
+                if let Err(e) = created.and_then(|()| remote.save_tokens()) {
+                    tracing::error!("{e:#}");
+                }
+                self.guest_label.clear();
+            }
+        });
+
+        let mut state = remote.state();
+        state.tokens.prune_expired();
+        let mut revoke = None;
+        egui::Grid::new("guest_links")
+            .num_columns(4)
+            .striped(true)
+            .show(ui, |ui| {
+                for guest in state.tokens.iter() {
+                    let url = remote.guest_url(&guest.token);
+                    let name = if guest.label.is_empty() {
+                        "Guest"
+                    } else {
+                        &guest.label
+                    };
+                    ui.label(name).on_hover_text(&url);
+
+                    let left = guest.time_left().as_secs();
+                    ui.label(format!(
+                        "{}/{} requests, {}h{:02} left",
+                        guest.requests,
+                        guest.request_limit,
+                        left / 3600,
+                        left % 3600 / 60
+                    ));
+                    if ui.small_button("📋 Copy link").clicked() {
+                        ui.ctx().copy_text(url);
+                    }
+                    if ui.small_button("Revoke").clicked() {
+                        revoke = Some(guest.token.clone());
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(token) = revoke {
+            state.tokens.revoke(&token);
+            drop(state);
+            if let Err(e) = remote.save_tokens() {
+                tracing::error!("{e:#}");
+            }
+        }
+
+        changed || restart
+    }
+}
@@ -0,0 +1,94 @@
+//! Results of a scored song: each singer's score, how it breaks down and how
+//! it compares with the song's best.
+
+use crate::app::{song_title, KaraokeApp};
+use crate::audio::scoring::MAX_SCORE;
+use crate::library::results::SingerResults;
+
+const SECTION_BAR_WIDTH: f32 = 160.0;
+
+impl KaraokeApp {
+    pub(crate) fn results_window(&mut self, ctx: &egui::Context) {
+        // The summary at the end of the song shows them instead
+        if self.song_summary.is_some() {
+            return;
+        }
+        let Some(results) = &self.run_results else {
+            return;
+        };
+        let mut open = true;
+        let mut close = false;
+        egui::Window::new("Results")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.heading(song_title(&results.song));
+                ui.horizontal_top(|ui| {
+                    for singer in &results.singers {
+                        ui.group(|ui| singer_results(ui, singer));
+                    }
+                });
+                ui.add_space(8.0);
+                close = ui.button("Close").clicked();
+            });
+        if !open || close {
+            self.run_results = None;
+        }
+    }
+}
+
+pub(crate) fn singer_results(ui: &mut egui::Ui, singer: &SingerResults) {
+    let result = &singer.result;
+    ui.vertical(|ui| {
+        ui.strong(&result.singer);
+        ui.label(
+            egui::RichText::new(format!("{} / {MAX_SCORE}", result.score))
+                .size(28.0)
+                .strong(),
+        );
+        match singer.previous_best {
+            Some(best) if result.score <= best => {
+                ui.weak(format!("Best so far: {best}"));
+            },
+            _ => {
+                ui.colored_label(ui.visuals().warn_fg_color, "🏆 New best!");
+            },
+        }
+        ui.add_space(4.0);
+        egui::Grid::new(("results_totals", &result.singer))
+            .num_columns(2)
+            .spacing([16.0, 2.0])
+            .show(ui, |ui| {
+                ui.label("On pitch");
+                ui.label(format!("{:.0}%", result.accuracy * 100.0));
+                ui.end_row();
+                ui.label("Notes hit");
+                ui.label(format!("{} of {}", result.notes_hit, result.notes));
+                ui.end_row();
+                ui.label("Longest streak");
+                ui.label(format!("{} notes", result.longest_streak));
+                ui.end_row();
+            });
+
+        if singer.sections.is_empty() {
+            return;
+        }
+        ui.add_space(4.0);
+        egui::Grid::new(("results_sections", &result.singer))
+            .num_columns(2)
+            .spacing([8.0, 2.0])
+            .show(ui, |ui| {
+                for (section, accuracy) in &singer.sections {
+                    ui.label(section);
+                    ui.add(
+                        egui::ProgressBar::new(*accuracy)
+                            .desired_width(SECTION_BAR_WIDTH)
+                            .text(format!("{:.0}%", accuracy * 100.0)),
+                    );
+                    ui.end_row();
+                }
+            });
+    });
+}
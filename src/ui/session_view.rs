@@ -0,0 +1,208 @@
+//! Timeline of the running or a past session, with CSV export.
+
+use std::path::Path;
+
+use crate::app::KaraokeApp;
+use crate::session::{self, LogEntry, SessionEvent};
+
+const TIMELINE_HEIGHT: f32 = 28.0;
+
+impl KaraokeApp {
+    pub(crate) fn session_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Session log");
+        ui.separator();
+
+        ui.horizontal(|ui| {
+            ui.label("Session:");
+            let selected = match &self.viewed_session {
+                Some((path, _)) => session_name(path),
+                None => "This session".to_string(),
+            };
+            egui::ComboBox::from_id_salt("viewed_session")
+                .selected_text(selected)
+                .show_ui(ui, |ui| {
+                    if ui
+                        .selectable_label(self.viewed_session.is_none(), "This session")
+                        .clicked()
+                    {
+                        self.viewed_session = None;
+                    }
+                    let current = self.session_log.path();
+                    for path in session::list_sessions() {
+                        if path == current {
+                            continue;
+                        }
+                        let shown = self
+                            .viewed_session
+                            .as_ref()
+                            .is_some_and(|(viewed, _)| *viewed == path);
+                        if ui.selectable_label(shown, session_name(&path)).clicked() {
+                            match session::load_session(&path) {
+                                Ok(entries) => self.viewed_session = Some((path, entries)),
+                                Err(e) => self.status = Some(format!("{e:#}")),
+                            }
+                        }
+                    }
+                });
+
+            if ui.button("Export CSV…").clicked() {
+                self.export_session();
+            }
+        });
+
+        let entries = match &self.viewed_session {
+            Some((_, entries)) => entries.as_slice(),
+            None => self.session_log.entries(),
+        };
+        if entries.is_empty() {
+            ui.add_space(16.0);
+            ui.weak("Nothing has happened yet. Songs, pauses, requests and applause show up here.");
+            return;
+        }
+
+        ui.add_space(8.0);
+        timeline(ui, entries);
+        ui.add_space(8.0);
+        egui::ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(self.viewed_session.is_none())
+            .show(ui, |ui| {
+                egui::Grid::new("session_entries")
+                    .num_columns(3)
+                    .spacing([16.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for entry in entries {
+                            ui.monospace(session::format_secs(entry.at().as_secs_f32()));
+                            ui.label(format!("{} {}", entry.event.icon(), entry.event.kind()));
+                            ui.label(entry.event.details());
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// Save the shown session as CSV where the user picks.
+    fn export_session(&mut self) {
+        let (name, entries) = match &self.viewed_session {
+            Some((path, entries)) => (session_name(path), entries.as_slice()),
+            None => (
+                session_name(self.session_log.path()),
+                self.session_log.entries(),
+            ),
+        };
+        let Some(output) = rfd::FileDialog::new()
+            .set_file_name(format!("{name}.csv"))
+            .add_filter("CSV", &["csv"])
+            .save_file()
+        else {
+            return;
+        };
+        self.status = Some(match session::export_csv(entries, &output) {
+            Ok(()) => format!("Session exported to {}", output.display()),
+            Err(e) => format!("{e:#}"),
+        });
+    }
+}
+
+/// Start time of a session, from its file name.
+fn session_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Songs as bars along the session, with markers for the other events.
+/// Hovering shows what happened.
+fn timeline(ui: &mut egui::Ui, entries: &[LogEntry]) {
+    let width = ui.available_width();
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(width, TIMELINE_HEIGHT), egui::Sense::hover());
+    let end = entries.last().map_or(1, |entry| entry.at_ms.max(1)) as f32;
+    let x = |at_ms: u64| rect.left() + at_ms as f32 / end * rect.width();
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+    let mut bars = Vec::new();
+    let mut open: Option<&LogEntry> = None;
+    for entry in entries {
+        match &entry.event {
+            SessionEvent::SongStarted { .. } => open = Some(entry),
+            SessionEvent::SongEnded { .. } => {
+                if let Some(start) = open.take() {
+                    bars.push((start, entry.at_ms));
+                }
+            },
+            _ => {},
+        }
+    }
+    if let Some(start) = open {
+        bars.push((start, end as u64));
+    }
+    let song_rect = |start: &LogEntry, end_ms: u64| {
+        egui::Rect::from_x_y_ranges(
+            x(start.at_ms)..=x(end_ms).max(x(start.at_ms) + 2.0),
+            rect.top() + 4.0..=rect.bottom() - 4.0,
+        )
+    };
+    for &(start, end_ms) in &bars {
+        painter.rect_filled(song_rect(start, end_ms), 2.0, visuals.selection.bg_fill);
+    }
+
+    let markers: Vec<&LogEntry> = entries
+        .iter()
+        .filter(|entry| {
+            matches!(
+                entry.event,
+                SessionEvent::Applause
+                    | SessionEvent::Paused { .. }
+                    | SessionEvent::Seeked { .. }
+                    | SessionEvent::OutputRecovered { .. }
+                    | SessionEvent::PlaybackStalled { .. }
+            )
+        })
+        .collect();
+    for entry in &markers {
+        let color = match entry.event {
+            SessionEvent::Applause => visuals.warn_fg_color,
+            SessionEvent::OutputRecovered { .. } | SessionEvent::PlaybackStalled { .. } => {
+                visuals.error_fg_color
+            },
+            _ => visuals.weak_text_color(),
+        };
+        painter.vline(
+            x(entry.at_ms),
+            rect.y_range(),
+            egui::Stroke::new(2.0, color),
+        );
+    }
+
+    let Some(pointer) = response.hover_pos() else {
+        return;
+    };
+    let marker = markers
+        .iter()
+        .find(|entry| (x(entry.at_ms) - pointer.x).abs() <= 3.0);
+    let hovered = match marker {
+        Some(entry) => Some(format!(
+            "{} {} {}",
+            session::format_secs(entry.at().as_secs_f32()),
+            entry.event.kind(),
+            entry.event.details()
+        )),
+        None => bars
+            .iter()
+            .find(|(start, end_ms)| song_rect(start, *end_ms).contains(pointer))
+            .map(|(start, _)| {
+                format!(
+                    "{} {}",
+                    session::format_secs(start.at().as_secs_f32()),
+                    start.event.details()
+                )
+            }),
+    };
+    if let Some(text) = hovered {
+        response.on_hover_text_at_pointer(text);
+    }
+}
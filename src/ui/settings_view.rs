@@ -0,0 +1,1109 @@
+//! Settings panel.
+
+use std::collections::BTreeMap;
+
+use super::effects_rack::effects_rack_editor;
+use super::fonts;
+use super::lyrics_layout::{lyric_preview, Easing, LineTransition, LyricColors, TextEffect};
+use super::theme::{self, Palette, ThemeMode};
+use crate::adjustments::AdjustmentPolicy;
+use crate::app::KaraokeApp;
+use crate::audio::input::{self, MicInput};
+use crate::audio::player::GuideEar;
+use crate::audio::recorder::RecordingFormat;
+use crate::audio::separation::SeparationBackend;
+use crate::config::{DisplayConfig, LyricsConfig, RecordingConfig};
+use crate::lyrics::cache::LyricsCache;
+use crate::lyrics::LyricsProvider;
+use crate::video::VIDEO_EXTENSIONS;
+
+impl KaraokeApp {
+    pub(crate) fn settings_view(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut mic_changed = false;
+        let mut theme_changed = false;
+        let mut font_changed = false;
+        let mut restart_mic = false;
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            ui.heading("Audio System");
+            egui::Grid::new("audio_settings")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    let audio = &mut self.config.audio;
+
+                    if let Some(player) = &self.player {
+                        ui.label("Output device");
+                        ui.label(player.device_name());
+                        ui.end_row();
+                    }
+
+                    ui.label("Master volume");
+                    changed |= ui
+                        .add(egui::Slider::new(&mut audio.master_volume, 0.0..=1.0))
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Music level");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut audio.music_level_db, -24.0..=6.0)
+                                .suffix(" dB")
+                                .step_by(0.5),
+                        )
+                        .on_hover_text(
+                            "Music against the microphones, on top of each song's volume",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Balance");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut audio.pan, -1.0..=1.0)
+                                    .custom_formatter(|pan, _| pan_label(pan)),
+                            )
+                            .on_hover_text("Bias the output toward the left or right speaker")
+                            .changed();
+                        if ui.small_button("Centre").clicked() {
+                            audio.pan = 0.0;
+                            changed = true;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Output limiter");
+                    changed |= ui.checkbox(&mut audio.limiter_enabled, "Enabled").changed();
+                    ui.end_row();
+
+                    ui.label("Limiter threshold");
+                    changed |= ui
+                        .add_enabled(
+                            audio.limiter_enabled,
+                            egui::Slider::new(&mut audio.limiter_threshold_db, -12.0..=0.0)
+                                .suffix(" dB"),
+                        )
+                        .on_hover_text("Peaks above this level are smoothly reduced")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Vocal removal");
+                    changed |= ui
+                        .checkbox(&mut audio.vocal_removal, "Enabled")
+                        .on_hover_text("Attenuate centre-panned lead vocals on stereo tracks")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Vocal removal strength");
+                    changed |= ui
+                        .add_enabled(
+                            audio.vocal_removal,
+                            egui::Slider::new(&mut audio.vocal_removal_strength, 0.0..=1.0),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Stem separation");
+                    egui::ComboBox::from_id_salt("separation_backend")
+                        .selected_text(audio.separation_backend.label())
+                        .show_ui(ui, |ui| {
+                            for backend in SeparationBackend::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut audio.separation_backend,
+                                        backend,
+                                        backend.label(),
+                                    )
+                                    .changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text("Python tool used to produce instrumentals");
+                    ui.end_row();
+
+                    ui.label("Guide vocals");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut audio.guide_vocals, "In one ear")
+                            .on_hover_text(
+                                "While the instrumental plays, feed the original vocals quietly \
+                                 into one channel only, for the performer's earpiece",
+                            )
+                            .changed();
+                        ui.add_enabled_ui(audio.guide_vocals, |ui| {
+                            for ear in GuideEar::ALL {
+                                changed |= ui
+                                    .selectable_value(&mut audio.guide_vocals_ear, ear, ear.label())
+                                    .changed();
+                            }
+                            changed |= ui
+                                .add(
+                                    egui::Slider::new(
+                                        &mut audio.guide_vocals_level_db,
+                                        -30.0..=0.0,
+                                    )
+                                    .suffix(" dB"),
+                                )
+                                .changed();
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Feedback suppression");
+                    changed |= ui
+                        .checkbox(&mut audio.feedback_suppression, "Enabled")
+                        .on_hover_text("Automatically notch out howling tones")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Skip silence");
+                    changed |= ui
+                        .checkbox(&mut audio.skip_silence, "Enabled")
+                        .on_hover_text(
+                            "Start songs at the first audible sound and move on after the last",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Count-in");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut audio.count_in, "Enabled")
+                            .on_hover_text("Click the song's tempo before it starts")
+                            .changed();
+                        changed |= ui
+                            .add_enabled(
+                                audio.count_in,
+                                egui::Slider::new(&mut audio.count_in_beats, 1..=8)
+                                    .suffix(" beats"),
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Song adjustments");
+                    egui::ComboBox::from_id_salt("adjustment_policy")
+                        .selected_text(audio.adjustments.label())
+                        .show_ui(ui, |ui| {
+                            for policy in AdjustmentPolicy::ALL {
+                                changed |= ui
+                                    .selectable_value(
+                                        &mut audio.adjustments,
+                                        policy,
+                                        policy.label(),
+                                    )
+                                    .changed();
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Where key, speed and lyric timing changes made during a song are kept",
+                        );
+                    ui.end_row();
+
+                    ui.label("Decode ahead");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .add(
+                                egui::Slider::new(&mut audio.precache_song_mb, 0..=512)
+                                    .custom_formatter(|mb, _| match mb as u32 {
+                                        0 => "Off".to_string(),
+                                        mb => format!("songs up to {mb} MB"),
+                                    }),
+                            )
+                            .on_hover_text(
+                                "Decode queued songs into memory so seeking and restarting \
+                                 are instant; longer songs stream from disk",
+                            )
+                            .changed();
+                        changed |= ui
+                            .add_enabled(
+                                audio.precache_song_mb > 0,
+                                egui::DragValue::new(&mut audio.precache_total_mb)
+                                    .range(64..=8192)
+                                    .prefix("at most ")
+                                    .suffix(" MB in all"),
+                            )
+                            .changed();
+                    });
+                    ui.end_row();
+                });
+
+            ui.add_space(16.0);
+            ui.heading("Microphone");
+            egui::Grid::new("mic_settings")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Live passthrough");
+                    mic_changed |= ui
+                        .checkbox(&mut self.config.audio.mic_passthrough, "Enabled")
+                        .on_hover_text("Play the microphone through the speakers")
+                        .changed();
+                    ui.end_row();
+
+                    let audio = &mut self.config.audio;
+                    ui.label("Input gain");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut audio.input_gain_db, -12.0..=24.0)
+                                .suffix(" dB")
+                                .step_by(0.5),
+                        )
+                        .on_hover_text("Boost a quiet microphone or tame a hot one")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Noise gate");
+                    changed |= ui
+                        .checkbox(&mut audio.noise_gate_enabled, "Enabled")
+                        .on_hover_text("Mute the mic between phrases to cut room noise")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Gate threshold");
+                    changed |= ui
+                        .add_enabled(
+                            audio.noise_gate_enabled,
+                            egui::Slider::new(&mut audio.noise_gate_threshold_db, -80.0..=-10.0)
+                                .suffix(" dB"),
+                        )
+                        .on_hover_text("Mic level below which the gate closes")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Automatic gain");
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut audio.agc_enabled, "Enabled")
+                            .on_hover_text(
+                                "Even out quiet and loud singers without touching the input gain",
+                            )
+                            .changed();
+                        if audio.agc_enabled && self.mic.is_some() {
+                            ui.weak(format!("now {:+.1} dB", self.params.mic(0).agc_gain_db()));
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Target level");
+                    changed |= ui
+                        .add_enabled(
+                            audio.agc_enabled,
+                            egui::Slider::new(&mut audio.agc_target_db, -36.0..=-6.0).suffix(" dB"),
+                        )
+                        .on_hover_text("Average voice level the automatic gain aims for")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Maximum boost");
+                    changed |= ui
+                        .add_enabled(
+                            audio.agc_enabled,
+                            egui::Slider::new(&mut audio.agc_max_gain_db, 0.0..=30.0).suffix(" dB"),
+                        )
+                        .on_hover_text("Keeps a distant mic from being raised into feedback")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Music ducking");
+                    changed |= ui
+                        .checkbox(&mut audio.ducking, "Enabled")
+                        .on_hover_text("Lower the music while someone talks into the mic")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Ducking threshold");
+                    changed |= ui
+                        .add_enabled(
+                            audio.ducking,
+                            egui::Slider::new(&mut audio.ducking_threshold_db, -60.0..=0.0)
+                                .suffix(" dB"),
+                        )
+                        .on_hover_text("Mic level that triggers ducking")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Ducking amount");
+                    changed |= ui
+                        .add_enabled(
+                            audio.ducking,
+                            egui::Slider::new(&mut audio.ducking_depth_db, 0.0..=30.0)
+                                .suffix(" dB"),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Input device");
+                    restart_mic |= input_device_combo(
+                        ui,
+                        "input_device",
+                        &mut audio.input_device,
+                        self.mic.as_ref(),
+                    );
+                    ui.end_row();
+
+                    ui.label("Input channel");
+                    restart_mic |= input_channel_combo(
+                        ui,
+                        "input_channel",
+                        audio.input_device.as_deref(),
+                        &mut audio.input_channel,
+                    );
+                    ui.end_row();
+
+                    ui.label("Monitor latency");
+                    let latency = ui
+                        .add(
+                            egui::Slider::new(&mut audio.mic_latency_ms, input::LATENCY_RANGE_MS)
+                                .suffix(" ms"),
+                        )
+                        .on_hover_text(
+                            "Raise it if the live voice crackles; lower it if it echoes late",
+                        );
+                    // Reopen once dragging ends, not on every value
+                    restart_mic |=
+                        latency.drag_stopped() || (latency.changed() && !latency.dragged());
+                    ui.end_row();
+
+                    ui.label("Latency offset");
+                    changed |= latency_offset_slider(
+                        ui,
+                        &mut audio.mic_latency_offsets_ms,
+                        self.mic.as_ref(),
+                    );
+                    ui.end_row();
+
+                    ui.label("Low-latency monitor");
+                    restart_mic |= ui
+                        .checkbox(&mut audio.low_latency_monitor, "Separate output stream")
+                        .on_hover_text(
+                            "Play the live voice on its own small-buffered stream instead of \
+                             through the music's mix. Skips the limiter and feedback \
+                             ducking, and is not in the recorded mix.",
+                        )
+                        .changed();
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            ui.label("Calibration");
+            ui.horizontal(|ui| {
+                mic_changed |= ui
+                    .toggle_value(&mut self.mic_test, "🎤 Test microphone")
+                    .on_hover_text("Open the mic to watch its level without hearing it")
+                    .changed();
+                if self.mic.is_some() {
+                    let gate = self
+                        .config
+                        .audio
+                        .noise_gate_enabled
+                        .then_some(self.config.audio.noise_gate_threshold_db);
+                    self.mic_meter(ui, 0, 240.0, gate);
+                }
+            });
+            if self.mic.is_some() {
+                ui.weak(if self.config.audio.noise_gate_enabled {
+                    "Sing at your loudest: the bar should stay out of the red. \
+                     Set the gate (thin tick) just above the level while you are silent."
+                } else {
+                    "Sing at your loudest: the bar should stay out of the red."
+                });
+            }
+            changed |= ui
+                .checkbox(
+                    &mut self.config.display.show_mic_meter,
+                    "Show the meter on the karaoke screen",
+                )
+                .changed();
+            changed |= ui
+                .checkbox(
+                    &mut self.config.display.show_pitch_guide,
+                    "Show the pitch guide on the karaoke screen",
+                )
+                .on_hover_text(
+                    "The song's melody, tracked from the audio, with the pitch you sing on it",
+                )
+                .changed();
+
+            ui.add_space(8.0);
+            ui.label("Default voice effects");
+            changed |= effects_rack_editor(
+                ui,
+                "default_voice_effects",
+                &mut self.config.audio.voice_effects,
+            );
+
+            ui.add_space(16.0);
+            ui.heading("Duet");
+            egui::Grid::new("duet_settings")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    let duet = &mut self.config.audio.duet;
+                    ui.label("Second microphone");
+                    mic_changed |= ui
+                        .checkbox(&mut duet.enabled, "Enabled")
+                        .on_hover_text(
+                            "Open a second mic so two singers are heard, metered and scored \
+                             separately",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    if !duet.enabled {
+                        return;
+                    }
+                    ui.label("Input device");
+                    restart_mic |= input_device_combo(
+                        ui,
+                        "duet_input_device",
+                        &mut duet.input_device,
+                        self.duet_mic.as_ref(),
+                    );
+                    ui.end_row();
+
+                    ui.label("Input channel");
+                    restart_mic |= input_channel_combo(
+                        ui,
+                        "duet_input_channel",
+                        duet.input_device.as_deref(),
+                        &mut duet.input_channel,
+                    );
+                    ui.end_row();
+
+                    ui.label("Latency offset");
+                    changed |= latency_offset_slider(
+                        ui,
+                        &mut self.config.audio.mic_latency_offsets_ms,
+                        self.duet_mic.as_ref(),
+                    );
+                    ui.end_row();
+
+                    let duet = &mut self.config.audio.duet;
+                    ui.label("Input gain");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut duet.input_gain_db, -12.0..=24.0)
+                                .suffix(" dB")
+                                .step_by(0.5),
+                        )
+                        .changed();
+                    ui.end_row();
+                });
+            if self.config.audio.duet.enabled {
+                if self.duet_mic.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.label("Level");
+                        self.mic_meter(ui, 1, 240.0, None);
+                    });
+                }
+                ui.label("Second singer's voice effects");
+                changed |= effects_rack_editor(
+                    ui,
+                    "duet_voice_effects",
+                    &mut self.config.audio.duet.voice_effects,
+                );
+            }
+
+            ui.add_space(16.0);
+            ui.heading("Recording");
+            changed |= recording_settings(ui, &mut self.config.recording);
+
+            ui.add_space(16.0);
+            ui.heading("Web Remote");
+            changed |= self.remote_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Network");
+            egui::Grid::new("network_settings")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    let network = &mut self.config.network;
+
+                    ui.label("Offline mode");
+                    changed |= ui
+                        .checkbox(&mut network.offline, "Enabled")
+                        .on_hover_text("Never go online; online features only use cached answers")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Request timeout");
+                    changed |= ui
+                        .add_enabled(
+                            !network.offline,
+                            egui::Slider::new(&mut network.timeout_secs, 2..=60).suffix(" s"),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Retries");
+                    changed |= ui
+                        .add_enabled(
+                            !network.offline,
+                            egui::Slider::new(&mut network.retries, 0..=5),
+                        )
+                        .on_hover_text(
+                            "Attempts after a failed connection or server error, each waiting \
+                             twice as long",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Retry missing lyrics after");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut network.negative_cache_hours, 1.0..=720.0)
+                                .logarithmic(true)
+                                .suffix(" h"),
+                        )
+                        .on_hover_text("Songs without lyrics online are looked up again after this")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Lyrics cache");
+                    if ui
+                        .add_enabled(self.lyrics_fetch.is_none(), egui::Button::new("Clear"))
+                        .on_hover_text("Forget every lyrics lookup, found or not")
+                        .clicked()
+                    {
+                        self.status = Some(match LyricsCache::clear() {
+                            Ok(()) => "Lyrics cache cleared".to_string(),
+                            Err(e) => format!("{e:#}"),
+                        });
+                    }
+                    ui.end_row();
+                });
+
+            ui.add_space(16.0);
+            ui.heading("Lyrics Providers");
+            ui.weak("Fetch lyrics asks these in order until one has synced lyrics.");
+            changed |= lyrics_provider_settings(ui, &mut self.config.lyrics);
+
+            ui.add_space(16.0);
+            ui.heading("Downloads");
+            changed |= self.download_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Singers");
+            self.profile_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Announcer");
+            changed |= self.announcer_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Singer Screen");
+            changed |= self.singer_screen_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Data");
+            self.data_settings(ui);
+
+            ui.add_space(16.0);
+            ui.heading("Display");
+            let theme_colors = LyricColors::new(ui.visuals(), &DisplayConfig::default());
+            egui::Grid::new("display_settings")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    let display = &mut self.config.display;
+
+                    ui.label("Theme");
+                    egui::ComboBox::from_id_salt("theme_mode")
+                        .selected_text(display.theme_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ThemeMode::ALL {
+                                theme_changed |= ui
+                                    .selectable_value(&mut display.theme_mode, mode, mode.label())
+                                    .changed();
+                            }
+                        });
+                    ui.end_row();
+
+                    for (label, palette) in [
+                        ("Dark palette", &mut display.dark_palette),
+                        ("Light palette", &mut display.light_palette),
+                    ] {
+                        ui.label(label);
+                        egui::ComboBox::from_id_salt(label)
+                            .selected_text(palette.label())
+                            .show_ui(ui, |ui| {
+                                for choice in Palette::ALL {
+                                    theme_changed |= ui
+                                        .selectable_value(palette, choice, choice.label())
+                                        .changed();
+                                }
+                            });
+                        ui.end_row();
+                    }
+
+                    ui.label("Lyrics font size");
+                    changed |= ui
+                        .add(egui::Slider::new(&mut display.font_size, 16.0..=96.0))
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Smallest lyrics size");
+                    changed |= ui
+                        .add(egui::Slider::new(
+                            &mut display.min_font_size,
+                            12.0..=display.font_size,
+                        ))
+                        .on_hover_text("Long lines shrink down to this size, then wrap")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Upcoming lines");
+                    changed |= ui
+                        .add(egui::Slider::new(&mut display.upcoming_lines, 0..=4))
+                        .on_hover_text("Lyric lines shown after the one being sung, to read ahead")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Dim upcoming lines");
+                    changed |= ui
+                        .add_enabled(
+                            display.upcoming_lines > 0,
+                            egui::Slider::new(&mut display.upcoming_dim, 0.0..=0.9)
+                                .custom_formatter(|dim, _| format!("{:.0}%", dim * 100.0)),
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Break countdown");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut display.break_countdown_secs, 0.0..=30.0)
+                                .step_by(1.0)
+                                .custom_formatter(|secs, _| match secs {
+                                    0.0 => "Off".to_string(),
+                                    secs => format!("after {secs:.0} s"),
+                                }),
+                        )
+                        .on_hover_text(
+                            "Count down to the next line after instrumental breaks this long",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Line progress");
+                    changed |= ui
+                        .checkbox(&mut display.line_progress, "Bar under the sung line")
+                        .on_hover_text("How far through the line the song is, to pace long lines")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Unsynced lyrics");
+                    changed |= ui
+                        .checkbox(&mut display.teleprompter, "Teleprompter")
+                        .on_hover_text(
+                            "Scroll through lyrics without timestamps at the pace of the song. \
+                             + and − change the speed while they scroll.",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Video background");
+                    changed |= ui
+                        .checkbox(
+                            &mut display.show_video,
+                            "Play music videos behind the lyrics",
+                        )
+                        .on_hover_text(format!(
+                            "Songs with a video next to them ({}), decoded with ffmpeg",
+                            VIDEO_EXTENSIONS.join(", ")
+                        ))
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Cover art background");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut display.cover_background, 0.0..=1.0)
+                                .custom_formatter(|intensity, _| match intensity {
+                                    0.0 => "Off".to_string(),
+                                    intensity => format!("{:.0}%", intensity * 100.0),
+                                }),
+                        )
+                        .on_hover_text("The song's cover, blurred, behind the lyrics")
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Song summary");
+                    changed |= ui
+                        .checkbox(&mut display.song_summary, "Show a summary when a song ends")
+                        .on_hover_text(
+                            "Time sung and score, to sing again or go on with the queue. \
+                             The next song waits until it is started from the summary.",
+                        )
+                        .changed();
+                    ui.end_row();
+
+                    ui.label("Line transition");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("line_transition")
+                            .selected_text(display.line_transition.label())
+                            .show_ui(ui, |ui| {
+                                for transition in LineTransition::ALL {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut display.line_transition,
+                                            transition,
+                                            transition.label(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        ui.add_enabled_ui(
+                            display.line_transition != LineTransition::Instant,
+                            |ui| {
+                                changed |= ui
+                                    .add(
+                                        egui::Slider::new(&mut display.transition_secs, 0.1..=1.0)
+                                            .suffix(" s"),
+                                    )
+                                    .changed();
+                                egui::ComboBox::from_id_salt("transition_easing")
+                                    .selected_text(display.transition_easing.label())
+                                    .show_ui(ui, |ui| {
+                                        for easing in Easing::ALL {
+                                            changed |= ui
+                                                .selectable_value(
+                                                    &mut display.transition_easing,
+                                                    easing,
+                                                    easing.label(),
+                                                )
+                                                .changed();
+                                        }
+                                    });
+                            },
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label("Sung line colour");
+                    changed |= lyric_color(ui, &mut display.active_color, theme_colors.active);
+                    ui.end_row();
+
+                    ui.label("Other lines colour");
+                    changed |= lyric_color(ui, &mut display.inactive_color, theme_colors.inactive);
+                    ui.end_row();
+
+                    ui.label("Highlight colour");
+                    changed |=
+                        lyric_color(ui, &mut display.highlight_color, theme_colors.highlight);
+                    ui.end_row();
+
+                    ui.label("Outline or shadow");
+                    ui.horizontal(|ui| {
+                        egui::ComboBox::from_id_salt("text_effect")
+                            .selected_text(display.text_effect.label())
+                            .show_ui(ui, |ui| {
+                                for effect in TextEffect::ALL {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut display.text_effect,
+                                            effect,
+                                            effect.label(),
+                                        )
+                                        .changed();
+                                }
+                            });
+                        ui.add_enabled_ui(display.text_effect != TextEffect::None, |ui| {
+                            changed |= ui
+                                .color_edit_button_srgb(&mut display.effect_color)
+                                .changed();
+                        });
+                    });
+                    ui.end_row();
+
+                    ui.label("Lyrics font");
+                    ui.horizontal(|ui| {
+                        match display
+                            .lyric_font
+                            .as_deref()
+                            .and_then(|path| path.file_name())
+                        {
+                            Some(name) => ui.label(name.to_string_lossy()),
+                            None => ui.weak("Default"),
+                        };
+                        if ui.button("Choose…").clicked() {
+                            if let Some(file) = rfd::FileDialog::new()
+                                .add_filter("Fonts", &["ttf", "otf", "ttc"])
+                                .pick_file()
+                            {
+                                display.lyric_font = Some(file);
+                                font_changed = true;
+                            }
+                        }
+                        if display.lyric_font.is_some() && ui.button("Default").clicked() {
+                            display.lyric_font = None;
+                            font_changed = true;
+                        }
+                    });
+                    ui.end_row();
+                });
+
+            ui.add_space(8.0);
+            lyric_preview(ui, &self.config.display);
+        });
+
+        if restart_mic {
+            self.mic = None;
+            self.duet_mic = None;
+        }
+        if mic_changed || restart_mic {
+            self.update_mic();
+        }
+        if theme_changed {
+            theme::apply_theme(ui.ctx(), &self.config.display);
+        }
+        if font_changed {
+            fonts::install_fonts(ui.ctx(), self.config.display.lyric_font.as_deref());
+        }
+        if changed || mic_changed || restart_mic || theme_changed || font_changed {
+            self.params.apply_config(&self.config.audio);
+            self.apply_song_settings();
+            if let Some(player) = &self.player {
+                player.set_volume(self.config.audio.master_volume);
+            }
+            self.config_unsaved = true;
+        }
+    }
+}
+
+/// Colour picker for a lyric colour that follows the theme's `theme`
+/// colour until one is picked. Returns true when it changed.
+fn lyric_color(ui: &mut egui::Ui, chosen: &mut Option<[u8; 3]>, theme: egui::Color32) -> bool {
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        let mut color = chosen.unwrap_or([theme.r(), theme.g(), theme.b()]);
+        if ui.color_edit_button_srgb(&mut color).changed() {
+            *chosen = Some(color);
+            changed = true;
+        }
+        if chosen.is_none() {
+            ui.weak("Theme");
+        } else if ui
+            .small_button("Theme")
+            .on_hover_text("Go back to the theme's colour")
+            .clicked()
+        {
+            *chosen = None;
+            changed = true;
+        }
+    });
+    changed
+}
+
+/// "L 40%", "Centre" or "R 25%".
+/// Provider chain editor: enable, reorder, and the local lyrics folder.
+/// Returns whether anything changed.
+fn recording_settings(ui: &mut egui::Ui, recording: &mut RecordingConfig) -> bool {
+    let mut changed = false;
+    egui::Grid::new("recording_settings")
+        .num_columns(2)
+        .spacing([24.0, 8.0])
+        .show(ui, |ui| {
+            ui.label("Automatic");
+            changed |= ui
+                .checkbox(&mut recording.enabled, "Record every song")
+                .on_hover_text("Start recording whenever a song starts, and stop when it ends")
+                .changed();
+            ui.end_row();
+
+            ui.label("Backing track");
+            ui.vertical(|ui| {
+                changed |= ui
+                    .checkbox(&mut recording.separate_music, "Record the music separately")
+                    .on_hover_text(
+                        "Keep the backing track as played on its own track, so exports can be \
+                         remixed with the exact music heard",
+                    )
+                    .changed();
+                changed |= ui
+                    .checkbox(&mut recording.include_mix, "Also record the mix")
+                    .on_hover_text(
+                        "A file with the output as played; it has your voice only when live \
+                         passthrough is on",
+                    )
+                    .changed();
+            });
+            ui.end_row();
+
+            ui.label("Format");
+            egui::ComboBox::from_id_salt("recording_format")
+                .selected_text(recording.format.label())
+                .show_ui(ui, |ui| {
+                    for format in RecordingFormat::ALL {
+                        changed |= ui
+                            .selectable_value(&mut recording.format, format, format.label())
+                            .changed();
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Folder");
+            ui.horizontal(|ui| {
+                ui.label(recording.folder().display().to_string());
+                if ui.small_button("Change…").clicked() {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        recording.folder = Some(folder);
+                        changed = true;
+                    }
+                }
+            });
+            ui.end_row();
+        });
+    changed
+}
+
+fn lyrics_provider_settings(ui: &mut egui::Ui, lyrics: &mut LyricsConfig) -> bool {
+    let mut changed = false;
+    let mut swap = None;
+    let count = lyrics.providers.len();
+    for (index, (provider, enabled)) in lyrics.providers.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(index > 0, egui::Button::new("⬆").small())
+                .on_hover_text("Ask earlier")
+                .clicked()
+            {
+                swap = Some(index - 1);
+            }
+            if ui
+                .add_enabled(index + 1 < count, egui::Button::new("⬇").small())
+                .on_hover_text("Ask later")
+                .clicked()
+            {
+                swap = Some(index);
+            }
+            changed |= ui
+                .checkbox(enabled, format!("{}. {}", index + 1, provider.label()))
+                .on_hover_text(provider.description())
+                .changed();
+            if *provider == LyricsProvider::LocalFolder {
+                let folder = lyrics
+                    .local_folder
+                    .as_ref()
+                    .map_or("No folder chosen".to_string(), |folder| {
+                        folder.display().to_string()
+                    });
+                ui.weak(folder);
+                if ui.small_button("Choose…").clicked() {
+                    if let Some(folder) = rfd::FileDialog::new().pick_folder() {
+                        lyrics.local_folder = Some(folder);
+                        changed = true;
+                    }
+                }
+            }
+        });
+    }
+    if let Some(index) = swap {
+        lyrics.providers.swap(index, index + 1);
+        changed = true;
+    }
+    changed
+}
+
+/// Input device picker; `open` names the device behind "Default". Returns
+/// whether the choice changed.
+/// Latency offset of the device `mic` is open on; offsets are kept per
+/// device, so they follow a Bluetooth headset from slot to slot.
+fn latency_offset_slider(
+    ui: &mut egui::Ui,
+    offsets: &mut BTreeMap<String, u32>,
+    mic: Option<&MicInput>,
+) -> bool {
+    let Some(mic) = mic else {
+        ui.weak("Open the mic to set its offset");
+        return false;
+    };
+    let device = mic.device_name();
+    let mut offset = offsets.get(device).copied().unwrap_or(0);
+    let changed = ui
+        .add(egui::Slider::new(&mut offset, 0..=500).suffix(" ms"))
+        .on_hover_text(format!(
+            "How late {device} delivers your voice. Scoring and recording mixes take it \
+             off; raise it for Bluetooth or USB mics."
+        ))
+        .changed();
+    if changed {
+        if offset == 0 {
+            offsets.remove(device);
+        } else {
+            offsets.insert(device.to_string(), offset);
+        }
+    }
+    changed
+}
+
+fn input_device_combo(
+    ui: &mut egui::Ui,
+    id: &str,
+    device: &mut Option<String>,
+    open: Option<&MicInput>,
+) -> bool {
+    let selected = match (&*device, open) {
+        (Some(name), _) => name.clone(),
+        (None, Some(mic)) => format!("Default ({})", mic.device_name()),
+        (None, None) => "Default".to_string(),
+    };
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(selected)
+        .show_ui(ui, |ui| {
+            changed |= ui.selectable_value(device, None, "Default").changed();
+            // Listed only while the menu is open
+            for name in input::input_device_names() {
+                changed |= ui
+                    .selectable_value(device, Some(name.clone()), name)
+                    .changed();
+            }
+        });
+    changed
+}
+
+/// Picker for one input channel of `device`, or all of them mixed. Returns
+/// whether the choice changed.
+fn input_channel_combo(
+    ui: &mut egui::Ui,
+    id: &str,
+    device: Option<&str>,
+    channel: &mut Option<u16>,
+) -> bool {
+    let label = |channel: Option<u16>| match channel {
+        Some(channel) => format!("Channel {}", channel + 1),
+        None => "All (mixed)".to_string(),
+    };
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(label(*channel))
+        .show_ui(ui, |ui| {
+            changed |= ui.selectable_value(channel, None, label(None)).changed();
+            // Queried only while the menu is open
+            for index in 0..input::input_channel_count(device) {
+                changed |= ui
+                    .selectable_value(channel, Some(index), label(Some(index)))
+                    .changed();
+            }
+        })
+        .response
+        .on_hover_text("Use one input of a multi-channel interface, e.g. one mic per singer");
+    changed
+}
+
+fn pan_label(pan: f64) -> String {
+    let percent = (pan.abs() * 100.0).round();
+    if percent == 0.0 {
+        "Centre".to_owned()
+    } else if pan < 0.0 {
+        format!("L {percent}%")
+    } else {
+        format!("R {percent}%")
+    }
+}
@@ -0,0 +1,177 @@
+//! Singer profiles: the picker for who sings next, and Settings → Singers
+//! for creating them and looking through their scores.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::app::{display_title, KaraokeApp};
+use crate::audio::recorder;
+use crate::profiles::{self, MAX_KEY_OFFSET};
+
+/// Results listed in a profile's history.
+const HISTORY_SHOWN: usize = 10;
+
+impl KaraokeApp {
+    /// Combo box choosing the active profile, which songs queued from the
+    /// library and the next song started are attributed to.
+    pub(crate) fn profile_picker(&mut self, ui: &mut egui::Ui) {
+        if self.profiles.is_empty() {
+            return;
+        }
+        let active = self.profiles.active().map(|profile| profile.name.clone());
+        let mut chosen = None;
+        egui::ComboBox::from_id_salt("active_profile")
+            .selected_text(format!("👤 {}", active.as_deref().unwrap_or("No singer")))
+            .show_ui(ui, |ui| {
+                if ui.selectable_label(active.is_none(), "No singer").clicked() {
+                    chosen = Some(None);
+                }
+                for profile in self.profiles.iter() {
+                    let text = egui::RichText::new(&profile.name).color(profile.color32());
+                    let selected = active.as_deref() == Some(profile.name.as_str());
+                    if ui.selectable_label(selected, text).clicked() {
+                        chosen = Some(Some(profile.name.clone()));
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Who sings next: queued songs and scores go to this singer");
+        if let Some(name) = chosen {
+            self.profiles.set_active(name.as_deref());
+            self.save_profiles();
+        }
+    }
+
+    /// `name` in its profile's color, weak when it has no profile, e.g. a
+    /// guest of the web remote.
+    pub(crate) fn singer_label(&self, ui: &mut egui::Ui, name: Option<&str>) {
+        let Some(name) = name else {
+            return;
+        };
+        match self.profiles.get(name) {
+            Some(profile) => ui.colored_label(profile.color32(), format!("👤 {name}")),
+            None => ui.weak(format!("👤 {name}")),
+        };
+    }
+
+    /// The key the current singer prefers `song` in, when it differs from
+    /// the original, and whether the track is playing in it.
+    pub(crate) fn preferred_key_hint(&self, ui: &mut egui::Ui, song: &Path) {
+        let Some(profile) = self
+            .current_singer
+            .as_deref()
+            .and_then(|name| self.profiles.get(name))
+            .filter(|profile| profile.key_offset != 0)
+        else {
+            return;
+        };
+        let Some(key) = self.storage.entry(song).and_then(|entry| entry.key) else {
+            return;
+        };
+        let preferred = key.transposed(profile.key_offset);
+        let text = if self.key_shift() == profile.key_offset {
+            format!(
+                "In {}'s key: {preferred} ({:+} semitones from {key})",
+                profile.name, profile.key_offset
+            )
+        } else {
+            format!(
+                "{} prefers {preferred} ({:+} semitones from {key})",
+                profile.name, profile.key_offset
+            )
+        };
+        ui.colored_label(profile.color32(), text);
+    }
+
+    pub(crate) fn profile_settings(&mut self, ui: &mut egui::Ui) {
+        let mut changed = false;
+        let mut remove = None;
+        let profiles: Vec<String> = self
+            .profiles
+            .iter()
+            .map(|profile| profile.name.clone())
+            .collect();
+        if profiles.is_empty() {
+            ui.weak("No singers yet. Add one to keep their scores and preferred key.");
+        }
+        for profile in self.profiles.iter_mut() {
+            ui.horizontal(|ui| {
+                changed |= ui.color_edit_button_srgb(&mut profile.color).changed();
+                ui.strong(&profile.name);
+                ui.label("Preferred key");
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut profile.key_offset)
+                            .range(-MAX_KEY_OFFSET..=MAX_KEY_OFFSET)
+                            .suffix(" st"),
+                    )
+                    .on_hover_text(
+                        "Semitones from the original key; their songs start shifted by this much",
+                    )
+                    .changed();
+                if ui.small_button("Remove").clicked() {
+                    remove = Some(profile.name.clone());
+                }
+            });
+        }
+        for name in &profiles {
+            self.profile_history(ui, name);
+        }
+
+        ui.horizontal(|ui| {
+            let input = ui.add(
+                egui::TextEdit::singleline(&mut self.new_profile_name)
+                    .hint_text("Name")
+                    .desired_width(160.0),
+            );
+            let entered =
+                input.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+            if ui.button("Add singer").clicked() || entered {
+                match self.profiles.add(&self.new_profile_name) {
+                    Ok(()) => {
+                        self.new_profile_name.clear();
+                        changed = true;
+                    },
+                    Err(e) => self.status = Some(format!("{e:#}")),
+                }
+            }
+        });
+
+        if let Some(name) = remove {
+            self.profiles.remove(&name);
+            changed = true;
+        }
+        if changed {
+            self.save_profiles();
+        }
+    }
+
+    /// Scores sung under `name`, newest first.
+    fn profile_history(&self, ui: &mut egui::Ui, name: &str) {
+        let history = profiles::history(&self.storage, name);
+        let best = history.iter().map(|(_, result)| result.score).max();
+        let heading = match best {
+            Some(best) => format!("{name}: {} scores, best {best}", history.len()),
+            None => format!("{name}: nothing scored yet"),
+        };
+        egui::CollapsingHeader::new(heading)
+            .id_salt(("profile_history", name))
+            .enabled(!history.is_empty())
+            .show(ui, |ui| {
+                egui::Grid::new(("profile_history_grid", name))
+                    .num_columns(3)
+                    .spacing([16.0, 2.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (song, result) in history.iter().take(HISTORY_SHOWN) {
+                            let sung_at =
+                                SystemTime::UNIX_EPOCH + Duration::from_secs(result.sung_at);
+                            ui.weak(recorder::timestamp(sung_at));
+                            ui.label(display_title(&self.storage, song));
+                            ui.label(format!("🏆 {}", result.score));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,213 @@
+//! Singer screen: a second window with just the lyrics, the song's
+//! progress and who sings next, for a TV facing the singers while the main
+//! window keeps the operator controls.
+//!
+//! egui cannot pick a monitor by name, so the window is placed by position
+//! first and only then made fullscreen, which fills the display it landed
+//! on.
+
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use super::lyrics_layout::fit_line;
+use crate::app::{display_title, format_time, song_title, KaraokeApp};
+
+/// Size of the window before it goes fullscreen.
+const WINDOW_SIZE: egui::Vec2 = egui::vec2(960.0, 540.0);
+
+/// Which display the singer screen opens on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScreenPlacement {
+    /// The display to the right of the main one
+    RightOfMain,
+    /// The display to the left of the main one
+    LeftOfMain,
+    /// Wherever the configured position is
+    Custom,
+}
+
+impl ScreenPlacement {
+    pub const ALL: [Self; 3] = [Self::RightOfMain, Self::LeftOfMain, Self::Custom];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::RightOfMain => "Right of the main display",
+            Self::LeftOfMain => "Left of the main display",
+            Self::Custom => "Custom position",
+        }
+    }
+}
+
+impl KaraokeApp {
+    /// The singer screen window, while it is turned on. Closing the window
+    /// turns it off.
+    pub(crate) fn singer_screen(&mut self, ctx: &egui::Context) {
+        if !self.config.singer_screen.enabled {
+            self.singer_screen_placed = None;
+            return;
+        }
+
+        let screen = &self.config.singer_screen;
+        let main_width = ctx
+            .input(|i| i.viewport().monitor_size)
+            .map_or(1920.0, |size| size.x);
+        let position = match screen.placement {
+            ScreenPlacement::RightOfMain => egui::pos2(main_width, 0.0),
+            ScreenPlacement::LeftOfMain => egui::pos2(-WINDOW_SIZE.x, 0.0),
+            ScreenPlacement::Custom => screen.position.into(),
+        };
+        // Moved out of fullscreen to a new position first, so going
+        // fullscreen on the next frame fills the display it moved to
+        let placed = self.singer_screen_placed == Some(position);
+        self.singer_screen_placed = Some(position);
+        let builder = egui::ViewportBuilder::default()
+            .with_title("PWE Karaoke: Singers")
+            .with_inner_size(WINDOW_SIZE)
+            .with_position(position)
+            .with_fullscreen(screen.fullscreen && placed);
+
+        let mut closed = false;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("singer_screen"),
+            builder,
+            |ctx, _class| {
+                closed = ctx.input(|i| i.viewport().close_requested());
+                egui::CentralPanel::default().show(ctx, |ui| self.singer_screen_contents(ui));
+            },
+        );
+        if closed {
+            self.config.singer_screen.enabled = false;
+            self.save_config();
+        }
+    }
+
+    /// Lyrics of the song playing, with who sings next and the progress
+    /// of the song under them.
+    fn singer_screen_contents(&mut self, ui: &mut egui::Ui) {
+        let current = self
+            .player
+            .as_ref()
+            .and_then(|player| player.current_path())
+            .map(Path::to_path_buf);
+        if let Some(path) = &current {
+            self.karaoke_background(ui, path);
+        }
+        self.ticker(ui);
+
+        egui::TopBottomPanel::bottom("singer_screen_status")
+            .frame(egui::Frame::none().inner_margin(8.0))
+            .show_inside(ui, |ui| {
+                if let Some(next) = self.next_singer() {
+                    ui.vertical_centered(|ui| ui.label(egui::RichText::new(next).heading()));
+                }
+                let progress = self.player.as_ref().and_then(|player| {
+                    let duration = player.duration()?;
+                    let position = player.get_position().min(duration);
+                    current.is_some().then_some((position, duration))
+                });
+                if let Some((position, duration)) = progress {
+                    let fraction = position.as_secs_f32() / duration.as_secs_f32().max(0.001);
+                    ui.add(egui::ProgressBar::new(fraction).text(format!(
+                        "{} / {}",
+                        format_time(position),
+                        format_time(duration)
+                    )));
+                }
+            });
+
+        if current
+            .as_deref()
+            .is_some_and(|path| self.cdg_graphics(ui, path))
+        {
+            return;
+        }
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 4.0);
+            let Some(path) = current else {
+                ui.label(egui::RichText::new("🎤").size(self.config.display.font_size * 2.0));
+                return;
+            };
+            let direction = self
+                .storage
+                .entry(&path)
+                .map(|entry| entry.text_direction)
+                .unwrap_or_default();
+            let title = fit_line(
+                ui,
+                &song_title(&path),
+                direction,
+                &self.config.display,
+                ui.visuals().text_color(),
+                ui.available_width(),
+            );
+            ui.label(title);
+            ui.add_space(self.config.display.font_size);
+            self.synced_lyrics(ui, &path, direction);
+        });
+    }
+
+    /// "Next: Ana – Song" for the first queued song.
+    fn next_singer(&self) -> Option<String> {
+        let song = self.queue.front()?;
+        let title = display_title(&self.storage, song);
+        Some(match self.session_log.requester(song) {
+            Some(singer) => format!("Next: {singer} – {title}"),
+            None => format!("Next: {title}"),
+        })
+    }
+
+    /// Returns true when the config changed.
+    pub(crate) fn singer_screen_settings(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut changed = false;
+        egui::Grid::new("singer_screen_settings")
+            .num_columns(2)
+            .spacing([24.0, 8.0])
+            .show(ui, |ui| {
+                let screen = &mut self.config.singer_screen;
+
+                ui.label("Singer screen");
+                changed |= ui
+                    .checkbox(&mut screen.enabled, "Show")
+                    .on_hover_text(
+                        "A window with just the lyrics, for a display facing the singers",
+                    )
+                    .changed();
+                ui.end_row();
+
+                ui.label("Display");
+                egui::ComboBox::from_id_salt("singer_screen_placement")
+                    .selected_text(screen.placement.label())
+                    .show_ui(ui, |ui| {
+                        for placement in ScreenPlacement::ALL {
+                            changed |= ui
+                                .selectable_value(
+                                    &mut screen.placement,
+                                    placement,
+                                    placement.label(),
+                                )
+                                .changed();
+                        }
+                    });
+                ui.end_row();
+
+                if screen.placement == ScreenPlacement::Custom {
+                    ui.label("Position");
+                    ui.horizontal(|ui| {
+                        let [x, y] = &mut screen.position;
+                        changed |= ui.add(egui::DragValue::new(x).prefix("x ")).changed();
+                        changed |= ui.add(egui::DragValue::new(y).prefix("y ")).changed();
+                    });
+                    ui.end_row();
+                }
+
+                ui.label("Fullscreen");
+                changed |= ui
+                    .checkbox(&mut screen.fullscreen, "Fill the display")
+                    .changed();
+                ui.end_row();
+            });
+        ui.weak("Toggle it from 📺 in the top bar. Closing the window turns it off.");
+        changed
+    }
+}
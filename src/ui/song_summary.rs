@@ -0,0 +1,120 @@
+//! Summary shown in the karaoke view when a song has played to the end:
+//! what was sung, by whom, for how long and how well, with the choice to
+//! sing it again or go on with the queue.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::results_view::singer_results;
+use crate::app::{display_title, format_time, KaraokeApp, View};
+
+/// A song that has just ended. The queue waits while it is shown.
+#[derive(Debug, Clone)]
+pub struct SongSummary {
+    pub song: PathBuf,
+    pub singer: Option<String>,
+    /// From the start of the song to its end, pauses included
+    pub time_sung: Duration,
+}
+
+impl KaraokeApp {
+    pub(crate) fn song_summary_view(&mut self, ui: &mut egui::Ui) {
+        let Some(summary) = self.song_summary.clone() else {
+            return;
+        };
+        let song = summary.song;
+        let entry = self.storage.entry(&song);
+        let key = entry.and_then(|entry| entry.key);
+        let bpm = entry.and_then(|entry| entry.bpm);
+        let length = self.player.as_ref().and_then(|player| player.duration());
+        let title = display_title(&self.storage, &song);
+        let next = self
+            .queue
+            .front()
+            .map(|next| display_title(&self.storage, next));
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(ui.available_height() / 8.0);
+            ui.label(
+                egui::RichText::new(title)
+                    .size(self.config.display.font_size)
+                    .strong(),
+            );
+            if let Some(singer) = &summary.singer {
+                ui.label(format!("Sung by {singer}"));
+            }
+            ui.add_space(8.0);
+            egui::Grid::new("song_summary")
+                .num_columns(2)
+                .spacing([16.0, 4.0])
+                .show(ui, |ui| {
+                    ui.label("Time sung");
+                    ui.label(format_time(summary.time_sung));
+                    ui.end_row();
+                    if let Some(length) = length {
+                        ui.label("Length");
+                        ui.label(format_time(length));
+                        ui.end_row();
+                    }
+                    if let Some(key) = key {
+                        ui.label("Key");
+                        ui.label(key.to_string());
+                        ui.end_row();
+                    }
+                    if let Some(bpm) = bpm {
+                        ui.label("Tempo");
+                        ui.label(format!("{bpm:.0} BPM"));
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(results) = self
+                .run_results
+                .as_ref()
+                .filter(|results| results.song == song)
+            {
+                ui.add_space(8.0);
+                ui.horizontal_top(|ui| {
+                    for singer in &results.singers {
+                        ui.group(|ui| singer_results(ui, singer));
+                    }
+                });
+            }
+
+            ui.add_space(16.0);
+            ui.horizontal(|ui| {
+                if ui.button("🔁 Sing again").clicked() {
+                    self.play_song(&song);
+                }
+                let next_button =
+                    ui.add_enabled(next.is_some(), egui::Button::new("⏭ Next in queue"));
+                let next_button = match &next {
+                    Some(next) => next_button.on_hover_text(next),
+                    None => next_button.on_disabled_hover_text("The queue is empty"),
+                };
+                if next_button.clicked() {
+                    self.close_song_summary();
+                    self.start_next_queued();
+                }
+                if ui.button("📚 Library").clicked() {
+                    self.close_song_summary();
+                    self.view = View::Library;
+                }
+            });
+        });
+    }
+
+    /// Dismiss the summary and the results it showed.
+    pub(crate) fn close_song_summary(&mut self) {
+        let Some(summary) = self.song_summary.take() else {
+            return;
+        };
+        if self
+            .run_results
+            .as_ref()
+            .is_some_and(|results| results.song == summary.song)
+        {
+            self.run_results = None;
+        }
+    }
+}
@@ -0,0 +1,240 @@
+//! Colour themes.
+//!
+//! A palette is a full set of egui visuals. The app keeps one palette for
+//! dark mode and one for light mode; the theme mode picks between them, or
+//! lets egui follow the OS preference live.
+//!
+//! Besides the regular palettes there are accessibility variants: high
+//! contrast (pure black/white with heavy outlines) and colour-blind safe
+//! (blue/orange accents from the Okabe-Ito set, which stay distinct under
+//! deuteranopia and protanopia).
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::DisplayConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemeMode {
+    /// Follow the OS dark-mode preference
+    Auto,
+    Dark,
+    Light,
+}
+
+impl ThemeMode {
+    pub const ALL: [Self; 3] = [Self::Auto, Self::Dark, Self::Light];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Auto => "Auto (follow system)",
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+        }
+    }
+
+    pub fn icon(self) -> &'static str {
+        match self {
+            Self::Auto => "🖥",
+            Self::Dark => "🌙",
+            Self::Light => "☀",
+        }
+    }
+
+    /// Next mode for the top panel toggle.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Dark,
+            Self::Dark => Self::Light,
+            Self::Light => Self::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    /// Dark: charcoal with crimson accents
+    Tekkadan,
+    /// Light: white armour with blue and gold accents
+    Barbatos,
+    /// Dark: white on black, yellow accents
+    HighContrastDark,
+    /// Light: black on white, deep blue accents
+    HighContrastLight,
+    /// Dark: blue and orange accents, no red/green pairs
+    ColourSafeDark,
+    /// Light: blue and orange accents, no red/green pairs
+    ColourSafeLight,
+}
+
+impl Palette {
+    pub const ALL: [Self; 6] = [
+        Self::Tekkadan,
+        Self::Barbatos,
+        Self::HighContrastDark,
+        Self::HighContrastLight,
+        Self::ColourSafeDark,
+        Self::ColourSafeLight,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Tekkadan => "Tekkadan",
+            Self::Barbatos => "Barbatos",
+            Self::HighContrastDark => "High contrast (dark)",
+            Self::HighContrastLight => "High contrast (light)",
+            Self::ColourSafeDark => "Colour-blind safe (dark)",
+            Self::ColourSafeLight => "Colour-blind safe (light)",
+        }
+    }
+
+    /// Colours for a note sung on pitch and one missed. The colour-blind
+    /// safe palettes use blue against orange instead of green against red.
+    pub fn hit_miss_colors(self) -> (egui::Color32, egui::Color32) {
+        match self {
+            Self::Tekkadan => (
+                egui::Color32::from_rgb(0x50, 0xaa, 0x5a),
+                egui::Color32::from_rgb(0xe0, 0x6c, 0x70),
+            ),
+            Self::Barbatos => (
+                egui::Color32::from_rgb(0x2e, 0x8b, 0x57),
+                egui::Color32::from_rgb(0xc2, 0x3b, 0x22),
+            ),
+            Self::HighContrastDark => (
+                egui::Color32::from_rgb(0x00, 0xff, 0x66),
+                egui::Color32::from_rgb(0xff, 0x33, 0x33),
+            ),
+            Self::HighContrastLight => (
+                egui::Color32::from_rgb(0x00, 0x66, 0x00),
+                egui::Color32::from_rgb(0xb0, 0x00, 0x00),
+            ),
+            Self::ColourSafeDark => (
+                egui::Color32::from_rgb(0x56, 0xb4, 0xe9),
+                egui::Color32::from_rgb(0xe6, 0x9f, 0x00),
+            ),
+            Self::ColourSafeLight => (
+                egui::Color32::from_rgb(0x00, 0x72, 0xb2),
+                egui::Color32::from_rgb(0xd5, 0x5e, 0x00),
+            ),
+        }
+    }
+
+    pub fn visuals(self) -> egui::Visuals {
+        match self {
+            Self::Tekkadan => {
+                let accent = egui::Color32::from_rgb(0xb3, 0x28, 0x2d);
+                let mut visuals = egui::Visuals::dark();
+                visuals.panel_fill = egui::Color32::from_rgb(0x1c, 0x18, 0x1a);
+                visuals.window_fill = egui::Color32::from_rgb(0x24, 0x1f, 0x21);
+                visuals.extreme_bg_color = egui::Color32::from_rgb(0x12, 0x0f, 0x10);
+                visuals.faint_bg_color = egui::Color32::from_rgb(0x2a, 0x24, 0x26);
+                visuals.hyperlink_color = egui::Color32::from_rgb(0xe0, 0x6c, 0x70);
+                visuals.selection.bg_fill = accent;
+                visuals.selection.stroke.color = egui::Color32::from_rgb(0xf4, 0xe9, 0xe9);
+                visuals.widgets.hovered.bg_stroke.color = accent;
+                visuals
+            },
+            Self::Barbatos => {
+                let accent = egui::Color32::from_rgb(0x2a, 0x5c, 0xaa);
+                let mut visuals = egui::Visuals::light();
+                visuals.panel_fill = egui::Color32::from_rgb(0xf3, 0xf4, 0xf6);
+                visuals.window_fill = egui::Color32::from_rgb(0xfb, 0xfb, 0xfc);
+                visuals.extreme_bg_color = egui::Color32::from_rgb(0xe4, 0xe7, 0xec);
+                visuals.faint_bg_color = egui::Color32::from_rgb(0xe9, 0xec, 0xf1);
+                visuals.hyperlink_color = accent;
+                visuals.warn_fg_color = egui::Color32::from_rgb(0xc2, 0x8a, 0x00);
+                visuals.selection.bg_fill = egui::Color32::from_rgb(0xa9, 0xc2, 0xe8);
+                visuals.selection.stroke.color = egui::Color32::from_rgb(0x1b, 0x3f, 0x7a);
+                visuals.widgets.hovered.bg_stroke.color = accent;
+                visuals
+            },
+            Self::HighContrastDark => high_contrast(
+                egui::Visuals::dark(),
+                egui::Color32::BLACK,
+                egui::Color32::WHITE,
+                egui::Color32::from_rgb(0xff, 0xd4, 0x00),
+            ),
+            Self::HighContrastLight => high_contrast(
+                egui::Visuals::light(),
+                egui::Color32::WHITE,
+                egui::Color32::BLACK,
+                egui::Color32::from_rgb(0x00, 0x33, 0x99),
+            ),
+            Self::ColourSafeDark => {
+                let blue = egui::Color32::from_rgb(0x56, 0xb4, 0xe9);
+                let mut visuals = egui::Visuals::dark();
+                visuals.hyperlink_color = blue;
+                visuals.warn_fg_color = egui::Color32::from_rgb(0xe6, 0x9f, 0x00);
+                visuals.error_fg_color = egui::Color32::from_rgb(0xd5, 0x5e, 0x00);
+                visuals.selection.bg_fill = egui::Color32::from_rgb(0x00, 0x72, 0xb2);
+                visuals.selection.stroke.color = egui::Color32::WHITE;
+                visuals.widgets.hovered.bg_stroke.color = blue;
+                visuals
+            },
+            Self::ColourSafeLight => {
+                let blue = egui::Color32::from_rgb(0x00, 0x72, 0xb2);
+                let mut visuals = egui::Visuals::light();
+                visuals.hyperlink_color = blue;
+                visuals.warn_fg_color = egui::Color32::from_rgb(0xb3, 0x6b, 0x00);
+                visuals.error_fg_color = egui::Color32::from_rgb(0xd5, 0x5e, 0x00);
+                visuals.selection.bg_fill = egui::Color32::from_rgb(0x9f, 0xd0, 0xf0);
+                visuals.selection.stroke.color = egui::Color32::from_rgb(0x00, 0x3d, 0x63);
+                visuals.widgets.hovered.bg_stroke.color = blue;
+                visuals
+            },
+        }
+    }
+}
+
+/// Flat `background`, `foreground` text and outlines on every widget, and
+/// `accent` for selection and focus.
+fn high_contrast(
+    mut visuals: egui::Visuals,
+    background: egui::Color32,
+    foreground: egui::Color32,
+    accent: egui::Color32,
+) -> egui::Visuals {
+    visuals.panel_fill = background;
+    visuals.window_fill = background;
+    visuals.extreme_bg_color = background;
+    visuals.faint_bg_color = background;
+    visuals.window_stroke = egui::Stroke::new(2.0, foreground);
+    visuals.hyperlink_color = accent;
+    visuals.selection.bg_fill = accent;
+    visuals.selection.stroke = egui::Stroke::new(2.0, background);
+    for widget in [
+        &mut visuals.widgets.noninteractive,
+        &mut visuals.widgets.inactive,
+        &mut visuals.widgets.hovered,
+        &mut visuals.widgets.active,
+        &mut visuals.widgets.open,
+    ] {
+        widget.bg_fill = background;
+        widget.weak_bg_fill = background;
+        widget.bg_stroke = egui::Stroke::new(1.5, foreground);
+        widget.fg_stroke.color = foreground;
+    }
+    visuals.widgets.noninteractive.bg_stroke.color = foreground.gamma_multiply(0.6);
+    visuals.widgets.hovered.bg_stroke = egui::Stroke::new(2.0, accent);
+    visuals.widgets.active.bg_stroke = egui::Stroke::new(2.5, accent);
+    visuals
+}
+
+/// The palette `ui` is being drawn with.
+pub fn active_palette(ui: &egui::Ui, display: &DisplayConfig) -> Palette {
+    if ui.visuals().dark_mode {
+        display.dark_palette
+    } else {
+        display.light_palette
+    }
+}
+
+/// Apply the configured palettes and mode.
+pub fn apply_theme(ctx: &egui::Context, display: &DisplayConfig) {
+    ctx.set_visuals_of(egui::Theme::Dark, display.dark_palette.visuals());
+    ctx.set_visuals_of(egui::Theme::Light, display.light_palette.visuals());
+    ctx.set_theme(match display.theme_mode {
+        ThemeMode::Auto => egui::ThemePreference::System,
+        ThemeMode::Dark => egui::ThemePreference::Dark,
+        ThemeMode::Light => egui::ThemePreference::Light,
+    });
+}
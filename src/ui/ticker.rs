@@ -0,0 +1,203 @@
+//! Message ticker: host messages ("Happy birthday Ana!", "Last call
+//! 1:30am") scrolling along the bottom of the karaoke view, and the
+//! bottom panel popover that edits them while the show goes on.
+//!
+//! A message can be scheduled to start in some minutes and to show for
+//! some minutes; the schedule is kept as absolute times, so it survives a
+//! restart.
+
+use std::time::SystemTime;
+
+use crate::app::KaraokeApp;
+use crate::config::TickerMessage;
+
+/// Ticker text size against the lyric font size.
+const TICKER_SCALE: f32 = 0.4;
+/// Between two messages, and between the end of the text and its start
+/// coming round again.
+const SEPARATOR: &str = "     •     ";
+
+impl KaraokeApp {
+    /// The ticker strip along the bottom of `ui`, while any message is
+    /// showing. Call before laying out the rest of the view.
+    pub(crate) fn ticker(&self, ui: &mut egui::Ui) {
+        let live = self.config.ticker.live(SystemTime::now());
+        if live.is_empty() {
+            return;
+        }
+        let text = live.join(SEPARATOR);
+        let size = (self.config.display.font_size * TICKER_SCALE).max(12.0);
+        let speed = self.config.ticker.speed;
+        egui::TopBottomPanel::bottom("ticker")
+            .frame(egui::Frame::none().fill(ui.visuals().extreme_bg_color))
+            .show_inside(ui, |ui| scrolling_text(ui, &text, size, speed));
+    }
+
+    /// "Ticker" button with a popover editing the ticker messages.
+    pub(crate) fn ticker_popover(&mut self, ui: &mut egui::Ui) {
+        let popup_id = ui.make_persistent_id("ticker_popover");
+        let now = SystemTime::now();
+        let live = self
+            .config
+            .ticker
+            .messages
+            .iter()
+            .filter(|message| message.is_live(now))
+            .count();
+        let button = ui
+            .button(format!("📢 Ticker ({live})"))
+            .on_hover_text("Messages scrolling under the lyrics");
+        if button.clicked() {
+            ui.memory_mut(|memory| memory.toggle_popup(popup_id));
+        }
+
+        let mut changed = false;
+        let mut remove = None;
+        egui::popup_above_or_below_widget(
+            ui,
+            popup_id,
+            &button,
+            egui::AboveOrBelow::Above,
+            egui::PopupCloseBehavior::CloseOnClickOutside,
+            |ui| {
+                ui.set_min_width(360.0);
+                let ticker = &mut self.config.ticker;
+                if ticker.messages.is_empty() {
+                    ui.weak("No messages yet");
+                }
+                for (index, message) in ticker.messages.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        changed |= ui
+                            .checkbox(&mut message.enabled, "")
+                            .on_hover_text("Show this message")
+                            .changed();
+                        changed |= ui
+                            .add(
+                                egui::TextEdit::singleline(&mut message.text)
+                                    .hint_text("Happy birthday Ana!")
+                                    .desired_width(200.0),
+                            )
+                            .changed();
+                        changed |= schedule_menu(ui, message);
+                        if ui.small_button("✖").on_hover_text("Remove").clicked() {
+                            remove = Some(index);
+                        }
+                    });
+                }
+                ui.horizontal(|ui| {
+                    if ui.button("➕ Add message").clicked() {
+                        ticker.messages.push(TickerMessage {
+                            enabled: true,
+                            ..TickerMessage::default()
+                        });
+                        changed = true;
+                    }
+                    ui.label("Speed");
+                    changed |= ui
+                        .add(
+                            egui::Slider::new(&mut ticker.speed, 20.0..=300.0)
+                                .suffix(" pt/s")
+                                .step_by(10.0),
+                        )
+                        .changed();
+                });
+            },
+        );
+
+        if let Some(index) = remove {
+            self.config.ticker.messages.remove(index);
+            changed = true;
+        }
+        if changed {
+            self.save_config();
+        }
+    }
+}
+
+/// "⏱" menu showing when `message` is scheduled and changing it. Returns
+/// true when the schedule changed.
+fn schedule_menu(ui: &mut egui::Ui, message: &mut TickerMessage) -> bool {
+    let now = unix_now();
+    let status = match (message.from, message.until) {
+        (_, Some(until)) if until <= now => "Ended".to_string(),
+        (Some(from), _) if from > now => format!("In {} min", (from - now).div_ceil(60)),
+        (_, Some(until)) => format!("{} min left", (until - now).div_ceil(60)),
+        _ => "Always".to_string(),
+    };
+
+    let mut changed = false;
+    ui.menu_button(format!("⏱ {status}"), |ui| {
+        let start = message.from.unwrap_or(now).max(now);
+        let mut start_in = (start - now).div_ceil(60);
+        let mut show_for = message
+            .until
+            .map_or(0, |until| until.saturating_sub(start).div_ceil(60));
+        egui::Grid::new("ticker_schedule")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Start in");
+                let start_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut start_in)
+                            .range(0..=24 * 60)
+                            .suffix(" min"),
+                    )
+                    .on_hover_text("0 shows it right away")
+                    .changed();
+                ui.end_row();
+
+                ui.label("Show for");
+                let length_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut show_for)
+                            .range(0..=24 * 60)
+                            .suffix(" min"),
+                    )
+                    .on_hover_text("0 shows it until it is turned off")
+                    .changed();
+                ui.end_row();
+
+                if start_changed || length_changed {
+                    let start = now + start_in * 60;
+                    message.from = (start_in > 0).then_some(start);
+                    message.until = (show_for > 0).then_some(start + show_for * 60);
+                    changed = true;
+                }
+            });
+        if ui.button("Clear schedule").clicked() {
+            message.from = None;
+            message.until = None;
+            changed = true;
+            ui.close_menu();
+        }
+    });
+    changed
+}
+
+/// `text` moving right to left across the full width, starting over once
+/// it has gone past the left edge.
+fn scrolling_text(ui: &mut egui::Ui, text: &str, size: f32, speed: f32) {
+    let color = ui.visuals().strong_text_color();
+    let galley = ui.painter().layout_no_wrap(
+        format!("{text}{SEPARATOR}"),
+        egui::FontId::proportional(size),
+        color,
+    );
+    let (rect, _) = ui.allocate_exact_size(
+        egui::vec2(ui.available_width(), galley.size().y),
+        egui::Sense::hover(),
+    );
+    let cycle = rect.width() + galley.size().x;
+    let travelled = (ui.input(|input| input.time) as f32 * speed.max(1.0)) % cycle;
+    let left = rect.right() - travelled;
+    ui.painter()
+        .with_clip_rect(rect)
+        .galley(egui::pos2(left, rect.top()), galley, color);
+    ui.ctx().request_repaint();
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
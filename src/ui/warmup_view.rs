@@ -0,0 +1,255 @@
+//! Warm-up exercises with live pitch feedback.
+
+use std::time::Duration;
+
+use super::theme::{self, Palette};
+use crate::app::KaraokeApp;
+use crate::audio::pitch;
+use crate::audio::scoring::HIT_TOLERANCE_CENTS;
+use crate::audio::warmup::{Exercise, WarmupSession};
+
+/// Notes shown above and below the exercise range.
+const MARGIN_NOTES: f32 = 3.0;
+
+/// Exercise choices, kept while switching views.
+#[derive(Debug, Clone)]
+pub(crate) struct WarmupOptions {
+    pub exercise: Exercise,
+    /// Starting note (MIDI)
+    pub root: u8,
+    pub repeats: u8,
+    pub note_length_secs: f32,
+}
+
+impl Default for WarmupOptions {
+    fn default() -> Self {
+        Self {
+            exercise: Exercise::FiveNoteScale,
+            root: 55,
+            repeats: 3,
+            note_length_secs: 0.7,
+        }
+    }
+}
+
+impl KaraokeApp {
+    pub(crate) fn warmup_view(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Warm-up");
+        ui.separator();
+
+        let running = self.warmup_running();
+        ui.add_enabled_ui(!running, |ui| {
+            let options = &mut self.warmup_options;
+            egui::Grid::new("warmup_options")
+                .num_columns(2)
+                .spacing([24.0, 8.0])
+                .show(ui, |ui| {
+                    ui.label("Exercise");
+                    egui::ComboBox::from_id_salt("warmup_exercise")
+                        .selected_text(options.exercise.label())
+                        .show_ui(ui, |ui| {
+                            for exercise in Exercise::ALL {
+                                ui.selectable_value(
+                                    &mut options.exercise,
+                                    exercise,
+                                    exercise.label(),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Starting note");
+                    ui.add(
+                        egui::Slider::new(&mut options.root, 36..=72)
+                            .custom_formatter(|note, _| pitch::note_name(note as u8)),
+                    );
+                    ui.end_row();
+
+                    ui.label("Repetitions");
+                    ui.add(egui::Slider::new(&mut options.repeats, 1..=8))
+                        .on_hover_text("Each repetition starts a semitone higher");
+                    ui.end_row();
+
+                    ui.label("Note length");
+                    ui.add(
+                        egui::Slider::new(&mut options.note_length_secs, 0.3..=1.5).suffix(" s"),
+                    );
+                    ui.end_row();
+                });
+        });
+
+        ui.horizontal(|ui| {
+            if running {
+                if ui.button("⏹ Stop").clicked() {
+                    self.stop_warmup();
+                }
+            } else if ui.button("▶ Start").clicked() {
+                self.start_warmup();
+            }
+            if let Some(session) = &self.warmup {
+                ui.separator();
+                let accuracies: Vec<Option<f32>> = (0..session.singers)
+                    .map(|singer| session.total_score(singer).accuracy())
+                    .collect();
+                match accuracies.as_slice() {
+                    [Some(accuracy)] => ui.label(format!(
+                        "{}: {:.0}% on pitch",
+                        session.exercise.label(),
+                        accuracy * 100.0
+                    )),
+                    [first, second] => ui.label(format!(
+                        "{}: singer 1 {} · singer 2 {} on pitch",
+                        session.exercise.label(),
+                        percent(*first),
+                        percent(*second)
+                    )),
+                    _ => ui.label(session.exercise.label()),
+                };
+            }
+        });
+        ui.separator();
+
+        match &self.warmup {
+            Some(session) => {
+                let voiced =
+                    (0..session.singers).any(|singer| self.params.mic(singer).pitch().is_some());
+                let palette = theme::active_palette(ui, &self.config.display);
+                pitch_roll(ui, session, voiced, palette);
+            },
+            None => {
+                ui.weak("Sing along with the guide tones; your pitch is drawn over the targets.");
+            },
+        }
+    }
+
+    fn start_warmup(&mut self) {
+        let Some(player) = &self.player else {
+            return;
+        };
+        player.pause();
+        let options = &self.warmup_options;
+        self.warmup = Some(WarmupSession::start(
+            player,
+            options.exercise,
+            options.root,
+            options.repeats,
+            Duration::from_secs_f32(options.note_length_secs),
+            if self.config.audio.duet.enabled { 2 } else { 1 },
+        ));
+        self.params.set_pitch_tracking(true);
+        self.update_mic();
+    }
+
+    fn stop_warmup(&mut self) {
+        self.warmup = None;
+        self.params.set_pitch_tracking(false);
+        self.update_mic();
+    }
+}
+
+fn percent(accuracy: Option<f32>) -> String {
+    accuracy.map_or("–".to_string(), |accuracy| {
+        format!("{:.0}%", accuracy * 100.0)
+    })
+}
+
+/// Targets as bars on a time/pitch grid, with the sung pitch trail on top.
+fn pitch_roll(ui: &mut egui::Ui, session: &WarmupSession, voiced: bool, palette: Palette) {
+    let size = egui::vec2(ui.available_width(), ui.available_height().max(200.0));
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 4.0, visuals.extreme_bg_color);
+
+    let Some(last) = session.notes.last() else {
+        return;
+    };
+    let total = (last.start + session.note_length).as_secs_f32();
+    let low = session
+        .notes
+        .iter()
+        .map(|note| note.note)
+        .min()
+        .unwrap_or(60);
+    let high = session
+        .notes
+        .iter()
+        .map(|note| note.note)
+        .max()
+        .unwrap_or(72);
+    let (bottom, top) = (
+        f32::from(low) - MARGIN_NOTES,
+        f32::from(high) + MARGIN_NOTES,
+    );
+
+    let x_of = |secs: f32| rect.left() + secs / total * rect.width();
+    let y_of = |note: f32| rect.bottom() - (note - bottom) / (top - bottom) * rect.height();
+    let half_band = HIT_TOLERANCE_CENTS / 100.0;
+
+    let (hit, miss) = palette.hit_miss_colors();
+    let current = session.current();
+    for (index, note) in session.notes.iter().enumerate() {
+        let start = note.start.as_secs_f32();
+        let target = f32::from(note.note);
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(x_of(start), y_of(target + half_band)),
+            egui::pos2(
+                x_of(start + session.note_length.as_secs_f32()),
+                y_of(target - half_band),
+            ),
+        );
+        // Hits and misses are marked as well as coloured, and the note due
+        // now is outlined and labelled larger, so nothing relies on colour
+        let (color, mark) = match session.note_score(note).accuracy() {
+            Some(accuracy) if accuracy >= 0.5 => (hit, " ✔"),
+            Some(_) => (miss, " ✖"),
+            None => (visuals.widgets.inactive.bg_fill, ""),
+        };
+        painter.rect_filled(bar, 2.0, color);
+        let active = current == Some(index);
+        let (label_size, label_color) = if active {
+            painter.rect_stroke(
+                bar.expand(2.0),
+                3.0,
+                egui::Stroke::new(2.5, visuals.strong_text_color()),
+            );
+            (14.0, visuals.strong_text_color())
+        } else {
+            (11.0, visuals.weak_text_color())
+        };
+        painter.text(
+            bar.left_top() + egui::vec2(2.0, -2.0),
+            egui::Align2::LEFT_BOTTOM,
+            format!("{}{mark}", pitch::note_name(note.note)),
+            egui::FontId::proportional(label_size),
+            label_color,
+        );
+    }
+
+    // The second singer is drawn with hollow dots as well as another colour
+    for (singer, trail) in session.trails.iter().enumerate() {
+        for &(time, note) in trail {
+            let center = egui::pos2(x_of(time.as_secs_f32()), y_of(note));
+            if singer == 0 {
+                painter.circle_filled(center, 2.0, visuals.strong_text_color());
+            } else {
+                painter.circle_stroke(center, 2.5, (1.0, visuals.hyperlink_color));
+            }
+        }
+    }
+
+    let now = x_of(session.elapsed().as_secs_f32().min(total));
+    painter.line_segment(
+        [egui::pos2(now, rect.top()), egui::pos2(now, rect.bottom())],
+        egui::Stroke::new(1.0, visuals.selection.bg_fill),
+    );
+    if !voiced && !session.is_finished() {
+        painter.text(
+            rect.right_top() + egui::vec2(-8.0, 8.0),
+            egui::Align2::RIGHT_TOP,
+            "No voice detected",
+            egui::FontId::proportional(13.0),
+            visuals.weak_text_color(),
+        );
+    }
+}
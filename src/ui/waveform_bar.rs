@@ -0,0 +1,99 @@
+//! Seek bar drawn over the track's waveform, tinted by song section.
+
+use std::time::Duration;
+
+use crate::audio::waveform::Waveform;
+use crate::library::sections::{SectionKind, SongSection};
+
+const HEIGHT: f32 = 28.0;
+/// Height of the solid section strip along the bottom edge.
+const SECTION_STRIP: f32 = 3.0;
+
+/// Colour of a section kind, from the Okabe-Ito set so neighbouring
+/// sections stay distinguishable with colour-blindness.
+pub(crate) fn section_color(kind: SectionKind) -> egui::Color32 {
+    match kind {
+        SectionKind::Intro | SectionKind::Outro => egui::Color32::from_rgb(0x99, 0x99, 0x99),
+        SectionKind::Verse => egui::Color32::from_rgb(0x00, 0x72, 0xb2),
+        SectionKind::PreChorus => egui::Color32::from_rgb(0x56, 0xb4, 0xe9),
+        SectionKind::Chorus => egui::Color32::from_rgb(0xe6, 0x9f, 0x00),
+        SectionKind::Bridge => egui::Color32::from_rgb(0xcc, 0x79, 0xa7),
+        SectionKind::Solo => egui::Color32::from_rgb(0x00, 0x9e, 0x73),
+    }
+}
+
+/// Draw the waveform with the played part highlighted and the song's
+/// sections as coloured segments. Returns the position the user clicked or
+/// dragged to.
+pub(crate) fn waveform_seek_bar(
+    ui: &mut egui::Ui,
+    waveform: &Waveform,
+    sections: &[SongSection],
+    position: Duration,
+    duration: Duration,
+    width: f32,
+) -> Option<Duration> {
+    let (rect, response) =
+        ui.allocate_exact_size(egui::vec2(width, HEIGHT), egui::Sense::click_and_drag());
+    let painter = ui.painter_at(rect);
+    let visuals = ui.visuals();
+    painter.rect_filled(rect, 2.0, visuals.extreme_bg_color);
+
+    let total = duration.as_secs_f32().max(f32::EPSILON);
+    let x_of = |secs: f32| rect.left() + (secs / total).clamp(0.0, 1.0) * rect.width();
+    for (index, section) in sections.iter().enumerate() {
+        let end = sections.get(index + 1).map_or(total, |next| next.start);
+        let color = section_color(section.kind);
+        let band = egui::Rect::from_x_y_ranges(x_of(section.start)..=x_of(end), rect.y_range());
+        painter.rect_filled(band, 0.0, color.gamma_multiply(0.25));
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(
+                band.x_range(),
+                rect.bottom() - SECTION_STRIP..=rect.bottom(),
+            ),
+            0.0,
+            color,
+        );
+    }
+
+    let played_x = rect.left() + (position.as_secs_f32() / total).min(1.0) * rect.width();
+    let played = visuals.selection.bg_fill;
+    let unplayed = visuals.widgets.inactive.fg_stroke.color;
+
+    // One vertical line per pixel column, spanning the peaks it covers
+    let bucket_secs = Waveform::bucket_duration().as_secs_f32();
+    let columns = rect.width().max(1.0) as usize;
+    for column in 0..columns {
+        let from = column as f32 / columns as f32 * total;
+        let to = (column + 1) as f32 / columns as f32 * total;
+        let first = (from / bucket_secs) as usize;
+        let last = ((to / bucket_secs) as usize).max(first + 1);
+        let Some(peaks) = waveform.peaks.get(first..last.min(waveform.peaks.len())) else {
+            continue;
+        };
+        let (low, high) = peaks
+            .iter()
+            .fold((0.0_f32, 0.0_f32), |(low, high), &(min, max)| {
+                (low.min(min), high.max(max))
+            });
+
+        let x = rect.left() + column as f32 + 0.5;
+        let centre = rect.center().y;
+        let half = rect.height() / 2.0;
+        let color = if x <= played_x { played } else { unplayed };
+        painter.line_segment(
+            [
+                egui::pos2(x, centre - high.clamp(0.0, 1.0) * half),
+                egui::pos2(x, centre - low.clamp(-1.0, 0.0) * half + 1.0),
+            ],
+            egui::Stroke::new(1.0, color),
+        );
+    }
+
+    if response.clicked() || response.dragged() {
+        let pointer = response.interact_pointer_pos()?;
+        let fraction = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+        return Some(Duration::from_secs_f32(fraction * total));
+    }
+    None
+}
@@ -0,0 +1,225 @@
+//! Music-video playback for songs with a video next to their audio.
+//!
+//! The video is decoded by an `ffmpeg` child process into scaled RGBA
+//! frames at a fixed rate, on a background thread. The audio keeps playing
+//! through the [`AudioPlayer`](crate::audio::AudioPlayer); the UI shows the
+//! frame due at the song position, so the picture follows the music
+//! through pauses, speed changes and seeks.
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::thread;
+use std::time::Duration;
+
+/// Containers looked for next to a song, in order.
+pub const VIDEO_EXTENSIONS: [&str; 3] = ["mp4", "mkv", "webm"];
+/// Decoded frame rate, whatever the source's.
+const FPS: u32 = 25;
+/// Frames are scaled down to at most this width.
+const MAX_WIDTH: u32 = 960;
+/// Frames decoded ahead of the one on screen.
+const QUEUE_FRAMES: usize = 4;
+/// A position this far from the decoded frames means a seek.
+const RESYNC_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// The video kept for `song`, if one exists.
+pub fn video_path(song: &Path) -> Option<PathBuf> {
+    VIDEO_EXTENSIONS
+        .iter()
+        .map(|extension| song.with_extension(extension))
+        .find(|path| path.exists())
+}
+
+struct Frame {
+    time: Duration,
+    image: egui::ColorImage,
+}
+
+enum Message {
+    Frame(Frame),
+    Error(String),
+}
+
+/// A video following the song position.
+pub struct VideoPlayback {
+    path: PathBuf,
+    receiver: Receiver<Message>,
+    /// Frame decoded ahead of the position
+    next: Option<Frame>,
+    /// Time of the frame on screen
+    shown: Option<Duration>,
+    /// Where the running decoder started
+    start: Duration,
+    texture: Option<egui::TextureHandle>,
+    error: Option<String>,
+}
+
+impl VideoPlayback {
+    /// Start decoding `path` from `position`.
+    pub fn open(path: &Path, position: Duration) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            receiver: spawn_decoder(path.to_path_buf(), position),
+            next: None,
+            shown: None,
+            start: position,
+            texture: None,
+            error: None,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Why the video cannot be shown, if it failed.
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Advance to the frame due at `position` and return the texture to
+    /// draw, once the first frame has arrived.
+    pub fn update(
+        &mut self,
+        ctx: &egui::Context,
+        position: Duration,
+    ) -> Option<&egui::TextureHandle> {
+        if self.error.is_some() {
+            return None;
+        }
+        let seeked = match self.shown {
+            Some(shown) => {
+                position + RESYNC_THRESHOLD < shown || position > shown + RESYNC_THRESHOLD
+            },
+            // Still waiting for the first frame, which may take a moment
+            None => position + RESYNC_THRESHOLD < self.start,
+        };
+        if seeked {
+            // Restart the decoder at the new position
+            self.receiver = spawn_decoder(self.path.clone(), position);
+            self.next = None;
+            self.shown = None;
+            self.start = position;
+        }
+
+        let mut due = None;
+        loop {
+            let frame = match self.next.take() {
+                Some(frame) => frame,
+                None => match self.receiver.try_recv() {
+                    Ok(Message::Frame(frame)) => frame,
+                    Ok(Message::Error(e)) => {
+                        tracing::warn!("Video {}: {e}", self.path.display());
+                        self.error = Some(e);
+                        return None;
+                    },
+                    Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+                },
+            };
+            if frame.time > position {
+                self.next = Some(frame);
+                break;
+            }
+            due = Some(frame);
+        }
+
+        if let Some(frame) = due {
+            self.shown = Some(frame.time);
+            match &mut self.texture {
+                Some(texture) => texture.set(frame.image, egui::TextureOptions::LINEAR),
+                None => {
+                    self.texture =
+                        Some(ctx.load_texture("video", frame.image, egui::TextureOptions::LINEAR));
+                },
+            }
+        }
+        self.texture.as_ref()
+    }
+}
+
+fn spawn_decoder(path: PathBuf, start: Duration) -> Receiver<Message> {
+    let (sender, receiver) = mpsc::sync_channel(QUEUE_FRAMES);
+    thread::spawn(move || {
+        if let Err(e) = decode(&path, start, &sender) {
+            // The receiver may be gone already; nobody is left to tell then
+            if sender.send(Message::Error(e)).is_err() {
+                tracing::debug!("Video decoder for {} stopped", path.display());
+            }
+        }
+    });
+    receiver
+}
+
+/// Decode frames from `start` until the end or until the receiver is dropped.
+fn decode(path: &Path, start: Duration, sender: &SyncSender<Message>) -> Result<(), String> {
+    let (width, height) = probe_size(path)?;
+    let mut child = Command::new("ffmpeg")
+        .args([
+            "-v",
+            "error",
+            "-ss",
+            &format!("{:.3}", start.as_secs_f64()),
+            "-i",
+        ])
+        .arg(path)
+        .args([
+            "-an",
+            "-sn",
+            "-vf",
+            &format!("scale={width}:{height},fps={FPS}"),
+        ])
+        .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Cannot run ffmpeg: {e}"))?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| "ffmpeg has no output".to_string())?;
+
+    let size = [width as usize, height as usize];
+    let mut buffer = vec![0; size[0] * size[1] * 4];
+    let mut index = 0_u32;
+    // A short read is the end of the video
+    while stdout.read_exact(&mut buffer).is_ok() {
+        let frame = Frame {
+            time: start + Duration::from_secs(1) * index / FPS,
+            image: egui::ColorImage::from_rgba_unmultiplied(size, &buffer),
+        };
+        if sender.send(Message::Frame(frame)).is_err() {
+            break;
+        }
+        index += 1;
+    }
+    if let Err(e) = child.kill().and_then(|()| child.wait().map(drop)) {
+        tracing::debug!("ffmpeg for {} already exited: {e}", path.display());
+    }
+    Ok(())
+}
+
+/// Size frames are scaled to: the source size within [`MAX_WIDTH`], even
+/// in both directions as the scaler requires.
+fn probe_size(path: &Path) -> Result<(u32, u32), String> {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-select_streams", "v:0"])
+        .args(["-show_entries", "stream=width,height", "-of", "csv=p=0"])
+        .arg(path)
+        .output()
+        .map_err(|e| format!("Cannot run ffprobe: {e}"))?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let (width, height) = text
+        .trim()
+        .split_once(',')
+        .and_then(|(w, h)| Some((w.trim().parse::<u32>().ok()?, h.trim().parse::<u32>().ok()?)))
+        .filter(|&(w, h)| w > 0 && h > 0)
+        .ok_or_else(|| "No video stream".to_string())?;
+
+    let scaled = width.min(MAX_WIDTH) / 2 * 2;
+    let scaled_height =
+        ((u64::from(height) * u64::from(scaled) / u64::from(width)) as u32 / 2 * 2).max(2);
+    Ok((scaled, scaled_height))
+}